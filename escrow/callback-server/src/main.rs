@@ -0,0 +1,174 @@
+//! Self-hostable webhook notifier for escrow payments.
+//!
+//! Follows the escrow program's logs for one escrow, picks up the
+//! `PaymentNotificationEvent` that `process_payment_callback` emits once the
+//! escrow has a registered webhook key, signs its Borsh encoding with that
+//! key and POSTs it to the merchant's endpoint. Receivers check the signature
+//! against the escrow's on-chain `webhook_key`, key their records on the
+//! `payment` account, and use `sequence` to order them.
+//!
+//! Notifications are opt-in per escrow: until its owner calls
+//! `register_webhook_key`, the program emits nothing for this server to
+//! forward. See README.md.
+//!
+//! Configured through the environment:
+//!
+//! - `RPC_WS_URL`: websocket RPC endpoint
+//! - `ESCROW`: escrow account to notify for; it must have opted in
+//! - `WEBHOOK_URL`: endpoint notifications are POSTed to
+//! - `WEBHOOK_KEYPAIR`: keypair file for the escrow's `webhook_key`
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AnchorDeserialize, AnchorSerialize, Discriminator};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use escrow::{PaymentNotificationEvent, PaymentNotificationStatus};
+use futures_util::StreamExt;
+use serde::Serialize;
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+use std::{env, error::Error, str::FromStr, time::Duration};
+
+/// Prefix Anchor's `emit!` gives event data in the program logs.
+const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+/// Delivery attempts per notification before it is dropped.
+const MAX_DELIVERY_ATTEMPTS: u32 = 5;
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+struct Config {
+    rpc_ws_url: String,
+    escrow: Pubkey,
+    webhook_url: String,
+    webhook_key: Keypair,
+}
+
+impl Config {
+    fn from_env() -> Result<Self, Box<dyn Error>> {
+        let var = |name: &str| env::var(name).map_err(|_| format!("{name} is not set"));
+        Ok(Self {
+            rpc_ws_url: var("RPC_WS_URL")?,
+            escrow: Pubkey::from_str(&var("ESCROW")?)?,
+            webhook_url: var("WEBHOOK_URL")?,
+            webhook_key: read_keypair_file(var("WEBHOOK_KEYPAIR")?)?,
+        })
+    }
+}
+
+/// Body POSTed to the merchant. `payload` is the Borsh encoding of the
+/// event and `signature` the webhook key's ed25519 signature over it; the
+/// other fields are the same event, decoded for convenience.
+#[derive(Serialize)]
+struct Notification {
+    escrow: String,
+    sequence: u64,
+    payment: String,
+    commitment: String,
+    status: &'static str,
+    timestamp: i64,
+    payload: String,
+    signature: String,
+}
+
+impl Notification {
+    fn signed(event: &PaymentNotificationEvent, key: &Keypair) -> Result<Self, Box<dyn Error>> {
+        let mut payload = Vec::new();
+        event.serialize(&mut payload)?;
+        Ok(Self {
+            escrow: event.escrow.to_string(),
+            sequence: event.sequence,
+            payment: event.payment.to_string(),
+            commitment: event
+                .commitment
+                .iter()
+                .map(|b| format!("{b:02x}"))
+                .collect(),
+            status: match event.status {
+                PaymentNotificationStatus::Recorded => "recorded",
+                PaymentNotificationStatus::Rejected => "rejected",
+            },
+            timestamp: event.timestamp,
+            signature: key.sign_message(&payload).to_string(),
+            payload: BASE64.encode(payload),
+        })
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let config = Config::from_env()?;
+    let http = reqwest::Client::new();
+    loop {
+        if let Err(err) = follow(&config, &http).await {
+            eprintln!("log subscription failed: {err}");
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+/// Forward every notification for the configured escrow until the log
+/// subscription ends.
+async fn follow(config: &Config, http: &reqwest::Client) -> Result<(), Box<dyn Error>> {
+    let pubsub = PubsubClient::new(&config.rpc_ws_url).await?;
+    let (mut logs, _unsubscribe) = pubsub
+        .logs_subscribe(
+            RpcTransactionLogsFilter::Mentions(vec![config.escrow.to_string()]),
+            RpcTransactionLogsConfig {
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        )
+        .await?;
+
+    while let Some(response) = logs.next().await {
+        if response.value.err.is_some() {
+            continue;
+        }
+        let lines = response.value.logs.iter().map(String::as_str);
+        for event in lines.filter_map(decode_notification) {
+            if event.escrow != config.escrow {
+                continue;
+            }
+            let notification = Notification::signed(&event, &config.webhook_key)?;
+            if let Err(err) = deliver(http, &config.webhook_url, &notification).await {
+                eprintln!(
+                    "dropping notification {} for payment {}: {err}",
+                    notification.sequence, notification.payment
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The `PaymentNotificationEvent` in a program log line, if it holds one.
+fn decode_notification(log: &str) -> Option<PaymentNotificationEvent> {
+    let data = BASE64.decode(log.strip_prefix(PROGRAM_DATA_PREFIX)?).ok()?;
+    let body = data.strip_prefix(PaymentNotificationEvent::DISCRIMINATOR)?;
+    PaymentNotificationEvent::deserialize(&mut &body[..]).ok()
+}
+
+/// POST `notification`, retrying with exponential backoff.
+async fn deliver(
+    http: &reqwest::Client,
+    url: &str,
+    notification: &Notification,
+) -> Result<(), reqwest::Error> {
+    let mut attempt = 1;
+    loop {
+        let result = http
+            .post(url)
+            .json(notification)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        match result {
+            Ok(_) => return Ok(()),
+            Err(err) if attempt >= MAX_DELIVERY_ATTEMPTS => return Err(err),
+            Err(_) => {
+                tokio::time::sleep(RETRY_BASE_DELAY * 2u32.pow(attempt - 1)).await;
+                attempt += 1;
+            }
+        }
+    }
+}
@@ -0,0 +1,385 @@
+//! The full confidential payment flow against a deployed escrow program,
+//! the Rust counterpart of the "runs a confidential payment end to end"
+//! test: comp defs, escrow, an encrypted `send_payment_encrypted`, its MPC
+//! callback and `reveal_payment_count`. It exits non-zero on the first step
+//! that fails, so it also serves as a devnet regression check:
+//!
+//! ```sh
+//! RPC_URL=https://api.devnet.solana.com \
+//! ARCIUM_CLUSTER_PUBKEY=... MXE_PUBLIC_KEY=... \
+//!     cargo run -p escrow-client --example confidential_flow
+//! ```
+//!
+//! Configured through the environment:
+//!
+//! - `RPC_URL`: RPC endpoint of the cluster the program is deployed to
+//! - `KEYPAIR`: owner and payer keypair; defaults to the Solana CLI's
+//! - `ARCIUM_CLUSTER_PUBKEY`: Arcium cluster account computations go to
+//! - `MXE_PUBLIC_KEY`: the MXE's x25519 public key, in hex
+//! - `AMOUNT`: lamports to pay; defaults to 1 SOL, enough that every fee leg
+//!   leaves the fresh recipient and referral accounts rent exempt
+//!
+//! Finalizing a comp def goes through `buildFinalizeCompDefTx` in
+//! `@arcium-hq/client`, which has no Rust counterpart, so missing comp defs
+//! are set up by the deploy script (`anchor migrate`), as for any fresh
+//! deployment. Amounts are encrypted through `cipher::NodeCipher` for the
+//! same reason.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{Id, InstructionData, ToAccountMetas};
+use arcium_anchor::prelude::*;
+use escrow::seeds::{
+    find_escrow_address, find_payment_address, find_protocol_config_address, find_spend_cap_address,
+};
+use escrow::{EscrowAccount, PaymentCountEvent, ID, STATS_DIMENSIONS_ALL};
+use escrow_client::cipher::{public_key, Cipher, NodeCipher, SharedSecret};
+use escrow_client::tx::{send_with_retry, SendConfig};
+use escrow_client::{decode_event, event_data, fetch_account, transaction_logs, Error};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+use solana_sdk::system_program;
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Circuits the flow queues, with the instruction name their callback logs.
+const CIRCUITS: [(&str, &str); 3] = [
+    ("init_escrow_stats", "InitEscrowStatsCallback"),
+    ("process_payment", "ProcessPaymentCallback"),
+    ("reveal_payment_count", "RevealPaymentCountCallback"),
+];
+const DEFAULT_AMOUNT: u64 = LAMPORTS_PER_SOL;
+/// How long to wait for the cluster to call a computation back.
+const CALLBACK_TIMEOUT: Duration = Duration::from_secs(180);
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+struct Config {
+    rpc_url: String,
+    keypair_path: PathBuf,
+    cluster_account: Pubkey,
+    mxe_public_key: [u8; 32],
+    amount: u64,
+}
+
+impl Config {
+    fn from_env() -> Result<Self, Error> {
+        let var = |name: &str| env::var(name).map_err(|_| format!("{name} is not set"));
+        let keypair_path = match env::var("KEYPAIR") {
+            Ok(path) => PathBuf::from(path),
+            Err(_) => PathBuf::from(var("HOME")?).join(".config/solana/id.json"),
+        };
+        let mxe_public_key = var("MXE_PUBLIC_KEY")?;
+        if mxe_public_key.len() != 64 || !mxe_public_key.is_ascii() {
+            return Err("MXE_PUBLIC_KEY must be 32 bytes of hex".into());
+        }
+        let mut key = [0; 32];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&mxe_public_key[2 * i..2 * i + 2], 16)?;
+        }
+        Ok(Self {
+            rpc_url: var("RPC_URL")?,
+            keypair_path,
+            cluster_account: Pubkey::from_str(&var("ARCIUM_CLUSTER_PUBKEY")?)?,
+            mxe_public_key: key,
+            amount: env::var("AMOUNT").map_or(Ok(DEFAULT_AMOUNT), |amount| amount.parse())?,
+        })
+    }
+}
+
+/// The Arcium accounts every queueing instruction takes, as `sdk/arcium.ts`
+/// derives them.
+struct ArciumAccounts {
+    cluster_account: Pubkey,
+}
+
+impl ArciumAccounts {
+    fn comp_def_account(circuit: &str) -> Pubkey {
+        derive_comp_def_pda!(comp_def_offset(circuit))
+    }
+
+    fn computation_account(computation_offset: u64) -> Pubkey {
+        derive_comp_pda!(computation_offset)
+    }
+}
+
+/// Run `anchor migrate` against the configured cluster unless every comp
+/// def the flow queues already exists.
+async fn ensure_comp_defs(rpc: &RpcClient, config: &Config) -> Result<(), Error> {
+    let mut missing = Vec::new();
+    for (circuit, _) in CIRCUITS {
+        let account = rpc
+            .get_account_with_commitment(
+                &ArciumAccounts::comp_def_account(circuit),
+                rpc.commitment(),
+            )
+            .await?
+            .value;
+        if account.is_none() {
+            missing.push(circuit);
+        }
+    }
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    println!(
+        "comp defs missing ({}), running anchor migrate",
+        missing.join(", ")
+    );
+    let status = Command::new("anchor")
+        .current_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/.."))
+        .arg("migrate")
+        .arg("--provider.cluster")
+        .arg(&config.rpc_url)
+        .arg("--provider.wallet")
+        .arg(&config.keypair_path)
+        .status()?;
+    if !status.success() {
+        return Err(format!("anchor migrate failed: {status}").into());
+    }
+    Ok(())
+}
+
+/// Logs of the transaction in which the cluster called back the computation
+/// at `computation_offset` into `callback`. Every callback transaction
+/// references its computation account, so only that account's history is
+/// searched.
+async fn await_callback(
+    rpc: &RpcClient,
+    computation_offset: u64,
+    callback: &str,
+) -> Result<Vec<String>, Error> {
+    let computation_account = ArciumAccounts::computation_account(computation_offset);
+    let marker = format!("Program log: Instruction: {callback}");
+    let deadline = Instant::now() + CALLBACK_TIMEOUT;
+    while Instant::now() < deadline {
+        for status in rpc.get_signatures_for_address(&computation_account).await? {
+            let signature = status.signature.parse()?;
+            let (logs, failed) = transaction_logs(rpc, &signature).await?;
+            if !logs.iter().any(|log| *log == marker) {
+                continue;
+            }
+            if failed {
+                return Err(format!("{callback} failed in {signature}").into());
+            }
+            return Ok(logs);
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+    Err(format!("no {callback} within {}s", CALLBACK_TIMEOUT.as_secs()).into())
+}
+
+fn initialize_escrow(
+    owner: &Pubkey,
+    arcium: &ArciumAccounts,
+    computation_offset: u64,
+) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: escrow::accounts::InitializeEscrow {
+            owner: *owner,
+            sign_pda_account: derive_sign_pda!(),
+            mxe_account: derive_mxe_pda!(),
+            mempool_account: derive_mempool_pda!(),
+            executing_pool: derive_execpool_pda!(),
+            computation_account: ArciumAccounts::computation_account(computation_offset),
+            comp_def_account: ArciumAccounts::comp_def_account("init_escrow_stats"),
+            protocol_config: find_protocol_config_address().0,
+            cluster_account: arcium.cluster_account,
+            pool_account: ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+            clock_account: ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+            system_program: system_program::ID,
+            arcium_program: Arcium::id(),
+            escrow: find_escrow_address(owner).0,
+        }
+        .to_account_metas(None),
+        data: escrow::instruction::InitializeEscrow {
+            computation_offset,
+            treasury_address: *owner,
+            nonce: rand::random(),
+            stats_dimensions: STATS_DIMENSIONS_ALL,
+        }
+        .data(),
+    }
+}
+
+struct EncryptedAmount {
+    public_key: [u8; 32],
+    nonce: u128,
+    ciphertext: [u8; 32],
+}
+
+fn send_payment_encrypted(
+    owner: &Pubkey,
+    arcium: &ArciumAccounts,
+    computation_offset: u64,
+    recipient: Pubkey,
+    referral: Pubkey,
+    amount: u64,
+    encrypted: &EncryptedAmount,
+) -> Instruction {
+    // Pays into the owner's own escrow, with the owner as sender and
+    // treasury, like the end-to-end test.
+    let escrow = find_escrow_address(owner).0;
+    Instruction {
+        program_id: ID,
+        accounts: escrow::accounts::SendPaymentSolEncrypted {
+            sender: *owner,
+            recipient,
+            referral: Some(referral),
+            treasury: *owner,
+            spend_cap: find_spend_cap_address(owner).0,
+            payment: find_payment_address(owner, &computation_offset.to_le_bytes()).0,
+            owner: *owner,
+            escrow,
+            jurisdiction_pause: None,
+            relayer: None,
+            relayer_registry: None,
+            compute_budget_vault: None,
+            sign_pda_account: derive_sign_pda!(),
+            mxe_account: derive_mxe_pda!(),
+            mempool_account: derive_mempool_pda!(),
+            executing_pool: derive_execpool_pda!(),
+            computation_account: ArciumAccounts::computation_account(computation_offset),
+            comp_def_account: ArciumAccounts::comp_def_account("process_payment"),
+            protocol_config: find_protocol_config_address().0,
+            cluster_account: arcium.cluster_account,
+            pool_account: ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+            clock_account: ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+            system_program: system_program::ID,
+            arcium_program: Arcium::id(),
+        }
+        .to_account_metas(None),
+        data: escrow::instruction::SendPaymentEncrypted {
+            computation_offset,
+            referal: referral,
+            amount,
+            recipient,
+            payment_encryption_pubkey: encrypted.public_key,
+            payment_nonce: encrypted.nonce,
+            encrypted_amount: encrypted.ciphertext,
+            valid_after_slot: None,
+            valid_before_slot: None,
+        }
+        .data(),
+    }
+}
+
+fn reveal_payment_count(
+    owner: &Pubkey,
+    arcium: &ArciumAccounts,
+    computation_offset: u64,
+) -> Instruction {
+    Instruction {
+        program_id: ID,
+        accounts: escrow::accounts::RevealPaymentCount {
+            authority: *owner,
+            escrow: find_escrow_address(owner).0,
+            analytics_operator: None,
+            viewer: None,
+            activity_log: None,
+            sign_pda_account: derive_sign_pda!(),
+            mxe_account: derive_mxe_pda!(),
+            mempool_account: derive_mempool_pda!(),
+            executing_pool: derive_execpool_pda!(),
+            computation_account: ArciumAccounts::computation_account(computation_offset),
+            comp_def_account: ArciumAccounts::comp_def_account("reveal_payment_count"),
+            protocol_config: find_protocol_config_address().0,
+            cluster_account: arcium.cluster_account,
+            pool_account: ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+            clock_account: ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+            system_program: system_program::ID,
+            arcium_program: Arcium::id(),
+        }
+        .to_account_metas(None),
+        data: escrow::instruction::RevealPaymentCount { computation_offset }.data(),
+    }
+}
+
+/// Send `instruction` and wait for the callback of the computation it
+/// queued at `computation_offset`.
+async fn queue(
+    rpc: &RpcClient,
+    owner: &Keypair,
+    instruction: Instruction,
+    computation_offset: u64,
+    callback: &str,
+) -> Result<Vec<String>, Error> {
+    let signature = send_with_retry(
+        rpc,
+        &[instruction],
+        &owner.pubkey(),
+        &[owner as &dyn Signer],
+        &SendConfig::default(),
+    )
+    .await?;
+    println!("queued {callback} in {signature}");
+    await_callback(rpc, computation_offset, callback).await
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let config = Config::from_env()?;
+    let owner = read_keypair_file(&config.keypair_path)?;
+    let rpc = RpcClient::new_with_commitment(config.rpc_url.clone(), CommitmentConfig::confirmed());
+    let arcium = ArciumAccounts {
+        cluster_account: config.cluster_account,
+    };
+
+    ensure_comp_defs(&rpc, &config).await?;
+
+    let escrow = find_escrow_address(&owner.pubkey()).0;
+    if fetch_account::<EscrowAccount>(&rpc, &escrow)
+        .await?
+        .is_none()
+    {
+        let offset = rand::random();
+        let instruction = initialize_escrow(&owner.pubkey(), &arcium, offset);
+        queue(&rpc, &owner, instruction, offset, CIRCUITS[0].1).await?;
+    }
+    println!("escrow {escrow} ready");
+
+    let private_key: [u8; 32] = rand::random();
+    let secret = SharedSecret::derive(&private_key, &config.mxe_public_key);
+    let nonce = rand::random();
+    let [ciphertext] = NodeCipher::default().encrypt(&secret, nonce, &[config.amount])?[..] else {
+        return Err("expected one ciphertext for the amount".into());
+    };
+    let encrypted = EncryptedAmount {
+        public_key: public_key(&private_key),
+        nonce,
+        ciphertext,
+    };
+
+    let offset = rand::random();
+    let recipient = Keypair::new().pubkey();
+    let referral = Keypair::new().pubkey();
+    let instruction = send_payment_encrypted(
+        &owner.pubkey(),
+        &arcium,
+        offset,
+        recipient,
+        referral,
+        config.amount,
+        &encrypted,
+    );
+    queue(&rpc, &owner, instruction, offset, CIRCUITS[1].1).await?;
+    println!("paid {} lamports to {recipient}", config.amount);
+
+    let offset = rand::random();
+    let instruction = reveal_payment_count(&owner.pubkey(), &arcium, offset);
+    let logs = queue(&rpc, &owner, instruction, offset, CIRCUITS[2].1).await?;
+    let count = event_data(&logs)
+        .find_map(|data| decode_event::<PaymentCountEvent>(&data))
+        .ok_or("no PaymentCountEvent in the reveal callback")?;
+    if count.total_payments == 0 {
+        return Err("revealed payment count is 0 after a payment".into());
+    }
+    println!("escrow has {} payments", count.total_payments);
+    Ok(())
+}
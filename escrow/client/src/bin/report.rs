@@ -0,0 +1,327 @@
+//! Settlement report for one merchant: every payment they received over a
+//! date range, with gross, fees by type, refunds and net, as CSV or JSON.
+//!
+//! Payments are found with `gpa::get_payments`, so the report reads only the
+//! merchant's `PaymentAccount`s. Amounts are totalled per asset, since SOL
+//! and token amounts don't add up. Archived payments are closed and no
+//! longer show up; insurance contributions aren't recorded per payment, so
+//! on escrows with `insurance_bps` the net includes that carve.
+//!
+//! With an auditor key, the report also includes the escrow's confidential
+//! stats, decrypted from the `SharedStatsEvent` that `share_stats_with_viewer`
+//! produced for that key.
+//!
+//! Configured through the environment:
+//!
+//! - `RPC_URL`: RPC endpoint
+//! - `MERCHANT`: recipient the report is for
+//! - `FROM`, `UNTIL`: range of payment times, as `YYYY-MM-DD` (UTC) or unix
+//!   seconds; `FROM` inclusive, `UNTIL` exclusive
+//! - `FORMAT`: `csv` (default) or `json`
+//! - `MINT`: only payments in this asset; `Pubkey::default()` for SOL
+//!
+//! and, for the stats:
+//!
+//! - `STATS_KEY`: file holding the auditor's x25519 private key, in hex
+//! - `MXE_PUBLIC_KEY`: the MXE's x25519 public key, in hex
+//! - `STATS_SIGNATURE`: transaction that emitted the `SharedStatsEvent`
+//! - `STATS_NONCE`: `viewer_nonce` the stats were requested with
+//! - `ESCROW`: escrow the stats are for
+
+use anchor_lang::prelude::Pubkey;
+use escrow::{PaymentAccount, SharedStatsEvent};
+use escrow_client::cipher::{NodeCipher, SharedSecret};
+use escrow_client::gpa::{get_payments, PaymentQuery};
+use escrow_client::stats::{decrypt_shared_stats, EscrowStats};
+use escrow_client::{decode_event, event_data, transaction_logs, Error};
+use serde::Serialize;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::Signature;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::{env, fs, str::FromStr};
+
+/// Payments fetched per `get_payments` page.
+const PAGE_SIZE: usize = 100;
+const SECONDS_PER_DAY: i64 = 86_400;
+
+enum Format {
+    Csv,
+    Json,
+}
+
+struct StatsConfig {
+    private_key: [u8; 32],
+    mxe_public_key: [u8; 32],
+    signature: Signature,
+    viewer_nonce: u128,
+    escrow: Pubkey,
+}
+
+struct Config {
+    rpc_url: String,
+    merchant: Pubkey,
+    time: Range<i64>,
+    format: Format,
+    mint: Option<Pubkey>,
+    stats: Option<StatsConfig>,
+}
+
+impl Config {
+    fn from_env() -> Result<Self, Error> {
+        let var = |name: &str| env::var(name).map_err(|_| format!("{name} is not set"));
+        let format = match env::var("FORMAT").as_deref() {
+            Err(_) | Ok("csv") => Format::Csv,
+            Ok("json") => Format::Json,
+            Ok(other) => return Err(format!("unknown FORMAT {other}").into()),
+        };
+        let stats = match env::var("STATS_KEY") {
+            Ok(path) => Some(StatsConfig {
+                private_key: parse_key(fs::read_to_string(path)?.trim())?,
+                mxe_public_key: parse_key(&var("MXE_PUBLIC_KEY")?)?,
+                signature: Signature::from_str(&var("STATS_SIGNATURE")?)?,
+                viewer_nonce: var("STATS_NONCE")?.parse()?,
+                escrow: Pubkey::from_str(&var("ESCROW")?)?,
+            }),
+            Err(_) => None,
+        };
+        Ok(Self {
+            rpc_url: var("RPC_URL")?,
+            merchant: Pubkey::from_str(&var("MERCHANT")?)?,
+            time: parse_time(&var("FROM")?)?..parse_time(&var("UNTIL")?)?,
+            format,
+            mint: env::var("MINT")
+                .ok()
+                .map(|mint| Pubkey::from_str(&mint))
+                .transpose()?,
+            stats,
+        })
+    }
+}
+
+/// Unix seconds, given as such or as a `YYYY-MM-DD` UTC date.
+fn parse_time(value: &str) -> Result<i64, Error> {
+    if let Ok(seconds) = value.parse() {
+        return Ok(seconds);
+    }
+    let parts: Vec<&str> = value.split('-').collect();
+    let [year, month, day] = parts[..] else {
+        return Err(format!("{value} is neither unix seconds nor YYYY-MM-DD").into());
+    };
+    let (year, month, day): (i64, i64, i64) = (year.parse()?, month.parse()?, day.parse()?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(format!("{value} is not a date").into());
+    }
+    // Days since 1970-01-01 in the proleptic Gregorian calendar, counting
+    // years from March so the leap day falls last.
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Ok((era * 146_097 + day_of_era - 719_468) * SECONDS_PER_DAY)
+}
+
+fn parse_key(hex: &str) -> Result<[u8; 32], Error> {
+    if hex.len() != 64 || !hex.is_ascii() {
+        return Err("expected a 32-byte key in hex".into());
+    }
+    let mut key = [0; 32];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)?;
+    }
+    Ok(key)
+}
+
+/// One payment the merchant received. `net` is what the merchant kept:
+/// gross less every fee and whatever they refunded.
+#[derive(Serialize)]
+struct Row {
+    payment: String,
+    timestamp: i64,
+    sender: String,
+    mint: String,
+    gross: u64,
+    referral_fee: u64,
+    treasury_fee: u64,
+    app_fee: u64,
+    refunded: u64,
+    net: u64,
+}
+
+impl Row {
+    fn new(address: &Pubkey, payment: &PaymentAccount) -> Self {
+        Self {
+            payment: address.to_string(),
+            timestamp: payment.timestamp,
+            sender: payment.sender.to_string(),
+            mint: payment.asset_mint.to_string(),
+            gross: payment.amount,
+            referral_fee: payment.referal_reward,
+            treasury_fee: payment.treasury_reward,
+            app_fee: payment.app_fee,
+            refunded: payment.refunded,
+            net: payment
+                .amount
+                .saturating_sub(payment.referal_reward)
+                .saturating_sub(payment.treasury_reward)
+                .saturating_sub(payment.app_fee)
+                .saturating_sub(payment.refunded),
+        }
+    }
+}
+
+/// Sums of one asset's rows.
+#[derive(Default, Serialize)]
+struct Totals {
+    payments: u64,
+    gross: u64,
+    referral_fee: u64,
+    treasury_fee: u64,
+    app_fee: u64,
+    refunds: u64,
+    refunded: u64,
+    net: u64,
+}
+
+impl Totals {
+    fn add(&mut self, row: &Row) {
+        self.payments += 1;
+        self.gross += row.gross;
+        self.referral_fee += row.referral_fee;
+        self.treasury_fee += row.treasury_fee;
+        self.app_fee += row.app_fee;
+        self.refunds += u64::from(row.refunded > 0);
+        self.refunded += row.refunded;
+        self.net += row.net;
+    }
+}
+
+#[derive(Serialize)]
+struct Report {
+    merchant: String,
+    from: i64,
+    until: i64,
+    payments: Vec<Row>,
+    /// By mint
+    totals: BTreeMap<String, Totals>,
+    stats: Option<EscrowStats>,
+}
+
+impl Report {
+    fn write_csv(&self) {
+        println!(
+            "payment,timestamp,sender,mint,gross,referral_fee,treasury_fee,app_fee,refunded,net"
+        );
+        for row in &self.payments {
+            println!(
+                "{},{},{},{},{},{},{},{},{},{}",
+                row.payment,
+                row.timestamp,
+                row.sender,
+                row.mint,
+                row.gross,
+                row.referral_fee,
+                row.treasury_fee,
+                row.app_fee,
+                row.refunded,
+                row.net
+            );
+        }
+
+        println!();
+        println!("mint,payments,gross,referral_fee,treasury_fee,app_fee,refunds,refunded,net");
+        for (mint, totals) in &self.totals {
+            println!(
+                "{mint},{},{},{},{},{},{},{},{}",
+                totals.payments,
+                totals.gross,
+                totals.referral_fee,
+                totals.treasury_fee,
+                totals.app_fee,
+                totals.refunds,
+                totals.refunded,
+                totals.net
+            );
+        }
+
+        if let Some(stats) = &self.stats {
+            println!();
+            println!("stat,value");
+            println!("total_payments,{}", stats.total_payments);
+            println!("total_volume,{}", stats.total_volume);
+            println!("total_fees_collected,{}", stats.total_fees_collected);
+            println!("recent_volume,{}", stats.recent_volume);
+            println!("total_refunds,{}", stats.total_refunds);
+            println!("refund_volume,{}", stats.refund_volume);
+            for (asset, volume) in stats.asset_volumes.iter().enumerate() {
+                println!("asset_volume_{asset},{volume}");
+            }
+        }
+    }
+}
+
+/// The stats shared with the auditor key, from the transaction that
+/// emitted them.
+async fn shared_stats(rpc: &RpcClient, config: &StatsConfig) -> Result<EscrowStats, Error> {
+    let (logs, _) = transaction_logs(rpc, &config.signature).await?;
+    let event = event_data(&logs)
+        .find_map(|data| decode_event::<SharedStatsEvent>(&data))
+        .ok_or_else(|| format!("no SharedStatsEvent in {}", config.signature))?;
+
+    let secret = SharedSecret::derive(&config.private_key, &config.mxe_public_key);
+    decrypt_shared_stats(
+        &NodeCipher::default(),
+        &secret,
+        &event,
+        &config.escrow,
+        config.viewer_nonce,
+    )
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let config = Config::from_env()?;
+    let rpc = RpcClient::new_with_commitment(config.rpc_url.clone(), CommitmentConfig::confirmed());
+
+    let query = PaymentQuery {
+        recipient: Some(config.merchant),
+        mint: config.mint,
+        time: Some(config.time.clone()),
+        ..PaymentQuery::default()
+    };
+    let mut payments = Vec::new();
+    let mut totals: BTreeMap<String, Totals> = BTreeMap::new();
+    let mut after = None;
+    loop {
+        let page = get_payments(&rpc, &query, after, PAGE_SIZE).await?;
+        for (address, payment) in &page.payments {
+            let row = Row::new(address, payment);
+            totals.entry(row.mint.clone()).or_default().add(&row);
+            payments.push(row);
+        }
+        match page.next {
+            Some(next) => after = Some(next),
+            None => break,
+        }
+    }
+
+    let stats = match &config.stats {
+        Some(stats) => Some(shared_stats(&rpc, stats).await?),
+        None => None,
+    };
+    let report = Report {
+        merchant: config.merchant.to_string(),
+        from: config.time.start,
+        until: config.time.end,
+        payments,
+        totals,
+        stats,
+    };
+    match config.format {
+        Format::Csv => report.write_csv(),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    Ok(())
+}
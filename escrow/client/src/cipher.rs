@@ -0,0 +1,164 @@
+//! Encryption to the MXE and decryption of what it encrypted to a client
+//! key.
+//!
+//! Arcium publishes its Rescue cipher only in `@arcium-hq/client`; there is
+//! no Rust implementation to depend on, and a port could not be checked
+//! against the MPC nodes. `NodeCipher` therefore runs `sdk/rescue.js`, which
+//! calls the same `RescueCipher` the tests use, while the x25519 key
+//! agreement that yields the cipher's shared secret is done here. Anything
+//! else implementing `Cipher` can replace it.
+
+use crate::Error;
+use std::path::PathBuf;
+use std::process::Command;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+/// Rescue key shared between a client and the MXE.
+#[derive(Clone, Copy)]
+pub struct SharedSecret(pub [u8; 32]);
+
+impl SharedSecret {
+    /// x25519 agreement between the client's private key and the MXE's
+    /// public key, as `x25519.getSharedSecret` computes it in the SDK.
+    pub fn derive(private_key: &[u8; 32], mxe_public_key: &[u8; 32]) -> Self {
+        let shared =
+            StaticSecret::from(*private_key).diffie_hellman(&PublicKey::from(*mxe_public_key));
+        Self(shared.to_bytes())
+    }
+}
+
+/// The x25519 public key to give the program for `private_key`, e.g. as
+/// `share_stats_with_viewer`'s `viewer_pubkey`.
+pub fn public_key(private_key: &[u8; 32]) -> [u8; 32] {
+    PublicKey::from(&StaticSecret::from(*private_key)).to_bytes()
+}
+
+/// A Rescue cipher keyed by a `SharedSecret`.
+pub trait Cipher {
+    /// Ciphertexts of `values` under `secret` and `nonce`, as instruction
+    /// arguments such as `send_payment_encrypted`'s `encrypted_amount` take
+    /// them.
+    fn encrypt(
+        &self,
+        secret: &SharedSecret,
+        nonce: u128,
+        values: &[u64],
+    ) -> Result<Vec<[u8; 32]>, Error>;
+
+    /// Plaintext field elements, little-endian, of `ciphertexts` encrypted
+    /// under `secret` and `nonce`. A wrong key still decrypts, to arbitrary
+    /// field elements; callers check the plaintexts fit their types.
+    fn decrypt(
+        &self,
+        secret: &SharedSecret,
+        nonce: u128,
+        ciphertexts: &[[u8; 32]],
+    ) -> Result<Vec<[u8; 32]>, Error>;
+}
+
+/// `Cipher` backed by `@arcium-hq/client`, run through Node.
+#[derive(Clone, Debug)]
+pub struct NodeCipher {
+    /// Node executable
+    pub node: PathBuf,
+    /// `sdk/rescue.js`; `@arcium-hq/client` must resolve from it
+    pub script: PathBuf,
+}
+
+impl Default for NodeCipher {
+    fn default() -> Self {
+        Self {
+            node: PathBuf::from("node"),
+            script: PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/../sdk/rescue.js")),
+        }
+    }
+}
+
+impl NodeCipher {
+    /// Output lines of `sdk/rescue.js <command>`, one per input in `inputs`.
+    fn run(
+        &self,
+        command: &str,
+        secret: &SharedSecret,
+        nonce: u128,
+        inputs: Vec<String>,
+    ) -> Result<Vec<String>, Error> {
+        let output = Command::new(&self.node)
+            .arg(&self.script)
+            .arg(command)
+            .arg(to_hex(&secret.0))
+            .arg(to_hex(&nonce.to_le_bytes()))
+            .args(&inputs)
+            .output()?;
+        if !output.status.success() {
+            return Err(format!(
+                "{} failed: {}",
+                self.script.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
+        }
+
+        let lines: Vec<String> = String::from_utf8(output.stdout)?
+            .lines()
+            .map(str::to_owned)
+            .collect();
+        if lines.len() != inputs.len() {
+            return Err(format!(
+                "{} {command} returned {} values for {}",
+                self.script.display(),
+                lines.len(),
+                inputs.len()
+            )
+            .into());
+        }
+        Ok(lines)
+    }
+}
+
+impl Cipher for NodeCipher {
+    fn encrypt(
+        &self,
+        secret: &SharedSecret,
+        nonce: u128,
+        values: &[u64],
+    ) -> Result<Vec<[u8; 32]>, Error> {
+        let values = values.iter().map(u64::to_string).collect();
+        self.run("encrypt", secret, nonce, values)?
+            .iter()
+            .map(|line| from_hex(line))
+            .collect()
+    }
+
+    fn decrypt(
+        &self,
+        secret: &SharedSecret,
+        nonce: u128,
+        ciphertexts: &[[u8; 32]],
+    ) -> Result<Vec<[u8; 32]>, Error> {
+        let ciphertexts = ciphertexts
+            .iter()
+            .map(|ciphertext| to_hex(ciphertext))
+            .collect();
+        self.run("decrypt", secret, nonce, ciphertexts)?
+            .iter()
+            .map(|line| from_hex(line))
+            .collect()
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// A ciphertext or field element as `sdk/rescue.js` prints it.
+fn from_hex(line: &str) -> Result<[u8; 32], Error> {
+    if line.len() != 64 || !line.is_ascii() {
+        return Err(format!("not 32 bytes of hex: {line:?}").into());
+    }
+    let mut bytes = [0; 32];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&line[2 * i..2 * i + 2], 16)?;
+    }
+    Ok(bytes)
+}
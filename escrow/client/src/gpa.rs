@@ -0,0 +1,214 @@
+//! `getProgramAccounts` filters over `PaymentAccount` and paginated payment
+//! listings, so explorers and reports fetch only the payments they show.
+//!
+//! Sender, recipient and mint are exact `memcmp` filters. `memcmp` can't
+//! express a range, but timestamps are stored little-endian, so matching the
+//! top bytes of `timestamp` keeps one aligned window of time; a range is
+//! covered by one query per window and trimmed exactly on the client.
+//!
+//! `get_payments` lists matching addresses with only their timestamp
+//! (`dataSlice`), orders them by time and fetches the full accounts for one
+//! page at a time.
+
+use crate::Error;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountDeserialize, Discriminator, Space};
+use escrow::PaymentAccount;
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+/// Byte offsets of the filterable `PaymentAccount` fields, discriminator
+/// included. Every field up to `asset_mint` is fixed width.
+pub const SENDER_OFFSET: usize = 8;
+pub const RECIPIENT_OFFSET: usize = SENDER_OFFSET + Pubkey::INIT_SPACE;
+pub const TIMESTAMP_OFFSET: usize = RECIPIENT_OFFSET
+    + Pubkey::INIT_SPACE // recipient
+    + Pubkey::INIT_SPACE // referal
+    + u64::INIT_SPACE; // amount
+pub const ASSET_MINT_OFFSET: usize = TIMESTAMP_OFFSET
+    + i64::INIT_SPACE // timestamp
+    + u64::INIT_SPACE // referal_reward
+    + u64::INIT_SPACE; // treasury_reward
+
+/// Most time windows one range is split into before falling back to a
+/// coarser window width.
+pub const MAX_TIME_WINDOWS: usize = 8;
+
+/// Most accounts `getMultipleAccounts` returns per call.
+const MULTIPLE_ACCOUNTS_LIMIT: usize = 100;
+
+/// Which payments to list. Every field set narrows the listing.
+#[derive(Clone, Debug, Default)]
+pub struct PaymentQuery {
+    pub sender: Option<Pubkey>,
+    pub recipient: Option<Pubkey>,
+    /// `Pubkey::default()` for SOL payments
+    pub mint: Option<Pubkey>,
+    /// Payment timestamps, in unix seconds, start inclusive
+    pub time: Option<Range<i64>>,
+}
+
+impl PaymentQuery {
+    /// Exact filters for the query: the `PaymentAccount` discriminator plus
+    /// one `memcmp` per set field. The time range is not included; see
+    /// `time_windows`.
+    pub fn filters(&self) -> Vec<RpcFilterType> {
+        let mut filters = vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            0,
+            PaymentAccount::DISCRIMINATOR,
+        ))];
+        let fields = [
+            (SENDER_OFFSET, self.sender),
+            (RECIPIENT_OFFSET, self.recipient),
+            (ASSET_MINT_OFFSET, self.mint),
+        ];
+        for (offset, key) in fields {
+            if let Some(key) = key {
+                filters.push(RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                    offset,
+                    key.as_ref(),
+                )));
+            }
+        }
+        filters
+    }
+
+    fn matches_time(&self, timestamp: i64) -> bool {
+        self.time
+            .as_ref()
+            .is_none_or(|time| time.contains(&timestamp))
+    }
+}
+
+/// `memcmp` filters that together cover `range`, one per query: each pins
+/// the top bytes of `timestamp` to one aligned window of 2^16 seconds
+/// (about 18 hours), or of 2^24 or 2^32 seconds when the range would need
+/// more than `MAX_TIME_WINDOWS` of them. Empty for an empty range or one
+/// too long to split, meaning no time filter.
+pub fn time_windows(range: &Range<i64>) -> Vec<RpcFilterType> {
+    if range.is_empty() {
+        return Vec::new();
+    }
+    for shift in [16u32, 24, 32] {
+        let first = range.start >> shift;
+        let last = (range.end - 1) >> shift;
+        if (last - first) as u64 >= MAX_TIME_WINDOWS as u64 {
+            continue;
+        }
+        let skipped = shift as usize / 8;
+        return (first..=last)
+            .map(|window| {
+                let bytes = (window << shift).to_le_bytes();
+                RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                    TIMESTAMP_OFFSET + skipped,
+                    &bytes[skipped..],
+                ))
+            })
+            .collect();
+    }
+    Vec::new()
+}
+
+/// Position in a time-ordered payment listing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Cursor {
+    pub timestamp: i64,
+    pub address: Pubkey,
+}
+
+/// One page of payments, oldest first.
+#[derive(Debug)]
+pub struct Page {
+    pub payments: Vec<(Pubkey, PaymentAccount)>,
+    /// Pass as `after` for the next page; `None` on the last one
+    pub next: Option<Cursor>,
+}
+
+/// Every payment matching `query`, as timestamp and address in time order.
+/// Only the 8 timestamp bytes of each account are transferred.
+pub async fn payment_index(rpc: &RpcClient, query: &PaymentQuery) -> Result<Vec<Cursor>, Error> {
+    let windows = query.time.as_ref().map(time_windows).unwrap_or_default();
+    let base = query.filters();
+    let filter_sets: Vec<Vec<RpcFilterType>> = if windows.is_empty() {
+        vec![base]
+    } else {
+        windows
+            .into_iter()
+            .map(|window| {
+                let mut filters = base.clone();
+                filters.push(window);
+                filters
+            })
+            .collect()
+    };
+
+    let mut index = BTreeSet::new();
+    for filters in filter_sets {
+        let accounts = rpc
+            .get_program_accounts_with_config(
+                &escrow::ID,
+                RpcProgramAccountsConfig {
+                    filters: Some(filters),
+                    account_config: RpcAccountInfoConfig {
+                        encoding: Some(UiAccountEncoding::Base64),
+                        data_slice: Some(UiDataSliceConfig {
+                            offset: TIMESTAMP_OFFSET,
+                            length: i64::INIT_SPACE,
+                        }),
+                        commitment: Some(rpc.commitment()),
+                        ..RpcAccountInfoConfig::default()
+                    },
+                    ..RpcProgramAccountsConfig::default()
+                },
+            )
+            .await?;
+        for (address, account) in accounts {
+            let bytes: [u8; 8] = account.data[..]
+                .try_into()
+                .map_err(|_| format!("{address}: short timestamp slice"))?;
+            let timestamp = i64::from_le_bytes(bytes);
+            if query.matches_time(timestamp) {
+                index.insert(Cursor { timestamp, address });
+            }
+        }
+    }
+    Ok(index.into_iter().collect())
+}
+
+/// Up to `limit` payments matching `query`, oldest first, starting after
+/// `after`. The listing is taken afresh on every call, so payments landing
+/// between pages show up in a later page rather than shifting earlier ones.
+pub async fn get_payments(
+    rpc: &RpcClient,
+    query: &PaymentQuery,
+    after: Option<Cursor>,
+    limit: usize,
+) -> Result<Page, Error> {
+    let index = payment_index(rpc, query).await?;
+    let start = after.map_or(0, |after| index.partition_point(|cursor| *cursor <= after));
+    let page = &index[start..index.len().min(start.saturating_add(limit))];
+
+    let mut payments = Vec::with_capacity(page.len());
+    for chunk in page.chunks(MULTIPLE_ACCOUNTS_LIMIT) {
+        let addresses: Vec<Pubkey> = chunk.iter().map(|cursor| cursor.address).collect();
+        let accounts = rpc.get_multiple_accounts(&addresses).await?;
+        for (address, account) in addresses.into_iter().zip(accounts) {
+            // Closed since the listing was taken, e.g. archived.
+            let Some(account) = account else { continue };
+            payments.push((
+                address,
+                PaymentAccount::try_deserialize(&mut &account.data[..])?,
+            ));
+        }
+    }
+
+    let next = match page.last() {
+        Some(last) if start + page.len() < index.len() => Some(*last),
+        _ => None,
+    };
+    Ok(Page { payments, next })
+}
@@ -0,0 +1,89 @@
+//! Rust client for the escrow program: off-chain helpers built on the
+//! program crate's own accounts, instructions and events, so they can't
+//! drift from what the program does.
+//!
+//! - `sim`: predicts a plaintext payment's effects and checks them against
+//!   a simulated transaction
+//! - `gpa`: `getProgramAccounts` filters and paginated payment listings
+//! - `tx`: compute budgets, priority fees and blockhash retry
+//! - `cipher`: x25519 key agreement and Rescue decryption via the SDK
+//! - `stats`: typed, validated `EscrowStats` from shared stats
+//!
+//! The `report` binary builds merchant settlement reports on `gpa` and
+//! `stats`; `examples/confidential_flow.rs` runs the whole confidential
+//! payment flow against a deployed program.
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AccountDeserialize, AnchorDeserialize, Discriminator};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::signature::Signature;
+use solana_transaction_status_client_types::option_serializer::OptionSerializer;
+use solana_transaction_status_client_types::UiTransactionEncoding;
+
+pub mod cipher;
+pub mod gpa;
+pub mod sim;
+pub mod stats;
+pub mod tx;
+
+/// Errors from the client helpers: RPC failures, accounts that don't
+/// decode and the helpers' own checks.
+pub type Error = Box<dyn std::error::Error + Send + Sync>;
+
+/// Prefix Anchor's `emit!` gives event data in the program logs.
+const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+
+/// The event payloads, discriminator included, in a transaction's logs.
+pub fn event_data(logs: &[String]) -> impl Iterator<Item = Vec<u8>> + '_ {
+    logs.iter()
+        .filter_map(|log| BASE64.decode(log.strip_prefix(PROGRAM_DATA_PREFIX)?).ok())
+}
+
+/// `data` decoded as event `E`, if it is one.
+pub fn decode_event<E: AnchorDeserialize + Discriminator>(data: &[u8]) -> Option<E> {
+    let body = data.strip_prefix(E::DISCRIMINATOR)?;
+    E::deserialize(&mut &body[..]).ok()
+}
+
+/// Fetch and decode the program account at `address`; `None` if it doesn't
+/// exist yet.
+pub async fn fetch_account<T: AccountDeserialize>(
+    rpc: &RpcClient,
+    address: &Pubkey,
+) -> Result<Option<T>, Error> {
+    let account = rpc
+        .get_account_with_commitment(address, rpc.commitment())
+        .await?
+        .value;
+    match account {
+        Some(account) => Ok(Some(T::try_deserialize(&mut &account.data[..])?)),
+        None => Ok(None),
+    }
+}
+
+/// Logs of the landed transaction `signature`, and whether it failed.
+pub async fn transaction_logs(
+    rpc: &RpcClient,
+    signature: &Signature,
+) -> Result<(Vec<String>, bool), Error> {
+    let transaction = rpc
+        .get_transaction_with_config(
+            signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Json),
+                commitment: Some(rpc.commitment()),
+                max_supported_transaction_version: Some(0),
+            },
+        )
+        .await?;
+    let meta = transaction
+        .transaction
+        .meta
+        .ok_or_else(|| format!("no status for {signature}"))?;
+    match meta.log_messages {
+        OptionSerializer::Some(logs) => Ok((logs, meta.err.is_some())),
+        _ => Err(format!("no logs for {signature}").into()),
+    }
+}
@@ -0,0 +1,518 @@
+//! Expected effects of a plaintext SOL `send_payment`, for catching economic
+//! regressions in CI.
+//!
+//! `predict` runs the program's fee pipeline over a snapshot of the escrow:
+//! the `FeeConfig` tier, a redeemed fee voucher, the insurance carve, the
+//! app fee and the referral route. `check` then simulates the real
+//! transaction and lists every balance, `PaymentAccount` field, escrow
+//! counter and event that came out differently.
+//!
+//! Campaign boosts, fee hooks and USDC-settled referral rewards depend on
+//! state the snapshot doesn't carry, so plans that would hit them are
+//! refused rather than predicted wrong. Referrers are taken as given;
+//! registered `ReferralLink`s are not resolved.
+
+use crate::{decode_event, event_data, fetch_account, Error};
+use anchor_lang::prelude::{Pubkey, Rent};
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::{
+    system_program, AccountDeserialize, AnchorSerialize, InstructionData, Space, ToAccountMetas,
+};
+use escrow::seeds::{
+    find_escrow_address, find_fee_config_address, find_fee_waiver_address,
+    find_insurance_pool_address, find_jurisdiction_pause_address, find_payment_sequence_address,
+    find_protocol_config_address, find_referral_vesting_address, find_sequenced_payment_address,
+    find_treasury_vault_address,
+};
+use escrow::{
+    fee_split, payment_intent_hash, AppFeeChargedEvent, CounterSignature, EscrowAccount,
+    EscrowUpdatedEvent, FeeConfig, InsuranceMovement, InsurancePoolEvent, IntentAmount,
+    PaymentAccount, PaymentSequence, ReferralFallback, DEFAULT_TREASURY_FEE_BPS,
+    UNTAGGED_JURISDICTION,
+};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{
+    RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig,
+};
+use solana_sdk::message::Message;
+use solana_sdk::transaction::Transaction;
+use std::collections::BTreeMap;
+
+/// The on-chain state a prediction starts from.
+#[derive(Clone, Debug)]
+pub struct Snapshot {
+    pub escrow_address: Pubkey,
+    pub escrow: EscrowAccount,
+    pub fee_config: Option<FeeConfig>,
+    /// The sender's `PaymentSequence`, `None` before their first plaintext
+    /// payment
+    pub payment_sequence: Option<PaymentSequence>,
+}
+
+impl Snapshot {
+    /// Read the escrow owned by `owner` and the accounts a payment from
+    /// `sender` depends on.
+    pub async fn fetch(rpc: &RpcClient, owner: &Pubkey, sender: &Pubkey) -> Result<Self, Error> {
+        let escrow_address = find_escrow_address(owner).0;
+        let escrow = fetch_account(rpc, &escrow_address)
+            .await?
+            .ok_or("escrow does not exist")?;
+        Ok(Self {
+            escrow_address,
+            escrow,
+            fee_config: fetch_account(rpc, &find_fee_config_address().0).await?,
+            payment_sequence: fetch_account(rpc, &find_payment_sequence_address(sender).0).await?,
+        })
+    }
+
+    fn next_sequence(&self) -> u64 {
+        self.payment_sequence
+            .as_ref()
+            .map_or(0, |sequence| sequence.next)
+    }
+}
+
+/// A `send_payment` to predict.
+#[derive(Clone, Debug)]
+pub struct PlannedPayment {
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    /// `Pubkey::default()` for a payment without a referrer
+    pub referrer: Pubkey,
+    pub amount: u64,
+    pub app_fee_bps: u16,
+    /// Required with a non-zero `app_fee_bps`
+    pub app_fee_destination: Option<Pubkey>,
+    /// Spend one of the sender's fee voucher credits on this payment
+    pub redeem_fee_waiver: bool,
+}
+
+/// Events `send_payment` emits, without their timestamps.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PaymentEffect {
+    AppFeeCharged {
+        payment: Pubkey,
+        destination: Pubkey,
+        app_fee: u64,
+    },
+    InsuranceContribution {
+        pool: Pubkey,
+        sender: Pubkey,
+        amount: u64,
+    },
+    EscrowUpdated {
+        escrow: Pubkey,
+        sequence: u64,
+    },
+    /// Any other event, by discriminator; never expected
+    Other(Vec<u8>),
+}
+
+/// What `send_payment` should do to the cluster.
+#[derive(Clone, Debug)]
+pub struct Prediction {
+    pub payment_address: Pubkey,
+    /// The new `PaymentAccount`, with `timestamp` left at 0: the cluster
+    /// clock sets it
+    pub payment: PaymentAccount,
+    /// Lamport change per account, network fees excluded
+    pub balance_changes: BTreeMap<Pubkey, i128>,
+    pub total_fund_regulated: u64,
+    pub escrow_sequence: u64,
+    pub events: Vec<PaymentEffect>,
+}
+
+/// One way a simulated payment differed from its prediction.
+#[derive(Clone, Debug)]
+pub enum Mismatch {
+    Balance {
+        account: Pubkey,
+        expected: i128,
+        actual: i128,
+    },
+    Payment {
+        expected: Box<PaymentAccount>,
+        actual: Box<PaymentAccount>,
+    },
+    TotalFundRegulated {
+        expected: u64,
+        actual: u64,
+    },
+    EscrowSequence {
+        expected: u64,
+        actual: u64,
+    },
+    Events {
+        expected: Vec<PaymentEffect>,
+        actual: Vec<PaymentEffect>,
+    },
+}
+
+/// Predict the effects of `plan` on the state in `snapshot`.
+pub fn predict(snapshot: &Snapshot, plan: &PlannedPayment) -> Result<Prediction, Error> {
+    let escrow = &snapshot.escrow;
+    if !escrow.active {
+        return Err("escrow is paused".into());
+    }
+    if escrow.fee_hook != Pubkey::default() {
+        return Err("escrows with a fee hook can't be predicted".into());
+    }
+    let pays_referrer = escrow.referrals_enabled && plan.referrer != Pubkey::default();
+    if pays_referrer && escrow.usdc_referral_rewards {
+        return Err("USDC-settled referral rewards can't be predicted".into());
+    }
+
+    let amount = plan.amount;
+    let treasury_bps = snapshot
+        .fee_config
+        .as_ref()
+        .map_or(DEFAULT_TREASURY_FEE_BPS, |config| {
+            config.treasury_bps(escrow.total_fund_regulated)
+        });
+    let (referal_reward, mut treasury_reward) = fee_split(amount, treasury_bps);
+    if plan.redeem_fee_waiver {
+        treasury_reward = 0;
+    }
+    let insurance = (amount
+        .checked_mul(escrow.insurance_bps as u64)
+        .ok_or("amount overflows the insurance carve")?
+        / 10_000)
+        .min(treasury_reward);
+    treasury_reward -= insurance;
+    let app_fee = amount
+        .checked_mul(plan.app_fee_bps as u64)
+        .ok_or("amount overflows the app fee")?
+        / 10_000;
+    let net = amount
+        .checked_sub(referal_reward + treasury_reward + insurance + app_fee)
+        .ok_or("fees exceed the amount")?;
+
+    let sequence = snapshot.next_sequence();
+    let (payment_address, _) = find_sequenced_payment_address(&plan.sender, sequence);
+    let treasury = treasury_destination(snapshot);
+    let referral = if !escrow.referrals_enabled {
+        treasury
+    } else if plan.referrer == Pubkey::default() {
+        match escrow.referral_fallback {
+            ReferralFallback::Treasury => treasury,
+            ReferralFallback::RecipientRebate => plan.recipient,
+            ReferralFallback::Burn => anchor_lang::solana_program::incinerator::ID,
+        }
+    } else if escrow.vest_referral_rewards {
+        find_referral_vesting_address(&snapshot.escrow_address, &plan.referrer).0
+    } else {
+        plan.referrer
+    };
+
+    let rent = Rent::default();
+    let payment_rent = rent.minimum_balance(8 + PaymentAccount::INIT_SPACE);
+    let sequence_rent = match snapshot.payment_sequence {
+        Some(_) => 0,
+        None => rent.minimum_balance(8 + PaymentSequence::INIT_SPACE),
+    };
+
+    let mut balance_changes = BTreeMap::new();
+    let mut credit = |account: Pubkey, lamports: u64| {
+        *balance_changes.entry(account).or_insert(0i128) += lamports as i128;
+    };
+    credit(plan.recipient, net);
+    credit(treasury, treasury_reward);
+    credit(referral, referal_reward);
+    credit(payment_address, payment_rent);
+    if sequence_rent > 0 {
+        credit(find_payment_sequence_address(&plan.sender).0, sequence_rent);
+    }
+    let mut events = Vec::new();
+    let app_fee_destination = if plan.app_fee_bps > 0 {
+        plan.app_fee_destination
+            .ok_or("an app fee needs a destination")?
+    } else {
+        Pubkey::default()
+    };
+    if app_fee > 0 {
+        credit(app_fee_destination, app_fee);
+        events.push(PaymentEffect::AppFeeCharged {
+            payment: payment_address,
+            destination: app_fee_destination,
+            app_fee,
+        });
+    }
+    if insurance > 0 {
+        let pool = find_insurance_pool_address(&snapshot.escrow_address).0;
+        credit(pool, insurance);
+        events.push(PaymentEffect::InsuranceContribution {
+            pool,
+            sender: plan.sender,
+            amount: insurance,
+        });
+    }
+    let debit = amount as i128 + payment_rent as i128 + sequence_rent as i128;
+    *balance_changes.entry(plan.sender).or_insert(0) -= debit;
+
+    let escrow_sequence = escrow.sequence + 1;
+    events.push(PaymentEffect::EscrowUpdated {
+        escrow: snapshot.escrow_address,
+        sequence: escrow_sequence,
+    });
+
+    let asset_mint = Pubkey::default();
+    let payment = PaymentAccount {
+        sender: plan.sender,
+        recipient: plan.recipient,
+        referal: plan.referrer,
+        amount,
+        timestamp: 0,
+        referal_reward,
+        treasury_reward,
+        asset_mint,
+        app_fee,
+        app_fee_destination,
+        app_fee_refunded: false,
+        intent_hash: payment_intent_hash(
+            &plan.sender,
+            &plan.recipient,
+            IntentAmount::Plain(amount),
+            &asset_mint,
+            sequence as u128,
+        ),
+        sequence,
+        counter_signature: CounterSignature::None,
+        refunded: 0,
+        unlock_at: None,
+        expires_at: None,
+    };
+
+    Ok(Prediction {
+        payment_address,
+        payment,
+        balance_changes,
+        total_fund_regulated: escrow
+            .total_fund_regulated
+            .checked_add(amount)
+            .ok_or("escrow volume overflows")?,
+        escrow_sequence,
+        events,
+    })
+}
+
+/// Where the treasury leg goes: the escrow's treasury vault in accrual
+/// mode, else the treasury itself.
+fn treasury_destination(snapshot: &Snapshot) -> Pubkey {
+    if snapshot.escrow.accrue_treasury_fees {
+        find_treasury_vault_address(&snapshot.escrow_address).0
+    } else {
+        snapshot.escrow.treasury
+    }
+}
+
+/// The `send_payment` instruction for `plan`, with the optional accounts
+/// the escrow's settings call for.
+pub fn send_payment_instruction(
+    snapshot: &Snapshot,
+    plan: &PlannedPayment,
+    prediction: &Prediction,
+) -> Instruction {
+    let escrow = &snapshot.escrow;
+    let escrow_address = snapshot.escrow_address;
+    let referrer = (plan.referrer != Pubkey::default()).then_some(plan.referrer);
+    let vests = escrow.referrals_enabled && escrow.vest_referral_rewards;
+    let burns = escrow.referrals_enabled
+        && referrer.is_none()
+        && escrow.referral_fallback == ReferralFallback::Burn;
+    let accounts = escrow::accounts::SendPaymentSol {
+        sender: plan.sender,
+        recipient: plan.recipient,
+        referral: referrer.filter(|_| escrow.referrals_enabled && !vests),
+        incinerator: burns.then_some(anchor_lang::solana_program::incinerator::ID),
+        protocol_config: (plan.app_fee_bps > 0).then(|| find_protocol_config_address().0),
+        app_fee_destination: plan.app_fee_destination,
+        referral_link: None,
+        campaign: None,
+        fee_config: snapshot
+            .fee_config
+            .as_ref()
+            .map(|_| find_fee_config_address().0),
+        referral_vesting: referrer
+            .filter(|_| vests)
+            .map(|referrer| find_referral_vesting_address(&escrow_address, &referrer).0),
+        referral_usdc_ledger: None,
+        sol_fee_rate: None,
+        treasury: (!escrow.accrue_treasury_fees).then_some(escrow.treasury),
+        treasury_vault: escrow
+            .accrue_treasury_fees
+            .then(|| find_treasury_vault_address(&escrow_address).0),
+        insurance_pool: (escrow.insurance_bps > 0)
+            .then(|| find_insurance_pool_address(&escrow_address).0),
+        fee_waiver: plan
+            .redeem_fee_waiver
+            .then(|| find_fee_waiver_address(&escrow_address, &plan.sender).0),
+        fee_hook_program: None,
+        instructions_sysvar: None,
+        payment_sequence: find_payment_sequence_address(&plan.sender).0,
+        payment: prediction.payment_address,
+        owner: escrow.authority(),
+        escrow: escrow_address,
+        jurisdiction_pause: (escrow.jurisdiction != UNTAGGED_JURISDICTION)
+            .then(|| find_jurisdiction_pause_address(escrow.jurisdiction).0),
+        relayer: None,
+        relayer_registry: None,
+        system_program: system_program::ID,
+    };
+    Instruction {
+        program_id: escrow::ID,
+        accounts: accounts.to_account_metas(None),
+        data: escrow::instruction::SendPayment {
+            referal: plan.referrer,
+            amount: plan.amount,
+            recipient: plan.recipient,
+            app_fee_bps: plan.app_fee_bps,
+            valid_after_slot: None,
+            valid_before_slot: None,
+        }
+        .data(),
+    }
+}
+
+/// Simulate `plan` against the cluster and list how the result differs
+/// from `predict`. An empty list means the program did exactly what the
+/// client expected. The snapshot should be fresh: the accounts are read
+/// again just before simulating, and anything that changed in between
+/// shows up as a mismatch.
+pub async fn check(
+    rpc: &RpcClient,
+    snapshot: &Snapshot,
+    plan: &PlannedPayment,
+) -> Result<Vec<Mismatch>, Error> {
+    let prediction = predict(snapshot, plan)?;
+    let instruction = send_payment_instruction(snapshot, plan, &prediction);
+    let message = Message::new(&[instruction], Some(&plan.sender));
+    let network_fee = rpc.get_fee_for_message(&message).await?;
+
+    let mut addresses: Vec<Pubkey> = prediction.balance_changes.keys().copied().collect();
+    addresses.push(snapshot.escrow_address);
+    let before = rpc.get_multiple_accounts(&addresses).await?;
+    let simulation = rpc
+        .simulate_transaction_with_config(
+            &Transaction::new_unsigned(message),
+            RpcSimulateTransactionConfig {
+                sig_verify: false,
+                replace_recent_blockhash: true,
+                commitment: Some(rpc.commitment()),
+                accounts: Some(RpcSimulateTransactionAccountsConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    addresses: addresses.iter().map(Pubkey::to_string).collect(),
+                }),
+                ..RpcSimulateTransactionConfig::default()
+            },
+        )
+        .await?
+        .value;
+    if let Some(err) = simulation.err {
+        let logs = simulation.logs.unwrap_or_default().join("\n");
+        return Err(format!("simulated payment failed: {err}\n{logs}").into());
+    }
+    let after = simulation
+        .accounts
+        .ok_or("simulation returned no accounts")?;
+    let data_after = |index: usize| -> Result<Vec<u8>, Error> {
+        after[index]
+            .as_ref()
+            .and_then(|account| account.data.decode())
+            .ok_or_else(|| format!("{} missing after the payment", addresses[index]).into())
+    };
+
+    let mut mismatches = Vec::new();
+    for (index, account) in addresses[..addresses.len() - 1].iter().enumerate() {
+        let lamports_before = before[index].as_ref().map_or(0, |account| account.lamports);
+        let lamports_after = after[index].as_ref().map_or(0, |account| account.lamports);
+        let mut expected = prediction.balance_changes[account];
+        if *account == plan.sender {
+            expected -= network_fee as i128;
+        }
+        let actual = lamports_after as i128 - lamports_before as i128;
+        if actual != expected {
+            mismatches.push(Mismatch::Balance {
+                account: *account,
+                expected,
+                actual,
+            });
+        }
+    }
+
+    let payment_index = addresses
+        .iter()
+        .position(|address| *address == prediction.payment_address)
+        .ok_or("payment account not tracked")?;
+    let mut actual = PaymentAccount::try_deserialize(&mut &data_after(payment_index)?[..])?;
+    let timestamp = actual.timestamp;
+    actual.timestamp = 0;
+    if encode(&actual)? != encode(&prediction.payment)? {
+        actual.timestamp = timestamp;
+        mismatches.push(Mismatch::Payment {
+            expected: Box::new(prediction.payment.clone()),
+            actual: Box::new(actual),
+        });
+    }
+
+    let escrow = EscrowAccount::try_deserialize(&mut &data_after(addresses.len() - 1)?[..])?;
+    if escrow.total_fund_regulated != prediction.total_fund_regulated {
+        mismatches.push(Mismatch::TotalFundRegulated {
+            expected: prediction.total_fund_regulated,
+            actual: escrow.total_fund_regulated,
+        });
+    }
+    if escrow.sequence != prediction.escrow_sequence {
+        mismatches.push(Mismatch::EscrowSequence {
+            expected: prediction.escrow_sequence,
+            actual: escrow.sequence,
+        });
+    }
+
+    let logs = simulation.logs.unwrap_or_default();
+    let events: Vec<PaymentEffect> = event_data(&logs).map(|data| decode_effect(&data)).collect();
+    if events != prediction.events {
+        mismatches.push(Mismatch::Events {
+            expected: prediction.events,
+            actual: events,
+        });
+    }
+
+    Ok(mismatches)
+}
+
+fn decode_effect(data: &[u8]) -> PaymentEffect {
+    if let Some(event) = decode_event::<AppFeeChargedEvent>(data) {
+        PaymentEffect::AppFeeCharged {
+            payment: event.payment,
+            destination: event.app_fee_destination,
+            app_fee: event.app_fee,
+        }
+    } else if let Some(event) = decode_event::<InsurancePoolEvent>(data) {
+        match event.movement {
+            InsuranceMovement::Contribution => PaymentEffect::InsuranceContribution {
+                pool: event.pool,
+                sender: event.counterparty,
+                amount: event.amount,
+            },
+            _ => other_effect(data),
+        }
+    } else if let Some(event) = decode_event::<EscrowUpdatedEvent>(data) {
+        PaymentEffect::EscrowUpdated {
+            escrow: event.escrow,
+            sequence: event.sequence,
+        }
+    } else {
+        other_effect(data)
+    }
+}
+
+fn other_effect(data: &[u8]) -> PaymentEffect {
+    PaymentEffect::Other(data.iter().take(8).copied().collect())
+}
+
+fn encode(payment: &PaymentAccount) -> Result<Vec<u8>, Error> {
+    let mut data = Vec::new();
+    payment.serialize(&mut data)?;
+    Ok(data)
+}
@@ -0,0 +1,122 @@
+//! Typed, validated `EscrowStats` for dashboards.
+//!
+//! `EscrowAccount::encrypted_stats` is encrypted to the MXE alone, so not
+//! even the owner can decrypt it in place. The owner, or an analytics
+//! operator or viewer they authorized, calls `share_stats_with_viewer` with
+//! an x25519 public key and a fresh nonce, and the MXE answers with a
+//! `SharedStatsEvent` re-encrypted to that key; `decrypt_shared_stats`
+//! turns that event into an `EscrowStats`.
+
+use crate::cipher::{Cipher, SharedSecret};
+use crate::Error;
+use anchor_lang::prelude::Pubkey;
+use escrow::{EscrowAccount, SharedStatsEvent, ESCROW_STATS_FIELDS, STATS_ASSET_COUNT};
+use serde::Serialize;
+
+/// The escrow's confidential metrics, in the circuits' `EscrowStats` field
+/// order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct EscrowStats {
+    pub total_payments: u64,
+    pub total_volume: u64,
+    pub total_fees_collected: u64,
+    /// Volume that halves every elapsed half-life
+    pub recent_volume: u64,
+    pub total_refunds: u64,
+    pub refund_volume: u64,
+    /// Lifetime volume per asset, indexed by `STATS_ASSET_*`
+    pub asset_volumes: [u64; STATS_ASSET_COUNT],
+}
+
+impl EscrowStats {
+    fn from_fields(fields: [u64; ESCROW_STATS_FIELDS]) -> Self {
+        let mut asset_volumes = [0; STATS_ASSET_COUNT];
+        asset_volumes.copy_from_slice(&fields[6..]);
+        Self {
+            total_payments: fields[0],
+            total_volume: fields[1],
+            total_fees_collected: fields[2],
+            recent_volume: fields[3],
+            total_refunds: fields[4],
+            refund_volume: fields[5],
+            asset_volumes,
+        }
+    }
+}
+
+/// Decrypt stats ciphertexts encrypted under `secret` and `nonce`.
+///
+/// Fails on a zero nonce, which the MXE never encrypts under, and on any
+/// field that doesn't decrypt to a `u64`: under the wrong key or nonce,
+/// Rescue yields arbitrary field elements that almost never fit.
+pub fn decrypt_stats(
+    cipher: &impl Cipher,
+    secret: &SharedSecret,
+    nonce: u128,
+    ciphertexts: &[[u8; 32]; ESCROW_STATS_FIELDS],
+) -> Result<EscrowStats, Error> {
+    if nonce == 0 {
+        return Err("stats nonce is zero".into());
+    }
+    let plaintexts = cipher.decrypt(secret, nonce, ciphertexts)?;
+    if plaintexts.len() != ESCROW_STATS_FIELDS {
+        return Err(format!(
+            "{} plaintexts for {ESCROW_STATS_FIELDS} stats fields",
+            plaintexts.len()
+        )
+        .into());
+    }
+
+    let mut fields = [0u64; ESCROW_STATS_FIELDS];
+    for (i, (field, plaintext)) in fields.iter_mut().zip(&plaintexts).enumerate() {
+        if plaintext[8..].iter().any(|byte| *byte != 0) {
+            return Err(format!("stats field {i} doesn't fit in a u64: wrong key or nonce").into());
+        }
+        *field = u64::from_le_bytes(plaintext[..8].try_into().unwrap());
+    }
+    Ok(EscrowStats::from_fields(fields))
+}
+
+/// Decrypt the stats `share_stats_with_viewer` shared for `escrow` with
+/// the key behind `secret`, requested with `viewer_nonce`.
+///
+/// The MXE encrypts its answer under the request's nonce plus one, so
+/// checking the event's nonce against `viewer_nonce` also rejects an event
+/// answering some other request, e.g. an older one still in the logs.
+pub fn decrypt_shared_stats(
+    cipher: &impl Cipher,
+    secret: &SharedSecret,
+    event: &SharedStatsEvent,
+    escrow: &Pubkey,
+    viewer_nonce: u128,
+) -> Result<EscrowStats, Error> {
+    if event.escrow != *escrow {
+        return Err(format!("stats shared for {}, not {escrow}", event.escrow).into());
+    }
+    if event.nonce != viewer_nonce.wrapping_add(1) {
+        return Err(format!(
+            "stats shared under nonce {}, not the answer to request nonce {viewer_nonce}",
+            event.nonce
+        )
+        .into());
+    }
+    decrypt_stats(cipher, secret, event.nonce, &event.ciphertexts)
+}
+
+/// Check the escrow's stats before requesting them: initialized, and not
+/// older than the stats nonce the caller last saw, as `health_check`'s
+/// `HEALTH_NONCE_REGRESSED` does on-chain. Returns the escrow's nonce, to
+/// pass as `last_seen_nonce` next time.
+pub fn check_stats_nonce(escrow: &EscrowAccount, last_seen_nonce: u128) -> Result<u128, Error> {
+    if escrow.nonce == 0 || escrow.encrypted_stats.iter().all(|c| *c == [0; 32]) {
+        return Err("escrow stats are not initialized yet".into());
+    }
+    if escrow.nonce < last_seen_nonce {
+        return Err(format!(
+            "escrow stats nonce {} is behind the last seen {last_seen_nonce}",
+            escrow.nonce
+        )
+        .into());
+    }
+    Ok(escrow.nonce)
+}
@@ -0,0 +1,230 @@
+//! Transaction plumbing for escrow clients and relayers: compute-budget
+//! instructions sized per instruction from the ceilings in
+//! `test-vectors/compute_units.rs`, a priority fee estimated from what
+//! recent transactions paid to write the same accounts, and submission
+//! that re-signs against a fresh blockhash once the last one expires.
+//!
+//! The encrypted payment instructions carry a dozen Arcium accounts on top
+//! of the escrow's own, so they are the ones that suffer most from a
+//! default compute limit and a stale blockhash.
+
+use crate::Error;
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::instruction::Instruction;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::compute_budget::{self, ComputeBudgetInstruction};
+use solana_sdk::signature::{Signature, Signer};
+use solana_sdk::transaction::Transaction;
+use solana_sdk::{ed25519_program, system_program};
+use std::time::Duration;
+
+/// Per-instruction compute unit ceilings; see
+/// `test-vectors/compute_units.rs`.
+pub mod budgets {
+    include!("../../test-vectors/compute_units.rs");
+}
+
+/// Units allowed for an instruction without a budget row: the runtime's
+/// default per-instruction limit.
+pub const DEFAULT_INSTRUCTION_UNITS: u32 = 200_000;
+/// Units allowed for a system, compute budget or ed25519 instruction.
+pub const BUILTIN_INSTRUCTION_UNITS: u32 = 3_000;
+/// Most compute units one transaction may request.
+pub const MAX_TRANSACTION_UNITS: u32 = 1_400_000;
+/// Most accounts `getRecentPrioritizationFees` accepts.
+const PRIORITIZATION_FEE_ACCOUNTS_LIMIT: usize = 128;
+
+/// Compute units to request for `instruction`.
+pub fn instruction_units(instruction: &Instruction) -> u32 {
+    let program = instruction.program_id;
+    if program == system_program::ID
+        || program == compute_budget::ID
+        || program == ed25519_program::ID
+    {
+        return BUILTIN_INSTRUCTION_UNITS;
+    }
+    if program != escrow::ID {
+        return DEFAULT_INSTRUCTION_UNITS;
+    }
+    budgets::COMPUTE_UNIT_BUDGETS
+        .iter()
+        .find(|(name, _)| {
+            instruction
+                .data
+                .starts_with(&instruction_discriminator(name))
+        })
+        .map_or(DEFAULT_INSTRUCTION_UNITS, |(_, units)| *units)
+}
+
+/// Anchor's discriminator for the instruction `name`.
+fn instruction_discriminator(name: &str) -> [u8; 8] {
+    let digest = hash(format!("global:{name}").as_bytes()).to_bytes();
+    let mut discriminator = [0; 8];
+    discriminator.copy_from_slice(&digest[..8]);
+    discriminator
+}
+
+/// Compute unit limit for a transaction of `instructions`.
+pub fn compute_unit_limit(instructions: &[Instruction]) -> u32 {
+    instructions
+        .iter()
+        .map(instruction_units)
+        .fold(0u32, u32::saturating_add)
+        .min(MAX_TRANSACTION_UNITS)
+}
+
+/// Every account `instructions` write, once each.
+pub fn writable_accounts(instructions: &[Instruction]) -> Vec<Pubkey> {
+    let mut writable: Vec<Pubkey> = instructions
+        .iter()
+        .flat_map(|instruction| &instruction.accounts)
+        .filter(|meta| meta.is_writable)
+        .map(|meta| meta.pubkey)
+        .collect();
+    writable.sort_unstable();
+    writable.dedup();
+    writable
+}
+
+/// Priority fee, in micro-lamports per compute unit: the `percentile`th of
+/// what transactions writing any of `writable` paid over the slots the RPC
+/// node remembers. 0 when nothing was paid.
+pub async fn estimate_priority_fee(
+    rpc: &RpcClient,
+    writable: &[Pubkey],
+    percentile: u8,
+) -> Result<u64, Error> {
+    let accounts = &writable[..writable.len().min(PRIORITIZATION_FEE_ACCOUNTS_LIMIT)];
+    let mut fees: Vec<u64> = rpc
+        .get_recent_prioritization_fees(accounts)
+        .await?
+        .into_iter()
+        .map(|fee| fee.prioritization_fee)
+        .collect();
+    if fees.is_empty() {
+        return Ok(0);
+    }
+    fees.sort_unstable();
+    Ok(fees[(fees.len() - 1) * percentile.min(100) as usize / 100])
+}
+
+/// `instructions` behind a compute unit limit and, when `micro_lamports`
+/// isn't 0, a compute unit price.
+pub fn with_compute_budget(
+    instructions: &[Instruction],
+    units: u32,
+    micro_lamports: u64,
+) -> Vec<Instruction> {
+    let mut budgeted = vec![ComputeBudgetInstruction::set_compute_unit_limit(units)];
+    if micro_lamports > 0 {
+        budgeted.push(ComputeBudgetInstruction::set_compute_unit_price(
+            micro_lamports,
+        ));
+    }
+    budgeted.extend_from_slice(instructions);
+    budgeted
+}
+
+/// How `send_with_retry` prices and retries a transaction.
+#[derive(Clone, Copy, Debug)]
+pub struct SendConfig {
+    /// Percentile of recent priority fees to pay
+    pub fee_percentile: u8,
+    /// Most to pay per compute unit, in micro-lamports
+    pub max_priority_fee: u64,
+    /// Blockhashes to try before giving up
+    pub max_attempts: u32,
+    pub poll_interval: Duration,
+}
+
+impl Default for SendConfig {
+    fn default() -> Self {
+        Self {
+            fee_percentile: 75,
+            max_priority_fee: 50_000,
+            max_attempts: 3,
+            poll_interval: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Send `instructions` with a sized compute budget and an estimated
+/// priority fee, and wait for the RPC client's commitment. If the blockhash
+/// expires first, the fee is estimated again and the transaction re-signed
+/// against a fresh blockhash. A transaction is only re-signed once its
+/// blockhash has expired, so the same instructions can never land twice.
+pub async fn send_with_retry(
+    rpc: &RpcClient,
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    signers: &[&dyn Signer],
+    config: &SendConfig,
+) -> Result<Signature, Error> {
+    let units = compute_unit_limit(instructions);
+    let writable = writable_accounts(instructions);
+    for _ in 0..config.max_attempts {
+        let micro_lamports = estimate_priority_fee(rpc, &writable, config.fee_percentile)
+            .await?
+            .min(config.max_priority_fee);
+        let (blockhash, last_valid_block_height) = rpc
+            .get_latest_blockhash_with_commitment(rpc.commitment())
+            .await?;
+        let transaction = Transaction::new_signed_with_payer(
+            &with_compute_budget(instructions, units, micro_lamports),
+            Some(payer),
+            signers,
+            blockhash,
+        );
+        let signature = rpc.send_transaction(&transaction).await?;
+        if confirm(
+            rpc,
+            &signature,
+            last_valid_block_height,
+            config.poll_interval,
+        )
+        .await?
+        {
+            return Ok(signature);
+        }
+    }
+    Err(format!(
+        "transaction expired on {} blockhashes in a row",
+        config.max_attempts
+    )
+    .into())
+}
+
+/// Whether `signature` reached the RPC client's commitment before its
+/// blockhash expired; an error if it landed and failed.
+async fn confirm(
+    rpc: &RpcClient,
+    signature: &Signature,
+    last_valid_block_height: u64,
+    poll_interval: Duration,
+) -> Result<bool, Error> {
+    loop {
+        let status = rpc
+            .get_signature_statuses(&[*signature])
+            .await?
+            .value
+            .pop()
+            .flatten();
+        match status {
+            Some(status) => {
+                if let Some(err) = status.err {
+                    return Err(format!("transaction {signature} failed: {err}").into());
+                }
+                if status.satisfies_commitment(rpc.commitment()) {
+                    return Ok(true);
+                }
+            }
+            None => {
+                if rpc.get_block_height().await? > last_valid_block_height {
+                    return Ok(false);
+                }
+            }
+        }
+        tokio::time::sleep(poll_interval).await;
+    }
+}
@@ -8,6 +8,13 @@ mod circuits {
         total_payments: u64,
         total_volume: u64,
         total_fees_collected: u64,
+        /// Volume that halves every elapsed half-life, see `decay_recent_volume`
+        recent_volume: u64,
+        /// Refunds recorded by `process_refund`, and the amount they returned
+        total_refunds: u64,
+        refund_volume: u64,
+        /// Lifetime volume per asset, indexed by the on-chain `STATS_ASSET_*`
+        asset_volumes: [u64; 3],
     }
 
     pub struct ConfidentialPayment {
@@ -26,6 +33,10 @@ mod circuits {
             total_payments: 0,
             total_volume: 0,
             total_fees_collected: 0,
+            recent_volume: 0,
+            total_refunds: 0,
+            refund_volume: 0,
+            asset_volumes: [0; 3],
         };
         mxe.from_arcis(escrow_stats)
     }
@@ -39,21 +50,159 @@ mod circuits {
         mxe.from_arcis(referral_stats)
     }
 
+    /// Halve `volume` once per elapsed period. `periods` is plaintext and
+    /// capped on-chain, so the loop bound is public.
+    fn decay_recent_volume(volume: u64, periods: u8) -> u64 {
+        let mut decayed = volume;
+        for i in 0..16 {
+            if (i as u8) < periods {
+                decayed = decayed / 2;
+            }
+        }
+        decayed
+    }
+
+    fn add_asset_volume(asset_volumes: &mut [u64; 3], asset_index: u8, amount: u64) {
+        for i in 0..3 {
+            if (i as u8) == asset_index {
+                asset_volumes[i] += amount;
+            }
+        }
+    }
+
+    /// (referral, treasury) fee legs at the default rates: 0.6% and 1.4%,
+    /// each rounded down on its own like the program's `fee_split`.
+    /// `record_payment` and `calculate_fees` both charge through this.
+    fn fee_split(amount: u64) -> (u64, u64) {
+        ((amount * 6) / 1000, (amount * 14) / 1000)
+    }
+
+    /// Add one payment to the metrics the escrow opted into; the
+    /// `track_*` flags mirror its on-chain `stats_dimensions`.
+    fn record_payment(
+        escrow_stats: &mut EscrowStats,
+        amount: u64,
+        asset_index: u8,
+        track_payments: bool,
+        track_volume: bool,
+        track_fees: bool,
+        track_assets: bool,
+    ) {
+        if track_payments {
+            escrow_stats.total_payments += 1;
+        }
+        if track_volume {
+            escrow_stats.total_volume += amount;
+            escrow_stats.recent_volume += amount;
+        }
+        if track_fees {
+            let (referral_fee, treasury_fee) = fee_split(amount);
+            escrow_stats.total_fees_collected += referral_fee + treasury_fee;
+        }
+        if track_assets {
+            add_asset_volume(&mut escrow_stats.asset_volumes, asset_index, amount);
+        }
+    }
+
     #[instruction]
     pub fn process_payment(
         payment_ctxt: Enc<Shared, ConfidentialPayment>,
         escrow_stats_ctxt: Enc<Mxe, EscrowStats>,
-    ) -> Enc<Mxe, EscrowStats> {
+        decay_periods: u8,
+        asset_index: u8,
+        track_payments: bool,
+        track_volume: bool,
+        track_fees: bool,
+        track_assets: bool,
+    ) -> (Enc<Mxe, EscrowStats>, bool) {
         let payment = payment_ctxt.to_arcis();
         let mut escrow_stats = escrow_stats_ctxt.to_arcis();
+        escrow_stats.recent_volume = decay_recent_volume(escrow_stats.recent_volume, decay_periods);
 
         if payment.is_valid {
-            // Calculate fees (2% total: 1.4% treasury + 0.6% refferral)
-            let total_fee = (payment.amount * 20) / 1000; // 2%
+            record_payment(
+                &mut escrow_stats,
+                payment.amount,
+                asset_index,
+                track_payments,
+                track_volume,
+                track_fees,
+                track_assets,
+            );
+        }
 
-            escrow_stats.total_payments += 1;
-            escrow_stats.total_volume += payment.amount;
-            escrow_stats.total_fees_collected += total_fee;
+        // Validity is revealed so the callback can report the outcome.
+        (
+            escrow_stats_ctxt.owner.from_arcis(escrow_stats),
+            payment.is_valid.reveal(),
+        )
+    }
+
+    /// Fold a batch of buffered payment amounts into the stats with a single
+    /// computation. Only the first `count` deltas are applied; unused slots
+    /// carry zeroed ciphertexts.
+    #[instruction]
+    pub fn merge_deltas(
+        delta_0: Enc<Shared, u64>,
+        delta_1: Enc<Shared, u64>,
+        delta_2: Enc<Shared, u64>,
+        delta_3: Enc<Shared, u64>,
+        delta_4: Enc<Shared, u64>,
+        delta_5: Enc<Shared, u64>,
+        delta_6: Enc<Shared, u64>,
+        delta_7: Enc<Shared, u64>,
+        count: u8,
+        escrow_stats_ctxt: Enc<Mxe, EscrowStats>,
+        decay_periods: u8,
+        asset_index: u8,
+        track_payments: bool,
+        track_volume: bool,
+        track_fees: bool,
+        track_assets: bool,
+    ) -> Enc<Mxe, EscrowStats> {
+        let amounts = [
+            delta_0.to_arcis(),
+            delta_1.to_arcis(),
+            delta_2.to_arcis(),
+            delta_3.to_arcis(),
+            delta_4.to_arcis(),
+            delta_5.to_arcis(),
+            delta_6.to_arcis(),
+            delta_7.to_arcis(),
+        ];
+        let mut escrow_stats = escrow_stats_ctxt.to_arcis();
+        escrow_stats.recent_volume = decay_recent_volume(escrow_stats.recent_volume, decay_periods);
+
+        for i in 0..8 {
+            if (i as u8) < count {
+                record_payment(
+                    &mut escrow_stats,
+                    amounts[i],
+                    asset_index,
+                    track_payments,
+                    track_volume,
+                    track_fees,
+                    track_assets,
+                );
+            }
+        }
+
+        escrow_stats_ctxt.owner.from_arcis(escrow_stats)
+    }
+
+    /// Add a merchant refund to the escrow's refund count and volume.
+    #[instruction]
+    pub fn process_refund(
+        refund_ctxt: Enc<Shared, u64>,
+        escrow_stats_ctxt: Enc<Mxe, EscrowStats>,
+        track_refunds: bool,
+    ) -> Enc<Mxe, EscrowStats> {
+        let amount = refund_ctxt.to_arcis();
+        let mut escrow_stats = escrow_stats_ctxt.to_arcis();
+
+        if track_refunds {
+            escrow_stats.total_refunds += 1;
+            escrow_stats.refund_volume += amount;
         }
 
         escrow_stats_ctxt.owner.from_arcis(escrow_stats)
@@ -73,6 +222,25 @@ mod circuits {
         referral_stats_ctxt.owner.from_arcis(referral_stats)
     }
 
+    /// Hand the referrer a copy of their totals encrypted to their own key.
+    #[instruction]
+    pub fn referral_report(
+        referrer: Shared,
+        referral_stats_ctxt: Enc<Mxe, ReferralStats>,
+    ) -> Enc<Shared, ReferralStats> {
+        let referral_stats = referral_stats_ctxt.to_arcis();
+        referrer.from_arcis(referral_stats)
+    }
+
+    #[instruction]
+    pub fn share_escrow_stats(
+        viewer: Shared,
+        escrow_stats_ctxt: Enc<Mxe, EscrowStats>,
+    ) -> Enc<Shared, EscrowStats> {
+        let escrow_stats = escrow_stats_ctxt.to_arcis();
+        viewer.from_arcis(escrow_stats)
+    }
+
     #[instruction]
     pub fn check_volume_threshold(
         escrow_stats_ctxt: Enc<Mxe, EscrowStats>,
@@ -82,12 +250,67 @@ mod circuits {
         (escrow_stats.total_volume >= threshold).reveal()
     }
 
+    #[instruction]
+    pub fn check_recent_volume_threshold(
+        escrow_stats_ctxt: Enc<Mxe, EscrowStats>,
+        decay_periods: u8,
+        threshold: u64,
+    ) -> bool {
+        let escrow_stats = escrow_stats_ctxt.to_arcis();
+        let recent_volume = decay_recent_volume(escrow_stats.recent_volume, decay_periods);
+        (recent_volume >= threshold).reveal()
+    }
+
+    /// Whether refunds reach `threshold_bps` of payment volume. The threshold
+    /// is echoed back so the callback can report it.
+    #[instruction]
+    pub fn check_refund_rate(
+        escrow_stats_ctxt: Enc<Mxe, EscrowStats>,
+        threshold_bps: u16,
+    ) -> (u16, bool) {
+        let escrow_stats = escrow_stats_ctxt.to_arcis();
+        let refunded = (escrow_stats.refund_volume as u128) * 10_000;
+        let limit = (escrow_stats.total_volume as u128) * (threshold_bps as u128);
+        (threshold_bps, (refunded >= limit).reveal())
+    }
+
+    /// Whether the prover's encrypted amount is the payment's and falls in
+    /// `[bucket_min, bucket_max)`. Only the verdict is revealed.
+    #[instruction]
+    pub fn prove_payment_amount(
+        amount_ctxt: Enc<Shared, u64>,
+        payment_amount: u64,
+        bucket_min: u64,
+        bucket_max: u64,
+    ) -> bool {
+        let amount = amount_ctxt.to_arcis();
+        let in_bucket = amount >= bucket_min && amount < bucket_max;
+        (amount == payment_amount && in_bucket).reveal()
+    }
+
     #[instruction]
     pub fn reveal_payment_count(escrow_stats_ctxt: Enc<Mxe, EscrowStats>) -> u64 {
         let escrow_stats = escrow_stats_ctxt.to_arcis();
         escrow_stats.total_payments.reveal()
     }
 
+    /// Reveal one asset's volume. The index is echoed back so the callback
+    /// can attribute the result.
+    #[instruction]
+    pub fn reveal_asset_volume(
+        escrow_stats_ctxt: Enc<Mxe, EscrowStats>,
+        asset_index: u8,
+    ) -> (u8, u64) {
+        let escrow_stats = escrow_stats_ctxt.to_arcis();
+        let mut volume = 0u64;
+        for i in 0..3 {
+            if (i as u8) == asset_index {
+                volume = escrow_stats.asset_volumes[i];
+            }
+        }
+        (asset_index, volume.reveal())
+    }
+
     #[instruction]
     pub fn verify_payment_amount(
         payment_amount: Enc<Shared, u64>,
@@ -104,12 +327,14 @@ mod circuits {
         net_amount: u64,
     }
 
+    /// Fee breakdown of an encrypted amount, re-encrypted to the caller. The
+    /// program's `quote_fees` queues this; the tests run it over
+    /// `test-vectors/fees.rs` to keep the MPC split in line with the program.
     #[instruction]
     pub fn calculate_fees(amount_ctxt: Enc<Shared, u64>) -> Enc<Shared, FeeDistribution> {
         let amount = amount_ctxt.to_arcis();
 
-        let treasury_fee = (amount * 14) / 1000; // 1.4%
-        let referral_fee = (amount * 6) / 1000; // 0.6%
+        let (referral_fee, treasury_fee) = fee_split(amount);
         let net_amount = amount - treasury_fee - referral_fee;
 
         let distribution = FeeDistribution {
@@ -120,4 +345,146 @@ mod circuits {
 
         amount_ctxt.owner.from_arcis(distribution)
     }
+
+    pub struct SpendCap {
+        cap: u64,
+        spent: u64,
+    }
+
+    /// Open a sender's private budget from a cap they encrypted themselves.
+    #[instruction]
+    pub fn init_spend_cap(cap_ctxt: Enc<Shared, u64>, mxe: Mxe) -> Enc<Mxe, SpendCap> {
+        let spend_cap = SpendCap {
+            cap: cap_ctxt.to_arcis(),
+            spent: 0,
+        };
+        mxe.from_arcis(spend_cap)
+    }
+
+    /// Charge `amount` to the budget if it fits, after zeroing the spend
+    /// when a new period started. Only whether it fit is revealed.
+    #[instruction]
+    pub fn check_spend_cap(
+        amount_ctxt: Enc<Shared, u64>,
+        spend_cap_ctxt: Enc<Mxe, SpendCap>,
+        new_period: bool,
+    ) -> (Enc<Mxe, SpendCap>, bool) {
+        let amount = amount_ctxt.to_arcis();
+        let mut spend_cap = spend_cap_ctxt.to_arcis();
+        if new_period {
+            spend_cap.spent = 0;
+        }
+
+        let fits = amount <= spend_cap.cap && spend_cap.spent <= spend_cap.cap - amount;
+        if fits {
+            spend_cap.spent += amount;
+        }
+
+        (spend_cap_ctxt.owner.from_arcis(spend_cap), fits.reveal())
+    }
+
+    pub struct MatchTerms {
+        ratio_bps: u64,
+        max_match: u64,
+    }
+
+    /// Size a sponsor match for a public donation of `amount` from the
+    /// pool's private terms, bounded by what the pool still holds. Only the
+    /// match itself is revealed, since the callback has to pay it out.
+    #[instruction]
+    pub fn compute_match(terms_ctxt: Enc<Shared, MatchTerms>, amount: u64, available: u64) -> u64 {
+        let terms = terms_ctxt.to_arcis();
+        let mut matched = (amount * terms.ratio_bps) / 10000;
+        if matched > terms.max_match {
+            matched = terms.max_match;
+        }
+        if matched > available {
+            matched = available;
+        }
+        matched.reveal()
+    }
+
+    /// Check both sides of an OTC swap against each other's private minimum.
+    /// Deposits are public token transfers; only the agreed terms stay
+    /// encrypted, and the single revealed bit says whether they were met.
+    #[instruction]
+    pub fn verify_otc_terms(
+        maker_min_ctxt: Enc<Shared, u64>,
+        taker_min_ctxt: Enc<Shared, u64>,
+        maker_deposit: u64,
+        taker_deposit: u64,
+    ) -> bool {
+        let maker_min = maker_min_ctxt.to_arcis();
+        let taker_min = taker_min_ctxt.to_arcis();
+        (taker_deposit >= maker_min && maker_deposit >= taker_min).reveal()
+    }
+
+    pub struct PayrollSalaries {
+        amounts: [u64; 8],
+    }
+
+    /// Reveal this period's payouts to the callback. Slots at or past
+    /// `count` pay nothing regardless of their ciphertext.
+    #[instruction]
+    pub fn run_payroll(salaries_ctxt: Enc<Shared, PayrollSalaries>, count: u8) -> [u64; 8] {
+        let salaries = salaries_ctxt.to_arcis();
+        let mut payouts = [0u64; 8];
+        for i in 0..8 {
+            if (i as u8) < count {
+                payouts[i] = salaries.amounts[i];
+            }
+        }
+        payouts.reveal()
+    }
+
+    /// Second-price sealed-bid resolution. Bids above their public deposit
+    /// and slots at or past `count` are ignored. Reveals only the winner's
+    /// index (255 if nothing met the reserve) and the clearing price: the
+    /// second-highest valid bid, floored at the reserve.
+    #[instruction]
+    pub fn resolve_sealed_bids(
+        bid_0: Enc<Shared, u64>,
+        bid_1: Enc<Shared, u64>,
+        bid_2: Enc<Shared, u64>,
+        bid_3: Enc<Shared, u64>,
+        bid_4: Enc<Shared, u64>,
+        bid_5: Enc<Shared, u64>,
+        bid_6: Enc<Shared, u64>,
+        bid_7: Enc<Shared, u64>,
+        deposits: [u64; 8],
+        count: u8,
+        reserve_price: u64,
+    ) -> (u8, u64) {
+        let bids = [
+            bid_0.to_arcis(),
+            bid_1.to_arcis(),
+            bid_2.to_arcis(),
+            bid_3.to_arcis(),
+            bid_4.to_arcis(),
+            bid_5.to_arcis(),
+            bid_6.to_arcis(),
+            bid_7.to_arcis(),
+        ];
+
+        let mut best = 0u64;
+        let mut second = 0u64;
+        let mut winner = 255u8;
+        for i in 0..8 {
+            let valid = (i as u8) < count && bids[i] <= deposits[i];
+            let bid = if valid { bids[i] } else { 0 };
+            if bid > best {
+                second = best;
+                best = bid;
+                winner = i as u8;
+            } else if bid > second {
+                second = bid;
+            }
+        }
+
+        let has_winner = best > 0 && best >= reserve_price;
+        let price = if second > reserve_price { second } else { reserve_price };
+        let winner = if has_winner { winner } else { 255 };
+        let price = if has_winner { price } else { 0 };
+        (winner.reveal(), price.reveal())
+    }
 }
@@ -1,12 +1,199 @@
 // Migrations are an early feature. Currently, they're nothing more than this
 // single deploy script that's invoked from the CLI, injecting a provider
 // configured from the workspace's Anchor.toml.
+//
+// The script bootstraps a fresh deployment in a fixed order: protocol config,
+// every computation definition, the circuit registry, the SPL mint allowlist,
+// the relayer registry, then a demo escrow owned by the provider wallet. Each
+// step checks whether its account already exists and is skipped if so, so
+// `anchor migrate` can be re-run safely against devnet.
 
 import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { PublicKey } from "@solana/web3.js";
+import { randomBytes } from "crypto";
+import { deserializeLE } from "@arcium-hq/client";
+import { Escrow } from "../target/types/escrow";
+import {
+  ArciumAccounts,
+  CIRCUITS,
+  initCompDefIfMissing,
+  STATS_DIMENSIONS_ALL,
+} from "../sdk/arcium";
+
+// Same convention as tests/escrow.ts: null uses ARCIUM_CLUSTER_PUBKEY.
+const CLUSTER_OFFSET: number | null = null;
+
+// Version recorded in the circuit registry for every circuit in CIRCUITS.
+const CIRCUIT_VERSION = 1;
 
 module.exports = async function (provider: anchor.AnchorProvider) {
   // Configure client to use the provider.
   anchor.setProvider(provider);
 
-  // Add your deploy script here.
+  const program = anchor.workspace.Escrow as Program<Escrow>;
+  const admin = provider.wallet.publicKey;
+
+  await bootstrapProtocolConfig(provider, program, admin);
+
+  for (const circuit of CIRCUITS) {
+    const sig = await initCompDefIfMissing(
+      provider,
+      program,
+      circuit.name,
+      circuit.method
+    );
+    console.log(
+      sig
+        ? `Comp def ${circuit.name} initialized with signature ${sig}`
+        : `Comp def ${circuit.name} already initialized, skipping`
+    );
+  }
+
+  await bootstrapCircuitRegistry(provider, program, admin);
+
+  await bootstrapMintAllowlist(provider, program, admin);
+
+  await bootstrapRelayerRegistry(provider, program, admin);
+
+  await bootstrapDemoEscrow(provider, program, admin);
 };
+
+async function accountExists(
+  provider: anchor.AnchorProvider,
+  address: PublicKey
+): Promise<boolean> {
+  return (await provider.connection.getAccountInfo(address)) !== null;
+}
+
+async function bootstrapProtocolConfig(
+  provider: anchor.AnchorProvider,
+  program: Program<Escrow>,
+  admin: PublicKey
+) {
+  const [protocolConfig] = PublicKey.findProgramAddressSync(
+    [Buffer.from("protocol_config")],
+    program.programId
+  );
+
+  if (await accountExists(provider, protocolConfig)) {
+    console.log("Protocol config already initialized, skipping");
+    return;
+  }
+
+  // The admin wallet doubles as the treasury on a fresh deployment; it can
+  // be rotated later with propose_treasury / accept_treasury.
+  const sig = await program.methods
+    .initializeProtocolConfig(admin)
+    .accountsPartial({ admin, protocolConfig })
+    .rpc({ commitment: "confirmed" });
+  console.log("Protocol config initialized with signature", sig);
+}
+
+async function bootstrapCircuitRegistry(
+  provider: anchor.AnchorProvider,
+  program: Program<Escrow>,
+  admin: PublicKey
+) {
+  const [circuitRegistry] = PublicKey.findProgramAddressSync(
+    [Buffer.from("circuit_registry")],
+    program.programId
+  );
+
+  if (!(await accountExists(provider, circuitRegistry))) {
+    await program.methods
+      .initCircuitRegistry()
+      .accountsPartial({ admin, circuitRegistry })
+      .rpc({ commitment: "confirmed" });
+  }
+
+  // register_circuit is an upsert, so re-running this step is harmless.
+  for (const circuit of CIRCUITS) {
+    await program.methods
+      .registerCircuit(circuit.name, CIRCUIT_VERSION, true)
+      .accountsPartial({ admin, circuitRegistry })
+      .rpc({ commitment: "confirmed" });
+  }
+  console.log(`Registered ${CIRCUITS.length} circuits`);
+}
+
+async function bootstrapMintAllowlist(
+  provider: anchor.AnchorProvider,
+  program: Program<Escrow>,
+  admin: PublicKey
+) {
+  const [mintAllowlist] = PublicKey.findProgramAddressSync(
+    [Buffer.from("mint_allowlist")],
+    program.programId
+  );
+
+  if (await accountExists(provider, mintAllowlist)) {
+    console.log("Mint allowlist already initialized, skipping");
+    return;
+  }
+
+  // Starts out with USDC and ZenZEC; more mints via setMintAllowed.
+  const sig = await program.methods
+    .initMintAllowlist()
+    .accountsPartial({ admin, mintAllowlist })
+    .rpc({ commitment: "confirmed" });
+  console.log("Mint allowlist initialized with signature", sig);
+}
+
+async function bootstrapRelayerRegistry(
+  provider: anchor.AnchorProvider,
+  program: Program<Escrow>,
+  admin: PublicKey
+) {
+  const [relayerRegistry] = PublicKey.findProgramAddressSync(
+    [Buffer.from("relayer_registry")],
+    program.programId
+  );
+
+  if (await accountExists(provider, relayerRegistry)) {
+    console.log("Relayer registry already initialized, skipping");
+    return;
+  }
+
+  // Starts out empty; relayers are vetted and added with addRelayer.
+  const sig = await program.methods
+    .initRelayerRegistry()
+    .accountsPartial({ admin, relayerRegistry })
+    .rpc({ commitment: "confirmed" });
+  console.log("Relayer registry initialized with signature", sig);
+}
+
+async function bootstrapDemoEscrow(
+  provider: anchor.AnchorProvider,
+  program: Program<Escrow>,
+  owner: PublicKey
+) {
+  const [escrow] = PublicKey.findProgramAddressSync(
+    [Buffer.from("escrow"), owner.toBuffer()],
+    program.programId
+  );
+
+  if (await accountExists(provider, escrow)) {
+    console.log("Demo escrow already initialized, skipping");
+    return;
+  }
+
+  const computationOffset = new anchor.BN(randomBytes(8), "hex");
+  const nonce = randomBytes(16);
+  const arcium = new ArciumAccounts(program.programId, CLUSTER_OFFSET);
+
+  const sig = await program.methods
+    .initializeEscrow(
+      computationOffset,
+      owner,
+      new anchor.BN(deserializeLE(nonce).toString()),
+      STATS_DIMENSIONS_ALL
+    )
+    .accountsPartial({
+      owner,
+      escrow,
+      ...arcium.queue(computationOffset, "init_escrow_stats"),
+    })
+    .rpc({ skipPreflight: true, commitment: "confirmed" });
+  console.log("Demo escrow initialized with signature", sig);
+}
@@ -0,0 +1,295 @@
+//! Canonical payment intent hash, shared by the escrow program and its
+//! clients so both build the preimage byte for byte the same way.
+//!
+//! The hash is SHA-256 over the domain tag, program id, sender, recipient,
+//! tagged amount, mint and a little-endian u128 nonce, all fixed width. The
+//! program hashes `Preimage::as_bytes` with the sha256 syscall; everything
+//! else here is `const` so the shared vectors in `test-vectors/` can be
+//! checked at compile time.
+
+#![no_std]
+
+/// Domain tag for the payment intent hash; bump the version if the encoding
+/// changes.
+pub const DOMAIN: &[u8] = b"anon0mesh/payment-intent/v1";
+
+/// Longest preimage: domain, program id, sender and recipient, the amount
+/// tag, a 32-byte commitment, mint and nonce.
+pub const MAX_PREIMAGE_LEN: usize = DOMAIN.len() + 32 * 3 + 1 + 32 + 32 + 16;
+
+/// What a payment intent commits to: the public amount, or the amount
+/// ciphertext for confidential payments.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntentAmount<'a> {
+    Plain(u64),
+    Commitment(&'a [u8; 32]),
+}
+
+/// The bytes a payment intent hash is taken over.
+#[derive(Clone, Copy, Debug)]
+pub struct Preimage {
+    bytes: [u8; MAX_PREIMAGE_LEN],
+    len: usize,
+}
+
+impl Preimage {
+    pub const fn new(
+        program_id: &[u8; 32],
+        sender: &[u8; 32],
+        recipient: &[u8; 32],
+        amount: IntentAmount,
+        mint: &[u8; 32],
+        nonce: u128,
+    ) -> Self {
+        let preimage = Self {
+            bytes: [0; MAX_PREIMAGE_LEN],
+            len: 0,
+        }
+        .push(DOMAIN)
+        .push(program_id)
+        .push(sender)
+        .push(recipient);
+        let preimage = match amount {
+            IntentAmount::Plain(amount) => preimage.push(&[0]).push(&amount.to_le_bytes()),
+            IntentAmount::Commitment(commitment) => preimage.push(&[1]).push(commitment),
+        };
+        preimage.push(mint).push(&nonce.to_le_bytes())
+    }
+
+    pub const fn as_bytes(&self) -> &[u8] {
+        self.bytes.split_at(self.len).0
+    }
+
+    const fn push(mut self, data: &[u8]) -> Self {
+        let mut i = 0;
+        while i < data.len() {
+            self.bytes[self.len + i] = data[i];
+            i += 1;
+        }
+        self.len += data.len();
+        self
+    }
+}
+
+/// Payment intent hash of the given fields under `program_id`.
+pub const fn payment_intent_hash(
+    program_id: &[u8; 32],
+    sender: &[u8; 32],
+    recipient: &[u8; 32],
+    amount: IntentAmount,
+    mint: &[u8; 32],
+    nonce: u128,
+) -> [u8; 32] {
+    sha256(Preimage::new(program_id, sender, recipient, amount, mint, nonce).as_bytes())
+}
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const H0: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// SHA-256, usable in const contexts. The program only uses it to check
+/// the vectors; at runtime it hashes with the syscall.
+pub const fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut state = H0;
+    let full_blocks = data.len() / 64;
+    let mut block = 0;
+    while block < full_blocks {
+        state = compress(state, data, block * 64);
+        block += 1;
+    }
+
+    // Remaining bytes, the 0x80 marker and the bit length, over one or two
+    // blocks.
+    let rest = data.len() % 64;
+    let mut tail = [0u8; 128];
+    let mut i = 0;
+    while i < rest {
+        tail[i] = data[full_blocks * 64 + i];
+        i += 1;
+    }
+    tail[rest] = 0x80;
+    let tail_len = if rest < 56 { 64 } else { 128 };
+    let bit_len = (data.len() as u64).wrapping_mul(8).to_be_bytes();
+    let mut i = 0;
+    while i < 8 {
+        tail[tail_len - 8 + i] = bit_len[i];
+        i += 1;
+    }
+    state = compress(state, &tail, 0);
+    if tail_len == 128 {
+        state = compress(state, &tail, 64);
+    }
+
+    let mut out = [0u8; 32];
+    let mut i = 0;
+    while i < 8 {
+        let word = state[i].to_be_bytes();
+        out[i * 4] = word[0];
+        out[i * 4 + 1] = word[1];
+        out[i * 4 + 2] = word[2];
+        out[i * 4 + 3] = word[3];
+        i += 1;
+    }
+    out
+}
+
+const fn compress(state: [u32; 8], data: &[u8], at: usize) -> [u32; 8] {
+    let mut w = [0u32; 64];
+    let mut i = 0;
+    while i < 16 {
+        let j = at + i * 4;
+        w[i] = u32::from_be_bytes([data[j], data[j + 1], data[j + 2], data[j + 3]]);
+        i += 1;
+    }
+    while i < 64 {
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+        w[i] = w[i - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(s1);
+        i += 1;
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+    let mut i = 0;
+    while i < 64 {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ (!e & g);
+        let t1 = h
+            .wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(K[i])
+            .wrapping_add(w[i]);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(maj);
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+        i += 1;
+    }
+
+    [
+        state[0].wrapping_add(a),
+        state[1].wrapping_add(b),
+        state[2].wrapping_add(c),
+        state[3].wrapping_add(d),
+        state[4].wrapping_add(e),
+        state[5].wrapping_add(f),
+        state[6].wrapping_add(g),
+        state[7].wrapping_add(h),
+    ]
+}
+
+/// Decode 64 hex digits, as used by the vectors. Panics, and so fails the
+/// build in a const context, on anything else.
+pub const fn hex32(hex: &str) -> [u8; 32] {
+    const fn nibble(c: u8) -> u8 {
+        match c {
+            b'0'..=b'9' => c - b'0',
+            b'a'..=b'f' => c - b'a' + 10,
+            b'A'..=b'F' => c - b'A' + 10,
+            _ => panic!("invalid hex digit"),
+        }
+    }
+    let hex = hex.as_bytes();
+    assert!(hex.len() == 64, "expected 64 hex digits");
+    let mut out = [0u8; 32];
+    let mut i = 0;
+    while i < 32 {
+        out[i] = nibble(hex[i * 2]) << 4 | nibble(hex[i * 2 + 1]);
+        i += 1;
+    }
+    out
+}
+
+/// One row of `test-vectors/payment_intent.rs`.
+pub struct IntentVector {
+    pub sender: &'static str,
+    pub recipient: &'static str,
+    pub amount: VectorAmount,
+    pub mint: &'static str,
+    pub nonce: u128,
+    pub hash: &'static str,
+}
+
+/// `IntentAmount` as written in the vectors, with the commitment in hex.
+pub enum VectorAmount {
+    Plain(u64),
+    Commitment(&'static str),
+}
+
+impl IntentVector {
+    /// The row's preimage under `program_id`, so the program can check its
+    /// own id alongside the encoding.
+    pub const fn preimage(&self, program_id: &[u8; 32]) -> Preimage {
+        let commitment;
+        let amount = match self.amount {
+            VectorAmount::Plain(amount) => IntentAmount::Plain(amount),
+            VectorAmount::Commitment(hex) => {
+                commitment = hex32(hex);
+                IntentAmount::Commitment(&commitment)
+            }
+        };
+        Preimage::new(
+            program_id,
+            &hex32(self.sender),
+            &hex32(self.recipient),
+            amount,
+            &hex32(self.mint),
+            self.nonce,
+        )
+    }
+
+    /// Whether hashing the row under `program_id` gives its `hash`.
+    pub const fn holds(&self, program_id: &[u8; 32]) -> bool {
+        let actual = sha256(self.preimage(program_id).as_bytes());
+        let expected = hex32(self.hash);
+        let mut i = 0;
+        while i < 32 {
+            if actual[i] != expected[i] {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+}
+
+/// Fixtures shared with the program and the tests; see
+/// `test-vectors/payment_intent.rs`.
+pub mod vectors {
+    use super::{IntentVector, VectorAmount};
+
+    include!("../../test-vectors/payment_intent.rs");
+
+    // Fails to compile if the encoding or the hash drifts from the fixtures.
+    const _: () = {
+        let program_id = super::hex32(PROGRAM_ID);
+        let mut i = 0;
+        while i < PAYMENT_INTENT_VECTORS.len() {
+            assert!(
+                PAYMENT_INTENT_VECTORS[i].holds(&program_id),
+                "payment intent hash diverges from test-vectors/payment_intent.rs"
+            );
+            i += 1;
+        }
+    };
+}
@@ -1,8 +1,15 @@
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program::{get_return_data, invoke};
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
 use anchor_lang::solana_program::program_pack::Pack;
 use anchor_lang::solana_program::token_2022::spl_token::state::{Mint as SplMint, Account as SplAccount};
 use arcium_anchor::prelude::*;
 use arcium_client::idl::arcium::types::CallbackAccount;
+use anchor_spl::token::{self as anchor_token, Token};
 
 // Re-export token types for convenience
 pub use anchor_lang::solana_program::token_2022::spl_token::{
@@ -12,6 +19,9 @@ pub use anchor_lang::solana_program::token_2022::spl_token::{
     ID as TOKEN_PROGRAM_ID,
 };
 
+// Shared with off-chain clients through the `payment-intent` crate
+pub use payment_intent::IntentAmount;
+
 // Type aliases for better readability
 pub type TokenAccount = Account<'info, token_state::Account>;
 pub type Mint = Account<'info, token_state::Mint>;
@@ -22,1296 +32,18131 @@ const COMP_DEF_OFFSET_PROCESS_PAYMENT: u32 = comp_def_offset("process_payment");
 const COMP_DEF_OFFSET_UPDATE_REFERRAL: u32 = comp_def_offset("update_referral_stats");
 const COMP_DEF_OFFSET_CHECK_THRESHOLD: u32 = comp_def_offset("check_volume_threshold");
 const COMP_DEF_OFFSET_REVEAL_COUNT: u32 = comp_def_offset("reveal_payment_count");
+const COMP_DEF_OFFSET_MERGE_DELTAS: u32 = comp_def_offset("merge_deltas");
+const COMP_DEF_OFFSET_VERIFY_OTC_TERMS: u32 = comp_def_offset("verify_otc_terms");
+const COMP_DEF_OFFSET_RUN_PAYROLL: u32 = comp_def_offset("run_payroll");
+const COMP_DEF_OFFSET_REFERRAL_REPORT: u32 = comp_def_offset("referral_report");
+const COMP_DEF_OFFSET_CHECK_RECENT_THRESHOLD: u32 =
+    comp_def_offset("check_recent_volume_threshold");
+const COMP_DEF_OFFSET_REVEAL_ASSET_VOLUME: u32 = comp_def_offset("reveal_asset_volume");
+const COMP_DEF_OFFSET_RESOLVE_SEALED_BIDS: u32 = comp_def_offset("resolve_sealed_bids");
+const COMP_DEF_OFFSET_SHARE_ESCROW_STATS: u32 = comp_def_offset("share_escrow_stats");
+const COMP_DEF_OFFSET_INIT_SPEND_CAP: u32 = comp_def_offset("init_spend_cap");
+const COMP_DEF_OFFSET_CHECK_SPEND_CAP: u32 = comp_def_offset("check_spend_cap");
+const COMP_DEF_OFFSET_COMPUTE_MATCH: u32 = comp_def_offset("compute_match");
+const COMP_DEF_OFFSET_PROCESS_REFUND: u32 = comp_def_offset("process_refund");
+const COMP_DEF_OFFSET_CHECK_REFUND_RATE: u32 = comp_def_offset("check_refund_rate");
+const COMP_DEF_OFFSET_PROVE_PAYMENT_AMOUNT: u32 = comp_def_offset("prove_payment_amount");
+const COMP_DEF_OFFSET_CALCULATE_FEES: u32 = comp_def_offset("calculate_fees");
 
 pub const USDC_MINT: Pubkey = pubkey!("4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU");
 pub const ZENZEC_MINT: Pubkey = pubkey!("JDt9rRGaieF6aN1cJkXFeUmsy7ZE4yY3CZb8tVMXVroS");
 
-declare_id!("EujENt3gyDVwqN2h3GXrpi2T6DdkGV5pafPAdXMRo3CM");
+/// Slots after which a stats computation that never called back may be
+/// cleared by the owner (~10 minutes at 400ms slots).
+pub const STATS_LOCK_TIMEOUT_SLOTS: u64 = 1_500;
 
-#[arcium_program]
-pub mod escrow_anonmesh {
-    use super::*;
+/// `EscrowAccount::jurisdiction` of escrows no jurisdiction pause applies to.
+pub const UNTAGGED_JURISDICTION: u16 = 0;
 
-    // Initialize computation definitions for encrypted instructions
-    pub fn init_escrow_stats_comp_def(ctx: Context<InitEscrowStatsCompDef>) -> Result<()> {
-        init_comp_def(ctx.accounts, 0, None, None)?;
-        Ok(())
-    }
+/// Ceiling on the share of each SOL payment routed to an escrow's insurance
+/// pool. It is carved out of the treasury fee, so payers pay no more.
+pub const MAX_INSURANCE_BPS: u16 = 50;
+/// Bond posted with an insurance claim: returned if the claim is approved,
+/// slashed into the pool if it is rejected.
+pub const INSURANCE_CLAIM_BOND_LAMPORTS: u64 = 50_000_000;
 
-    pub fn init_referral_stats_comp_def(ctx: Context<InitReferralStatsCompDef>) -> Result<()> {
-        init_comp_def(ctx.accounts, 0, None, None)?;
-        Ok(())
-    }
+/// Token-2022, whose confidential transfer extension moves encrypted amounts.
+pub const TOKEN_2022_PROGRAM_ID: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+/// Token-2022 instruction tags of `ConfidentialTransferExtension` and its
+/// `Transfer` sub-instruction.
+const TOKEN_2022_CONFIDENTIAL_TRANSFER_EXTENSION: u8 = 27;
+const CONFIDENTIAL_TRANSFER_TRANSFER: u8 = 7;
+/// Flat treasury fee on confidential token payments. The program never sees
+/// the amount, so the usual percentage split can't be taken.
+pub const CONFIDENTIAL_TOKEN_FEE_LAMPORTS: u64 = 1_000_000;
 
-    pub fn init_process_payment_comp_def(ctx: Context<InitProcessPaymentCompDef>) -> Result<()> {
-        init_comp_def(ctx.accounts, 0, None, None)?;
-        Ok(())
-    }
+/// Ceiling on the total referral + treasury fee a fee hook may charge on
+/// one payment.
+pub const MAX_FEE_HOOK_BPS: u16 = 300;
+/// Instruction discriminator the escrow calls fee hooks with: Anchor's
+/// `sha256("global:adjust_fees")[..8]`, so hooks can be plain Anchor
+/// programs exposing `adjust_fees(input: FeeHookInput)`.
+pub const FEE_HOOK_DISCRIMINATOR: [u8; 8] = [183, 191, 56, 60, 36, 222, 58, 4];
 
-    /// Initialize escrow with encrypted statistics tracking
-    pub fn initialize_escrow(
-        ctx: Context<InitializeEscrow>,
-        computation_offset: u64,
-        treasury_address: Pubkey,
-        nonce: u128,
-    ) -> Result<()> {
-        // Get the escrow key before borrowing
-        let escrow_key = ctx.accounts.escrow.key();
+/// Window over which a compute budget vault's crank rewards are capped.
+pub const CRANK_REWARD_PERIOD_SECS: i64 = 24 * 60 * 60;
 
-        let escrow = &mut ctx.accounts.escrow;
-        escrow.owner = ctx.accounts.owner.key();
-        escrow.total_fund_regulated = 0;
-        escrow.last_updated = Clock::get()?.unix_timestamp;
-        escrow.active = true;
-        escrow.treasury = treasury_address;
-        escrow.bump = ctx.bumps.escrow;
-        escrow.nonce = nonce;
-        escrow.encrypted_stats = [[0; 32]; 3]; // Store encrypted statistics
+/// Length of the period a sender's encrypted spend cap covers.
+pub const SPEND_CAP_PERIOD_SECS: i64 = 30 * 86_400;
 
-        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+/// Bits in the mask returned by `health_check`, one per failed invariant.
+pub const HEALTH_STATS_UNINITIALIZED: u32 = 1 << 0;
+pub const HEALTH_NONCE_REGRESSED: u32 = 1 << 1;
+pub const HEALTH_VAULT_SHORTFALL: u32 = 1 << 2;
+pub const HEALTH_STUCK_COMPUTATION: u32 = 1 << 3;
 
-        // Initialize encrypted statistics through MPC
-        let args = vec![Argument::PlaintextU128(nonce)];
+/// Deltas folded per `merge_deltas` computation; matches the circuit arity.
+pub const MAX_PENDING_DELTAS: usize = 8;
 
-        queue_computation(
-            ctx.accounts,
-            computation_offset,
-            args,
-            None,
-            vec![InitEscrowStatsCallback::callback_ix(&[CallbackAccount {
-                pubkey: escrow_key,
-                is_writable: true,
-            }])],
-            1,
-        )?;
+/// Recipients per payroll schedule; matches the `run_payroll` circuit arity.
+pub const MAX_PAYROLL_RECIPIENTS: usize = 8;
 
-        Ok(())
-    }
+/// Size of each allowlist on a `SpendingPolicy`.
+pub const MAX_POLICY_MINTS: usize = 4;
+pub const MAX_POLICY_RECIPIENTS: usize = 8;
 
-    #[arcium_callback(encrypted_ix = "init_escrow_stats")]
-    pub fn init_escrow_stats_callback(
-        ctx: Context<InitEscrowStatsCallback>,
-        output: ComputationOutputs<InitEscrowStatsOutput>,
-    ) -> Result<()> {
-        let o = match output {
-            ComputationOutputs::Success(InitEscrowStatsOutput { field_0 }) => field_0,
-            _ => return Err(EscrowError::AbortedComputation.into()),
-        };
+/// Signers a `VaultSigners` list can hold; approvals are tracked in a u16.
+pub const MAX_VAULT_SIGNERS: usize = 10;
 
-        ctx.accounts.escrow.encrypted_stats = o.ciphertexts;
-        ctx.accounts.escrow.nonce = o.nonce;
+/// Ceiling on a campaign's boosted referral share: the whole protocol fee.
+pub const MAX_CAMPAIGN_REFERRAL_BPS: u16 = 200;
 
-        Ok(())
+/// Ceiling on the referral share an invoice creator may negotiate; it is
+/// taken out of the amount the invoice recipient receives.
+pub const MAX_INVOICE_REFERRAL_BPS: u16 = 1_000;
+
+/// Guardians a `RecoveryConfig` can hold.
+pub const MAX_RECOVERY_GUARDIANS: usize = 8;
+/// Minimum time between a recovery being initiated and the owner rotating.
+pub const RECOVERY_TIMELOCK_SECS: i64 = 7 * 86_400;
+/// Veto window left to the owner after the guardian threshold is reached.
+pub const RECOVERY_CHALLENGE_SECS: i64 = 2 * 86_400;
+
+/// Entries kept in an escrow's `ActivityLog` before the oldest is overwritten.
+pub const ACTIVITY_LOG_CAPACITY: usize = 64;
+
+/// Owner of Pyth `PriceUpdateV2` accounts (the Pyth Solana receiver).
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+
+/// Bidders per sealed-bid auction; matches the `resolve_sealed_bids` arity.
+pub const MAX_AUCTION_BIDDERS: usize = 8;
+
+/// Winner index revealed when no bid met the reserve.
+pub const NO_AUCTION_WINNER: u8 = u8::MAX;
+
+/// Tranches per `Milestones` account.
+pub const MAX_MILESTONES: usize = 8;
+
+/// Tips may discount the treasury fee but never exceed the standard 1.4%.
+pub const MAX_TIP_TREASURY_FEE_BPS: u16 = 140;
+
+/// Standard treasury fee, charged until a `FeeConfig` tier lowers it.
+pub const DEFAULT_TREASURY_FEE_BPS: u16 = 140;
+
+/// Referral leg of every payment fee (0.6%).
+pub const REFERRAL_FEE_BPS: u16 = 60;
+/// Volume milestones a `FeeConfig` curve can hold.
+pub const MAX_FEE_TIERS: usize = 6;
+
+/// Vault payments a `Quarantine` queue can hold awaiting review.
+pub const MAX_QUARANTINED_PAYMENTS: usize = 16;
+/// Vault payments a settlement window can hold until it next opens.
+pub const MAX_DEFERRED_PAYMENTS: usize = 16;
+
+/// Linear vesting period for referral rewards when an escrow vests them.
+pub const REFERRAL_VESTING_SECS: i64 = 30 * 86_400;
+
+/// Fixed-point scale of `FeeConversionRate::usdc_per_unit`.
+pub const FEE_RATE_SCALE: u64 = 1_000_000_000;
+
+/// Circuits the `CircuitRegistry` can describe, and the longest name allowed.
+pub const MAX_REGISTERED_CIRCUITS: usize = 32;
+pub const MAX_CIRCUIT_NAME_LEN: usize = 32;
+
+/// Mints `send_payment_spl` can be used with.
+pub const MAX_ALLOWED_MINTS: usize = 32;
+
+/// Relayers that may submit sponsored payments at once.
+pub const MAX_RELAYERS: usize = 16;
+
+/// Evidence hashes the parties to a dispute can submit between them.
+pub const MAX_DISPUTE_EVIDENCE: usize = 8;
+
+/// Assets with their own encrypted volume counter, indexed as passed to the
+/// circuits. SOL volume is in lamports, the SPL assets in their base units.
+pub const STATS_ASSET_SOL: u8 = 0;
+pub const STATS_ASSET_USDC: u8 = 1;
+pub const STATS_ASSET_ZENZEC: u8 = 2;
+pub const STATS_ASSET_COUNT: usize = 3;
+
+/// Ciphertexts in `EscrowAccount::encrypted_stats`, one per `EscrowStats`
+/// field with `asset_volumes` flattened at the end.
+pub const ESCROW_STATS_FIELDS: usize = 6 + STATS_ASSET_COUNT;
+
+/// Confidential metrics an escrow can opt into at creation, as bits of
+/// `EscrowAccount::stats_dimensions`. Untracked metrics stay at an
+/// encrypted zero and can't be revealed or thresholded.
+///
+/// The bits choose what the MPC updates and what can be disclosed, not what
+/// is stored: `encrypted_stats` holds all `ESCROW_STATS_FIELDS` ciphertexts
+/// whatever the mask, so opting out saves no rent. The circuits take one
+/// fixed `EscrowStats` shape, read from the account at
+/// `EscrowAccount::ENCRYPTED_STATS_OFFSET` with a fixed length; a region
+/// sized by mask would need a circuit variant per mask and would shift
+/// every field after it.
+pub const STATS_DIMENSION_PAYMENTS: u8 = 1 << 0;
+/// `total_volume` and `recent_volume`
+pub const STATS_DIMENSION_VOLUME: u8 = 1 << 1;
+pub const STATS_DIMENSION_FEES: u8 = 1 << 2;
+/// Per-asset `asset_volumes`
+pub const STATS_DIMENSION_ASSETS: u8 = 1 << 3;
+/// `total_refunds` and `refund_volume`
+pub const STATS_DIMENSION_REFUNDS: u8 = 1 << 4;
+pub const STATS_DIMENSIONS_ALL: u8 = STATS_DIMENSION_PAYMENTS
+    | STATS_DIMENSION_VOLUME
+    | STATS_DIMENSION_FEES
+    | STATS_DIMENSION_ASSETS
+    | STATS_DIMENSION_REFUNDS;
+
+/// Domain tag for `payment_intent_hash`; the encoding lives in the shared
+/// `payment-intent` crate.
+pub const PAYMENT_INTENT_DOMAIN: &[u8] = payment_intent::DOMAIN;
+/// Domain tag for `counter_signature_message`.
+pub const COUNTER_SIGNATURE_DOMAIN: &[u8] = b"anon0mesh/counter-signature/v2";
+
+/// Payments folded into one `Archive` per `archive_payments` call.
+pub const MAX_ARCHIVE_BATCH: usize = 32;
+/// Payments younger than this cannot be archived, so refunds and disputes
+/// still find their `PaymentAccount`.
+pub const ARCHIVE_MIN_AGE_SECS: i64 = 90 * 86_400;
+/// Domain tags for archive Merkle leaves and inner nodes.
+pub const ARCHIVE_LEAF_DOMAIN: &[u8] = b"anon0mesh/archive-leaf/v1";
+pub const ARCHIVE_NODE_DOMAIN: &[u8] = b"anon0mesh/archive-node/v1";
+/// Domain tag for `stats_commitment`.
+pub const STATS_COMMITMENT_DOMAIN: &[u8] = b"anon0mesh/stats-commitment/v1";
+
+/// The encrypted recent volume halves once per elapsed half-life; after
+/// `RECENT_VOLUME_MAX_PERIODS` half-lives it is treated as fully decayed.
+pub const RECENT_VOLUME_HALF_LIFE_SECS: i64 = 86_400;
+pub const RECENT_VOLUME_MAX_PERIODS: u8 = 16;
+
+/// Every PDA seed prefix the program derives addresses from, with matching
+/// `find_*_address` helpers for clients. Account constraints and signer
+/// seeds use these constants rather than byte-string literals.
+pub mod seeds {
+    use anchor_lang::prelude::Pubkey;
+
+    pub const ESCROW: &[u8] = b"escrow";
+    pub const PROTOCOL_CONFIG: &[u8] = b"protocol_config";
+    pub const FEE_CONFIG: &[u8] = b"fee_config";
+    pub const FEE_RATE: &[u8] = b"fee_rate";
+    pub const CIRCUIT_REGISTRY: &[u8] = b"circuit_registry";
+    pub const REBATE_POOL: &[u8] = b"rebate_pool";
+    pub const ARCHIVE: &[u8] = b"archive";
+    pub const ACTIVITY_LOG: &[u8] = b"activity_log";
+    pub const ANALYTICS_OPERATOR: &[u8] = b"analytics_operator";
+    pub const VIEWER: &[u8] = b"viewer";
+    pub const COMPUTE_BUDGET: &[u8] = b"compute_budget";
+    pub const PENDING_DELTAS: &[u8] = b"pending_deltas";
+    pub const SPENDING_POLICY: &[u8] = b"spending_policy";
+    pub const PENDING_APPROVAL: &[u8] = b"pending_approval";
+    pub const SUB_ACCOUNT: &[u8] = b"sub_account";
+    pub const ESCROW_VAULT: &[u8] = b"escrow_vault";
+    pub const TREASURY_VAULT: &[u8] = b"treasury_vault";
+    pub const INSURANCE_POOL: &[u8] = b"insurance_pool";
+    pub const INSURANCE_CLAIM: &[u8] = b"insurance_claim";
+    pub const MATCHING_POOL: &[u8] = b"matching_pool";
+    pub const PAYMENT_MATCH: &[u8] = b"payment_match";
+    pub const FEE_VOUCHER: &[u8] = b"fee_voucher";
+    pub const FEE_WAIVER: &[u8] = b"fee_waiver";
+    pub const JURISDICTION_PAUSE: &[u8] = b"jurisdiction_pause";
+    pub const SETTLEMENT_WINDOW: &[u8] = b"settlement_window";
+    pub const VAULT_SIGNERS: &[u8] = b"vault_signers";
+    pub const WITHDRAWAL_PROPOSAL: &[u8] = b"withdrawal_proposal";
+    pub const RECOVERY: &[u8] = b"recovery";
+    pub const QUARANTINE: &[u8] = b"quarantine";
+    pub const CAMPAIGN: &[u8] = b"campaign";
+    pub const ESCROW_TEMPLATE: &[u8] = b"escrow_template";
+    pub const REFERRAL_STATS: &[u8] = b"referral_stats";
+    pub const REFERRAL_VESTING: &[u8] = b"referral_vesting";
+    pub const REFERRAL_USDC_LEDGER: &[u8] = b"referral_usdc_ledger";
+    pub const REFERRAL_LINK: &[u8] = b"referral_link";
+    pub const SPEND_CAP: &[u8] = b"spend_cap";
+    pub const PAYMENTS: &[u8] = b"payments";
+    pub const PAYMENT_SEQUENCE: &[u8] = b"payment_sequence";
+    pub const MINT_ALLOWLIST: &[u8] = b"mint_allowlist";
+    pub const RELAYER_REGISTRY: &[u8] = b"relayer_registry";
+    pub const VAULT_PAYMENTS: &[u8] = b"vault_payments";
+    pub const INVOICE: &[u8] = b"invoice";
+    pub const PAYROLL: &[u8] = b"payroll";
+    pub const PAYROLL_VAULT: &[u8] = b"payroll_vault";
+    pub const MILESTONES: &[u8] = b"milestones";
+    pub const MILESTONE_VAULT: &[u8] = b"milestone_vault";
+    pub const OTC_SWAP: &[u8] = b"otc_swap";
+    pub const OTC_VAULT_A: &[u8] = b"otc_vault_a";
+    pub const OTC_VAULT_B: &[u8] = b"otc_vault_b";
+    pub const AUCTION: &[u8] = b"auction";
+    pub const AUCTION_VAULT: &[u8] = b"auction_vault";
+    pub const HTLC: &[u8] = b"htlc";
+    pub const ESCROW_DEPOSIT: &[u8] = b"escrow_deposit";
+    pub const ESCROW_DEPOSIT_VAULT: &[u8] = b"escrow_deposit_vault";
+    pub const DISPUTE: &[u8] = b"dispute";
+    pub const PAYMENT_PROOF: &[u8] = b"payment_proof";
+    pub const PULL_MANDATE: &[u8] = b"pull_mandate";
+    pub const PULL_DELEGATE: &[u8] = b"pull_delegate";
+
+    /// Third seed of plaintext `PaymentAccount`s, keeping the sender's
+    /// sequence numbers apart from computation offsets under `PAYMENTS`.
+    pub const SEQUENCED_PAYMENT: &[u8] = b"seq";
+
+    /// Every prefix above; checked at compile time for duplicates so two
+    /// account types can never share an address space.
+    pub const ALL: &[&[u8]] = &[
+        ESCROW,
+        PROTOCOL_CONFIG,
+        FEE_CONFIG,
+        FEE_RATE,
+        CIRCUIT_REGISTRY,
+        REBATE_POOL,
+        ARCHIVE,
+        ACTIVITY_LOG,
+        ANALYTICS_OPERATOR,
+        VIEWER,
+        COMPUTE_BUDGET,
+        PENDING_DELTAS,
+        SPENDING_POLICY,
+        PENDING_APPROVAL,
+        SUB_ACCOUNT,
+        ESCROW_VAULT,
+        TREASURY_VAULT,
+        INSURANCE_POOL,
+        INSURANCE_CLAIM,
+        MATCHING_POOL,
+        PAYMENT_MATCH,
+        FEE_VOUCHER,
+        FEE_WAIVER,
+        JURISDICTION_PAUSE,
+        SETTLEMENT_WINDOW,
+        VAULT_SIGNERS,
+        WITHDRAWAL_PROPOSAL,
+        RECOVERY,
+        QUARANTINE,
+        CAMPAIGN,
+        ESCROW_TEMPLATE,
+        REFERRAL_STATS,
+        REFERRAL_VESTING,
+        REFERRAL_USDC_LEDGER,
+        REFERRAL_LINK,
+        SPEND_CAP,
+        PAYMENTS,
+        PAYMENT_SEQUENCE,
+        MINT_ALLOWLIST,
+        RELAYER_REGISTRY,
+        VAULT_PAYMENTS,
+        INVOICE,
+        PAYROLL,
+        PAYROLL_VAULT,
+        MILESTONES,
+        MILESTONE_VAULT,
+        OTC_SWAP,
+        OTC_VAULT_A,
+        OTC_VAULT_B,
+        AUCTION,
+        AUCTION_VAULT,
+        HTLC,
+        ESCROW_DEPOSIT,
+        ESCROW_DEPOSIT_VAULT,
+        DISPUTE,
+        PAYMENT_PROOF,
+        PULL_MANDATE,
+        PULL_DELEGATE,
+    ];
+
+    const _: () = {
+        let mut i = 0;
+        while i < ALL.len() {
+            assert!(ALL[i].len() <= anchor_lang::solana_program::pubkey::MAX_SEED_LEN);
+            let mut j = i + 1;
+            while j < ALL.len() {
+                assert!(!super::bytes_eq(ALL[i], ALL[j]), "duplicate PDA seed");
+                j += 1;
+            }
+            i += 1;
+        }
+    };
+
+    fn find(seeds: &[&[u8]]) -> (Pubkey, u8) {
+        Pubkey::find_program_address(seeds, &crate::ID)
     }
 
-    pub fn pause_escrow(ctx: Context<UpdateEscrowActive>) -> Result<()> {
-        let escrow = &mut ctx.accounts.escrow;
-        require!(escrow.active, EscrowError::AlreadyPaused);
-        escrow.active = false;
-        escrow.last_updated = Clock::get()?.unix_timestamp;
-        Ok(())
+    pub fn find_escrow_address(owner: &Pubkey) -> (Pubkey, u8) {
+        find(&[ESCROW, owner.as_ref()])
     }
 
-    pub fn resume_escrow(ctx: Context<UpdateEscrowActive>) -> Result<()> {
-        let escrow = &mut ctx.accounts.escrow;
-        require!(!escrow.active, EscrowError::AlreadyActive);
-        escrow.active = true;
-        escrow.last_updated = Clock::get()?.unix_timestamp;
-        Ok(())
+    pub fn find_protocol_config_address() -> (Pubkey, u8) {
+        find(&[PROTOCOL_CONFIG])
     }
 
-    pub fn update_treasury(ctx: Context<UpdateTreasury>, new_treasury: Pubkey) -> Result<()> {
-        let escrow = &mut ctx.accounts.escrow;
-        escrow.treasury = new_treasury;
-        escrow.last_updated = Clock::get()?.unix_timestamp;
-        Ok(())
+    pub fn find_fee_config_address() -> (Pubkey, u8) {
+        find(&[FEE_CONFIG])
     }
 
-    /// SOL payment with encrypted statistics tracking
-    pub fn send_payment_encrypted(
-        ctx: Context<SendPaymentSolEncrypted>,
-        computation_offset: u64,
-        referal: Pubkey,
-        amount: u64,
-        recipient: Pubkey,
-        payment_encryption_pubkey: [u8; 32],
-        payment_nonce: u128,
-        encrypted_amount: [u8; 32],
-    ) -> Result<()> {
-        // Get escrow key and nonce before mutable borrow
-        let escrow_key = ctx.accounts.escrow.key();
-        let escrow_nonce = ctx.accounts.escrow.nonce;
+    pub fn find_fee_rate_address(mint: &Pubkey) -> (Pubkey, u8) {
+        find(&[FEE_RATE, mint.as_ref()])
+    }
 
-        require!(ctx.accounts.escrow.active, EscrowError::EscrowPaused);
+    pub fn find_circuit_registry_address() -> (Pubkey, u8) {
+        find(&[CIRCUIT_REGISTRY])
+    }
 
-        let payment = &mut ctx.accounts.payment;
-        payment.sender = ctx.accounts.sender.key();
-        payment.recipient = recipient;
-        payment.referal = referal;
-        payment.amount = amount; // Public amount for transfer
-        payment.timestamp = Clock::get()?.unix_timestamp;
-        payment.asset_mint = Pubkey::default();
+    pub fn find_rebate_pool_address() -> (Pubkey, u8) {
+        find(&[REBATE_POOL])
+    }
 
-        // Calculate fees
-        let referral_fee = amount.checked_mul(6).ok_or(ProgramError::InvalidArgument)? / 1000;
-        let treasury_fee = amount
-            .checked_mul(14)
-            .ok_or(ProgramError::InvalidArgument)?
-            / 1000;
-        let fees = referral_fee
-            .checked_add(treasury_fee)
-            .ok_or(ProgramError::InvalidArgument)?;
-        let net_amount = amount
-            .checked_sub(fees)
-            .ok_or(ProgramError::InvalidArgument)?;
+    pub fn find_archive_address(archive_id: u64) -> (Pubkey, u8) {
+        find(&[ARCHIVE, &archive_id.to_le_bytes()])
+    }
 
-        payment.referal_reward = referral_fee;
-        payment.treasury_reward = treasury_fee;
+    pub fn find_activity_log_address(escrow: &Pubkey) -> (Pubkey, u8) {
+        find(&[ACTIVITY_LOG, escrow.as_ref()])
+    }
 
-        // Perform actual transfers
-        let from = ctx.accounts.sender.to_account_info();
-        let to_recipient = ctx.accounts.recipient.to_account_info();
-        let to_treasury = ctx.accounts.treasury.to_account_info();
-        let to_referral = ctx.accounts.referral.to_account_info();
-        let system_program = ctx.accounts.system_program.to_account_info();
+    pub fn find_analytics_operator_address(escrow: &Pubkey, operator: &Pubkey) -> (Pubkey, u8) {
+        find(&[ANALYTICS_OPERATOR, escrow.as_ref(), operator.as_ref()])
+    }
 
-        // Transfer to recipient
-        let cpi_ctx_recipient = CpiContext::new(
-            system_program.clone(),
-            anchor_lang::system_program::Transfer {
-                from: from.clone(),
-                to: to_recipient,
-            },
-        );
-        anchor_lang::system_program::transfer(cpi_ctx_recipient, net_amount)?;
+    pub fn find_viewer_address(escrow: &Pubkey, viewer: &Pubkey) -> (Pubkey, u8) {
+        find(&[VIEWER, escrow.as_ref(), viewer.as_ref()])
+    }
 
-        // Transfer to treasury
-        let cpi_ctx_treasury = CpiContext::new(
-            system_program.clone(),
-            anchor_lang::system_program::Transfer {
-                from: from.clone(),
-                to: to_treasury,
-            },
-        );
-        anchor_lang::system_program::transfer(cpi_ctx_treasury, treasury_fee)?;
+    pub fn find_compute_budget_address(escrow: &Pubkey) -> (Pubkey, u8) {
+        find(&[COMPUTE_BUDGET, escrow.as_ref()])
+    }
 
-        let cpi_ctx_referral = CpiContext::new(
-            system_program,
-            anchor_lang::system_program::Transfer {
-                from,
-                to: to_referral,
-            },
-        );
-        anchor_lang::system_program::transfer(cpi_ctx_referral, referral_fee)?;
+    pub fn find_pending_deltas_address(escrow: &Pubkey) -> (Pubkey, u8) {
+        find(&[PENDING_DELTAS, escrow.as_ref()])
+    }
 
-        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+    pub fn find_spending_policy_address(escrow: &Pubkey) -> (Pubkey, u8) {
+        find(&[SPENDING_POLICY, escrow.as_ref()])
+    }
 
-        let args = vec![
-            Argument::ArcisPubkey(payment_encryption_pubkey),
-            Argument::PlaintextU128(payment_nonce),
-            Argument::EncryptedU64(encrypted_amount),
-            Argument::PlaintextBool(true),
-            Argument::PlaintextU128(escrow_nonce),
-            Argument::Account(escrow_key, 8 + 1, 32 * 3),
-        ];
-
-        queue_computation(
-            ctx.accounts,
-            computation_offset,
-            args,
-            None,
-            vec![ProcessPaymentCallback::callback_ix(&[CallbackAccount {
-                pubkey: escrow_key,
-                is_writable: true,
-            }])],
-            1,
-        )?;
+    pub fn find_pending_approval_address(escrow: &Pubkey, approval_id: u64) -> (Pubkey, u8) {
+        find(&[PENDING_APPROVAL, escrow.as_ref(), &approval_id.to_le_bytes()])
+    }
 
-        ctx.accounts.escrow.total_fund_regulated = ctx
-            .accounts
-            .escrow
-            .total_fund_regulated
-            .checked_add(amount)
-            .ok_or(ProgramError::InvalidArgument)?;
+    pub fn find_sub_account_address(escrow: &Pubkey, seller: &Pubkey) -> (Pubkey, u8) {
+        find(&[SUB_ACCOUNT, escrow.as_ref(), seller.as_ref()])
+    }
 
-        Ok(())
+    pub fn find_escrow_vault_address(escrow: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+        find(&[ESCROW_VAULT, escrow.as_ref(), mint.as_ref()])
     }
 
-    #[arcium_callback(encrypted_ix = "process_payment")]
-    pub fn process_payment_callback(
-        ctx: Context<ProcessPaymentCallback>,
-        output: ComputationOutputs<ProcessPaymentOutput>,
-    ) -> Result<()> {
-        let o = match output {
-            ComputationOutputs::Success(ProcessPaymentOutput { field_0 }) => field_0,
-            _ => return Err(EscrowError::AbortedComputation.into()),
-        };
+    pub fn find_treasury_vault_address(escrow: &Pubkey) -> (Pubkey, u8) {
+        find(&[TREASURY_VAULT, escrow.as_ref()])
+    }
 
-        ctx.accounts.escrow.encrypted_stats = o.ciphertexts;
-        ctx.accounts.escrow.nonce = o.nonce;
+    pub fn find_insurance_pool_address(escrow: &Pubkey) -> (Pubkey, u8) {
+        find(&[INSURANCE_POOL, escrow.as_ref()])
+    }
 
-        let clock = Clock::get()?;
-        emit!(ConfidentialPaymentEvent {
-            timestamp: clock.unix_timestamp,
-            sender: ctx.accounts.escrow.owner, // Don't reveal actual sender
-        });
+    pub fn find_insurance_claim_address(pool: &Pubkey, payment: &Pubkey) -> (Pubkey, u8) {
+        find(&[INSURANCE_CLAIM, pool.as_ref(), payment.as_ref()])
+    }
 
-        Ok(())
+    pub fn find_matching_pool_address(escrow: &Pubkey) -> (Pubkey, u8) {
+        find(&[MATCHING_POOL, escrow.as_ref()])
     }
 
-    pub fn check_volume_threshold(
-        ctx: Context<CheckVolumeThreshold>,
-        computation_offset: u64,
-        threshold: u64,
-    ) -> Result<()> {
-        require!(
-            ctx.accounts.authority.key() == ctx.accounts.escrow.owner,
-            EscrowError::InvalidAuthority
-        );
+    pub fn find_payment_match_address(pool: &Pubkey, payment: &Pubkey) -> (Pubkey, u8) {
+        find(&[PAYMENT_MATCH, pool.as_ref(), payment.as_ref()])
+    }
 
-        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+    pub fn find_fee_voucher_address(voucher_mint: &Pubkey) -> (Pubkey, u8) {
+        find(&[FEE_VOUCHER, voucher_mint.as_ref()])
+    }
 
-        let args = vec![
-            Argument::PlaintextU128(ctx.accounts.escrow.nonce),
-            Argument::Account(ctx.accounts.escrow.key(), 8 + 1, 32 * 3),
-            Argument::PlaintextU64(threshold),
-        ];
+    pub fn find_fee_waiver_address(escrow: &Pubkey, sender: &Pubkey) -> (Pubkey, u8) {
+        find(&[FEE_WAIVER, escrow.as_ref(), sender.as_ref()])
+    }
 
-        queue_computation(
-            ctx.accounts,
-            computation_offset,
-            args,
-            None,
-            vec![CheckVolumeThresholdCallback::callback_ix(&[])],
-            1,
-        )?;
+    pub fn find_jurisdiction_pause_address(jurisdiction: u16) -> (Pubkey, u8) {
+        find(&[JURISDICTION_PAUSE, &jurisdiction.to_le_bytes()])
+    }
 
-        Ok(())
+    pub fn find_settlement_window_address(escrow: &Pubkey) -> (Pubkey, u8) {
+        find(&[SETTLEMENT_WINDOW, escrow.as_ref()])
     }
 
-    #[arcium_callback(encrypted_ix = "check_volume_threshold")]
-    pub fn check_volume_threshold_callback(
-        ctx: Context<CheckVolumeThresholdCallback>,
-        output: ComputationOutputs<CheckVolumeThresholdOutput>,
-    ) -> Result<()> {
-        let result = match output {
-            ComputationOutputs::Success(CheckVolumeThresholdOutput { field_0 }) => field_0,
-            _ => return Err(EscrowError::AbortedComputation.into()),
-        };
+    pub fn find_vault_signers_address(escrow: &Pubkey) -> (Pubkey, u8) {
+        find(&[VAULT_SIGNERS, escrow.as_ref()])
+    }
 
-        emit!(ThresholdCheckEvent {
-            meets_threshold: result,
-            timestamp: Clock::get()?.unix_timestamp,
-        });
+    pub fn find_withdrawal_proposal_address(escrow: &Pubkey, proposal_id: u64) -> (Pubkey, u8) {
+        find(&[WITHDRAWAL_PROPOSAL, escrow.as_ref(), &proposal_id.to_le_bytes()])
+    }
 
-        Ok(())
+    pub fn find_recovery_address(escrow: &Pubkey) -> (Pubkey, u8) {
+        find(&[RECOVERY, escrow.as_ref()])
     }
 
-    pub fn reveal_payment_count(
-        ctx: Context<RevealPaymentCount>,
-        computation_offset: u64,
-    ) -> Result<()> {
-        require!(
-            ctx.accounts.authority.key() == ctx.accounts.escrow.owner,
-            EscrowError::InvalidAuthority
-        );
+    pub fn find_quarantine_address(escrow: &Pubkey) -> (Pubkey, u8) {
+        find(&[QUARANTINE, escrow.as_ref()])
+    }
 
-        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+    pub fn find_campaign_address(escrow: &Pubkey, campaign_id: u64) -> (Pubkey, u8) {
+        find(&[CAMPAIGN, escrow.as_ref(), &campaign_id.to_le_bytes()])
+    }
 
-        let args = vec![
-            Argument::PlaintextU128(ctx.accounts.escrow.nonce),
-            Argument::Account(ctx.accounts.escrow.key(), 8 + 1, 32 * 3),
-        ];
+    pub fn find_escrow_template_address(creator: &Pubkey, template_id: u64) -> (Pubkey, u8) {
+        find(&[ESCROW_TEMPLATE, creator.as_ref(), &template_id.to_le_bytes()])
+    }
 
-        queue_computation(
-            ctx.accounts,
-            computation_offset,
-            args,
-            None,
-            vec![RevealPaymentCountCallback::callback_ix(&[])],
-            1,
-        )?;
+    pub fn find_referral_stats_address(referrer: &Pubkey) -> (Pubkey, u8) {
+        find(&[REFERRAL_STATS, referrer.as_ref()])
+    }
 
-        Ok(())
+    pub fn find_referral_vesting_address(escrow: &Pubkey, referrer: &Pubkey) -> (Pubkey, u8) {
+        find(&[REFERRAL_VESTING, escrow.as_ref(), referrer.as_ref()])
     }
 
-    #[arcium_callback(encrypted_ix = "reveal_payment_count")]
-    pub fn reveal_payment_count_callback(
-        ctx: Context<RevealPaymentCountCallback>,
-        output: ComputationOutputs<RevealPaymentCountOutput>,
-    ) -> Result<()> {
-        let count = match output {
-            ComputationOutputs::Success(RevealPaymentCountOutput { field_0 }) => field_0,
-            _ => return Err(EscrowError::AbortedComputation.into()),
-        };
+    pub fn find_referral_usdc_ledger_address(escrow: &Pubkey, referrer: &Pubkey) -> (Pubkey, u8) {
+        find(&[REFERRAL_USDC_LEDGER, escrow.as_ref(), referrer.as_ref()])
+    }
 
-        emit!(PaymentCountEvent {
-            total_payments: count,
-            timestamp: Clock::get()?.unix_timestamp,
-        });
+    /// `scope` is an escrow, or the protocol config for protocol-wide links.
+    pub fn find_referral_link_address(scope: &Pubkey, referee: &Pubkey) -> (Pubkey, u8) {
+        find(&[REFERRAL_LINK, scope.as_ref(), referee.as_ref()])
+    }
 
-        Ok(())
+    pub fn find_spend_cap_address(sender: &Pubkey) -> (Pubkey, u8) {
+        find(&[SPEND_CAP, sender.as_ref()])
     }
 
-    pub fn send_payment(
-        ctx: Context<SendPaymentSol>,
-        referal: Pubkey,
-        amount: u64,
-        recipient: Pubkey,
-    ) -> Result<()> {
-        let payment = &mut ctx.accounts.payment;
-        let escrow = &mut ctx.accounts.escrow;
-        require!(escrow.active, EscrowError::EscrowPaused);
+    /// `tag` is the computation offset or payment nonce in little-endian.
+    pub fn find_payment_address(sender: &Pubkey, tag: &[u8]) -> (Pubkey, u8) {
+        find(&[PAYMENTS, sender.as_ref(), tag])
+    }
 
-        payment.sender = ctx.accounts.sender.key();
-        payment.recipient = recipient;
-        payment.referal = referal;
-        payment.amount = amount;
-        payment.timestamp = Clock::get()?.unix_timestamp;
-        payment.referal_reward = amount.checked_mul(6).ok_or(ProgramError::InvalidArgument)? / 1000;
-        payment.treasury_reward = amount
-            .checked_mul(14)
-            .ok_or(ProgramError::InvalidArgument)?
-            / 1000;
+    pub fn find_payment_sequence_address(sender: &Pubkey) -> (Pubkey, u8) {
+        find(&[PAYMENT_SEQUENCE, sender.as_ref()])
+    }
 
-        let fees = payment
-            .referal_reward
-            .checked_add(payment.treasury_reward)
-            .ok_or(ProgramError::InvalidArgument)?;
-        let transferable_amount = amount
-            .checked_sub(fees)
-            .ok_or(ProgramError::InvalidArgument)?;
-        payment.asset_mint = Pubkey::default();
+    /// Plaintext SOL, USDC and ZenZEC payments, numbered per sender.
+    pub fn find_sequenced_payment_address(sender: &Pubkey, sequence: u64) -> (Pubkey, u8) {
+        find(&[PAYMENTS, sender.as_ref(), SEQUENCED_PAYMENT, &sequence.to_le_bytes()])
+    }
 
-        let from = ctx.accounts.sender.to_account_info();
-        let to_recipient = ctx.accounts.recipient.to_account_info();
-        let to_treasury = ctx.accounts.treasury.to_account_info();
-        let to_referral = ctx.accounts.referral.to_account_info();
-        let system_program = ctx.accounts.system_program.to_account_info();
+    /// `send_payment_spl` payments, numbered per sender across every mint.
+    pub fn find_spl_payment_address(sender: &Pubkey, mint: &Pubkey, sequence: u64) -> (Pubkey, u8) {
+        find(&[PAYMENTS, sender.as_ref(), mint.as_ref(), &sequence.to_le_bytes()])
+    }
 
-        let cpi_ctx_recipient = CpiContext::new(
-            system_program.clone(),
-            anchor_lang::system_program::Transfer {
-                from: from.clone(),
-                to: to_recipient,
-            },
-        );
-        anchor_lang::system_program::transfer(cpi_ctx_recipient, transferable_amount)?;
+    pub fn find_mint_allowlist_address() -> (Pubkey, u8) {
+        find(&[MINT_ALLOWLIST])
+    }
 
-        let cpi_ctx_treasury = CpiContext::new(
-            system_program.clone(),
-            anchor_lang::system_program::Transfer {
-                from: from.clone(),
-                to: to_treasury,
-            },
-        );
-        anchor_lang::system_program::transfer(cpi_ctx_treasury, payment.treasury_reward)?;
+    pub fn find_relayer_registry_address() -> (Pubkey, u8) {
+        find(&[RELAYER_REGISTRY])
+    }
 
-        let cpi_ctx_referral = CpiContext::new(
-            system_program,
-            anchor_lang::system_program::Transfer {
-                from,
-                to: to_referral,
-            },
-        );
-        anchor_lang::system_program::transfer(cpi_ctx_referral, payment.referal_reward)?;
+    pub fn find_vault_payment_address(escrow: &Pubkey, payment_id: u64) -> (Pubkey, u8) {
+        find(&[VAULT_PAYMENTS, escrow.as_ref(), &payment_id.to_le_bytes()])
+    }
 
-        escrow.total_fund_regulated = escrow
-            .total_fund_regulated
-            .checked_add(amount)
-            .ok_or(ProgramError::InvalidArgument)?;
+    pub fn find_invoice_address(escrow: &Pubkey, invoice_id: u64) -> (Pubkey, u8) {
+        find(&[INVOICE, escrow.as_ref(), &invoice_id.to_le_bytes()])
+    }
 
-        Ok(())
+    pub fn find_payroll_address(escrow: &Pubkey, schedule_id: u64) -> (Pubkey, u8) {
+        find(&[PAYROLL, escrow.as_ref(), &schedule_id.to_le_bytes()])
     }
 
-    pub fn send_payment_usdc(
-        ctx: Context<SendPaymentUsdc>,
-        referal: Pubkey,
-        amount: u64,
-        recipient: Pubkey,
-    ) -> Result<()> {
-        let payment = &mut ctx.accounts.payment;
-        let escrow = &mut ctx.accounts.escrow;
-        require!(escrow.active, EscrowError::EscrowPaused);
+    pub fn find_payroll_vault_address(payroll_schedule: &Pubkey) -> (Pubkey, u8) {
+        find(&[PAYROLL_VAULT, payroll_schedule.as_ref()])
+    }
 
-        // Update payment details
-        payment.sender = ctx.accounts.sender.key();
-        payment.recipient = recipient;
-        payment.referal = referal;
-        payment.amount = amount;
-        payment.timestamp = Clock::get()?.unix_timestamp;
-        payment.referal_reward = amount.checked_mul(6).ok_or(ProgramError::InvalidArgument)? / 1000;
-        payment.treasury_reward = amount
-            .checked_mul(14)
-            .ok_or(ProgramError::InvalidArgument)?
-            / 1000;
-        payment.asset_mint = ctx.accounts.mint.key();
+    pub fn find_milestones_address(payer: &Pubkey, milestones_id: u64) -> (Pubkey, u8) {
+        find(&[MILESTONES, payer.as_ref(), &milestones_id.to_le_bytes()])
+    }
 
-        // Calculate transfer amounts
-        let fees = payment
-            .referal_reward
-            .checked_add(payment.treasury_reward)
-            .ok_or(ProgramError::InvalidArgument)?;
-        let transferable_amount = amount
-            .checked_sub(fees)
-            .ok_or(ProgramError::InvalidArgument)?;
+    pub fn find_milestone_vault_address(milestones: &Pubkey) -> (Pubkey, u8) {
+        find(&[MILESTONE_VAULT, milestones.as_ref()])
+    }
 
-        // Get token program and authority
-        let token_program = ctx.accounts.token_program.to_account_info();
-        let authority = ctx.accounts.sender.to_account_info();
+    pub fn find_otc_swap_address(maker: &Pubkey, swap_id: u64) -> (Pubkey, u8) {
+        find(&[OTC_SWAP, maker.as_ref(), &swap_id.to_le_bytes()])
+    }
 
-        // Transfer to recipient
-        let cpi_recipient = CpiContext::new(
-            token_program.clone(),
-            token_instruction::Transfer {
-                from: ctx.accounts.sender_token_account.to_account_info(),
-                to: ctx.accounts.recipient_token_account.to_account_info(),
-                authority: authority.clone(),
-            },
-        );
-        token_instruction::transfer(cpi_recipient, transferable_amount)?;
+    pub fn find_otc_vault_a_address(otc_swap: &Pubkey) -> (Pubkey, u8) {
+        find(&[OTC_VAULT_A, otc_swap.as_ref()])
+    }
 
-        // Transfer to treasury
-        let cpi_treasury = CpiContext::new(
-            token_program.clone(),
-            token_instruction::Transfer {
-                from: ctx.accounts.sender_token_account.to_account_info(),
-                to: ctx.accounts.treasury_token_account.to_account_info(),
-                authority: authority.clone(),
-            },
-        );
-        token_instruction::transfer(cpi_treasury, payment.treasury_reward)?;
+    pub fn find_otc_vault_b_address(otc_swap: &Pubkey) -> (Pubkey, u8) {
+        find(&[OTC_VAULT_B, otc_swap.as_ref()])
+    }
 
-        // Transfer to referral
-        let cpi_referral = CpiContext::new(
-            token_program.clone(),
-            token_instruction::Transfer {
-                from: ctx.accounts.sender_token_account.to_account_info(),
-                to: ctx.accounts.referral_token_account.to_account_info(),
-                authority,
-            },
-        );
-        token_instruction::transfer(cpi_referral, payment.referal_reward)?;
+    pub fn find_auction_address(seller: &Pubkey, auction_id: u64) -> (Pubkey, u8) {
+        find(&[AUCTION, seller.as_ref(), &auction_id.to_le_bytes()])
+    }
 
-        // Update escrow stats
-        escrow.total_fund_regulated = escrow
-            .total_fund_regulated
-            .checked_add(amount)
-            .ok_or(ProgramError::InvalidArgument)?;
+    pub fn find_auction_vault_address(auction: &Pubkey) -> (Pubkey, u8) {
+        find(&[AUCTION_VAULT, auction.as_ref()])
+    }
 
-        // Emit event
-        emit!(ConfidentialPaymentEvent {
-            sender: payment.sender,
-            recipient: payment.recipient,
-            amount,
-            timestamp: payment.timestamp,
-            asset_mint: payment.asset_mint,
-        });
+    pub fn find_htlc_address(sender: &Pubkey, htlc_id: u64) -> (Pubkey, u8) {
+        find(&[HTLC, sender.as_ref(), &htlc_id.to_le_bytes()])
+    }
 
-        Ok(())
+    pub fn find_escrow_deposit_address(depositor: &Pubkey, deposit_id: u64) -> (Pubkey, u8) {
+        find(&[ESCROW_DEPOSIT, depositor.as_ref(), &deposit_id.to_le_bytes()])
     }
 
-    pub fn send_payment_zenzec(
-        ctx: Context<SendPaymentZenZec>,
-        referal: Pubkey,
-        amount: u64,
-        recipient: Pubkey,
-    ) -> Result<()> {
-        let payment = &mut ctx.accounts.payment;
-        let escrow = &mut ctx.accounts.escrow;
-        require!(escrow.active, EscrowError::EscrowPaused);
+    pub fn find_escrow_deposit_vault_address(deposit: &Pubkey) -> (Pubkey, u8) {
+        find(&[ESCROW_DEPOSIT_VAULT, deposit.as_ref()])
+    }
 
-        // Update payment details
-        payment.sender = ctx.accounts.sender.key();
-        payment.recipient = recipient;
-        payment.referal = referal;
-        payment.amount = amount;
-        payment.timestamp = Clock::get()?.unix_timestamp;
-        payment.referal_reward = amount.checked_mul(6).ok_or(ProgramError::InvalidArgument)? / 1000;
-        payment.treasury_reward = amount
-            .checked_mul(14)
-            .ok_or(ProgramError::InvalidArgument)?
-            / 1000;
-        payment.asset_mint = ctx.accounts.mint.key();
+    pub fn find_dispute_address(deposit: &Pubkey) -> (Pubkey, u8) {
+        find(&[DISPUTE, deposit.as_ref()])
+    }
 
-        // Calculate transfer amounts
-        let fees = payment
-            .referal_reward
-            .checked_add(payment.treasury_reward)
-            .ok_or(ProgramError::InvalidArgument)?;
-        let transferable_amount = amount
-            .checked_sub(fees)
-            .ok_or(ProgramError::InvalidArgument)?;
+    pub fn find_payment_proof_address(
+        payment: &Pubkey,
+        bucket_min: u64,
+        bucket_max: u64,
+    ) -> (Pubkey, u8) {
+        find(&[
+            PAYMENT_PROOF,
+            payment.as_ref(),
+            &bucket_min.to_le_bytes(),
+            &bucket_max.to_le_bytes(),
+        ])
+    }
 
-        // Get token program and authority
-        let token_program = ctx.accounts.token_program.to_account_info();
-        let authority = ctx.accounts.sender.to_account_info();
+    pub fn find_pull_mandate_address(payer_token_account: &Pubkey) -> (Pubkey, u8) {
+        find(&[PULL_MANDATE, payer_token_account.as_ref()])
+    }
 
-        // Transfer to recipient
-        let cpi_recipient = CpiContext::new(
-            token_program.clone(),
-            token_instruction::Transfer {
-                from: ctx.accounts.sender_token_account.to_account_info(),
-                to: ctx.accounts.recipient_token_account.to_account_info(),
-                authority: authority.clone(),
-            },
-        );
-        token_instruction::transfer(cpi_recipient, transferable_amount)?;
+    pub fn find_pull_delegate_address(mandate: &Pubkey) -> (Pubkey, u8) {
+        find(&[PULL_DELEGATE, mandate.as_ref()])
+    }
+}
 
-        // Transfer to treasury
-        let cpi_treasury = CpiContext::new(
-            token_program.clone(),
-            token_instruction::Transfer {
-                from: ctx.accounts.sender_token_account.to_account_info(),
-                to: ctx.accounts.treasury_token_account.to_account_info(),
-                authority: authority.clone(),
-            },
-        );
-        token_instruction::transfer(cpi_treasury, payment.treasury_reward)?;
+/// Remaining-accounts convention for instructions paying a variable number
+/// of recipients. Accounts come in ordered triples, one per recipient:
+///
+/// 1. the recipient wallet, writable;
+/// 2. the recipient's token account for the instruction's mint, writable,
+///    or the recipient wallet again on SOL paths;
+/// 3. the referrer credited for this recipient, writable, or the program id
+///    when there is none (Anchor's placeholder for an absent account).
+pub mod accounts_util {
+    use super::{EscrowError, Pack, SplAccount};
+    use anchor_lang::prelude::*;
 
-        // Transfer to referral
-        let cpi_referral = CpiContext::new(
-            token_program.clone(),
-            token_instruction::Transfer {
-                from: ctx.accounts.sender_token_account.to_account_info(),
-                to: ctx.accounts.referral_token_account.to_account_info(),
-                authority,
-            },
-        );
-        token_instruction::transfer(cpi_referral, payment.referal_reward)?;
+    pub const RECIPIENT_ACCOUNTS_STRIDE: usize = 3;
 
-        // Update escrow stats
-        escrow.total_fund_regulated = escrow
-            .total_fund_regulated
-            .checked_add(amount)
-            .ok_or(ProgramError::InvalidArgument)?;
+    pub struct RecipientAccounts<'a, 'info> {
+        pub recipient: &'a AccountInfo<'info>,
+        pub token_account: &'a AccountInfo<'info>,
+        pub referral: Option<&'a AccountInfo<'info>>,
+    }
 
-        // Emit event
-        emit!(ConfidentialPaymentEvent {
-            sender: payment.sender,
-            recipient: payment.recipient,
-            amount,
-            timestamp: payment.timestamp,
-            asset_mint: payment.asset_mint,
-        });
+    impl RecipientAccounts<'_, '_> {
+        /// Check the token account is an SPL account of `mint` owned by the
+        /// recipient.
+        pub fn verify_token_account(&self, mint: &Pubkey) -> Result<()> {
+            require_keys_eq!(
+                *self.token_account.owner,
+                anchor_spl::token::ID,
+                EscrowError::InvalidRecipientAccounts
+            );
+            let data = self.token_account.try_borrow_data()?;
+            let account = SplAccount::unpack(&data)?;
+            require_keys_eq!(account.mint, *mint, EscrowError::InvalidRecipientAccounts);
+            require_keys_eq!(
+                account.owner,
+                self.recipient.key(),
+                EscrowError::InvalidRecipientAccounts
+            );
+            Ok(())
+        }
 
-        Ok(())
+        /// SOL paths pass the wallet in both of the first two slots.
+        pub fn verify_sol(&self) -> Result<()> {
+            require_keys_eq!(
+                self.token_account.key(),
+                self.recipient.key(),
+                EscrowError::InvalidRecipientAccounts
+            );
+            Ok(())
+        }
     }
-}
 
-#[queue_computation_accounts("init_escrow_stats", owner)]
-#[derive(Accounts)]
-#[instruction(computation_offset: u64)]
-pub struct InitializeEscrow<'info> {
-    #[account(mut)]
-    pub owner: Signer<'info>,
+    /// Split `remaining` into exactly `expected` recipient triples. Every
+    /// recipient and token account must be writable, and so must a present
+    /// referral.
+    pub fn parse_recipient_accounts<'a, 'info>(
+        remaining: &'a [AccountInfo<'info>],
+        expected: usize,
+    ) -> Result<Vec<RecipientAccounts<'a, 'info>>> {
+        require!(
+            expected > 0 && remaining.len() == expected * RECIPIENT_ACCOUNTS_STRIDE,
+            EscrowError::InvalidRecipientAccounts
+        );
 
-    #[account(
-        init_if_needed,
-        space = 9,
-        payer = owner,
-        seeds = [&SIGN_PDA_SEED],
-        bump,
-        address = derive_sign_pda!(),
-    )]
-    pub sign_pda_account: Account<'info, SignerAccount>,
+        remaining
+            .chunks_exact(RECIPIENT_ACCOUNTS_STRIDE)
+            .map(|triple| {
+                let referral = (triple[2].key() != crate::ID).then_some(&triple[2]);
+                require!(
+                    triple[0].is_writable
+                        && triple[1].is_writable
+                        && referral.is_none_or(|referral| referral.is_writable),
+                    EscrowError::InvalidRecipientAccounts
+                );
+                Ok(RecipientAccounts {
+                    recipient: &triple[0],
+                    token_account: &triple[1],
+                    referral,
+                })
+            })
+            .collect()
+    }
+}
 
-    #[account(
-        address = derive_mxe_pda!()
-    )]
-    pub mxe_account: Account<'info, MXEAccount>,
+declare_id!("EujENt3gyDVwqN2h3GXrpi2T6DdkGV5pafPAdXMRo3CM");
 
-    #[account(
-        mut,
-        address = derive_mempool_pda!()
-    )]
-    /// CHECK: mempool_account
-    pub mempool_account: UncheckedAccount<'info>,
+#[arcium_program]
+pub mod escrow_anonmesh {
+    use super::*;
 
-    #[account(
-        mut,
-        address = derive_execpool_pda!()
-    )]
-    /// CHECK: executing_pool
-    pub executing_pool: UncheckedAccount<'info>,
+    // Initialize computation definitions for encrypted instructions
+    pub fn init_escrow_stats_comp_def(ctx: Context<InitEscrowStatsCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, 0, None, None)?;
+        Ok(())
+    }
 
-    #[account(
-        mut,
-        address = derive_comp_pda!(computation_offset)
-    )]
-    /// CHECK: computation_account
-    pub computation_account: UncheckedAccount<'info>,
+    pub fn init_referral_stats_comp_def(ctx: Context<InitReferralStatsCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, 0, None, None)?;
+        Ok(())
+    }
 
-    #[account(
-        address = derive_comp_def_pda!(COMP_DEF_OFFSET_INIT_ESCROW_STATS)
-    )]
-    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+    pub fn init_process_payment_comp_def(ctx: Context<InitProcessPaymentCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, 0, None, None)?;
+        Ok(())
+    }
 
-    #[account(
-        mut,
-        address = derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet)
-    )]
-    pub cluster_account: Account<'info, Cluster>,
+    pub fn init_update_referral_stats_comp_def(
+        ctx: Context<InitUpdateReferralStatsCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, 0, None, None)?;
+        Ok(())
+    }
 
-    #[account(
-        mut,
-        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    pub fn init_check_volume_threshold_comp_def(
+        ctx: Context<InitCheckVolumeThresholdCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, 0, None, None)?;
+        Ok(())
+    }
+
+    pub fn init_reveal_payment_count_comp_def(
+        ctx: Context<InitRevealPaymentCountCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Pin the Arcium clusters computations are queued on. Leaving both unset
+    /// falls back to the cluster recorded on the MXE account.
+    pub fn set_clusters(
+        ctx: Context<UpdateProtocolConfig>,
+        primary_cluster: Option<Pubkey>,
+        secondary_cluster: Option<Pubkey>,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.protocol_config;
+        config.primary_cluster = primary_cluster;
+        config.secondary_cluster = secondary_cluster;
+        if secondary_cluster.is_none() {
+            config.use_secondary_cluster = false;
+        }
+        Ok(())
+    }
+
+    pub fn set_max_app_fee_bps(
+        ctx: Context<UpdateProtocolConfig>,
+        max_app_fee_bps: u16,
+    ) -> Result<()> {
+        require!(max_app_fee_bps <= 10_000, EscrowError::InvalidFeeBps);
+        ctx.accounts.protocol_config.max_app_fee_bps = max_app_fee_bps;
+        Ok(())
+    }
+
+    pub fn set_maintenance_mode(ctx: Context<UpdateProtocolConfig>, enabled: bool) -> Result<()> {
+        ctx.accounts.protocol_config.maintenance_mode = enabled;
+        emit!(MaintenanceModeChangedEvent {
+            enabled,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Fail over to (or back from) the secondary cluster.
+    pub fn switch_active_cluster(
+        ctx: Context<UpdateProtocolConfig>,
+        use_secondary: bool,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.protocol_config;
+        if use_secondary {
+            require!(
+                config.secondary_cluster.is_some(),
+                EscrowError::ClusterNotSet
+            );
+        }
+        config.use_secondary_cluster = use_secondary;
+
+        emit!(ActiveClusterChangedEvent {
+            cluster: config.active_cluster(),
+            use_secondary,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn init_circuit_registry(ctx: Context<InitCircuitRegistry>) -> Result<()> {
+        let registry = &mut ctx.accounts.circuit_registry;
+        registry.circuits = Vec::new();
+        registry.bump = ctx.bumps.circuit_registry;
+        Ok(())
+    }
+
+    /// Add or update a circuit entry. The comp-def offset is derived from the
+    /// name the same way `comp_def_offset` does for the constants above.
+    pub fn register_circuit(
+        ctx: Context<RegisterCircuit>,
+        name: String,
+        version: u16,
+        enabled: bool,
+    ) -> Result<()> {
+        require!(
+            !name.is_empty() && name.len() <= MAX_CIRCUIT_NAME_LEN,
+            EscrowError::InvalidCircuitName
+        );
+
+        let offset = comp_def_offset(&name);
+        let registry = &mut ctx.accounts.circuit_registry;
+        match registry.circuits.iter_mut().find(|entry| entry.name == name) {
+            Some(entry) => {
+                require!(version >= entry.version, EscrowError::CircuitVersionRegression);
+                entry.version = version;
+                entry.enabled = enabled;
+            }
+            None => {
+                require!(
+                    registry.circuits.len() < MAX_REGISTERED_CIRCUITS,
+                    EscrowError::CircuitRegistryFull
+                );
+                registry.circuits.push(CircuitEntry {
+                    name: name.clone(),
+                    comp_def_offset: offset,
+                    version,
+                    enabled,
+                });
+            }
+        }
+
+        emit!(CircuitRegisteredEvent {
+            name,
+            comp_def_offset: offset,
+            version,
+            enabled,
+        });
+
+        Ok(())
+    }
+
+    /// Create the mint allowlist for `send_payment_spl`, seeded with the
+    /// mints the legacy per-mint instructions accept.
+    pub fn init_mint_allowlist(ctx: Context<InitMintAllowlist>) -> Result<()> {
+        let allowlist = &mut ctx.accounts.mint_allowlist;
+        allowlist.mints = vec![USDC_MINT, ZENZEC_MINT];
+        allowlist.bump = ctx.bumps.mint_allowlist;
+        Ok(())
+    }
+
+    /// Add `mint` to, or remove it from, the `send_payment_spl` allowlist.
+    pub fn set_mint_allowed(
+        ctx: Context<SetMintAllowed>,
+        mint: Pubkey,
+        allowed: bool,
+    ) -> Result<()> {
+        let allowlist = &mut ctx.accounts.mint_allowlist;
+        let position = allowlist.mints.iter().position(|allowed_mint| *allowed_mint == mint);
+        match (position, allowed) {
+            (None, true) => {
+                require!(
+                    allowlist.mints.len() < MAX_ALLOWED_MINTS,
+                    EscrowError::MintAllowlistFull
+                );
+                allowlist.mints.push(mint);
+            }
+            (Some(index), false) => {
+                allowlist.mints.swap_remove(index);
+            }
+            _ => {}
+        }
+
+        emit!(MintAllowlistEvent {
+            mint,
+            allowed,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn init_relayer_registry(ctx: Context<InitRelayerRegistry>) -> Result<()> {
+        let registry = &mut ctx.accounts.relayer_registry;
+        registry.relayers = Vec::new();
+        registry.bump = ctx.bumps.relayer_registry;
+        Ok(())
+    }
+
+    /// Allow `relayer` to submit sponsored payments as their fee payer.
+    pub fn add_relayer(ctx: Context<UpdateRelayerRegistry>, relayer: Pubkey) -> Result<()> {
+        let registry = &mut ctx.accounts.relayer_registry;
+        require!(!registry.is_registered(&relayer), EscrowError::RelayerAlreadyRegistered);
+        require!(registry.relayers.len() < MAX_RELAYERS, EscrowError::RelayerRegistryFull);
+        registry.relayers.push(relayer);
+
+        emit!(RelayerRegistryEvent {
+            relayer,
+            registered: true,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Drop `relayer`. Sponsored transactions it still holds stop landing,
+    /// so a relayer caught reordering payments can't burn the rest.
+    pub fn remove_relayer(ctx: Context<UpdateRelayerRegistry>, relayer: Pubkey) -> Result<()> {
+        let registry = &mut ctx.accounts.relayer_registry;
+        let index = registry
+            .relayers
+            .iter()
+            .position(|registered| *registered == relayer)
+            .ok_or(EscrowError::RelayerNotRegistered)?;
+        registry.relayers.swap_remove(index);
+
+        emit!(RelayerRegistryEvent {
+            relayer,
+            registered: false,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn init_merge_deltas_comp_def(ctx: Context<InitMergeDeltasCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, 0, None, None)?;
+        Ok(())
+    }
+
+    pub fn init_verify_otc_terms_comp_def(ctx: Context<InitVerifyOtcTermsCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, 0, None, None)?;
+        Ok(())
+    }
+
+    pub fn init_run_payroll_comp_def(ctx: Context<InitRunPayrollCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, 0, None, None)?;
+        Ok(())
+    }
+
+    pub fn init_referral_report_comp_def(ctx: Context<InitReferralReportCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, 0, None, None)?;
+        Ok(())
+    }
+
+    pub fn init_check_recent_volume_threshold_comp_def(
+        ctx: Context<InitCheckRecentVolumeThresholdCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, 0, None, None)?;
+        Ok(())
+    }
+
+    pub fn init_reveal_asset_volume_comp_def(
+        ctx: Context<InitRevealAssetVolumeCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, 0, None, None)?;
+        Ok(())
+    }
+
+    pub fn init_resolve_sealed_bids_comp_def(
+        ctx: Context<InitResolveSealedBidsCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, 0, None, None)?;
+        Ok(())
+    }
+
+    pub fn init_share_escrow_stats_comp_def(
+        ctx: Context<InitShareEscrowStatsCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, 0, None, None)?;
+        Ok(())
+    }
+
+    pub fn init_spend_cap_comp_def(ctx: Context<InitSpendCapCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, 0, None, None)?;
+        Ok(())
+    }
+
+    pub fn init_check_spend_cap_comp_def(ctx: Context<InitCheckSpendCapCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, 0, None, None)?;
+        Ok(())
+    }
+
+    pub fn init_compute_match_comp_def(ctx: Context<InitComputeMatchCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, 0, None, None)?;
+        Ok(())
+    }
+
+    pub fn init_process_refund_comp_def(ctx: Context<InitProcessRefundCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, 0, None, None)?;
+        Ok(())
+    }
+
+    pub fn init_check_refund_rate_comp_def(ctx: Context<InitCheckRefundRateCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, 0, None, None)?;
+        Ok(())
+    }
+
+    pub fn init_prove_payment_amount_comp_def(
+        ctx: Context<InitProvePaymentAmountCompDef>,
+    ) -> Result<()> {
+        init_comp_def(ctx.accounts, 0, None, None)?;
+        Ok(())
+    }
+
+    pub fn init_calculate_fees_comp_def(ctx: Context<InitCalculateFeesCompDef>) -> Result<()> {
+        init_comp_def(ctx.accounts, 0, None, None)?;
+        Ok(())
+    }
+
+    /// Create the singleton protocol config. Bootstrap scripts check for the
+    /// PDA first, so running them twice is a no-op.
+    pub fn initialize_protocol_config(
+        ctx: Context<InitializeProtocolConfig>,
+        treasury: Pubkey,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.protocol_config;
+        config.admin = ctx.accounts.admin.key();
+        config.treasury = treasury;
+        config.primary_cluster = None;
+        config.secondary_cluster = None;
+        config.use_secondary_cluster = false;
+        config.bump = ctx.bumps.protocol_config;
+
+        emit!(ProtocolConfigInitializedEvent {
+            admin: config.admin,
+            treasury,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Initialize escrow with encrypted statistics tracking
+    pub fn initialize_escrow(
+        ctx: Context<InitializeEscrow>,
+        computation_offset: u64,
+        treasury_address: Pubkey,
+        nonce: u128,
+        stats_dimensions: u8,
+    ) -> Result<()> {
+        require!(
+            stats_dimensions != 0 && stats_dimensions & !STATS_DIMENSIONS_ALL == 0,
+            EscrowError::InvalidStatsDimensions
+        );
+
+        // Get the escrow key before borrowing
+        let escrow_key = ctx.accounts.escrow.key();
+        let computation_key = ctx.accounts.computation_account.key();
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.owner = ctx.accounts.owner.key();
+        escrow.total_fund_regulated = 0;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        escrow.active = true;
+        escrow.treasury = treasury_address;
+        escrow.bump = ctx.bumps.escrow;
+        escrow.nonce = nonce;
+        escrow.encrypted_stats = [[0; 32]; ESCROW_STATS_FIELDS]; // Store encrypted statistics
+        escrow.stats_dimensions = stats_dimensions;
+        escrow.pending_computation_offset = Some(computation_offset);
+        escrow.pending_since_slot = Clock::get()?.slot;
+        escrow.recent_volume_decayed_at = escrow.last_updated;
+        escrow.referrals_enabled = true;
+        sequence_escrow_update(escrow)?;
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        // Initialize encrypted statistics through MPC
+        #[cfg(not(feature = "mock-mpc"))]
+        {
+            let args = vec![Argument::PlaintextU128(nonce)];
+
+            queue_computation(
+                ctx.accounts,
+                computation_offset,
+                args,
+                None,
+                vec![InitEscrowStatsCallback::callback_ix(&[
+                    CallbackAccount {
+                        pubkey: escrow_key,
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: computation_key,
+                        is_writable: false,
+                    },
+                ])],
+                1,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "init_escrow_stats")]
+    pub fn init_escrow_stats_callback(
+        ctx: Context<InitEscrowStatsCallback>,
+        output: ComputationOutputs<InitEscrowStatsOutput>,
+    ) -> Result<()> {
+        let o = match output {
+            ComputationOutputs::Success(InitEscrowStatsOutput { field_0 }) => field_0,
+            _ => return Err(EscrowError::AbortedComputation.into()),
+        };
+
+        verify_pending_computation(
+            ctx.accounts.escrow.pending_computation_offset,
+            &ctx.accounts.computation_account.key(),
+        )?;
+
+        store_encrypted_stats(&mut ctx.accounts.escrow, o.ciphertexts, o.nonce);
+
+        sequence_escrow_update(&mut ctx.accounts.escrow)?;
+        Ok(())
+    }
+
+    pub fn pause_escrow(ctx: Context<ExerciseOwnerCapability>) -> Result<()> {
+        ctx.accounts.escrow.authorize_capability(
+            OwnerCapability::Pausing,
+            &ctx.accounts.authority.key(),
+            Clock::get()?.unix_timestamp,
+        )?;
+        let escrow = &mut ctx.accounts.escrow;
+        require!(escrow.active, EscrowError::AlreadyPaused);
+        escrow.active = false;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        if let Some(log) = ctx.accounts.activity_log.as_mut() {
+            log.record(ActivityKind::Paused, ctx.accounts.authority.key(), 0)?;
+        }
+        sequence_escrow_update(escrow)?;
+        Ok(())
+    }
+
+    pub fn resume_escrow(ctx: Context<ExerciseOwnerCapability>) -> Result<()> {
+        ctx.accounts.escrow.authorize_capability(
+            OwnerCapability::Pausing,
+            &ctx.accounts.authority.key(),
+            Clock::get()?.unix_timestamp,
+        )?;
+        let escrow = &mut ctx.accounts.escrow;
+        require!(!escrow.active, EscrowError::AlreadyActive);
+        escrow.active = true;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        if let Some(log) = ctx.accounts.activity_log.as_mut() {
+            log.record(ActivityKind::Resumed, ctx.accounts.authority.key(), 0)?;
+        }
+        sequence_escrow_update(escrow)?;
+        Ok(())
+    }
+
+    /// Release the stats lock when a computation never called back (e.g. the
+    /// cluster dropped it). Only allowed once the lock has timed out.
+    pub fn clear_pending_computation(ctx: Context<UpdateEscrowActive>) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        let offset = escrow
+            .pending_computation_offset
+            .ok_or(EscrowError::NoPendingComputation)?;
+        let current_slot = Clock::get()?.slot;
+        require!(
+            current_slot.saturating_sub(escrow.pending_since_slot) >= STATS_LOCK_TIMEOUT_SLOTS,
+            EscrowError::StatsComputationPending
+        );
+
+        escrow.pending_computation_offset = None;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        if let Some(log) = ctx.accounts.activity_log.as_mut() {
+            log.record(ActivityKind::PendingComputationCleared, ctx.accounts.owner.key(), offset)?;
+        }
+
+        let sequence = sequence_escrow_update(escrow)?;
+        emit!(PendingComputationClearedEvent {
+            escrow: escrow.key(),
+            sequence,
+            computation_offset: offset,
+            pending_since_slot: escrow.pending_since_slot,
+            cleared_at_slot: current_slot,
+        });
+
+        Ok(())
+    }
+
+    /// First step of a treasury rotation. Fees keep flowing to the current
+    /// treasury until `new_treasury` signs `accept_treasury`; a later
+    /// proposal replaces an unaccepted one.
+    pub fn propose_treasury(
+        ctx: Context<ExerciseOwnerCapability>,
+        new_treasury: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.escrow.authorize_capability(
+            OwnerCapability::Treasury,
+            &ctx.accounts.authority.key(),
+            Clock::get()?.unix_timestamp,
+        )?;
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.pending_treasury = Some(new_treasury);
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+
+        let sequence = sequence_escrow_update(escrow)?;
+        emit!(TreasuryRotationEvent {
+            escrow: escrow.key(),
+            sequence,
+            treasury: new_treasury,
+            stage: TreasuryRotationStage::Proposed,
+            timestamp: escrow.last_updated,
+        });
+
+        Ok(())
+    }
+
+    /// Second step of a treasury rotation, signed by the proposed treasury
+    /// so fees never move to an address nobody controls.
+    pub fn accept_treasury(ctx: Context<AcceptTreasury>) -> Result<()> {
+        let new_treasury = ctx.accounts.new_treasury.key();
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.treasury = new_treasury;
+        escrow.pending_treasury = None;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        if let Some(log) = ctx.accounts.activity_log.as_mut() {
+            log.record_with_key(ActivityKind::TreasuryChanged, new_treasury, new_treasury)?;
+        }
+
+        let sequence = sequence_escrow_update(escrow)?;
+        emit!(TreasuryRotationEvent {
+            escrow: escrow.key(),
+            sequence,
+            treasury: new_treasury,
+            stage: TreasuryRotationStage::Accepted,
+            timestamp: escrow.last_updated,
+        });
+
+        Ok(())
+    }
+
+    pub fn init_activity_log(ctx: Context<InitActivityLog>) -> Result<()> {
+        let log = &mut ctx.accounts.activity_log;
+        log.escrow = ctx.accounts.escrow.key();
+        log.head = 0;
+        log.total_recorded = 0;
+        log.bump = ctx.bumps.activity_log;
+        log.entries = [ActivityEntry::default(); ACTIVITY_LOG_CAPACITY];
+        Ok(())
+    }
+
+    /// SOL payment with encrypted statistics tracking
+    pub fn send_payment_encrypted(
+        ctx: Context<SendPaymentSolEncrypted>,
+        computation_offset: u64,
+        referal: Pubkey,
+        amount: u64,
+        recipient: Pubkey,
+        payment_encryption_pubkey: [u8; 32],
+        payment_nonce: u128,
+        encrypted_amount: [u8; 32],
+        valid_after_slot: Option<u64>,
+        valid_before_slot: Option<u64>,
+    ) -> Result<()> {
+        check_slot_window(valid_after_slot, valid_before_slot)?;
+        check_relayer(
+            ctx.accounts.relayer.as_ref(),
+            ctx.accounts.relayer_registry.as_deref(),
+        )?;
+
+        // Get escrow key and nonce before mutable borrow
+        let escrow_key = ctx.accounts.escrow.key();
+        let escrow_nonce = ctx.accounts.escrow.nonce;
+        let computation_key = ctx.accounts.computation_account.key();
+        let payment_key = ctx.accounts.payment.key();
+
+        ctx.accounts.escrow.ensure_active(ctx.accounts.jurisdiction_pause.as_deref())?;
+        // Stats are folded from the current ciphertext snapshot, so a second
+        // payment queued before the first callback would be computed from
+        // stale data and overwrite it.
+        require!(
+            ctx.accounts.escrow.pending_computation_offset.is_none(),
+            EscrowError::StatsComputationPending
+        );
+
+        // A sender with a spend cap must have had this exact amount approved
+        // against it by `authorize_capped_spend`.
+        let spend_cap_info = ctx.accounts.spend_cap.to_account_info();
+        if *spend_cap_info.owner == crate::ID {
+            let mut spend_cap = Account::<SpendCapAccount>::try_from(&spend_cap_info)?;
+            require!(
+                spend_cap.approved_amount == Some(encrypted_amount),
+                EscrowError::SpendCapNotApproved
+            );
+            spend_cap.approved_amount = None;
+            spend_cap.exit(&crate::ID)?;
+        }
+
+        let payment = &mut ctx.accounts.payment;
+        payment.sender = ctx.accounts.sender.key();
+        payment.recipient = recipient;
+        payment.referal = referal;
+        payment.amount = amount; // Public amount for transfer
+        payment.timestamp = Clock::get()?.unix_timestamp;
+        payment.asset_mint = Pubkey::default();
+        payment.intent_hash = payment_intent_hash(
+            &payment.sender,
+            &payment.recipient,
+            IntentAmount::Commitment(&encrypted_amount),
+            &payment.asset_mint,
+            computation_offset as u128,
+        );
+
+        // Calculate fees. Encrypted payments stay on the default rates, which
+        // `record_payment` fixes inside the MPC, rather than the FeeConfig curve
+        let (referral_fee, treasury_fee) = fee_split(amount, DEFAULT_TREASURY_FEE_BPS);
+        let fees = referral_fee
+            .checked_add(treasury_fee)
+            .ok_or(ProgramError::InvalidArgument)?;
+        let net_amount = amount
+            .checked_sub(fees)
+            .ok_or(ProgramError::InvalidArgument)?;
+        assert_fee_split(amount, &[net_amount, treasury_fee, referral_fee])?;
+
+        payment.referal_reward = referral_fee;
+        payment.treasury_reward = treasury_fee;
+
+        // Perform actual transfers
+        let from = ctx.accounts.sender.to_account_info();
+        let to_recipient = ctx.accounts.recipient.to_account_info();
+        let to_treasury = ctx.accounts.treasury.to_account_info();
+        let to_referral = if ctx.accounts.escrow.referrals_enabled {
+            ctx.accounts
+                .referral
+                .as_ref()
+                .ok_or(EscrowError::MissingReferralRoute)?
+                .to_account_info()
+        } else {
+            to_treasury.clone()
+        };
+        let system_program = ctx.accounts.system_program.to_account_info();
+
+        // Transfer to recipient
+        let cpi_ctx_recipient = CpiContext::new(
+            system_program.clone(),
+            anchor_lang::system_program::Transfer {
+                from: from.clone(),
+                to: to_recipient,
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_ctx_recipient, net_amount)?;
+
+        // Transfer to treasury
+        let cpi_ctx_treasury = CpiContext::new(
+            system_program.clone(),
+            anchor_lang::system_program::Transfer {
+                from: from.clone(),
+                to: to_treasury,
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_ctx_treasury, treasury_fee)?;
+
+        let cpi_ctx_referral = CpiContext::new(
+            system_program,
+            anchor_lang::system_program::Transfer {
+                from,
+                to: to_referral,
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_ctx_referral, referral_fee)?;
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        let decay_periods =
+            advance_recent_volume_clock(&mut ctx.accounts.escrow, Clock::get()?.unix_timestamp);
+        let asset_index = stats_asset_index(&ctx.accounts.payment.asset_mint)?;
+        let stats_dimensions = ctx.accounts.escrow.stats_dimensions;
+        let balance_before_queue = ctx.accounts.sender.lamports();
+
+        #[cfg(not(feature = "mock-mpc"))]
+        {
+            let mut args = vec![
+                Argument::ArcisPubkey(payment_encryption_pubkey),
+                Argument::PlaintextU128(payment_nonce),
+                Argument::EncryptedU64(encrypted_amount),
+                Argument::PlaintextBool(true),
+                Argument::PlaintextU128(escrow_nonce),
+                Argument::Account(
+                    escrow_key,
+                    EscrowAccount::ENCRYPTED_STATS_OFFSET,
+                    32 * ESCROW_STATS_FIELDS as u32,
+                ),
+                Argument::PlaintextU8(decay_periods),
+                Argument::PlaintextU8(asset_index),
+            ];
+            args.extend(stats_dimension_args(stats_dimensions));
+
+            queue_computation(
+                ctx.accounts,
+                computation_offset,
+                args,
+                None,
+                vec![ProcessPaymentCallback::callback_ix(&[
+                    CallbackAccount {
+                        pubkey: escrow_key,
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: computation_key,
+                        is_writable: false,
+                    },
+                    CallbackAccount {
+                        pubkey: payment_key,
+                        is_writable: false,
+                    },
+                ])],
+                1,
+            )?;
+        }
+
+        if let Some(vault) = ctx.accounts.compute_budget_vault.as_mut() {
+            let debited = balance_before_queue.saturating_sub(ctx.accounts.sender.lamports());
+            reimburse_computation_fee(vault, &ctx.accounts.sender.to_account_info(), debited)?;
+        }
+
+        ctx.accounts.escrow.total_fund_regulated = ctx
+            .accounts
+            .escrow
+            .total_fund_regulated
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+        ctx.accounts.escrow.pending_computation_offset = Some(computation_offset);
+        ctx.accounts.escrow.pending_since_slot = Clock::get()?.slot;
+
+        sequence_escrow_update(&mut ctx.accounts.escrow)?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "process_payment")]
+    pub fn process_payment_callback(
+        ctx: Context<ProcessPaymentCallback>,
+        output: ComputationOutputs<ProcessPaymentOutput>,
+    ) -> Result<()> {
+        let (o, is_valid) = match output {
+            ComputationOutputs::Success(ProcessPaymentOutput { field_0 }) => {
+                (field_0.field_0, field_0.field_1)
+            }
+            _ => return Err(EscrowError::AbortedComputation.into()),
+        };
+
+        verify_pending_computation(
+            ctx.accounts.escrow.pending_computation_offset,
+            &ctx.accounts.computation_account.key(),
+        )?;
+
+        let payment = ctx.accounts.payment.key();
+        record_processed_payment(
+            &mut ctx.accounts.escrow,
+            payment,
+            o.ciphertexts,
+            o.nonce,
+            is_valid,
+        )
+    }
+
+    /// Create the delta buffer used by `send_payment_batched`.
+    pub fn init_pending_deltas(ctx: Context<InitPendingDeltas>) -> Result<()> {
+        let pending_deltas = &mut ctx.accounts.pending_deltas;
+        pending_deltas.escrow = ctx.accounts.escrow.key();
+        pending_deltas.count = 0;
+        pending_deltas.in_flight = 0;
+        pending_deltas.bump = ctx.bumps.pending_deltas;
+        pending_deltas.deltas = [EncryptedDelta::default(); MAX_PENDING_DELTAS];
+        Ok(())
+    }
+
+    /// SOL payment whose encrypted amount is buffered instead of queueing an
+    /// MPC computation per payment. Stats catch up on the next `merge_deltas`.
+    pub fn send_payment_batched(
+        ctx: Context<SendPaymentBatched>,
+        referal: Pubkey,
+        amount: u64,
+        recipient: Pubkey,
+        payment_encryption_pubkey: [u8; 32],
+        payment_nonce: u128,
+        encrypted_amount: [u8; 32],
+        valid_after_slot: Option<u64>,
+        valid_before_slot: Option<u64>,
+    ) -> Result<()> {
+        check_slot_window(valid_after_slot, valid_before_slot)?;
+        check_relayer(
+            ctx.accounts.relayer.as_ref(),
+            ctx.accounts.relayer_registry.as_deref(),
+        )?;
+
+        ctx.accounts.escrow.ensure_active(ctx.accounts.jurisdiction_pause.as_deref())?;
+
+        let pending_deltas = &mut ctx.accounts.pending_deltas;
+        let slot = pending_deltas.count as usize;
+        require!(slot < MAX_PENDING_DELTAS, EscrowError::DeltaBufferFull);
+        pending_deltas.deltas[slot] = EncryptedDelta {
+            encryption_pubkey: payment_encryption_pubkey,
+            nonce: payment_nonce,
+            ciphertext: encrypted_amount,
+        };
+        pending_deltas.count += 1;
+
+        let payment = &mut ctx.accounts.payment;
+        payment.sender = ctx.accounts.sender.key();
+        payment.recipient = recipient;
+        payment.referal = referal;
+        payment.amount = amount;
+        payment.timestamp = Clock::get()?.unix_timestamp;
+        payment.asset_mint = Pubkey::default();
+        payment.intent_hash = payment_intent_hash(
+            &payment.sender,
+            &payment.recipient,
+            IntentAmount::Commitment(&encrypted_amount),
+            &payment.asset_mint,
+            payment_nonce,
+        );
+        // Default rates, as the MPC records them; see `send_payment_encrypted`
+        (payment.referal_reward, payment.treasury_reward) =
+            fee_split(amount, DEFAULT_TREASURY_FEE_BPS);
+
+        let fees = payment
+            .referal_reward
+            .checked_add(payment.treasury_reward)
+            .ok_or(ProgramError::InvalidArgument)?;
+        let net_amount = amount
+            .checked_sub(fees)
+            .ok_or(ProgramError::InvalidArgument)?;
+        assert_fee_split(amount, &[net_amount, payment.treasury_reward, payment.referal_reward])?;
+
+        let from = ctx.accounts.sender.to_account_info();
+        let to_treasury = ctx.accounts.treasury.to_account_info();
+        let to_referral = if ctx.accounts.escrow.referrals_enabled {
+            ctx.accounts
+                .referral
+                .as_ref()
+                .ok_or(EscrowError::MissingReferralRoute)?
+                .to_account_info()
+        } else {
+            to_treasury.clone()
+        };
+        let system_program = ctx.accounts.system_program.to_account_info();
+
+        let cpi_ctx_recipient = CpiContext::new(
+            system_program.clone(),
+            anchor_lang::system_program::Transfer {
+                from: from.clone(),
+                to: ctx.accounts.recipient.to_account_info(),
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_ctx_recipient, net_amount)?;
+
+        let cpi_ctx_treasury = CpiContext::new(
+            system_program.clone(),
+            anchor_lang::system_program::Transfer {
+                from: from.clone(),
+                to: to_treasury,
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_ctx_treasury, payment.treasury_reward)?;
+
+        let cpi_ctx_referral = CpiContext::new(
+            system_program,
+            anchor_lang::system_program::Transfer {
+                from,
+                to: to_referral,
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_ctx_referral, payment.referal_reward)?;
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.total_fund_regulated = escrow
+            .total_fund_regulated
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        sequence_escrow_update(escrow)?;
+        Ok(())
+    }
+
+    /// Permissionless crank folding every buffered delta into the encrypted
+    /// stats with one `merge_deltas` computation.
+    pub fn merge_deltas(ctx: Context<MergeDeltas>, computation_offset: u64) -> Result<()> {
+        let escrow_key = ctx.accounts.escrow.key();
+        let escrow_nonce = ctx.accounts.escrow.nonce;
+        let pending_deltas_key = ctx.accounts.pending_deltas.key();
+        let computation_key = ctx.accounts.computation_account.key();
+
+        require!(
+            ctx.accounts.escrow.pending_computation_offset.is_none(),
+            EscrowError::StatsComputationPending
+        );
+        let count = ctx.accounts.pending_deltas.count;
+        require!(count > 0, EscrowError::NoPendingDeltas);
+
+        // Anything appended after this point waits for the next merge.
+        ctx.accounts.pending_deltas.in_flight = count;
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        let decay_periods =
+            advance_recent_volume_clock(&mut ctx.accounts.escrow, Clock::get()?.unix_timestamp);
+        let balance_before_queue = ctx.accounts.payer.lamports();
+
+        #[cfg(not(feature = "mock-mpc"))]
+        {
+            let mut args = Vec::with_capacity(MAX_PENDING_DELTAS * 3 + 5);
+            for delta in ctx.accounts.pending_deltas.deltas.iter() {
+                args.push(Argument::ArcisPubkey(delta.encryption_pubkey));
+                args.push(Argument::PlaintextU128(delta.nonce));
+                args.push(Argument::EncryptedU64(delta.ciphertext));
+            }
+            args.push(Argument::PlaintextU8(count));
+            args.push(Argument::PlaintextU128(escrow_nonce));
+            args.push(Argument::Account(
+                escrow_key,
+                EscrowAccount::ENCRYPTED_STATS_OFFSET,
+                32 * ESCROW_STATS_FIELDS as u32,
+            ));
+            args.push(Argument::PlaintextU8(decay_periods));
+            // Batched payments are SOL-only.
+            args.push(Argument::PlaintextU8(STATS_ASSET_SOL));
+            args.extend(stats_dimension_args(ctx.accounts.escrow.stats_dimensions));
+
+            queue_computation(
+                ctx.accounts,
+                computation_offset,
+                args,
+                None,
+                vec![MergeDeltasCallback::callback_ix(&[
+                    CallbackAccount {
+                        pubkey: escrow_key,
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: computation_key,
+                        is_writable: false,
+                    },
+                    CallbackAccount {
+                        pubkey: pending_deltas_key,
+                        is_writable: true,
+                    },
+                ])],
+                1,
+            )?;
+        }
+
+        if let Some(vault) = ctx.accounts.compute_budget_vault.as_mut() {
+            let payer = ctx.accounts.payer.to_account_info();
+            let debited = balance_before_queue.saturating_sub(payer.lamports());
+            reimburse_computation_fee(vault, &payer, debited)?;
+            pay_crank_reward(vault, &payer, CrankKind::MergeDeltas)?;
+        }
+
+        ctx.accounts.escrow.pending_computation_offset = Some(computation_offset);
+        ctx.accounts.escrow.pending_since_slot = Clock::get()?.slot;
+
+        sequence_escrow_update(&mut ctx.accounts.escrow)?;
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "merge_deltas")]
+    pub fn merge_deltas_callback(
+        ctx: Context<MergeDeltasCallback>,
+        output: ComputationOutputs<MergeDeltasOutput>,
+    ) -> Result<()> {
+        let o = match output {
+            ComputationOutputs::Success(MergeDeltasOutput { field_0 }) => field_0,
+            _ => return Err(EscrowError::AbortedComputation.into()),
+        };
+
+        verify_pending_computation(
+            ctx.accounts.escrow.pending_computation_offset,
+            &ctx.accounts.computation_account.key(),
+        )?;
+        store_encrypted_stats(&mut ctx.accounts.escrow, o.ciphertexts, o.nonce);
+
+        let pending_deltas = &mut ctx.accounts.pending_deltas;
+        let merged = pending_deltas.in_flight;
+        pending_deltas.drain_merged();
+
+        let sequence = sequence_escrow_update(&mut ctx.accounts.escrow)?;
+        emit!(DeltasMergedEvent {
+            escrow: ctx.accounts.escrow.key(),
+            sequence,
+            merged,
+            remaining: pending_deltas.count,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Create the vault the escrow uses to absorb Arcium computation fees on
+    /// behalf of its payers.
+    pub fn init_compute_budget_vault(
+        ctx: Context<InitComputeBudgetVault>,
+        fee_per_computation: u64,
+        low_balance_threshold: u64,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.compute_budget_vault;
+        vault.escrow = ctx.accounts.escrow.key();
+        vault.fee_per_computation = fee_per_computation;
+        vault.low_balance_threshold = low_balance_threshold;
+        vault.total_reimbursed = 0;
+        vault.bump = ctx.bumps.compute_budget_vault;
+        Ok(())
+    }
+
+    pub fn update_compute_budget_vault(
+        ctx: Context<UpdateComputeBudgetVault>,
+        fee_per_computation: u64,
+        low_balance_threshold: u64,
+    ) -> Result<()> {
+        let vault = &mut ctx.accounts.compute_budget_vault;
+        vault.fee_per_computation = fee_per_computation;
+        vault.low_balance_threshold = low_balance_threshold;
+        Ok(())
+    }
+
+    pub fn fund_compute_budget(ctx: Context<FundComputeBudget>, amount: u64) -> Result<()> {
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: ctx.accounts.funder.to_account_info(),
+                to: ctx.accounts.compute_budget_vault.to_account_info(),
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_ctx, amount)?;
+
+        emit!(ComputeBudgetFundedEvent {
+            escrow: ctx.accounts.compute_budget_vault.escrow,
+            funder: ctx.accounts.funder.key(),
+            amount,
+            balance: available_compute_budget(&ctx.accounts.compute_budget_vault)?,
+        });
+
+        Ok(())
+    }
+
+    pub fn withdraw_compute_budget(
+        ctx: Context<UpdateComputeBudgetVault>,
+        amount: u64,
+    ) -> Result<()> {
+        let vault = &ctx.accounts.compute_budget_vault;
+        require!(
+            available_compute_budget(vault)? >= amount,
+            EscrowError::InsufficientComputeBudget
+        );
+
+        **vault.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.owner.to_account_info().try_borrow_mut_lamports()? += amount;
+
+        Ok(())
+    }
+
+    /// Pay third parties for running the escrow's permissionless cranks out
+    /// of the compute budget vault, at most `cap_per_period` lamports per
+    /// `CRANK_REWARD_PERIOD_SECS`.
+    pub fn set_crank_reward(
+        ctx: Context<UpdateComputeBudgetVault>,
+        reward_per_crank: u64,
+        cap_per_period: u64,
+    ) -> Result<()> {
+        require!(reward_per_crank <= cap_per_period, EscrowError::InvalidCrankReward);
+        let vault = &mut ctx.accounts.compute_budget_vault;
+        vault.crank_reward = reward_per_crank;
+        vault.crank_reward_cap = cap_per_period;
+        Ok(())
+    }
+
+    pub fn check_volume_threshold(
+        ctx: Context<CheckVolumeThreshold>,
+        computation_offset: u64,
+        threshold: u64,
+    ) -> Result<()> {
+        authorize_analytics_request(
+            &ctx.accounts.escrow,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.viewer.as_ref(),
+            ctx.accounts.analytics_operator.as_mut(),
+        )?;
+        ctx.accounts.escrow.require_stats_dimension(STATS_DIMENSION_VOLUME)?;
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        if let Some(log) = ctx.accounts.activity_log.as_mut() {
+            log.record(ActivityKind::ThresholdChecked, ctx.accounts.authority.key(), threshold)?;
+        }
+
+        #[cfg(not(feature = "mock-mpc"))]
+        {
+            let args = vec![
+                Argument::PlaintextU128(ctx.accounts.escrow.nonce),
+                Argument::Account(
+                    ctx.accounts.escrow.key(),
+                    EscrowAccount::ENCRYPTED_STATS_OFFSET,
+                    32 * ESCROW_STATS_FIELDS as u32,
+                ),
+                Argument::PlaintextU64(threshold),
+            ];
+
+            queue_computation(
+                ctx.accounts,
+                computation_offset,
+                args,
+                None,
+                vec![CheckVolumeThresholdCallback::callback_ix(&[
+                    CallbackAccount {
+                        pubkey: ctx.accounts.escrow.key(),
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: ctx.accounts.computation_account.key(),
+                        is_writable: false,
+                    },
+                ])],
+                1,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "check_volume_threshold")]
+    pub fn check_volume_threshold_callback(
+        ctx: Context<CheckVolumeThresholdCallback>,
+        output: ComputationOutputs<CheckVolumeThresholdOutput>,
+    ) -> Result<()> {
+        let result = match output {
+            ComputationOutputs::Success(CheckVolumeThresholdOutput { field_0 }) => field_0,
+            _ => return Err(EscrowError::AbortedComputation.into()),
+        };
+
+        let timestamp = Clock::get()?.unix_timestamp;
+        emit!(ThresholdCheckEvent {
+            meets_threshold: result,
+            timestamp,
+        });
+
+        // Only the check queued by `check_circuit_breaker` may pause; plain
+        // owner threshold checks are informational.
+        let escrow = &mut ctx.accounts.escrow;
+        let is_breaker_check = verify_pending_computation(
+            escrow.circuit_breaker_check_offset,
+            &ctx.accounts.computation_account.key(),
+        )
+        .is_ok();
+        if is_breaker_check {
+            escrow.circuit_breaker_check_offset = None;
+            let sequence = sequence_escrow_update(escrow)?;
+            if result && escrow.compliance_officer != Pubkey::default() {
+                // Park vault payments for review rather than failing them.
+                escrow.quarantine_active = true;
+                escrow.last_updated = timestamp;
+                emit!(CircuitBreakerTrippedEvent {
+                    escrow: escrow.key(),
+                    sequence,
+                    threshold: escrow.circuit_breaker_threshold,
+                    timestamp,
+                });
+            } else if result && escrow.active {
+                escrow.active = false;
+                escrow.last_updated = timestamp;
+                emit!(CircuitBreakerTrippedEvent {
+                    escrow: escrow.key(),
+                    sequence,
+                    threshold: escrow.circuit_breaker_threshold,
+                    timestamp,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn reveal_payment_count(
+        ctx: Context<RevealPaymentCount>,
+        computation_offset: u64,
+    ) -> Result<()> {
+        authorize_analytics_request(
+            &ctx.accounts.escrow,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.viewer.as_ref(),
+            ctx.accounts.analytics_operator.as_mut(),
+        )?;
+        ctx.accounts.escrow.require_stats_dimension(STATS_DIMENSION_PAYMENTS)?;
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        if let Some(log) = ctx.accounts.activity_log.as_mut() {
+            log.record(ActivityKind::CountRevealed, ctx.accounts.authority.key(), 0)?;
+        }
+
+        #[cfg(not(feature = "mock-mpc"))]
+        {
+            let args = vec![
+                Argument::PlaintextU128(ctx.accounts.escrow.nonce),
+                Argument::Account(
+                    ctx.accounts.escrow.key(),
+                    EscrowAccount::ENCRYPTED_STATS_OFFSET,
+                    32 * ESCROW_STATS_FIELDS as u32,
+                ),
+            ];
+
+            queue_computation(
+                ctx.accounts,
+                computation_offset,
+                args,
+                None,
+                vec![RevealPaymentCountCallback::callback_ix(&[])],
+                1,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "reveal_payment_count")]
+    pub fn reveal_payment_count_callback(
+        ctx: Context<RevealPaymentCountCallback>,
+        output: ComputationOutputs<RevealPaymentCountOutput>,
+    ) -> Result<()> {
+        let count = match output {
+            ComputationOutputs::Success(RevealPaymentCountOutput { field_0 }) => field_0,
+            _ => return Err(EscrowError::AbortedComputation.into()),
+        };
+
+        emit!(PaymentCountEvent {
+            total_payments: count,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Localnet-only stand-in for the Arcium callbacks. With `mock-mpc` the
+    /// queueing instructions skip `queue_computation`, and tests feed
+    /// synthetic outputs through here instead.
+    #[cfg(feature = "mock-mpc")]
+    pub fn mock_callback(
+        ctx: Context<MockCallback>,
+        kind: MockCallbackKind,
+        ciphertexts: [[u8; 32]; ESCROW_STATS_FIELDS],
+        nonce: u128,
+    ) -> Result<()> {
+        verify_pending_computation(
+            ctx.accounts.escrow.pending_computation_offset,
+            &ctx.accounts.computation_account.key(),
+        )?;
+
+        let escrow = &mut ctx.accounts.escrow;
+        match kind {
+            MockCallbackKind::InitEscrowStats => {
+                store_encrypted_stats(escrow, ciphertexts, nonce);
+                sequence_escrow_update(escrow)?;
+                Ok(())
+            }
+            MockCallbackKind::ProcessPayment { is_valid } => {
+                let payment = ctx
+                    .accounts
+                    .payment
+                    .as_ref()
+                    .ok_or(anchor_lang::error::ErrorCode::AccountNotEnoughKeys)?
+                    .key();
+                record_processed_payment(escrow, payment, ciphertexts, nonce, is_valid)
+            }
+        }
+    }
+
+    pub fn send_payment(
+        ctx: Context<SendPaymentSol>,
+        referal: Pubkey,
+        amount: u64,
+        recipient: Pubkey,
+        app_fee_bps: u16,
+        valid_after_slot: Option<u64>,
+        valid_before_slot: Option<u64>,
+    ) -> Result<()> {
+        check_slot_window(valid_after_slot, valid_before_slot)?;
+        check_relayer(
+            ctx.accounts.relayer.as_ref(),
+            ctx.accounts.relayer_registry.as_deref(),
+        )?;
+
+        let referal = resolve_referrer(
+            referal,
+            ctx.accounts.referral_link.as_deref(),
+            &ctx.accounts.sender.key(),
+            &ctx.accounts.escrow.key(),
+            ctx.accounts
+                .protocol_config
+                .as_ref()
+                .map(|config| config.key()),
+        )?;
+
+        let payment = &mut ctx.accounts.payment;
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.ensure_active(ctx.accounts.jurisdiction_pause.as_deref())?;
+
+        payment.sender = ctx.accounts.sender.key();
+        payment.sequence = ctx
+            .accounts
+            .payment_sequence
+            .advance(ctx.accounts.sender.key(), ctx.bumps.payment_sequence)?;
+        payment.recipient = recipient;
+        payment.referal = referal;
+        payment.amount = amount;
+        payment.timestamp = Clock::get()?.unix_timestamp;
+        payment.counter_signature = recipient_counter_signature(
+            &escrow.key(),
+            &payment.sender,
+            &ctx.accounts.recipient,
+            amount,
+            payment.sequence,
+            valid_before_slot,
+            ctx.accounts.instructions_sysvar.as_ref(),
+        )?;
+        if payment.counter_signature != CounterSignature::None {
+            require_keys_eq!(
+                recipient,
+                ctx.accounts.recipient.key(),
+                EscrowError::CounterSignatureRequired
+            );
+        } else {
+            require!(
+                !escrow.require_counter_signature,
+                EscrowError::CounterSignatureRequired
+            );
+        }
+        payment.asset_mint = Pubkey::default();
+        payment.intent_hash = payment_intent_hash(
+            &payment.sender,
+            &payment.recipient,
+            IntentAmount::Plain(amount),
+            &payment.asset_mint,
+            payment.sequence as u128,
+        );
+        let PaymentFees {
+            referal_reward,
+            treasury_reward,
+            insurance,
+        } = plaintext_payment_fees(
+            escrow,
+            FeeFeatureAccounts {
+                fee_config: ctx.accounts.fee_config.as_deref(),
+                campaign: ctx.accounts.campaign.as_mut(),
+                fee_hook_program: ctx.accounts.fee_hook_program.as_ref(),
+                fee_waiver: ctx.accounts.fee_waiver.as_mut(),
+            },
+            payment,
+        )?;
+        payment.referal_reward = referal_reward;
+        payment.treasury_reward = treasury_reward;
+
+        let fees = payment
+            .referal_reward
+            .checked_add(payment.treasury_reward)
+            .and_then(|fees| fees.checked_add(insurance))
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        // Integrator fee on top of the protocol fees, capped by the protocol.
+        if app_fee_bps > 0 {
+            let config = ctx
+                .accounts
+                .protocol_config
+                .as_ref()
+                .ok_or(EscrowError::AppFeeNotAllowed)?;
+            require!(
+                app_fee_bps <= config.max_app_fee_bps,
+                EscrowError::AppFeeNotAllowed
+            );
+            let destination = ctx
+                .accounts
+                .app_fee_destination
+                .as_ref()
+                .ok_or(EscrowError::AppFeeNotAllowed)?;
+            payment.app_fee = amount
+                .checked_mul(app_fee_bps as u64)
+                .ok_or(ProgramError::InvalidArgument)?
+                / 10_000;
+            payment.app_fee_destination = destination.key();
+        }
+
+        let transferable_amount = amount
+            .checked_sub(fees)
+            .and_then(|net| net.checked_sub(payment.app_fee))
+            .ok_or(ProgramError::InvalidArgument)?;
+        assert_fee_split(
+            amount,
+            &[
+                transferable_amount,
+                payment.treasury_reward,
+                payment.referal_reward,
+                insurance,
+                payment.app_fee,
+            ],
+        )?;
+
+        let from = ctx.accounts.sender.to_account_info();
+        let to_recipient = ctx.accounts.recipient.to_account_info();
+        // In accrual mode treasury fees collect in the escrow's treasury
+        // vault until `sweep_treasury`, so payers don't pass the treasury.
+        let to_treasury = if escrow.accrue_treasury_fees {
+            ctx.accounts
+                .treasury_vault
+                .as_ref()
+                .ok_or(EscrowError::MissingTreasuryRoute)?
+                .to_account_info()
+        } else {
+            ctx.accounts
+                .treasury
+                .as_ref()
+                .ok_or(EscrowError::MissingTreasuryRoute)?
+                .to_account_info()
+        };
+        let system_program = ctx.accounts.system_program.to_account_info();
+        let to_referral = sol_referral_destination(
+            escrow,
+            SolReferralAccounts {
+                referral: ctx.accounts.referral.as_ref(),
+                incinerator: ctx.accounts.incinerator.as_ref(),
+                referral_vesting: ctx.accounts.referral_vesting.as_mut(),
+                referral_usdc_ledger: ctx.accounts.referral_usdc_ledger.as_mut(),
+                sol_fee_rate: ctx.accounts.sol_fee_rate.as_ref(),
+            },
+            referal,
+            payment.referal_reward,
+            payment.timestamp,
+            &to_treasury,
+            &to_recipient,
+        )?;
+
+        let cpi_ctx_recipient = CpiContext::new(
+            system_program.clone(),
+            anchor_lang::system_program::Transfer {
+                from: from.clone(),
+                to: to_recipient,
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_ctx_recipient, transferable_amount)?;
+
+        let cpi_ctx_treasury = CpiContext::new(
+            system_program.clone(),
+            anchor_lang::system_program::Transfer {
+                from: from.clone(),
+                to: to_treasury,
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_ctx_treasury, payment.treasury_reward)?;
+
+        let cpi_ctx_referral = CpiContext::new(
+            system_program,
+            anchor_lang::system_program::Transfer {
+                from,
+                to: to_referral,
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_ctx_referral, payment.referal_reward)?;
+
+        if let Some(destination) = ctx.accounts.app_fee_destination.as_ref() {
+            if payment.app_fee > 0 {
+                let cpi_ctx_app_fee = CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.sender.to_account_info(),
+                        to: destination.to_account_info(),
+                    },
+                );
+                anchor_lang::system_program::transfer(cpi_ctx_app_fee, payment.app_fee)?;
+
+                emit!(AppFeeChargedEvent {
+                    payment: payment.key(),
+                    app_fee_destination: destination.key(),
+                    app_fee: payment.app_fee,
+                    timestamp: payment.timestamp,
+                });
+            }
+        }
+
+        if insurance > 0 {
+            let pool = ctx
+                .accounts
+                .insurance_pool
+                .as_mut()
+                .ok_or(EscrowError::MissingInsurancePool)?;
+            let cpi_ctx_insurance = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.sender.to_account_info(),
+                    to: pool.to_account_info(),
+                },
+            );
+            anchor_lang::system_program::transfer(cpi_ctx_insurance, insurance)?;
+            pool.total_contributed = pool
+                .total_contributed
+                .checked_add(insurance)
+                .ok_or(ProgramError::InvalidArgument)?;
+
+            emit!(InsurancePoolEvent {
+                pool: pool.key(),
+                movement: InsuranceMovement::Contribution,
+                counterparty: payment.sender,
+                amount: insurance,
+                timestamp: payment.timestamp,
+            });
+        }
+
+        escrow.total_fund_regulated = escrow
+            .total_fund_regulated
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        sequence_escrow_update(escrow)?;
+        Ok(())
+    }
+
+    /// Deprecated: use `send_payment_spl` with `USDC_MINT`. Keeps its
+    /// original accounts and payment address for existing clients, but
+    /// settles like `send_payment_spl`.
+    pub fn send_payment_usdc(
+        ctx: Context<SendPaymentUsdc>,
+        referal: Pubkey,
+        amount: u64,
+        recipient: Pubkey,
+        valid_after_slot: Option<u64>,
+        valid_before_slot: Option<u64>,
+    ) -> Result<()> {
+        check_slot_window(valid_after_slot, valid_before_slot)?;
+        check_relayer(
+            ctx.accounts.relayer.as_ref(),
+            ctx.accounts.relayer_registry.as_deref(),
+        )?;
+
+        let sequence = ctx
+            .accounts
+            .payment_sequence
+            .advance(ctx.accounts.sender.key(), ctx.bumps.payment_sequence)?;
+        settle_spl_payment(
+            &mut ctx.accounts.escrow,
+            &mut ctx.accounts.payment,
+            SplPaymentAccounts {
+                sender: &ctx.accounts.sender,
+                sender_token_account: &ctx.accounts.sender_token_account,
+                recipient_token_account: &ctx.accounts.recipient_token_account,
+                referral_token_account: Some(&ctx.accounts.referral_token_account),
+                treasury_token_account: &ctx.accounts.treasury_token_account,
+                insurance_pool: ctx.accounts.insurance_pool.as_ref(),
+                insurance_token_account: ctx.accounts.insurance_token_account.as_ref(),
+                mint: &ctx.accounts.mint,
+                token_program: &ctx.accounts.token_program,
+                jurisdiction_pause: ctx.accounts.jurisdiction_pause.as_deref(),
+                fees: FeeFeatureAccounts {
+                    fee_config: ctx.accounts.fee_config.as_deref(),
+                    campaign: ctx.accounts.campaign.as_mut(),
+                    fee_hook_program: ctx.accounts.fee_hook_program.as_ref(),
+                    fee_waiver: ctx.accounts.fee_waiver.as_mut(),
+                },
+            },
+            referal,
+            amount,
+            recipient,
+            sequence,
+        )
+    }
+
+    /// Deprecated: use `send_payment_spl` with `ZENZEC_MINT`. Keeps its
+    /// original accounts and payment address for existing clients, but
+    /// settles like `send_payment_spl`.
+    pub fn send_payment_zenzec(
+        ctx: Context<SendPaymentZenZec>,
+        referal: Pubkey,
+        amount: u64,
+        recipient: Pubkey,
+        valid_after_slot: Option<u64>,
+        valid_before_slot: Option<u64>,
+    ) -> Result<()> {
+        check_slot_window(valid_after_slot, valid_before_slot)?;
+        check_relayer(
+            ctx.accounts.relayer.as_ref(),
+            ctx.accounts.relayer_registry.as_deref(),
+        )?;
+
+        let sequence = ctx
+            .accounts
+            .payment_sequence
+            .advance(ctx.accounts.sender.key(), ctx.bumps.payment_sequence)?;
+        settle_spl_payment(
+            &mut ctx.accounts.escrow,
+            &mut ctx.accounts.payment,
+            SplPaymentAccounts {
+                sender: &ctx.accounts.sender,
+                sender_token_account: &ctx.accounts.sender_token_account,
+                recipient_token_account: &ctx.accounts.recipient_token_account,
+                referral_token_account: Some(&ctx.accounts.referral_token_account),
+                treasury_token_account: &ctx.accounts.treasury_token_account,
+                insurance_pool: ctx.accounts.insurance_pool.as_ref(),
+                insurance_token_account: ctx.accounts.insurance_token_account.as_ref(),
+                mint: &ctx.accounts.mint,
+                token_program: &ctx.accounts.token_program,
+                jurisdiction_pause: ctx.accounts.jurisdiction_pause.as_deref(),
+                fees: FeeFeatureAccounts {
+                    fee_config: ctx.accounts.fee_config.as_deref(),
+                    campaign: ctx.accounts.campaign.as_mut(),
+                    fee_hook_program: ctx.accounts.fee_hook_program.as_ref(),
+                    fee_waiver: ctx.accounts.fee_waiver.as_mut(),
+                },
+            },
+            referal,
+            amount,
+            recipient,
+            sequence,
+        )
+    }
+
+    /// Plaintext payment in any allowlisted SPL mint, split between the
+    /// recipient, the escrow treasury and the referrer with the same fee
+    /// features as `send_payment`.
+    pub fn send_payment_spl(
+        ctx: Context<SendPaymentSpl>,
+        referal: Pubkey,
+        amount: u64,
+        recipient: Pubkey,
+        valid_after_slot: Option<u64>,
+        valid_before_slot: Option<u64>,
+    ) -> Result<()> {
+        check_slot_window(valid_after_slot, valid_before_slot)?;
+        check_relayer(
+            ctx.accounts.relayer.as_ref(),
+            ctx.accounts.relayer_registry.as_deref(),
+        )?;
+        require!(
+            ctx.accounts
+                .mint_allowlist
+                .mints
+                .contains(&ctx.accounts.mint.key()),
+            EscrowError::MintNotAllowed
+        );
+
+        let sequence = ctx
+            .accounts
+            .payment_sequence
+            .advance(ctx.accounts.sender.key(), ctx.bumps.payment_sequence)?;
+        settle_spl_payment(
+            &mut ctx.accounts.escrow,
+            &mut ctx.accounts.payment,
+            SplPaymentAccounts {
+                sender: &ctx.accounts.sender,
+                sender_token_account: &ctx.accounts.sender_token_account,
+                recipient_token_account: &ctx.accounts.recipient_token_account,
+                referral_token_account: ctx.accounts.referral_token_account.as_ref(),
+                treasury_token_account: &ctx.accounts.treasury_token_account,
+                insurance_pool: ctx.accounts.insurance_pool.as_ref(),
+                insurance_token_account: ctx.accounts.insurance_token_account.as_ref(),
+                mint: &ctx.accounts.mint,
+                token_program: &ctx.accounts.token_program,
+                jurisdiction_pause: ctx.accounts.jurisdiction_pause.as_deref(),
+                fees: FeeFeatureAccounts {
+                    fee_config: ctx.accounts.fee_config.as_deref(),
+                    campaign: ctx.accounts.campaign.as_mut(),
+                    fee_hook_program: ctx.accounts.fee_hook_program.as_ref(),
+                    fee_waiver: ctx.accounts.fee_waiver.as_mut(),
+                },
+            },
+            referal,
+            amount,
+            recipient,
+            sequence,
+        )
+    }
+
+    /// Payment in an allowlisted Token-2022 mint through its confidential
+    /// transfer extension, so the amount moved is encrypted on-chain too.
+    /// The client verifies the equality, ciphertext-validity and range
+    /// proofs into context state accounts first; Token-2022 checks them.
+    /// Encrypted stats are not updated since the amount is never revealed.
+    pub fn send_payment_confidential_token(
+        ctx: Context<SendPaymentConfidentialToken>,
+        recipient: Pubkey,
+        new_source_decryptable_balance: [u8; 36],
+        auditor_ciphertext_lo: [u8; 64],
+        auditor_ciphertext_hi: [u8; 64],
+        valid_after_slot: Option<u64>,
+        valid_before_slot: Option<u64>,
+    ) -> Result<()> {
+        check_slot_window(valid_after_slot, valid_before_slot)?;
+        check_relayer(
+            ctx.accounts.relayer.as_ref(),
+            ctx.accounts.relayer_registry.as_deref(),
+        )?;
+
+        let mint = ctx.accounts.mint.key();
+        require!(
+            ctx.accounts.mint_allowlist.mints.contains(&mint),
+            EscrowError::MintNotAllowed
+        );
+        ctx.accounts.escrow.ensure_active(ctx.accounts.jurisdiction_pause.as_deref())?;
+        require_keys_eq!(
+            token_2022_account_owner(&ctx.accounts.sender_token_account, &mint)?,
+            ctx.accounts.sender.key(),
+            EscrowError::InvalidConfidentialTokenAccount
+        );
+        require_keys_eq!(
+            token_2022_account_owner(&ctx.accounts.recipient_token_account, &mint)?,
+            recipient,
+            EscrowError::InvalidConfidentialTokenAccount
+        );
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: ctx.accounts.sender.to_account_info(),
+                to: ctx.accounts.treasury.to_account_info(),
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_ctx, CONFIDENTIAL_TOKEN_FEE_LAMPORTS)?;
+
+        // Proof offsets of 0 point Token-2022 at the context state accounts
+        // rather than at proof instructions in this transaction.
+        let mut data = Vec::with_capacity(2 + 36 + 64 + 64 + 3);
+        data.push(TOKEN_2022_CONFIDENTIAL_TRANSFER_EXTENSION);
+        data.push(CONFIDENTIAL_TRANSFER_TRANSFER);
+        data.extend_from_slice(&new_source_decryptable_balance);
+        data.extend_from_slice(&auditor_ciphertext_lo);
+        data.extend_from_slice(&auditor_ciphertext_hi);
+        data.extend_from_slice(&[0, 0, 0]);
+        let accounts = &ctx.accounts;
+        let transfer = Instruction {
+            program_id: TOKEN_2022_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(accounts.sender_token_account.key(), false),
+                AccountMeta::new_readonly(mint, false),
+                AccountMeta::new(accounts.recipient_token_account.key(), false),
+                AccountMeta::new_readonly(accounts.equality_proof.key(), false),
+                AccountMeta::new_readonly(accounts.ciphertext_validity_proof.key(), false),
+                AccountMeta::new_readonly(accounts.range_proof.key(), false),
+                AccountMeta::new_readonly(accounts.sender.key(), true),
+            ],
+            data,
+        };
+        invoke(
+            &transfer,
+            &[
+                accounts.sender_token_account.to_account_info(),
+                accounts.mint.to_account_info(),
+                accounts.recipient_token_account.to_account_info(),
+                accounts.equality_proof.to_account_info(),
+                accounts.ciphertext_validity_proof.to_account_info(),
+                accounts.range_proof.to_account_info(),
+                accounts.sender.to_account_info(),
+            ],
+        )?;
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        sequence_escrow_update(escrow)?;
+
+        emit!(ConfidentialTokenPaymentEvent {
+            escrow: escrow.key(),
+            sequence: escrow.sequence,
+            sender: ctx.accounts.sender.key(),
+            recipient,
+            mint,
+            fee_lamports: CONFIDENTIAL_TOKEN_FEE_LAMPORTS,
+            timestamp: escrow.last_updated,
+        });
+
+        Ok(())
+    }
+
+    /// Open an OTC swap: the maker deposits `amount_a` of `mint_a` and
+    /// records the minimum of `mint_b` they will accept, encrypted.
+    pub fn create_otc_swap(
+        ctx: Context<CreateOtcSwap>,
+        swap_id: u64,
+        taker: Pubkey,
+        amount_a: u64,
+        expires_at: Deadline,
+        terms_encryption_pubkey: [u8; 32],
+        terms_nonce: u128,
+        encrypted_min_b: [u8; 32],
+    ) -> Result<()> {
+        require!(amount_a > 0, EscrowError::InvalidOtcAmount);
+        require!(
+            !expires_at.has_passed(&Clock::get()?),
+            EscrowError::OtcSwapExpired
+        );
+
+        let swap = &mut ctx.accounts.otc_swap;
+        swap.swap_id = swap_id;
+        swap.maker = ctx.accounts.maker.key();
+        swap.taker = taker;
+        swap.mint_a = ctx.accounts.mint_a.key();
+        swap.mint_b = ctx.accounts.mint_b.key();
+        swap.amount_a = amount_a;
+        swap.amount_b = 0;
+        swap.maker_terms = EncryptedDelta {
+            encryption_pubkey: terms_encryption_pubkey,
+            nonce: terms_nonce,
+            ciphertext: encrypted_min_b,
+        };
+        swap.taker_terms = EncryptedDelta::default();
+        swap.expires_at = expires_at;
+        swap.status = OtcSwapStatus::Open;
+        swap.pending_computation_offset = None;
+        swap.bump = ctx.bumps.otc_swap;
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            anchor_token::Transfer {
+                from: ctx.accounts.maker_token_a.to_account_info(),
+                to: ctx.accounts.vault_a.to_account_info(),
+                authority: ctx.accounts.maker.to_account_info(),
+            },
+        );
+        anchor_token::transfer(cpi_ctx, amount_a)?;
+
+        emit!(OtcSwapEvent {
+            otc_swap: swap.key(),
+            status: swap.status,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// The counterparty deposits `amount_b` of `mint_b` with their own
+    /// encrypted minimum of `mint_a`.
+    pub fn accept_otc_swap(
+        ctx: Context<AcceptOtcSwap>,
+        amount_b: u64,
+        terms_encryption_pubkey: [u8; 32],
+        terms_nonce: u128,
+        encrypted_min_a: [u8; 32],
+    ) -> Result<()> {
+        require!(amount_b > 0, EscrowError::InvalidOtcAmount);
+        let swap = &mut ctx.accounts.otc_swap;
+        require!(swap.status == OtcSwapStatus::Open, EscrowError::InvalidOtcStatus);
+        require!(
+            !swap.expires_at.has_passed(&Clock::get()?),
+            EscrowError::OtcSwapExpired
+        );
+
+        swap.amount_b = amount_b;
+        swap.taker_terms = EncryptedDelta {
+            encryption_pubkey: terms_encryption_pubkey,
+            nonce: terms_nonce,
+            ciphertext: encrypted_min_a,
+        };
+        swap.status = OtcSwapStatus::Funded;
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            anchor_token::Transfer {
+                from: ctx.accounts.taker_token_b.to_account_info(),
+                to: ctx.accounts.vault_b.to_account_info(),
+                authority: ctx.accounts.taker.to_account_info(),
+            },
+        );
+        anchor_token::transfer(cpi_ctx, amount_b)?;
+
+        emit!(OtcSwapEvent {
+            otc_swap: swap.key(),
+            status: swap.status,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Ask the MPC cluster whether both deposits satisfy the other side's
+    /// encrypted terms. Either party may trigger it once both are funded.
+    pub fn verify_otc_swap(ctx: Context<VerifyOtcSwap>, computation_offset: u64) -> Result<()> {
+        let swap_key = ctx.accounts.otc_swap.key();
+        let computation_key = ctx.accounts.computation_account.key();
+        let swap = &ctx.accounts.otc_swap;
+        require!(swap.status == OtcSwapStatus::Funded, EscrowError::InvalidOtcStatus);
+        let party = ctx.accounts.party.key();
+        require!(
+            party == swap.maker || party == swap.taker,
+            EscrowError::InvalidAuthority
+        );
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        #[cfg(not(feature = "mock-mpc"))]
+        {
+            let args = vec![
+                Argument::ArcisPubkey(swap.maker_terms.encryption_pubkey),
+                Argument::PlaintextU128(swap.maker_terms.nonce),
+                Argument::EncryptedU64(swap.maker_terms.ciphertext),
+                Argument::ArcisPubkey(swap.taker_terms.encryption_pubkey),
+                Argument::PlaintextU128(swap.taker_terms.nonce),
+                Argument::EncryptedU64(swap.taker_terms.ciphertext),
+                Argument::PlaintextU64(swap.amount_a),
+                Argument::PlaintextU64(swap.amount_b),
+            ];
+
+            queue_computation(
+                ctx.accounts,
+                computation_offset,
+                args,
+                None,
+                vec![VerifyOtcTermsCallback::callback_ix(&[
+                    CallbackAccount {
+                        pubkey: swap_key,
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: computation_key,
+                        is_writable: false,
+                    },
+                ])],
+                1,
+            )?;
+        }
+
+        let swap = &mut ctx.accounts.otc_swap;
+        swap.status = OtcSwapStatus::Verifying;
+        swap.pending_computation_offset = Some(computation_offset);
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "verify_otc_terms")]
+    pub fn verify_otc_terms_callback(
+        ctx: Context<VerifyOtcTermsCallback>,
+        output: ComputationOutputs<VerifyOtcTermsOutput>,
+    ) -> Result<()> {
+        let terms_met = match output {
+            ComputationOutputs::Success(VerifyOtcTermsOutput { field_0 }) => field_0,
+            _ => return Err(EscrowError::AbortedComputation.into()),
+        };
+
+        verify_pending_computation(
+            ctx.accounts.otc_swap.pending_computation_offset,
+            &ctx.accounts.computation_account.key(),
+        )?;
+
+        let swap = &mut ctx.accounts.otc_swap;
+        swap.pending_computation_offset = None;
+        swap.status = if terms_met {
+            OtcSwapStatus::Verified
+        } else {
+            OtcSwapStatus::Rejected
+        };
+
+        emit!(OtcSwapEvent {
+            otc_swap: swap.key(),
+            status: swap.status,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Atomically hand each vault to the other party once the terms check
+    /// passed.
+    pub fn settle_otc_swap(ctx: Context<SettleOtcSwap>) -> Result<()> {
+        require!(
+            ctx.accounts.otc_swap.status == OtcSwapStatus::Verified,
+            EscrowError::InvalidOtcStatus
+        );
+
+        let swap = &ctx.accounts.otc_swap;
+        let swap_id = swap.swap_id.to_le_bytes();
+        let seeds: &[&[u8]] = &[seeds::OTC_SWAP, swap.maker.as_ref(), &swap_id, &[swap.bump]];
+        let signer = &[seeds];
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let authority = ctx.accounts.otc_swap.to_account_info();
+
+        let cpi_to_taker = CpiContext::new_with_signer(
+            token_program.clone(),
+            anchor_token::Transfer {
+                from: ctx.accounts.vault_a.to_account_info(),
+                to: ctx.accounts.taker_token_a.to_account_info(),
+                authority: authority.clone(),
+            },
+            signer,
+        );
+        anchor_token::transfer(cpi_to_taker, swap.amount_a)?;
+
+        let cpi_to_maker = CpiContext::new_with_signer(
+            token_program,
+            anchor_token::Transfer {
+                from: ctx.accounts.vault_b.to_account_info(),
+                to: ctx.accounts.maker_token_b.to_account_info(),
+                authority,
+            },
+            signer,
+        );
+        anchor_token::transfer(cpi_to_maker, swap.amount_b)?;
+
+        let swap = &mut ctx.accounts.otc_swap;
+        swap.status = OtcSwapStatus::Settled;
+
+        emit!(OtcSwapEvent {
+            otc_swap: swap.key(),
+            status: swap.status,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Return deposits to their owners when the terms were rejected or the
+    /// swap expired before settling.
+    pub fn refund_otc_swap(ctx: Context<SettleOtcSwap>) -> Result<()> {
+        let swap = &ctx.accounts.otc_swap;
+        let expired = swap.expires_at.has_passed(&Clock::get()?);
+        let refundable = match swap.status {
+            OtcSwapStatus::Rejected => true,
+            OtcSwapStatus::Open | OtcSwapStatus::Funded | OtcSwapStatus::Verified => expired,
+            // A check that never called back is abandoned at expiry; a late
+            // callback then fails the pending-computation binding.
+            OtcSwapStatus::Verifying => expired,
+            OtcSwapStatus::Settled | OtcSwapStatus::Refunded => false,
+        };
+        require!(refundable, EscrowError::InvalidOtcStatus);
+
+        let swap_id = swap.swap_id.to_le_bytes();
+        let seeds: &[&[u8]] = &[seeds::OTC_SWAP, swap.maker.as_ref(), &swap_id, &[swap.bump]];
+        let signer = &[seeds];
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let authority = ctx.accounts.otc_swap.to_account_info();
+
+        let cpi_to_maker = CpiContext::new_with_signer(
+            token_program.clone(),
+            anchor_token::Transfer {
+                from: ctx.accounts.vault_a.to_account_info(),
+                to: ctx.accounts.maker_token_a.to_account_info(),
+                authority: authority.clone(),
+            },
+            signer,
+        );
+        anchor_token::transfer(cpi_to_maker, ctx.accounts.vault_a.amount)?;
+
+        if ctx.accounts.vault_b.amount > 0 {
+            let cpi_to_taker = CpiContext::new_with_signer(
+                token_program,
+                anchor_token::Transfer {
+                    from: ctx.accounts.vault_b.to_account_info(),
+                    to: ctx.accounts.taker_token_b.to_account_info(),
+                    authority,
+                },
+                signer,
+            );
+            anchor_token::transfer(cpi_to_taker, ctx.accounts.vault_b.amount)?;
+        }
+
+        let swap = &mut ctx.accounts.otc_swap;
+        swap.status = OtcSwapStatus::Refunded;
+        swap.pending_computation_offset = None;
+
+        emit!(OtcSwapEvent {
+            otc_swap: swap.key(),
+            status: swap.status,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Create a recurring payroll paid from a schedule-owned token vault.
+    pub fn create_payroll_schedule(
+        ctx: Context<CreatePayrollSchedule>,
+        schedule_id: u64,
+        period_secs: i64,
+        first_run_at: i64,
+    ) -> Result<()> {
+        ctx.accounts.escrow.ensure_active(ctx.accounts.jurisdiction_pause.as_deref())?;
+        require!(period_secs > 0, EscrowError::InvalidPayrollPeriod);
+
+        let schedule = &mut ctx.accounts.payroll_schedule;
+        schedule.escrow = ctx.accounts.escrow.key();
+        schedule.schedule_id = schedule_id;
+        schedule.mint = ctx.accounts.mint.key();
+        schedule.period_secs = period_secs;
+        schedule.next_run_at = first_run_at;
+        schedule.recipient_count = 0;
+        schedule.recipients = [Pubkey::default(); MAX_PAYROLL_RECIPIENTS];
+        schedule.salaries_encryption_pubkey = [0; 32];
+        schedule.salaries_nonce = 0;
+        schedule.encrypted_salaries = [[0; 32]; MAX_PAYROLL_RECIPIENTS];
+        schedule.pending_computation_offset = None;
+        schedule.bump = ctx.bumps.payroll_schedule;
+
+        Ok(())
+    }
+
+    /// Replace the recipient list and their salaries. Salaries are encrypted
+    /// client-side as one `PayrollSalaries` struct; empty slots must encrypt 0.
+    pub fn set_payroll_recipients(
+        ctx: Context<UpdatePayrollSchedule>,
+        recipients: Vec<Pubkey>,
+        salaries_encryption_pubkey: [u8; 32],
+        salaries_nonce: u128,
+        encrypted_salaries: [[u8; 32]; MAX_PAYROLL_RECIPIENTS],
+    ) -> Result<()> {
+        require!(
+            !recipients.is_empty() && recipients.len() <= MAX_PAYROLL_RECIPIENTS,
+            EscrowError::TooManyPayrollRecipients
+        );
+        let schedule = &mut ctx.accounts.payroll_schedule;
+        require!(
+            schedule.pending_computation_offset.is_none(),
+            EscrowError::StatsComputationPending
+        );
+
+        schedule.recipients = [Pubkey::default(); MAX_PAYROLL_RECIPIENTS];
+        schedule.recipients[..recipients.len()].copy_from_slice(&recipients);
+        schedule.recipient_count = recipients.len() as u8;
+        schedule.salaries_encryption_pubkey = salaries_encryption_pubkey;
+        schedule.salaries_nonce = salaries_nonce;
+        schedule.encrypted_salaries = encrypted_salaries;
+
+        Ok(())
+    }
+
+    pub fn fund_payroll(ctx: Context<FundPayroll>, amount: u64) -> Result<()> {
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            anchor_token::Transfer {
+                from: ctx.accounts.funder_token_account.to_account_info(),
+                to: ctx.accounts.payroll_vault.to_account_info(),
+                authority: ctx.accounts.funder.to_account_info(),
+            },
+        );
+        anchor_token::transfer(cpi_ctx, amount)
+    }
+
+    /// Permissionless crank: once a period has elapsed, queue the payroll
+    /// circuit. Payouts happen in the callback.
+    pub fn run_payroll(ctx: Context<RunPayroll>, computation_offset: u64) -> Result<()> {
+        let schedule_key = ctx.accounts.payroll_schedule.key();
+        let computation_key = ctx.accounts.computation_account.key();
+        let now = Clock::get()?.unix_timestamp;
+
+        ctx.accounts.escrow.ensure_active(ctx.accounts.jurisdiction_pause.as_deref())?;
+        let schedule = &ctx.accounts.payroll_schedule;
+        require!(schedule.recipient_count > 0, EscrowError::TooManyPayrollRecipients);
+        require!(now >= schedule.next_run_at, EscrowError::PayrollNotDue);
+        require!(
+            schedule.pending_computation_offset.is_none(),
+            EscrowError::StatsComputationPending
+        );
+
+        if let Some(vault) = ctx.accounts.compute_budget_vault.as_mut() {
+            pay_crank_reward(vault, &ctx.accounts.payer.to_account_info(), CrankKind::RunPayroll)?;
+        }
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        #[cfg(not(feature = "mock-mpc"))]
+        {
+            let schedule = &ctx.accounts.payroll_schedule;
+            let mut args = Vec::with_capacity(MAX_PAYROLL_RECIPIENTS + 3);
+            args.push(Argument::ArcisPubkey(schedule.salaries_encryption_pubkey));
+            args.push(Argument::PlaintextU128(schedule.salaries_nonce));
+            for ciphertext in schedule.encrypted_salaries.iter() {
+                args.push(Argument::EncryptedU64(*ciphertext));
+            }
+            args.push(Argument::PlaintextU8(schedule.recipient_count));
+
+            // The callback pays out of the vault to each recipient's token
+            // account, so all of them ride along as callback accounts.
+            let mut callback_accounts = vec![
+                CallbackAccount {
+                    pubkey: schedule_key,
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: computation_key,
+                    is_writable: false,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.payroll_vault.key(),
+                    is_writable: true,
+                },
+                CallbackAccount {
+                    pubkey: ctx.accounts.token_program.key(),
+                    is_writable: false,
+                },
+            ];
+            for recipient in schedule.recipients[..schedule.recipient_count as usize].iter() {
+                callback_accounts.push(CallbackAccount {
+                    pubkey: *recipient,
+                    is_writable: true,
+                });
+            }
+
+            queue_computation(
+                ctx.accounts,
+                computation_offset,
+                args,
+                None,
+                vec![RunPayrollCallback::callback_ix(&callback_accounts)],
+                1,
+            )?;
+        }
+
+        let schedule = &mut ctx.accounts.payroll_schedule;
+        schedule.pending_computation_offset = Some(computation_offset);
+        // Advance from the scheduled time, not `now`, so a late crank doesn't
+        // shift every following period.
+        schedule.next_run_at = schedule
+            .next_run_at
+            .checked_add(schedule.period_secs)
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "run_payroll")]
+    pub fn run_payroll_callback(
+        ctx: Context<RunPayrollCallback>,
+        output: ComputationOutputs<RunPayrollOutput>,
+    ) -> Result<()> {
+        let payouts = match output {
+            ComputationOutputs::Success(RunPayrollOutput { field_0 }) => field_0,
+            _ => return Err(EscrowError::AbortedComputation.into()),
+        };
+
+        verify_pending_computation(
+            ctx.accounts.payroll_schedule.pending_computation_offset,
+            &ctx.accounts.computation_account.key(),
+        )?;
+
+        let schedule = &ctx.accounts.payroll_schedule;
+        let count = schedule.recipient_count as usize;
+        require!(
+            ctx.remaining_accounts.len() == count,
+            EscrowError::TooManyPayrollRecipients
+        );
+
+        let escrow_key = schedule.escrow;
+        let schedule_id = schedule.schedule_id.to_le_bytes();
+        let seeds: &[&[u8]] =
+            &[seeds::PAYROLL, escrow_key.as_ref(), &schedule_id, &[schedule.bump]];
+        let signer = &[seeds];
+
+        let mut total: u64 = 0;
+        for (i, recipient) in ctx.remaining_accounts.iter().enumerate() {
+            require_keys_eq!(
+                recipient.key(),
+                schedule.recipients[i],
+                EscrowError::InvalidAuthority
+            );
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                anchor_token::Transfer {
+                    from: ctx.accounts.payroll_vault.to_account_info(),
+                    to: recipient.clone(),
+                    authority: ctx.accounts.payroll_schedule.to_account_info(),
+                },
+                signer,
+            );
+            anchor_token::transfer(cpi_ctx, payouts[i])?;
+            total = total
+                .checked_add(payouts[i])
+                .ok_or(ProgramError::InvalidArgument)?;
+        }
+
+        ctx.accounts.payroll_schedule.pending_computation_offset = None;
+
+        // Only the period total is published; per-recipient amounts stay off
+        // the event stream.
+        emit!(PayrollRunEvent {
+            payroll_schedule: ctx.accounts.payroll_schedule.key(),
+            recipients: count as u8,
+            total,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Create the escrow-owned token vault for `mint` that vault payments
+    /// draw from. Anyone can top it up with a plain token transfer.
+    pub fn init_escrow_vault(ctx: Context<InitEscrowVault>) -> Result<()> {
+        emit!(EscrowVaultCreatedEvent {
+            escrow: ctx.accounts.escrow.key(),
+            vault: ctx.accounts.escrow_vault.key(),
+            mint: ctx.accounts.mint.key(),
+        });
+        Ok(())
+    }
+
+    pub fn set_spending_policy(
+        ctx: Context<SetSpendingPolicy>,
+        policy: SpendingPolicyRules,
+    ) -> Result<()> {
+        require!(
+            policy.allowed_mints.len() <= MAX_POLICY_MINTS
+                && policy.allowed_recipients.len() <= MAX_POLICY_RECIPIENTS,
+            EscrowError::PolicyListTooLong
+        );
+        require!(
+            policy.window_start_hour < 24 && policy.window_end_hour < 24,
+            EscrowError::InvalidPolicyWindow
+        );
+
+        let spending_policy = &mut ctx.accounts.spending_policy;
+        spending_policy.escrow = ctx.accounts.escrow.key();
+        spending_policy.rules = policy;
+        spending_policy.bump = ctx.bumps.spending_policy;
+        ctx.accounts.escrow.last_updated = Clock::get()?.unix_timestamp;
+
+        sequence_escrow_update(&mut ctx.accounts.escrow)?;
+        Ok(())
+    }
+
+    /// Pay out of the escrow vault, subject to the escrow's spending policy
+    /// when one is configured. Fees split the same way as sender payments.
+    pub fn send_payment_from_vault(
+        ctx: Context<SendPaymentFromVault>,
+        payment_id: u64,
+        referal: Pubkey,
+        amount: u64,
+        valid_after_slot: Option<u64>,
+        valid_before_slot: Option<u64>,
+    ) -> Result<()> {
+        check_slot_window(valid_after_slot, valid_before_slot)?;
+        check_relayer(
+            ctx.accounts.relayer.as_ref(),
+            ctx.accounts.relayer_registry.as_deref(),
+        )?;
+
+        ctx.accounts.escrow.ensure_active(ctx.accounts.jurisdiction_pause.as_deref())?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let mint = ctx.accounts.escrow_vault.mint;
+        let recipient = ctx.accounts.recipient_token_account.owner;
+        if let Some(policy) = ctx.accounts.spending_policy.as_ref() {
+            policy.rules.check(
+                &mint,
+                &recipient,
+                amount,
+                now,
+                ctx.accounts.cosigner.as_ref().map(|signer| signer.key()),
+            )?;
+        }
+
+        let payment = &mut ctx.accounts.payment;
+        payment.sender = ctx.accounts.escrow.key();
+        payment.recipient = recipient;
+        payment.referal = referal;
+        payment.amount = amount;
+        payment.timestamp = now;
+        payment.asset_mint = mint;
+        payment.intent_hash = payment_intent_hash(
+            &payment.sender,
+            &payment.recipient,
+            IntentAmount::Plain(amount),
+            &payment.asset_mint,
+            payment_id as u128,
+        );
+        (payment.referal_reward, payment.treasury_reward) = tiered_fee_split(
+            amount,
+            ctx.accounts.fee_config.as_deref(),
+            ctx.accounts.escrow.total_fund_regulated,
+        )?;
+        (payment.referal_reward, payment.treasury_reward) = apply_campaign_boost(
+            ctx.accounts.campaign.as_mut(),
+            &ctx.accounts.escrow.key(),
+            &mint,
+            amount,
+            payment.referal_reward,
+            payment.treasury_reward,
+            now,
+        )?;
+
+        let fees = payment
+            .referal_reward
+            .checked_add(payment.treasury_reward)
+            .ok_or(ProgramError::InvalidArgument)?;
+        let transferable_amount = amount
+            .checked_sub(fees)
+            .ok_or(ProgramError::InvalidArgument)?;
+        assert_fee_split(
+            amount,
+            &[transferable_amount, payment.treasury_reward, payment.referal_reward],
+        )?;
+
+        if ctx.accounts.escrow.approval_threshold > 0 {
+            require!(
+                amount < ctx.accounts.escrow.approval_threshold,
+                EscrowError::ApprovalRequired
+            );
+        }
+
+        // Funds stay in the vault until compliance releases or rejects it.
+        if ctx.accounts.escrow.quarantine_active {
+            let quarantine = ctx
+                .accounts
+                .quarantine
+                .as_mut()
+                .ok_or(EscrowError::QuarantineRequired)?;
+            require!(
+                quarantine.entries.len() < MAX_QUARANTINED_PAYMENTS,
+                EscrowError::QuarantineFull
+            );
+            quarantine.entries.push(QuarantinedPayment {
+                payment_id,
+                escrow_vault: ctx.accounts.escrow_vault.key(),
+                recipient_token_account: ctx.accounts.recipient_token_account.key(),
+                treasury_token_account: ctx.accounts.treasury_token_account.key(),
+                referral_token_account: ctx.accounts.referral_token_account.key(),
+                net_amount: transferable_amount,
+                treasury_reward: payment.treasury_reward,
+                referal_reward: payment.referal_reward,
+                quarantined_at: now,
+            });
+
+            let sequence = sequence_escrow_update(&mut ctx.accounts.escrow)?;
+            emit!(QuarantineEvent {
+                escrow: ctx.accounts.escrow.key(),
+                sequence,
+                payment_id,
+                amount,
+                action: QuarantineAction::Quarantined,
+                timestamp: now,
+            });
+            return Ok(());
+        }
+
+        // Outside business hours the payment waits for
+        // `drain_settlement_window`; funds stay in the vault meanwhile.
+        if ctx.accounts.escrow.settlement_window_active {
+            let window = ctx
+                .accounts
+                .settlement_window
+                .as_mut()
+                .ok_or(EscrowError::SettlementWindowRequired)?;
+            if !window.is_open(now) {
+                require!(
+                    window.entries.len() < MAX_DEFERRED_PAYMENTS,
+                    EscrowError::SettlementQueueFull
+                );
+                window.entries.push(QuarantinedPayment {
+                    payment_id,
+                    escrow_vault: ctx.accounts.escrow_vault.key(),
+                    recipient_token_account: ctx.accounts.recipient_token_account.key(),
+                    treasury_token_account: ctx.accounts.treasury_token_account.key(),
+                    referral_token_account: ctx.accounts.referral_token_account.key(),
+                    net_amount: transferable_amount,
+                    treasury_reward: payment.treasury_reward,
+                    referal_reward: payment.referal_reward,
+                    quarantined_at: now,
+                });
+
+                let sequence = sequence_escrow_update(&mut ctx.accounts.escrow)?;
+                emit!(SettlementWindowEvent {
+                    escrow: ctx.accounts.escrow.key(),
+                    sequence,
+                    payment_id,
+                    amount,
+                    action: SettlementWindowAction::Deferred,
+                    timestamp: now,
+                });
+                return Ok(());
+            }
+        }
+
+        let escrow = &ctx.accounts.escrow;
+        let vault = ctx.accounts.escrow_vault.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+        transfer_from_escrow_vault(
+            escrow,
+            &vault,
+            &ctx.accounts.recipient_token_account.to_account_info(),
+            &token_program,
+            transferable_amount,
+        )?;
+        transfer_from_escrow_vault(
+            escrow,
+            &vault,
+            &ctx.accounts.treasury_token_account.to_account_info(),
+            &token_program,
+            payment.treasury_reward,
+        )?;
+        transfer_from_escrow_vault(
+            escrow,
+            &vault,
+            &ctx.accounts.referral_token_account.to_account_info(),
+            &token_program,
+            payment.referal_reward,
+        )?;
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.total_fund_regulated = escrow
+            .total_fund_regulated
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        let sequence = sequence_escrow_update(escrow)?;
+        emit!(VaultPaymentEvent {
+            escrow: escrow.key(),
+            sequence,
+            payment_id,
+            recipient,
+            amount,
+            asset_mint: mint,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// Configure when vault payments need a second signer's approval.
+    /// A zero threshold turns the approval flow off.
+    pub fn set_approval_config(
+        ctx: Context<UpdateEscrowActive>,
+        approval_threshold: u64,
+        approver: Pubkey,
+        approval_window_secs: i64,
+    ) -> Result<()> {
+        require!(approval_window_secs > 0, EscrowError::InvalidApprovalWindow);
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.approval_threshold = approval_threshold;
+        escrow.approver = approver;
+        escrow.approval_window_secs = approval_window_secs;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        if let Some(log) = ctx.accounts.activity_log.as_mut() {
+            log.record_with_key(
+                ActivityKind::ApprovalConfigChanged,
+                ctx.accounts.owner.key(),
+                approver,
+            )?;
+        }
+        sequence_escrow_update(escrow)?;
+        Ok(())
+    }
+
+    /// Park a vault payment at or above the approval threshold until the
+    /// approver signs off. Nothing moves until `approve_pending_payment`.
+    pub fn request_vault_payment(
+        ctx: Context<RequestVaultPayment>,
+        approval_id: u64,
+        referal: Pubkey,
+        amount: u64,
+    ) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        escrow.ensure_active(ctx.accounts.jurisdiction_pause.as_deref())?;
+        require!(
+            escrow.approval_threshold > 0 && amount >= escrow.approval_threshold,
+            EscrowError::ApprovalNotRequired
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let mint = ctx.accounts.escrow_vault.mint;
+        let recipient = ctx.accounts.recipient_token_account.owner;
+        if let Some(policy) = ctx.accounts.spending_policy.as_ref() {
+            // The approver acts as the co-signer for policy purposes.
+            policy
+                .rules
+                .check(&mint, &recipient, amount, now, Some(escrow.approver))?;
+        }
+
+        let pending = &mut ctx.accounts.pending_approval;
+        pending.escrow = escrow.key();
+        pending.approval_id = approval_id;
+        pending.mint = mint;
+        pending.recipient_token_account = ctx.accounts.recipient_token_account.key();
+        pending.referral_token_account = ctx.accounts.referral_token_account.key();
+        pending.referal = referal;
+        pending.amount = amount;
+        pending.requested_at = now;
+        pending.expires_at = now
+            .checked_add(escrow.approval_window_secs)
+            .ok_or(ProgramError::InvalidArgument)?;
+        pending.bump = ctx.bumps.pending_approval;
+
+        emit!(PendingApprovalEvent {
+            pending_approval: pending.key(),
+            escrow: pending.escrow,
+            sequence: ctx.accounts.escrow.sequence,
+            amount,
+            approved: false,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// Approver releases a parked vault payment within its window.
+    pub fn approve_pending_payment(ctx: Context<ApprovePendingPayment>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let pending = &ctx.accounts.pending_approval;
+        require!(now < pending.expires_at, EscrowError::ApprovalExpired);
+        ctx.accounts.escrow.ensure_active(ctx.accounts.jurisdiction_pause.as_deref())?;
+
+        let amount = pending.amount;
+        let (referral_fee, treasury_fee) = tiered_fee_split(
+            amount,
+            ctx.accounts.fee_config.as_deref(),
+            ctx.accounts.escrow.total_fund_regulated,
+        )?;
+        let fees = referral_fee
+            .checked_add(treasury_fee)
+            .ok_or(ProgramError::InvalidArgument)?;
+        let transferable_amount = amount
+            .checked_sub(fees)
+            .ok_or(ProgramError::InvalidArgument)?;
+        assert_fee_split(amount, &[transferable_amount, treasury_fee, referral_fee])?;
+
+        let escrow = &ctx.accounts.escrow;
+        let vault = ctx.accounts.escrow_vault.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+        transfer_from_escrow_vault(
+            escrow,
+            &vault,
+            &ctx.accounts.recipient_token_account.to_account_info(),
+            &token_program,
+            transferable_amount,
+        )?;
+        transfer_from_escrow_vault(
+            escrow,
+            &vault,
+            &ctx.accounts.treasury_token_account.to_account_info(),
+            &token_program,
+            treasury_fee,
+        )?;
+        transfer_from_escrow_vault(
+            escrow,
+            &vault,
+            &ctx.accounts.referral_token_account.to_account_info(),
+            &token_program,
+            referral_fee,
+        )?;
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.total_fund_regulated = escrow
+            .total_fund_regulated
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        if let Some(log) = ctx.accounts.activity_log.as_mut() {
+            log.record(ActivityKind::LargePayment, ctx.accounts.approver.key(), amount)?;
+        }
+
+        let sequence = sequence_escrow_update(escrow)?;
+        emit!(PendingApprovalEvent {
+            pending_approval: ctx.accounts.pending_approval.key(),
+            escrow: escrow.key(),
+            sequence,
+            amount,
+            approved: true,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// Drop a parked payment. The owner can cancel at any time; anyone can
+    /// clean up once the approval window has lapsed.
+    pub fn cancel_pending_payment(ctx: Context<CancelPendingPayment>) -> Result<()> {
+        let pending = &ctx.accounts.pending_approval;
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            ctx.accounts.closer.key() == ctx.accounts.escrow.authority()
+                || now >= pending.expires_at,
+            EscrowError::InvalidAuthority
+        );
+        Ok(())
+    }
+
+    /// Create the referrer's encrypted stats account and queue its zeroed
+    /// initial state through `init_referral_stats`.
+    pub fn initialize_referral_stats(
+        ctx: Context<InitializeReferralStats>,
+        computation_offset: u64,
+        nonce: u128,
+    ) -> Result<()> {
+        let referral_stats_key = ctx.accounts.referral_stats.key();
+        let computation_key = ctx.accounts.computation_account.key();
+
+        let referral_stats = &mut ctx.accounts.referral_stats;
+        referral_stats.referrer = ctx.accounts.referrer.key();
+        referral_stats.bump = ctx.bumps.referral_stats;
+        referral_stats.nonce = nonce;
+        referral_stats.encrypted_stats = [[0; 32]; 2];
+        referral_stats.pending_computation_offset = Some(computation_offset);
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        #[cfg(not(feature = "mock-mpc"))]
+        {
+            let args = vec![Argument::PlaintextU128(nonce)];
+
+            queue_computation(
+                ctx.accounts,
+                computation_offset,
+                args,
+                None,
+                vec![InitReferralStatsCallback::callback_ix(&[
+                    CallbackAccount {
+                        pubkey: referral_stats_key,
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: computation_key,
+                        is_writable: false,
+                    },
+                ])],
+                1,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "init_referral_stats")]
+    pub fn init_referral_stats_callback(
+        ctx: Context<InitReferralStatsCallback>,
+        output: ComputationOutputs<InitReferralStatsOutput>,
+    ) -> Result<()> {
+        let o = match output {
+            ComputationOutputs::Success(InitReferralStatsOutput { field_0 }) => field_0,
+            _ => return Err(EscrowError::AbortedComputation.into()),
+        };
+
+        let referral_stats = &mut ctx.accounts.referral_stats;
+        verify_pending_computation(
+            referral_stats.pending_computation_offset,
+            &ctx.accounts.computation_account.key(),
+        )?;
+
+        referral_stats.encrypted_stats = o.ciphertexts;
+        referral_stats.nonce = o.nonce;
+        referral_stats.pending_computation_offset = None;
+
+        Ok(())
+    }
+
+    /// Re-encrypt the referrer's lifetime totals to `report_pubkey` so they
+    /// can check their earnings without anything being revealed on-chain.
+    pub fn request_referral_report(
+        ctx: Context<RequestReferralReport>,
+        computation_offset: u64,
+        report_pubkey: [u8; 32],
+        report_nonce: u128,
+    ) -> Result<()> {
+        require!(
+            ctx.accounts.referral_stats.pending_computation_offset.is_none(),
+            EscrowError::StatsComputationPending
+        );
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        #[cfg(not(feature = "mock-mpc"))]
+        {
+            let args = vec![
+                Argument::ArcisPubkey(report_pubkey),
+                Argument::PlaintextU128(report_nonce),
+                Argument::PlaintextU128(ctx.accounts.referral_stats.nonce),
+                Argument::Account(
+                    ctx.accounts.referral_stats.key(),
+                    ReferralStatsAccount::ENCRYPTED_STATS_OFFSET,
+                    32 * 2,
+                ),
+            ];
+
+            queue_computation(
+                ctx.accounts,
+                computation_offset,
+                args,
+                None,
+                vec![ReferralReportCallback::callback_ix(&[CallbackAccount {
+                    pubkey: ctx.accounts.referral_stats.key(),
+                    is_writable: false,
+                }])],
+                1,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "referral_report")]
+    pub fn referral_report_callback(
+        ctx: Context<ReferralReportCallback>,
+        output: ComputationOutputs<ReferralReportOutput>,
+    ) -> Result<()> {
+        let o = match output {
+            ComputationOutputs::Success(ReferralReportOutput { field_0 }) => field_0,
+            _ => return Err(EscrowError::AbortedComputation.into()),
+        };
+
+        emit!(ReferralReportEvent {
+            referrer: ctx.accounts.referral_stats.referrer,
+            encryption_key: o.encryption_key,
+            nonce: o.nonce,
+            ciphertexts: o.ciphertexts,
+        });
+
+        Ok(())
+    }
+
+    /// Like `check_volume_threshold`, but against the decayed recent volume,
+    /// so it reflects current activity rather than lifetime totals.
+    pub fn check_recent_volume_threshold(
+        ctx: Context<CheckRecentVolumeThreshold>,
+        computation_offset: u64,
+        threshold: u64,
+    ) -> Result<()> {
+        authorize_analytics_request(
+            &ctx.accounts.escrow,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.viewer.as_ref(),
+            ctx.accounts.analytics_operator.as_mut(),
+        )?;
+        ctx.accounts.escrow.require_stats_dimension(STATS_DIMENSION_VOLUME)?;
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        if let Some(log) = ctx.accounts.activity_log.as_mut() {
+            log.record(ActivityKind::ThresholdChecked, ctx.accounts.authority.key(), threshold)?;
+        }
+
+        #[cfg(not(feature = "mock-mpc"))]
+        {
+            let decay_periods =
+                recent_volume_periods(&ctx.accounts.escrow, Clock::get()?.unix_timestamp);
+            let args = vec![
+                Argument::PlaintextU128(ctx.accounts.escrow.nonce),
+                Argument::Account(
+                    ctx.accounts.escrow.key(),
+                    EscrowAccount::ENCRYPTED_STATS_OFFSET,
+                    32 * ESCROW_STATS_FIELDS as u32,
+                ),
+                Argument::PlaintextU8(decay_periods),
+                Argument::PlaintextU64(threshold),
+            ];
+
+            queue_computation(
+                ctx.accounts,
+                computation_offset,
+                args,
+                None,
+                vec![CheckRecentVolumeThresholdCallback::callback_ix(&[])],
+                1,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "check_recent_volume_threshold")]
+    pub fn check_recent_volume_threshold_callback(
+        ctx: Context<CheckRecentVolumeThresholdCallback>,
+        output: ComputationOutputs<CheckRecentVolumeThresholdOutput>,
+    ) -> Result<()> {
+        let result = match output {
+            ComputationOutputs::Success(CheckRecentVolumeThresholdOutput { field_0 }) => field_0,
+            _ => return Err(EscrowError::AbortedComputation.into()),
+        };
+
+        emit!(RecentVolumeThresholdCheckEvent {
+            meets_threshold: result,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Alert check on the share of volume refunded: reveals only whether
+    /// refund volume is at least `threshold_bps` of payment volume.
+    pub fn check_refund_rate(
+        ctx: Context<CheckRefundRate>,
+        computation_offset: u64,
+        threshold_bps: u16,
+    ) -> Result<()> {
+        authorize_analytics_request(
+            &ctx.accounts.escrow,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.viewer.as_ref(),
+            ctx.accounts.analytics_operator.as_mut(),
+        )?;
+        ctx.accounts.escrow.require_stats_dimension(STATS_DIMENSION_VOLUME)?;
+        ctx.accounts.escrow.require_stats_dimension(STATS_DIMENSION_REFUNDS)?;
+        require!(threshold_bps <= 10_000, EscrowError::InvalidRefundRateThreshold);
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        if let Some(log) = ctx.accounts.activity_log.as_mut() {
+            log.record(
+                ActivityKind::ThresholdChecked,
+                ctx.accounts.authority.key(),
+                threshold_bps as u64,
+            )?;
+        }
+
+        #[cfg(not(feature = "mock-mpc"))]
+        {
+            let args = vec![
+                Argument::PlaintextU128(ctx.accounts.escrow.nonce),
+                Argument::Account(
+                    ctx.accounts.escrow.key(),
+                    EscrowAccount::ENCRYPTED_STATS_OFFSET,
+                    32 * ESCROW_STATS_FIELDS as u32,
+                ),
+                Argument::PlaintextU16(threshold_bps),
+            ];
+
+            queue_computation(
+                ctx.accounts,
+                computation_offset,
+                args,
+                None,
+                vec![CheckRefundRateCallback::callback_ix(&[])],
+                1,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "check_refund_rate")]
+    pub fn check_refund_rate_callback(
+        ctx: Context<CheckRefundRateCallback>,
+        output: ComputationOutputs<CheckRefundRateOutput>,
+    ) -> Result<()> {
+        let (threshold_bps, exceeds_threshold) = match output {
+            ComputationOutputs::Success(CheckRefundRateOutput { field_0 }) => {
+                (field_0.field_0, field_0.field_1)
+            }
+            _ => return Err(EscrowError::AbortedComputation.into()),
+        };
+
+        emit!(RefundRateCheckEvent {
+            threshold_bps,
+            exceeds_threshold,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Reveal the lifetime volume of a single asset, in that asset's units.
+    pub fn reveal_asset_volume(
+        ctx: Context<RevealAssetVolume>,
+        computation_offset: u64,
+        asset_index: u8,
+    ) -> Result<()> {
+        authorize_analytics_request(
+            &ctx.accounts.escrow,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.viewer.as_ref(),
+            ctx.accounts.analytics_operator.as_mut(),
+        )?;
+        ctx.accounts.escrow.require_stats_dimension(STATS_DIMENSION_ASSETS)?;
+        require!(
+            (asset_index as usize) < STATS_ASSET_COUNT,
+            EscrowError::UnsupportedStatsAsset
+        );
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        #[cfg(not(feature = "mock-mpc"))]
+        {
+            let args = vec![
+                Argument::PlaintextU128(ctx.accounts.escrow.nonce),
+                Argument::Account(
+                    ctx.accounts.escrow.key(),
+                    EscrowAccount::ENCRYPTED_STATS_OFFSET,
+                    32 * ESCROW_STATS_FIELDS as u32,
+                ),
+                Argument::PlaintextU8(asset_index),
+            ];
+
+            queue_computation(
+                ctx.accounts,
+                computation_offset,
+                args,
+                None,
+                vec![RevealAssetVolumeCallback::callback_ix(&[])],
+                1,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "reveal_asset_volume")]
+    pub fn reveal_asset_volume_callback(
+        ctx: Context<RevealAssetVolumeCallback>,
+        output: ComputationOutputs<RevealAssetVolumeOutput>,
+    ) -> Result<()> {
+        let (asset_index, volume) = match output {
+            ComputationOutputs::Success(RevealAssetVolumeOutput { field_0 }) => {
+                (field_0.field_0, field_0.field_1)
+            }
+            _ => return Err(EscrowError::AbortedComputation.into()),
+        };
+
+        emit!(AssetVolumeEvent {
+            asset_index,
+            volume,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Move balances at or below `dust_threshold` out of the escrow's token
+    /// vault and compute budget vault into the treasury. Larger balances are
+    /// left alone; this only clears rounding residue.
+    pub fn sweep_dust(ctx: Context<SweepDust>, dust_threshold: u64) -> Result<()> {
+        let mut tokens_swept = 0;
+        let mut mint = None;
+        if let Some(vault) = ctx.accounts.escrow_vault.as_ref() {
+            if vault.amount > 0 && vault.amount <= dust_threshold {
+                let treasury_token_account = ctx
+                    .accounts
+                    .treasury_token_account
+                    .as_ref()
+                    .ok_or(EscrowError::MissingTreasuryTokenAccount)?;
+                transfer_from_escrow_vault(
+                    &ctx.accounts.escrow,
+                    &vault.to_account_info(),
+                    &treasury_token_account.to_account_info(),
+                    &ctx.accounts.token_program.to_account_info(),
+                    vault.amount,
+                )?;
+                tokens_swept = vault.amount;
+                mint = Some(vault.mint);
+            }
+        }
+
+        let mut lamports_swept = 0;
+        if let Some(vault) = ctx.accounts.compute_budget_vault.as_mut() {
+            let available = available_compute_budget(vault)?;
+            if available > 0 && available <= dust_threshold {
+                **vault.to_account_info().try_borrow_mut_lamports()? -= available;
+                **ctx.accounts.treasury.try_borrow_mut_lamports()? += available;
+                lamports_swept = available;
+            }
+        }
+
+        require!(
+            tokens_swept > 0 || lamports_swept > 0,
+            EscrowError::NothingToSweep
+        );
+
+        emit!(DustSweptEvent {
+            escrow: ctx.accounts.escrow.key(),
+            treasury: ctx.accounts.escrow.treasury,
+            mint,
+            tokens_swept,
+            lamports_swept,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Set the volume above which the escrow pauses itself. 0 disables the
+    /// circuit breaker.
+    pub fn set_circuit_breaker(
+        ctx: Context<ExerciseOwnerCapability>,
+        threshold: u64,
+    ) -> Result<()> {
+        ctx.accounts.escrow.authorize_capability(
+            OwnerCapability::Pausing,
+            &ctx.accounts.authority.key(),
+            Clock::get()?.unix_timestamp,
+        )?;
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.circuit_breaker_threshold = threshold;
+        escrow.circuit_breaker_check_offset = None;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        sequence_escrow_update(escrow)?;
+        Ok(())
+    }
+
+    /// Permissionless crank: run `check_volume_threshold` against the
+    /// configured circuit breaker threshold. If it is met, the callback pauses
+    /// the escrow and emits `CircuitBreakerTrippedEvent`.
+    pub fn check_circuit_breaker(
+        ctx: Context<CheckCircuitBreaker>,
+        computation_offset: u64,
+    ) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        escrow.ensure_active(ctx.accounts.jurisdiction_pause.as_deref())?;
+        escrow.require_stats_dimension(STATS_DIMENSION_VOLUME)?;
+        require!(
+            escrow.circuit_breaker_threshold > 0,
+            EscrowError::CircuitBreakerDisabled
+        );
+        let escrow_key = escrow.key();
+        let escrow_nonce = escrow.nonce;
+        let threshold = escrow.circuit_breaker_threshold;
+        let computation_key = ctx.accounts.computation_account.key();
+
+        if let Some(vault) = ctx.accounts.compute_budget_vault.as_mut() {
+            pay_crank_reward(
+                vault,
+                &ctx.accounts.payer.to_account_info(),
+                CrankKind::CircuitBreaker,
+            )?;
+        }
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        #[cfg(not(feature = "mock-mpc"))]
+        {
+            let args = vec![
+                Argument::PlaintextU128(escrow_nonce),
+                Argument::Account(
+                    escrow_key,
+                    EscrowAccount::ENCRYPTED_STATS_OFFSET,
+                    32 * ESCROW_STATS_FIELDS as u32,
+                ),
+                Argument::PlaintextU64(threshold),
+            ];
+
+            queue_computation(
+                ctx.accounts,
+                computation_offset,
+                args,
+                None,
+                vec![CheckVolumeThresholdCallback::callback_ix(&[
+                    CallbackAccount {
+                        pubkey: escrow_key,
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: computation_key,
+                        is_writable: false,
+                    },
+                ])],
+                1,
+            )?;
+        }
+
+        ctx.accounts.escrow.circuit_breaker_check_offset = Some(computation_offset);
+
+        sequence_escrow_update(&mut ctx.accounts.escrow)?;
+        Ok(())
+    }
+
+    /// Let `operator` request threshold checks and reveals on this escrow at
+    /// most once every `min_interval_secs`. Re-adding updates the limit.
+    pub fn set_analytics_operator(
+        ctx: Context<SetAnalyticsOperator>,
+        operator: Pubkey,
+        min_interval_secs: i64,
+    ) -> Result<()> {
+        require!(min_interval_secs >= 0, EscrowError::InvalidOperatorInterval);
+
+        let grant = &mut ctx.accounts.analytics_operator;
+        grant.escrow = ctx.accounts.escrow.key();
+        grant.operator = operator;
+        grant.min_interval_secs = min_interval_secs;
+        grant.bump = ctx.bumps.analytics_operator;
+
+        emit!(AnalyticsOperatorEvent {
+            escrow: grant.escrow,
+            operator,
+            min_interval_secs,
+            revoked: false,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    pub fn remove_analytics_operator(ctx: Context<RemoveAnalyticsOperator>) -> Result<()> {
+        let grant = &ctx.accounts.analytics_operator;
+        emit!(AnalyticsOperatorEvent {
+            escrow: grant.escrow,
+            operator: grant.operator,
+            min_interval_secs: grant.min_interval_secs,
+            revoked: true,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn set_referral_fallback(
+        ctx: Context<ExerciseOwnerCapability>,
+        route: ReferralFallback,
+    ) -> Result<()> {
+        ctx.accounts.escrow.authorize_capability(
+            OwnerCapability::Fees,
+            &ctx.accounts.authority.key(),
+            Clock::get()?.unix_timestamp,
+        )?;
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.referral_fallback = route;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        sequence_escrow_update(escrow)?;
+        Ok(())
+    }
+
+    pub fn set_tip_fee(ctx: Context<ExerciseOwnerCapability>, treasury_fee_bps: u16) -> Result<()> {
+        ctx.accounts.escrow.authorize_capability(
+            OwnerCapability::Fees,
+            &ctx.accounts.authority.key(),
+            Clock::get()?.unix_timestamp,
+        )?;
+        require!(
+            treasury_fee_bps <= MAX_TIP_TREASURY_FEE_BPS,
+            EscrowError::InvalidTipFee
+        );
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.tip_treasury_fee_bps = treasury_fee_bps;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        sequence_escrow_update(escrow)?;
+        Ok(())
+    }
+
+    /// Send a SOL tip with no referral fee and the escrow's (possibly zero)
+    /// tip treasury fee. The sender is only named in the event if they opt in.
+    pub fn send_tip(ctx: Context<SendTip>, amount: u64, attribute_sender: bool) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.ensure_active(ctx.accounts.jurisdiction_pause.as_deref())?;
+
+        let treasury_fee = amount
+            .checked_mul(escrow.tip_treasury_fee_bps as u64)
+            .ok_or(ProgramError::InvalidArgument)?
+            / 10_000;
+        let tip_amount = amount
+            .checked_sub(treasury_fee)
+            .ok_or(ProgramError::InvalidArgument)?;
+        assert_fee_split(amount, &[tip_amount, treasury_fee])?;
+
+        let from = ctx.accounts.sender.to_account_info();
+        let system_program = ctx.accounts.system_program.to_account_info();
+
+        let cpi_ctx_recipient = CpiContext::new(
+            system_program.clone(),
+            anchor_lang::system_program::Transfer {
+                from: from.clone(),
+                to: ctx.accounts.recipient.to_account_info(),
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_ctx_recipient, tip_amount)?;
+
+        if treasury_fee > 0 {
+            let cpi_ctx_treasury = CpiContext::new(
+                system_program,
+                anchor_lang::system_program::Transfer {
+                    from,
+                    to: ctx.accounts.treasury.to_account_info(),
+                },
+            );
+            anchor_lang::system_program::transfer(cpi_ctx_treasury, treasury_fee)?;
+        }
+
+        escrow.total_fund_regulated = escrow
+            .total_fund_regulated
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        let sequence = sequence_escrow_update(escrow)?;
+        emit!(TipEvent {
+            escrow: escrow.key(),
+            sequence,
+            recipient: ctx.accounts.recipient.key(),
+            sender: attribute_sender.then(|| ctx.accounts.sender.key()),
+            amount: tip_amount,
+            treasury_fee,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Open a per-seller ledger under the escrow. Incoming payments credited
+    /// to it are held on the sub-account until `payout_sub_account`.
+    pub fn create_sub_account(
+        ctx: Context<CreateSubAccount>,
+        seller: Pubkey,
+        payout_address: Pubkey,
+        marketplace_fee_bps: u16,
+    ) -> Result<()> {
+        require!(marketplace_fee_bps <= 10_000, EscrowError::InvalidFeeBps);
+
+        let sub_account = &mut ctx.accounts.sub_account;
+        sub_account.escrow = ctx.accounts.escrow.key();
+        sub_account.seller = seller;
+        sub_account.payout_address = payout_address;
+        sub_account.marketplace_fee_bps = marketplace_fee_bps;
+        sub_account.balance = 0;
+        sub_account.total_paid_out = 0;
+        sub_account.bump = ctx.bumps.sub_account;
+
+        Ok(())
+    }
+
+    pub fn set_sub_account_payout(
+        ctx: Context<SetSubAccountPayout>,
+        payout_address: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.sub_account.payout_address = payout_address;
+        Ok(())
+    }
+
+    /// Pay a seller through the marketplace escrow. Lamports are held by the
+    /// sub-account PDA and credited to its ledger.
+    pub fn credit_sub_account(ctx: Context<CreditSubAccount>, amount: u64) -> Result<()> {
+        ctx.accounts.escrow.ensure_active(ctx.accounts.jurisdiction_pause.as_deref())?;
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: ctx.accounts.sender.to_account_info(),
+                to: ctx.accounts.sub_account.to_account_info(),
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_ctx, amount)?;
+
+        let sub_account = &mut ctx.accounts.sub_account;
+        sub_account.balance = sub_account
+            .balance
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.total_fund_regulated = escrow
+            .total_fund_regulated
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        let sequence = sequence_escrow_update(escrow)?;
+        emit!(SubAccountCreditedEvent {
+            escrow: escrow.key(),
+            sequence,
+            seller: sub_account.seller,
+            amount,
+            balance: sub_account.balance,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Settle the ledger to the seller's payout address, less the
+    /// marketplace fee which goes to the escrow treasury.
+    pub fn payout_sub_account(ctx: Context<PayoutSubAccount>) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        let sub_account = &mut ctx.accounts.sub_account;
+        require!(
+            authority == sub_account.seller || authority == ctx.accounts.escrow.authority(),
+            EscrowError::InvalidAuthority
+        );
+
+        let amount = sub_account.balance;
+        require!(amount > 0, EscrowError::EmptySubAccount);
+        let marketplace_fee = amount
+            .checked_mul(sub_account.marketplace_fee_bps as u64)
+            .ok_or(ProgramError::InvalidArgument)?
+            / 10_000;
+        let seller_amount = amount - marketplace_fee;
+        assert_fee_split(amount, &[seller_amount, marketplace_fee])?;
+
+        **sub_account.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.payout_address.try_borrow_mut_lamports()? += seller_amount;
+        **ctx.accounts.treasury.try_borrow_mut_lamports()? += marketplace_fee;
+
+        sub_account.balance = 0;
+        sub_account.total_paid_out = sub_account
+            .total_paid_out
+            .checked_add(seller_amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        emit!(SubAccountPayoutEvent {
+            escrow: sub_account.escrow,
+            seller: sub_account.seller,
+            payout_address: sub_account.payout_address,
+            amount: seller_amount,
+            marketplace_fee,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Create an invoice denominated in USD cents, settled in `mint` at the
+    /// oracle price when it is paid.
+    pub fn create_invoice(
+        ctx: Context<CreateInvoice>,
+        invoice_id: u64,
+        usd_cents: u64,
+        price_feed_id: [u8; 32],
+        max_slippage_bps: u16,
+        max_price_age_secs: i64,
+        referrer: Pubkey,
+        referral_bps: u16,
+    ) -> Result<()> {
+        require!(usd_cents > 0, EscrowError::InvalidInvoice);
+        require!(max_slippage_bps <= 10_000, EscrowError::InvalidFeeBps);
+        require!(max_price_age_secs > 0, EscrowError::InvalidInvoice);
+        require!(referral_bps <= MAX_INVOICE_REFERRAL_BPS, EscrowError::InvalidFeeBps);
+        require!(
+            referral_bps == 0 || referrer != Pubkey::default(),
+            EscrowError::InvalidInvoice
+        );
+
+        let invoice = &mut ctx.accounts.invoice;
+        invoice.escrow = ctx.accounts.escrow.key();
+        invoice.invoice_id = invoice_id;
+        invoice.mint = ctx.accounts.mint.key();
+        invoice.recipient_token_account = ctx.accounts.recipient_token_account.key();
+        invoice.usd_cents = usd_cents;
+        invoice.price_feed_id = price_feed_id;
+        invoice.max_slippage_bps = max_slippage_bps;
+        invoice.max_price_age_secs = max_price_age_secs;
+        invoice.referrer = referrer;
+        invoice.referral_bps = referral_bps;
+        invoice.quoted_token_amount = 0;
+        invoice.quoted_at = 0;
+        invoice.settled_token_amount = 0;
+        invoice.status = InvoiceStatus::Open;
+        invoice.bump = ctx.bumps.invoice;
+
+        Ok(())
+    }
+
+    /// Lock in a token amount from a fresh oracle price. Also the re-quote
+    /// path: call again once a quote is older than `max_price_age_secs`.
+    pub fn quote_invoice(ctx: Context<QuoteInvoice>) -> Result<()> {
+        let invoice = &mut ctx.accounts.invoice;
+        require!(invoice.status == InvoiceStatus::Open, EscrowError::InvalidInvoiceStatus);
+
+        let now = Clock::get()?.unix_timestamp;
+        let token_amount = invoice_token_amount(
+            invoice,
+            &ctx.accounts.price_update,
+            ctx.accounts.mint.decimals,
+            now,
+        )?;
+        invoice.quoted_token_amount = token_amount;
+        invoice.quoted_at = now;
+
+        emit!(InvoiceQuotedEvent {
+            invoice: invoice.key(),
+            usd_cents: invoice.usd_cents,
+            token_amount,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// Pay an invoice at the current oracle price. Fails if the quote is
+    /// stale or the price moved more than the invoice's slippage bound.
+    /// The treasury fee and, for referred invoices, the negotiated referral
+    /// share are split off the recipient's amount.
+    pub fn pay_invoice(ctx: Context<PayInvoice>, max_token_amount: u64) -> Result<()> {
+        ctx.accounts.escrow.ensure_active(ctx.accounts.jurisdiction_pause.as_deref())?;
+        let invoice = &mut ctx.accounts.invoice;
+        require!(invoice.status == InvoiceStatus::Open, EscrowError::InvalidInvoiceStatus);
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            invoice.quoted_at > 0 && now - invoice.quoted_at <= invoice.max_price_age_secs,
+            EscrowError::StaleInvoiceQuote
+        );
+
+        let token_amount = invoice_token_amount(
+            invoice,
+            &ctx.accounts.price_update,
+            ctx.accounts.mint.decimals,
+            now,
+        )?;
+        let quoted = invoice.quoted_token_amount as u128;
+        let max_drift = quoted * invoice.max_slippage_bps as u128 / 10_000;
+        require!(
+            (token_amount as u128).abs_diff(quoted) <= max_drift,
+            EscrowError::InvoiceSlippageExceeded
+        );
+        require!(token_amount <= max_token_amount, EscrowError::InvoiceSlippageExceeded);
+
+        let referral_reward = if invoice.referrer == Pubkey::default() {
+            0
+        } else {
+            require!(
+                ctx.accounts.referral_token_account.is_some(),
+                EscrowError::MissingReferralRoute
+            );
+            token_amount
+                .checked_mul(invoice.referral_bps as u64)
+                .ok_or(ProgramError::InvalidArgument)?
+                / 10_000
+        };
+        let (_, treasury_fee) = tiered_fee_split(
+            token_amount,
+            ctx.accounts.fee_config.as_deref(),
+            ctx.accounts.escrow.total_fund_regulated,
+        )?;
+        let recipient_amount = token_amount
+            .checked_sub(referral_reward + treasury_fee)
+            .ok_or(ProgramError::InvalidArgument)?;
+        assert_fee_split(
+            token_amount,
+            &[recipient_amount, treasury_fee, referral_reward],
+        )?;
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            anchor_token::Transfer {
+                from: ctx.accounts.payer_token_account.to_account_info(),
+                to: ctx.accounts.recipient_token_account.to_account_info(),
+                authority: ctx.accounts.payer.to_account_info(),
+            },
+        );
+        anchor_token::transfer(cpi_ctx, recipient_amount)?;
+
+        if treasury_fee > 0 {
+            let cpi_ctx = CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                anchor_token::Transfer {
+                    from: ctx.accounts.payer_token_account.to_account_info(),
+                    to: ctx.accounts.treasury_token_account.to_account_info(),
+                    authority: ctx.accounts.payer.to_account_info(),
+                },
+            );
+            anchor_token::transfer(cpi_ctx, treasury_fee)?;
+        }
+
+        if let Some(referral_token_account) = ctx.accounts.referral_token_account.as_ref() {
+            if referral_reward > 0 {
+                let cpi_ctx = CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    anchor_token::Transfer {
+                        from: ctx.accounts.payer_token_account.to_account_info(),
+                        to: referral_token_account.to_account_info(),
+                        authority: ctx.accounts.payer.to_account_info(),
+                    },
+                );
+                anchor_token::transfer(cpi_ctx, referral_reward)?;
+            }
+        }
+
+        invoice.settled_token_amount = token_amount;
+        invoice.status = InvoiceStatus::Paid;
+
+        emit!(InvoiceSettledEvent {
+            invoice: invoice.key(),
+            payer: ctx.accounts.payer.key(),
+            mint: invoice.mint,
+            usd_cents: invoice.usd_cents,
+            token_amount,
+            treasury_fee,
+            referral_reward,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// Integrators can hand their app fee back to the payer, e.g. when the
+    /// order it was charged for is cancelled on their side.
+    pub fn refund_app_fee(ctx: Context<RefundAppFee>) -> Result<()> {
+        let payment = &mut ctx.accounts.payment;
+        require!(
+            payment.app_fee > 0 && !payment.app_fee_refunded,
+            EscrowError::NoAppFeeToRefund
+        );
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: ctx.accounts.app_fee_destination.to_account_info(),
+                to: ctx.accounts.sender.to_account_info(),
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_ctx, payment.app_fee)?;
+        payment.app_fee_refunded = true;
+
+        emit!(AppFeeRefundedEvent {
+            payment: payment.key(),
+            app_fee_destination: payment.app_fee_destination,
+            app_fee: payment.app_fee,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Merchant-side refund of a SOL payment into their escrow. Up to the
+    /// net amount received goes back to the sender, possibly over several
+    /// partial refunds, and `process_refund` adds it to the escrow's
+    /// encrypted refund count and volume.
+    pub fn refund_payment(
+        ctx: Context<RefundPayment>,
+        computation_offset: u64,
+        amount: u64,
+        refund_encryption_pubkey: [u8; 32],
+        refund_nonce: u128,
+        encrypted_amount: [u8; 32],
+    ) -> Result<()> {
+        let escrow_key = ctx.accounts.escrow.key();
+        let escrow_nonce = ctx.accounts.escrow.nonce;
+        let computation_key = ctx.accounts.computation_account.key();
+        require!(
+            ctx.accounts.escrow.pending_computation_offset.is_none(),
+            EscrowError::StatsComputationPending
+        );
+
+        let payment = &mut ctx.accounts.payment;
+        require!(payment.asset_mint == Pubkey::default(), EscrowError::UnsupportedRefundAsset);
+        require!(!payment.is_time_locked(), EscrowError::PaymentLocked);
+        let received = payment
+            .amount
+            .saturating_sub(payment.referal_reward)
+            .saturating_sub(payment.treasury_reward);
+        let refunded = payment
+            .refunded
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+        require!(amount > 0 && refunded <= received, EscrowError::RefundExceedsPayment);
+        payment.refunded = refunded;
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: ctx.accounts.recipient.to_account_info(),
+                to: ctx.accounts.sender.to_account_info(),
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_ctx, amount)?;
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+        let track_refunds = ctx.accounts.escrow.stats_dimensions & STATS_DIMENSION_REFUNDS != 0;
+
+        #[cfg(not(feature = "mock-mpc"))]
+        {
+            let args = vec![
+                Argument::ArcisPubkey(refund_encryption_pubkey),
+                Argument::PlaintextU128(refund_nonce),
+                Argument::EncryptedU64(encrypted_amount),
+                Argument::PlaintextU128(escrow_nonce),
+                Argument::Account(
+                    escrow_key,
+                    EscrowAccount::ENCRYPTED_STATS_OFFSET,
+                    32 * ESCROW_STATS_FIELDS as u32,
+                ),
+                Argument::PlaintextBool(track_refunds),
+            ];
+
+            queue_computation(
+                ctx.accounts,
+                computation_offset,
+                args,
+                None,
+                vec![ProcessRefundCallback::callback_ix(&[
+                    CallbackAccount {
+                        pubkey: escrow_key,
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: computation_key,
+                        is_writable: false,
+                    },
+                ])],
+                1,
+            )?;
+        }
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.pending_computation_offset = Some(computation_offset);
+        escrow.pending_since_slot = Clock::get()?.slot;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+
+        let sequence = sequence_escrow_update(escrow)?;
+        emit!(PaymentRefundedEvent {
+            escrow: escrow_key,
+            sequence,
+            payment: ctx.accounts.payment.key(),
+            sender: ctx.accounts.sender.key(),
+            amount,
+            timestamp: escrow.last_updated,
+        });
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "process_refund")]
+    pub fn process_refund_callback(
+        ctx: Context<ProcessRefundCallback>,
+        output: ComputationOutputs<ProcessRefundOutput>,
+    ) -> Result<()> {
+        let o = match output {
+            ComputationOutputs::Success(ProcessRefundOutput { field_0 }) => field_0,
+            _ => return Err(EscrowError::AbortedComputation.into()),
+        };
+
+        verify_pending_computation(
+            ctx.accounts.escrow.pending_computation_offset,
+            &ctx.accounts.computation_account.key(),
+        )?;
+
+        store_encrypted_stats(&mut ctx.accounts.escrow, o.ciphertexts, o.nonce);
+        sequence_escrow_update(&mut ctx.accounts.escrow)?;
+        Ok(())
+    }
+
+    /// Register (or clear, with `Pubkey::default()`) the notification key
+    /// off-chain notifiers sign event digests with for this escrow.
+    pub fn register_webhook_key(
+        ctx: Context<UpdateEscrowActive>,
+        webhook_key: Pubkey,
+    ) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.webhook_key = webhook_key;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+
+        let sequence = sequence_escrow_update(escrow)?;
+        emit!(WebhookKeyRegisteredEvent {
+            escrow: escrow.key(),
+            sequence,
+            webhook_key,
+            timestamp: escrow.last_updated,
+        });
+
+        Ok(())
+    }
+
+    /// Deposit the full contract value for a freelancer, split into up to
+    /// `MAX_MILESTONES` tranches released one by one.
+    pub fn create_milestones(
+        ctx: Context<CreateMilestones>,
+        milestones_id: u64,
+        amounts: Vec<u64>,
+        description_hashes: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        ctx.accounts.escrow.ensure_active(ctx.accounts.jurisdiction_pause.as_deref())?;
+        require!(
+            !amounts.is_empty()
+                && amounts.len() <= MAX_MILESTONES
+                && amounts.len() == description_hashes.len(),
+            EscrowError::InvalidMilestones
+        );
+
+        let mut total: u64 = 0;
+        let mut entries = [Milestone::default(); MAX_MILESTONES];
+        for (i, (amount, description_hash)) in amounts.iter().zip(description_hashes).enumerate() {
+            require!(*amount > 0, EscrowError::InvalidMilestones);
+            total = total
+                .checked_add(*amount)
+                .ok_or(ProgramError::InvalidArgument)?;
+            entries[i] = Milestone {
+                amount: *amount,
+                description_hash,
+                status: MilestoneStatus::Pending,
+            };
+        }
+
+        let milestones = &mut ctx.accounts.milestones;
+        milestones.escrow = ctx.accounts.escrow.key();
+        milestones.milestones_id = milestones_id;
+        milestones.payer = ctx.accounts.payer.key();
+        milestones.payee = ctx.accounts.payee_token_account.owner;
+        milestones.mint = ctx.accounts.mint.key();
+        milestones.payee_token_account = ctx.accounts.payee_token_account.key();
+        milestones.count = amounts.len() as u8;
+        milestones.milestones = entries;
+        milestones.bump = ctx.bumps.milestones;
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            anchor_token::Transfer {
+                from: ctx.accounts.payer_token_account.to_account_info(),
+                to: ctx.accounts.milestone_vault.to_account_info(),
+                authority: ctx.accounts.payer.to_account_info(),
+            },
+        );
+        anchor_token::transfer(cpi_ctx, total)
+    }
+
+    /// Payer signs off on a delivered milestone so the payee can claim it.
+    pub fn approve_milestone(ctx: Context<ApproveMilestone>, index: u8) -> Result<()> {
+        let milestone = ctx.accounts.milestones.milestone_mut(index)?;
+        require!(
+            milestone.status == MilestoneStatus::Pending,
+            EscrowError::InvalidMilestoneStatus
+        );
+        milestone.status = MilestoneStatus::Approved;
+
+        emit_milestone_event(&ctx.accounts.milestones, index)
+    }
+
+    /// Release an approved tranche to the payee, less treasury and referral
+    /// fees taken from that tranche.
+    pub fn claim_milestone(ctx: Context<ClaimMilestone>, index: u8) -> Result<()> {
+        let amount = {
+            let milestone = ctx.accounts.milestones.milestone_mut(index)?;
+            require!(
+                milestone.status == MilestoneStatus::Approved,
+                EscrowError::InvalidMilestoneStatus
+            );
+            milestone.status = MilestoneStatus::Claimed;
+            milestone.amount
+        };
+
+        let (referral_fee, treasury_fee) = tiered_fee_split(
+            amount,
+            ctx.accounts.fee_config.as_deref(),
+            ctx.accounts.escrow.total_fund_regulated,
+        )?;
+        let net_amount = amount - referral_fee - treasury_fee;
+        assert_fee_split(amount, &[net_amount, treasury_fee, referral_fee])?;
+
+        let milestones = &ctx.accounts.milestones;
+        let vault = ctx.accounts.milestone_vault.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let payee = ctx.accounts.payee_token_account.to_account_info();
+        let treasury = ctx.accounts.treasury_token_account.to_account_info();
+        // Without a referral account the referral share stays with the treasury.
+        let (referral, treasury_fee) = match ctx.accounts.referral_token_account.as_ref() {
+            Some(referral) => (Some(referral.to_account_info()), treasury_fee),
+            None => (None, treasury_fee + referral_fee),
+        };
+
+        transfer_from_milestone_vault(milestones, &vault, &payee, &token_program, net_amount)?;
+        transfer_from_milestone_vault(milestones, &vault, &treasury, &token_program, treasury_fee)?;
+        if let Some(referral) = referral {
+            transfer_from_milestone_vault(
+                milestones,
+                &vault,
+                &referral,
+                &token_program,
+                referral_fee,
+            )?;
+        }
+
+        emit_milestone_event(milestones, index)
+    }
+
+    /// Either party can freeze a pending or approved milestone; the escrow
+    /// owner then arbitrates with `resolve_milestone_dispute`.
+    pub fn dispute_milestone(ctx: Context<DisputeMilestone>, index: u8) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        let milestones = &mut ctx.accounts.milestones;
+        require!(
+            authority == milestones.payer || authority == milestones.payee,
+            EscrowError::InvalidAuthority
+        );
+
+        let milestone = milestones.milestone_mut(index)?;
+        require!(
+            matches!(
+                milestone.status,
+                MilestoneStatus::Pending | MilestoneStatus::Approved
+            ),
+            EscrowError::InvalidMilestoneStatus
+        );
+        milestone.status = MilestoneStatus::Disputed;
+
+        emit_milestone_event(&ctx.accounts.milestones, index)
+    }
+
+    /// Arbitrate a disputed milestone: approve it for the payee to claim, or
+    /// refund the tranche to the payer.
+    pub fn resolve_milestone_dispute(
+        ctx: Context<ResolveMilestoneDispute>,
+        index: u8,
+        release_to_payee: bool,
+    ) -> Result<()> {
+        let amount = {
+            let milestone = ctx.accounts.milestones.milestone_mut(index)?;
+            require!(
+                milestone.status == MilestoneStatus::Disputed,
+                EscrowError::InvalidMilestoneStatus
+            );
+            milestone.status = if release_to_payee {
+                MilestoneStatus::Approved
+            } else {
+                MilestoneStatus::Refunded
+            };
+            milestone.amount
+        };
+
+        if !release_to_payee {
+            transfer_from_milestone_vault(
+                &ctx.accounts.milestones,
+                &ctx.accounts.milestone_vault.to_account_info(),
+                &ctx.accounts.payer_token_account.to_account_info(),
+                &ctx.accounts.token_program.to_account_info(),
+                amount,
+            )?;
+        }
+
+        emit_milestone_event(&ctx.accounts.milestones, index)
+    }
+
+    /// Open a sealed-bid auction paid in `mint`. Bids stay encrypted until
+    /// the MPC cluster picks the winner; only the winner and price are public.
+    pub fn create_auction(
+        ctx: Context<CreateAuction>,
+        auction_id: u64,
+        reserve_price: u64,
+        bid_deadline: Deadline,
+    ) -> Result<()> {
+        require!(
+            !bid_deadline.has_passed(&Clock::get()?),
+            EscrowError::InvalidAuction
+        );
+
+        let auction = &mut ctx.accounts.auction;
+        auction.seller = ctx.accounts.seller.key();
+        auction.auction_id = auction_id;
+        auction.mint = ctx.accounts.mint.key();
+        auction.seller_token_account = ctx.accounts.seller_token_account.key();
+        auction.reserve_price = reserve_price;
+        auction.bid_deadline = bid_deadline;
+        auction.bidder_count = 0;
+        auction.bids = [SealedBid::default(); MAX_AUCTION_BIDDERS];
+        auction.status = AuctionStatus::Open;
+        auction.winner_index = NO_AUCTION_WINNER;
+        auction.clearing_price = 0;
+        auction.pending_computation_offset = None;
+        auction.bump = ctx.bumps.auction;
+
+        Ok(())
+    }
+
+    /// Escrow a deposit alongside an encrypted bid. Bids above the deposit
+    /// are ignored by the circuit, so the deposit caps what a bidder can owe.
+    pub fn submit_sealed_bid(
+        ctx: Context<SubmitSealedBid>,
+        deposit: u64,
+        bid_encryption_pubkey: [u8; 32],
+        bid_nonce: u128,
+        encrypted_bid: [u8; 32],
+    ) -> Result<()> {
+        let auction = &mut ctx.accounts.auction;
+        require!(auction.status == AuctionStatus::Open, EscrowError::InvalidAuctionStatus);
+        require!(
+            !auction.bid_deadline.has_passed(&Clock::get()?),
+            EscrowError::AuctionClosed
+        );
+        let slot = auction.bidder_count as usize;
+        require!(slot < MAX_AUCTION_BIDDERS, EscrowError::AuctionFull);
+        require!(deposit > 0, EscrowError::InvalidAuction);
+
+        auction.bids[slot] = SealedBid {
+            bidder: ctx.accounts.bidder.key(),
+            refund_token_account: ctx.accounts.bidder_token_account.key(),
+            deposit,
+            encryption_pubkey: bid_encryption_pubkey,
+            nonce: bid_nonce,
+            ciphertext: encrypted_bid,
+        };
+        auction.bidder_count += 1;
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            anchor_token::Transfer {
+                from: ctx.accounts.bidder_token_account.to_account_info(),
+                to: ctx.accounts.auction_vault.to_account_info(),
+                authority: ctx.accounts.bidder.to_account_info(),
+            },
+        );
+        anchor_token::transfer(cpi_ctx, deposit)
+    }
+
+    /// Permissionless crank once bidding has closed.
+    pub fn resolve_auction(ctx: Context<ResolveAuction>, computation_offset: u64) -> Result<()> {
+        let auction = &ctx.accounts.auction;
+        require!(auction.status == AuctionStatus::Open, EscrowError::InvalidAuctionStatus);
+        require!(
+            auction.bid_deadline.has_passed(&Clock::get()?),
+            EscrowError::AuctionStillOpen
+        );
+        let auction_key = auction.key();
+        let computation_key = ctx.accounts.computation_account.key();
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        #[cfg(not(feature = "mock-mpc"))]
+        {
+            let mut args = Vec::with_capacity(MAX_AUCTION_BIDDERS * 4 + 2);
+            for bid in auction.bids.iter() {
+                args.push(Argument::ArcisPubkey(bid.encryption_pubkey));
+                args.push(Argument::PlaintextU128(bid.nonce));
+                args.push(Argument::EncryptedU64(bid.ciphertext));
+            }
+            for bid in auction.bids.iter() {
+                args.push(Argument::PlaintextU64(bid.deposit));
+            }
+            args.push(Argument::PlaintextU8(auction.bidder_count));
+            args.push(Argument::PlaintextU64(auction.reserve_price));
+
+            queue_computation(
+                ctx.accounts,
+                computation_offset,
+                args,
+                None,
+                vec![ResolveSealedBidsCallback::callback_ix(&[
+                    CallbackAccount {
+                        pubkey: auction_key,
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: computation_key,
+                        is_writable: false,
+                    },
+                ])],
+                1,
+            )?;
+        }
+
+        let auction = &mut ctx.accounts.auction;
+        auction.status = AuctionStatus::Resolving;
+        auction.pending_computation_offset = Some(computation_offset);
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "resolve_sealed_bids")]
+    pub fn resolve_sealed_bids_callback(
+        ctx: Context<ResolveSealedBidsCallback>,
+        output: ComputationOutputs<ResolveSealedBidsOutput>,
+    ) -> Result<()> {
+        let (winner_index, clearing_price) = match output {
+            ComputationOutputs::Success(ResolveSealedBidsOutput { field_0 }) => {
+                (field_0.field_0, field_0.field_1)
+            }
+            _ => return Err(EscrowError::AbortedComputation.into()),
+        };
+
+        let auction = &mut ctx.accounts.auction;
+        verify_pending_computation(
+            auction.pending_computation_offset,
+            &ctx.accounts.computation_account.key(),
+        )?;
+
+        auction.pending_computation_offset = None;
+        auction.winner_index = winner_index;
+        auction.clearing_price = clearing_price;
+        auction.status = AuctionStatus::Resolved;
+
+        emit!(AuctionResolvedEvent {
+            auction: auction.key(),
+            winner: auction
+                .bids
+                .get(winner_index as usize)
+                .map(|bid| bid.bidder),
+            clearing_price,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Pay the seller the clearing price out of the winner's deposit and
+    /// refund everything else. Pass every bidder's refund token account as
+    /// remaining accounts, in bid order.
+    pub fn settle_auction(ctx: Context<SettleAuction>) -> Result<()> {
+        let auction = &ctx.accounts.auction;
+        require!(
+            auction.status == AuctionStatus::Resolved,
+            EscrowError::InvalidAuctionStatus
+        );
+        let count = auction.bidder_count as usize;
+        require!(
+            ctx.remaining_accounts.len() == count,
+            EscrowError::InvalidAuction
+        );
+
+        let seller = auction.seller;
+        let auction_id = auction.auction_id.to_le_bytes();
+        let seeds: &[&[u8]] = &[seeds::AUCTION, seller.as_ref(), &auction_id, &[auction.bump]];
+        let signer = &[seeds];
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let vault = ctx.accounts.auction_vault.to_account_info();
+        let authority = ctx.accounts.auction.to_account_info();
+
+        if auction.winner_index != NO_AUCTION_WINNER {
+            let cpi_ctx = CpiContext::new_with_signer(
+                token_program.clone(),
+                anchor_token::Transfer {
+                    from: vault.clone(),
+                    to: ctx.accounts.seller_token_account.to_account_info(),
+                    authority: authority.clone(),
+                },
+                signer,
+            );
+            anchor_token::transfer(cpi_ctx, auction.clearing_price)?;
+        }
+
+        for (i, refund_account) in ctx.remaining_accounts.iter().enumerate() {
+            let bid = &auction.bids[i];
+            require_keys_eq!(
+                refund_account.key(),
+                bid.refund_token_account,
+                EscrowError::InvalidAuthority
+            );
+            let refund = if i == auction.winner_index as usize {
+                bid.deposit - auction.clearing_price
+            } else {
+                bid.deposit
+            };
+            if refund == 0 {
+                continue;
+            }
+            let cpi_ctx = CpiContext::new_with_signer(
+                token_program.clone(),
+                anchor_token::Transfer {
+                    from: vault.clone(),
+                    to: refund_account.clone(),
+                    authority: authority.clone(),
+                },
+                signer,
+            );
+            anchor_token::transfer(cpi_ctx, refund)?;
+        }
+
+        ctx.accounts.auction.status = AuctionStatus::Settled;
+
+        Ok(())
+    }
+
+    /// Lock SOL for `recipient` behind the SHA-256 `hash_lock`. The recipient
+    /// gets it by revealing the preimage before `expires_at`; after that the
+    /// sender can take it back.
+    pub fn create_htlc(
+        ctx: Context<CreateHtlc>,
+        htlc_id: u64,
+        recipient: Pubkey,
+        amount: u64,
+        hash_lock: [u8; 32],
+        expires_at: Deadline,
+    ) -> Result<()> {
+        require!(amount > 0, EscrowError::InvalidHtlc);
+        require!(
+            !expires_at.has_passed(&Clock::get()?),
+            EscrowError::InvalidHtlc
+        );
+
+        let htlc = &mut ctx.accounts.htlc;
+        htlc.sender = ctx.accounts.sender.key();
+        htlc.htlc_id = htlc_id;
+        htlc.recipient = recipient;
+        htlc.amount = amount;
+        htlc.hash_lock = hash_lock;
+        htlc.expires_at = expires_at;
+        htlc.bump = ctx.bumps.htlc;
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: ctx.accounts.sender.to_account_info(),
+                to: ctx.accounts.htlc.to_account_info(),
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_ctx, amount)?;
+
+        emit!(HtlcEvent {
+            htlc: ctx.accounts.htlc.key(),
+            hash_lock,
+            preimage: None,
+            action: HtlcAction::Created,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Release the locked SOL to the recipient. Anyone holding the preimage
+    /// can submit it; publishing it in the event lets counterparties on
+    /// other programs or chains complete their side.
+    pub fn claim_htlc(ctx: Context<ClaimHtlc>, preimage: Vec<u8>) -> Result<()> {
+        let htlc = &ctx.accounts.htlc;
+        require!(
+            !htlc.expires_at.has_passed(&Clock::get()?),
+            EscrowError::HtlcExpired
+        );
+        require!(
+            anchor_lang::solana_program::hash::hash(&preimage).to_bytes() == htlc.hash_lock,
+            EscrowError::InvalidPreimage
+        );
+
+        **ctx.accounts.htlc.to_account_info().try_borrow_mut_lamports()? -= htlc.amount;
+        **ctx.accounts.recipient.try_borrow_mut_lamports()? += htlc.amount;
+
+        emit!(HtlcEvent {
+            htlc: htlc.key(),
+            hash_lock: htlc.hash_lock,
+            preimage: Some(preimage),
+            action: HtlcAction::Claimed,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Return the locked SOL to the sender once the HTLC has expired.
+    pub fn refund_htlc(ctx: Context<RefundHtlc>) -> Result<()> {
+        let htlc = &ctx.accounts.htlc;
+        require!(
+            htlc.expires_at.has_passed(&Clock::get()?),
+            EscrowError::HtlcNotExpired
+        );
+
+        emit!(HtlcEvent {
+            htlc: htlc.key(),
+            hash_lock: htlc.hash_lock,
+            preimage: None,
+            action: HtlcAction::Refunded,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        // Closing the account returns the locked amount with the rent.
+        Ok(())
+    }
+
+    /// SOL payment held on its `PaymentAccount` until the recipient claims
+    /// it. The recipient can claim from `unlock_at`; from `expires_at`,
+    /// anyone can send the funds back to the sender. Fees are paid up front,
+    /// with the referral share routed as in `send_payment`, and are not
+    /// returned on expiry.
+    pub fn send_payment_timelocked(
+        ctx: Context<SendPaymentTimelocked>,
+        referal: Pubkey,
+        amount: u64,
+        recipient: Pubkey,
+        unlock_at: Option<Deadline>,
+        expires_at: Option<Deadline>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        require!(
+            unlock_at.is_some() || expires_at.is_some(),
+            EscrowError::InvalidTimeLock
+        );
+        if let Some(expires_at) = expires_at {
+            require!(!expires_at.has_passed(&clock), EscrowError::InvalidTimeLock);
+        }
+        match (unlock_at, expires_at) {
+            (Some(Deadline::Slot(unlock)), Some(Deadline::Slot(expiry))) => {
+                require!(unlock < expiry, EscrowError::InvalidTimeLock)
+            }
+            (Some(Deadline::UnixTimestamp(unlock)), Some(Deadline::UnixTimestamp(expiry))) => {
+                require!(unlock < expiry, EscrowError::InvalidTimeLock)
+            }
+            _ => {}
+        }
+
+        let referal = resolve_referrer(
+            referal,
+            ctx.accounts.referral_link.as_deref(),
+            &ctx.accounts.sender.key(),
+            &ctx.accounts.escrow.key(),
+            ctx.accounts
+                .protocol_config
+                .as_ref()
+                .map(|config| config.key()),
+        )?;
+
+        let payment = &mut ctx.accounts.payment;
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.ensure_active(ctx.accounts.jurisdiction_pause.as_deref())?;
+
+        let (mut referal_reward, mut treasury_reward) = tiered_fee_split(
+            amount,
+            ctx.accounts.fee_config.as_deref(),
+            escrow.total_fund_regulated,
+        )?;
+        if escrow.referrals_enabled {
+            (referal_reward, treasury_reward) = apply_campaign_boost(
+                ctx.accounts.campaign.as_mut(),
+                &escrow.key(),
+                &Pubkey::default(),
+                amount,
+                referal_reward,
+                treasury_reward,
+                clock.unix_timestamp,
+            )?;
+        }
+        payment.sender = ctx.accounts.sender.key();
+        payment.sequence = ctx
+            .accounts
+            .payment_sequence
+            .advance(ctx.accounts.sender.key(), ctx.bumps.payment_sequence)?;
+        payment.recipient = recipient;
+        payment.referal = referal;
+        payment.amount = amount;
+        payment.timestamp = clock.unix_timestamp;
+        payment.referal_reward = referal_reward;
+        payment.treasury_reward = treasury_reward;
+        payment.asset_mint = Pubkey::default();
+        payment.intent_hash = payment_intent_hash(
+            &payment.sender,
+            &payment.recipient,
+            IntentAmount::Plain(amount),
+            &payment.asset_mint,
+            payment.sequence as u128,
+        );
+        payment.unlock_at = unlock_at;
+        payment.expires_at = expires_at;
+
+        let to_payment = payment.to_account_info();
+        let to_treasury = ctx.accounts.treasury.to_account_info();
+        let to_referral = sol_referral_destination(
+            escrow,
+            SolReferralAccounts {
+                referral: ctx.accounts.referral.as_ref(),
+                incinerator: ctx.accounts.incinerator.as_ref(),
+                referral_vesting: ctx.accounts.referral_vesting.as_mut(),
+                referral_usdc_ledger: ctx.accounts.referral_usdc_ledger.as_mut(),
+                sol_fee_rate: ctx.accounts.sol_fee_rate.as_ref(),
+            },
+            referal,
+            referal_reward,
+            payment.timestamp,
+            &to_treasury,
+            &to_payment,
+        )?;
+        // A recipient rebate is held with the payment until it is claimed.
+        if to_referral.key == to_payment.key {
+            payment.referal_reward = 0;
+        }
+
+        let held_amount = payment.net_amount();
+        assert_fee_split(
+            amount,
+            &[held_amount, payment.treasury_reward, payment.referal_reward],
+        )?;
+
+        let system_program = ctx.accounts.system_program.to_account_info();
+        for (destination, share) in [
+            (to_payment, held_amount),
+            (to_treasury, payment.treasury_reward),
+            (to_referral, payment.referal_reward),
+        ] {
+            let cpi_ctx = CpiContext::new(
+                system_program.clone(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.sender.to_account_info(),
+                    to: destination,
+                },
+            );
+            anchor_lang::system_program::transfer(cpi_ctx, share)?;
+        }
+
+        escrow.total_fund_regulated = escrow
+            .total_fund_regulated
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        emit!(TimeLockedPaymentEvent {
+            payment: payment.key(),
+            action: TimeLockAction::Created,
+            amount: held_amount,
+            timestamp: payment.timestamp,
+        });
+
+        sequence_escrow_update(escrow)?;
+        Ok(())
+    }
+
+    /// Pay a time-locked payment out to its recipient once unlocked and
+    /// before it expires. The rent goes back to the sender.
+    pub fn claim_payment(ctx: Context<ClaimPayment>) -> Result<()> {
+        let payment = &ctx.accounts.payment;
+        let clock = Clock::get()?;
+        if let Some(unlock_at) = payment.unlock_at {
+            require!(unlock_at.has_passed(&clock), EscrowError::PaymentLocked);
+        }
+        if let Some(expires_at) = payment.expires_at {
+            require!(!expires_at.has_passed(&clock), EscrowError::PaymentExpired);
+        }
+
+        let amount = payment.net_amount();
+        **ctx.accounts.payment.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.recipient.try_borrow_mut_lamports()? += amount;
+
+        emit!(TimeLockedPaymentEvent {
+            payment: payment.key(),
+            action: TimeLockAction::Claimed,
+            amount,
+            timestamp: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Return an expired, unclaimed payment to its sender. Permissionless,
+    /// funds only ever go back to the sender.
+    pub fn reclaim_expired(ctx: Context<ReclaimExpired>) -> Result<()> {
+        let payment = &ctx.accounts.payment;
+        let clock = Clock::get()?;
+        require!(
+            payment
+                .expires_at
+                .is_some_and(|expires_at| expires_at.has_passed(&clock)),
+            EscrowError::PaymentNotExpired
+        );
+
+        emit!(TimeLockedPaymentEvent {
+            payment: payment.key(),
+            action: TimeLockAction::Reclaimed,
+            amount: payment.net_amount(),
+            timestamp: clock.unix_timestamp,
+        });
+
+        // Closing the account returns the held amount with the rent.
+        Ok(())
+    }
+
+    /// Hold `amount` for `recipient` until the depositor or `arbiter`
+    /// releases it, or the recipient or `arbiter` refunds it. SOL sits on
+    /// the deposit account; SPL deposits pass the mint and token accounts
+    /// and sit in a vault owned by the deposit.
+    pub fn deposit_to_escrow(
+        ctx: Context<DepositToEscrow>,
+        deposit_id: u64,
+        recipient: Pubkey,
+        arbiter: Pubkey,
+        amount: u64,
+    ) -> Result<()> {
+        ctx.accounts.escrow.ensure_active(ctx.accounts.jurisdiction_pause.as_deref())?;
+        require!(amount > 0, EscrowError::InvalidEscrowDeposit);
+
+        let mint = match (
+            ctx.accounts.mint.as_ref(),
+            ctx.accounts.deposit_vault.as_ref(),
+            ctx.accounts.depositor_token_account.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+        ) {
+            (None, None, None, _) => {
+                let cpi_ctx = CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    anchor_lang::system_program::Transfer {
+                        from: ctx.accounts.depositor.to_account_info(),
+                        to: ctx.accounts.deposit.to_account_info(),
+                    },
+                );
+                anchor_lang::system_program::transfer(cpi_ctx, amount)?;
+                Pubkey::default()
+            }
+            (Some(mint), Some(vault), Some(from), Some(token_program)) => {
+                let cpi_ctx = CpiContext::new(
+                    token_program.to_account_info(),
+                    anchor_token::Transfer {
+                        from: from.to_account_info(),
+                        to: vault.to_account_info(),
+                        authority: ctx.accounts.depositor.to_account_info(),
+                    },
+                );
+                anchor_token::transfer(cpi_ctx, amount)?;
+                mint.key()
+            }
+            _ => return err!(EscrowError::MissingDepositTokenAccounts),
+        };
+
+        let deposit = &mut ctx.accounts.deposit;
+        deposit.escrow = ctx.accounts.escrow.key();
+        deposit.deposit_id = deposit_id;
+        deposit.depositor = ctx.accounts.depositor.key();
+        deposit.recipient = recipient;
+        deposit.arbiter = arbiter;
+        deposit.mint = mint;
+        deposit.amount = amount;
+        deposit.status = EscrowDepositStatus::Held;
+        deposit.created_at = Clock::get()?.unix_timestamp;
+        deposit.bump = ctx.bumps.deposit;
+
+        emit!(EscrowDepositEvent {
+            deposit: deposit.key(),
+            mint,
+            amount,
+            status: deposit.status,
+            timestamp: deposit.created_at,
+        });
+
+        Ok(())
+    }
+
+    /// Pay a held deposit out to its recipient, less the treasury and
+    /// referral fees, which both go to the treasury. Signed by the
+    /// depositor or the deposit's arbiter.
+    pub fn release_escrow(ctx: Context<ReleaseEscrow>) -> Result<()> {
+        let deposit = &ctx.accounts.deposit;
+        let authority = ctx.accounts.authority.key();
+        require!(
+            authority == deposit.depositor || deposit.is_arbiter(&authority),
+            EscrowError::InvalidAuthority
+        );
+
+        let (referral_fee, treasury_fee) = tiered_fee_split(
+            deposit.amount,
+            ctx.accounts.fee_config.as_deref(),
+            ctx.accounts.escrow.total_fund_regulated,
+        )?;
+        let fee = referral_fee + treasury_fee;
+        let net_amount = deposit.amount - fee;
+        assert_fee_split(deposit.amount, &[net_amount, fee])?;
+
+        let (recipient, treasury) = if deposit.mint == Pubkey::default() {
+            (
+                ctx.accounts.recipient.to_account_info(),
+                ctx.accounts.treasury.to_account_info(),
+            )
+        } else {
+            (
+                ctx.accounts
+                    .recipient_token_account
+                    .as_ref()
+                    .ok_or(EscrowError::MissingDepositTokenAccounts)?
+                    .to_account_info(),
+                ctx.accounts
+                    .treasury_token_account
+                    .as_ref()
+                    .ok_or(EscrowError::MissingDepositTokenAccounts)?
+                    .to_account_info(),
+            )
+        };
+        let vault = ctx.accounts.deposit_vault.as_ref();
+        let token_program = ctx.accounts.token_program.as_ref();
+        pay_out_of_deposit(deposit, vault, token_program, &recipient, net_amount)?;
+        pay_out_of_deposit(deposit, vault, token_program, &treasury, fee)?;
+
+        settle_escrow_deposit(&mut ctx.accounts.deposit, EscrowDepositStatus::Released)
+    }
+
+    /// Return a held deposit to the depositor in full. Signed by the
+    /// recipient or the deposit's arbiter.
+    pub fn refund_escrow(ctx: Context<RefundEscrow>) -> Result<()> {
+        let deposit = &ctx.accounts.deposit;
+        let authority = ctx.accounts.authority.key();
+        require!(
+            authority == deposit.recipient || deposit.is_arbiter(&authority),
+            EscrowError::InvalidAuthority
+        );
+
+        let depositor = if deposit.mint == Pubkey::default() {
+            ctx.accounts.depositor.to_account_info()
+        } else {
+            ctx.accounts
+                .depositor_token_account
+                .as_ref()
+                .ok_or(EscrowError::MissingDepositTokenAccounts)?
+                .to_account_info()
+        };
+        pay_out_of_deposit(
+            deposit,
+            ctx.accounts.deposit_vault.as_ref(),
+            ctx.accounts.token_program.as_ref(),
+            &depositor,
+            deposit.amount,
+        )?;
+
+        settle_escrow_deposit(&mut ctx.accounts.deposit, EscrowDepositStatus::Refunded)
+    }
+
+    /// Name the arbiter that resolves disputes on this escrow's deposits.
+    /// A replacement takes over disputes that are already open.
+    pub fn set_escrow_arbiter(ctx: Context<UpdateEscrowActive>, arbiter: Pubkey) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.arbiter = arbiter;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        sequence_escrow_update(escrow)?;
+        Ok(())
+    }
+
+    /// Freeze a held deposit for arbitration. Either party can open it;
+    /// release and refund are blocked until the arbiter resolves it.
+    pub fn open_dispute(ctx: Context<OpenDispute>) -> Result<()> {
+        require!(
+            ctx.accounts.escrow.arbiter != Pubkey::default(),
+            EscrowError::ArbiterNotSet
+        );
+        let authority = ctx.accounts.authority.key();
+        let deposit = &mut ctx.accounts.deposit;
+        require!(deposit.is_party(&authority), EscrowError::InvalidAuthority);
+        deposit.status = EscrowDepositStatus::Disputed;
+
+        let now = Clock::get()?.unix_timestamp;
+        let dispute = &mut ctx.accounts.dispute;
+        dispute.deposit = deposit.key();
+        dispute.opened_by = authority;
+        dispute.evidence = Vec::new();
+        dispute.status = DisputeStatus::Open;
+        dispute.recipient_amount = 0;
+        dispute.opened_at = now;
+        dispute.resolved_at = 0;
+        dispute.bump = ctx.bumps.dispute;
+
+        emit!(DisputeEvent {
+            dispute: dispute.key(),
+            deposit: dispute.deposit,
+            actor: authority,
+            status: dispute.status,
+            evidence_hash: None,
+            recipient_amount: None,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// Record the hash of off-chain evidence for the arbiter. Only the hash
+    /// goes on-chain; the parties exchange the documents themselves.
+    pub fn submit_evidence_hash(
+        ctx: Context<SubmitEvidenceHash>,
+        evidence_hash: [u8; 32],
+    ) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        require!(ctx.accounts.deposit.is_party(&authority), EscrowError::InvalidAuthority);
+
+        let dispute = &mut ctx.accounts.dispute;
+        require!(
+            dispute.evidence.len() < MAX_DISPUTE_EVIDENCE,
+            EscrowError::DisputeEvidenceFull
+        );
+        dispute.evidence.push(DisputeEvidence {
+            submitter: authority,
+            hash: evidence_hash,
+        });
+
+        emit!(DisputeEvent {
+            dispute: dispute.key(),
+            deposit: dispute.deposit,
+            actor: authority,
+            status: dispute.status,
+            evidence_hash: Some(evidence_hash),
+            recipient_amount: None,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Arbiter's ruling: `recipient_amount` of the deposit goes to the
+    /// recipient, less the fees as on release, and the rest back to the
+    /// depositor.
+    pub fn resolve_dispute(ctx: Context<ResolveDispute>, recipient_amount: u64) -> Result<()> {
+        let deposit = &ctx.accounts.deposit;
+        require!(recipient_amount <= deposit.amount, EscrowError::InvalidDisputeSplit);
+        let depositor_amount = deposit.amount - recipient_amount;
+        let (referral_fee, treasury_fee) = tiered_fee_split(
+            recipient_amount,
+            ctx.accounts.fee_config.as_deref(),
+            ctx.accounts.escrow.total_fund_regulated,
+        )?;
+        let fee = referral_fee + treasury_fee;
+        let net_amount = recipient_amount - fee;
+        assert_fee_split(deposit.amount, &[net_amount, fee, depositor_amount])?;
+
+        let (recipient, treasury, depositor) = if deposit.mint == Pubkey::default() {
+            (
+                ctx.accounts.recipient.to_account_info(),
+                ctx.accounts.treasury.to_account_info(),
+                ctx.accounts.depositor.to_account_info(),
+            )
+        } else {
+            (
+                ctx.accounts
+                    .recipient_token_account
+                    .as_ref()
+                    .ok_or(EscrowError::MissingDepositTokenAccounts)?
+                    .to_account_info(),
+                ctx.accounts
+                    .treasury_token_account
+                    .as_ref()
+                    .ok_or(EscrowError::MissingDepositTokenAccounts)?
+                    .to_account_info(),
+                ctx.accounts
+                    .depositor_token_account
+                    .as_ref()
+                    .ok_or(EscrowError::MissingDepositTokenAccounts)?
+                    .to_account_info(),
+            )
+        };
+        let vault = ctx.accounts.deposit_vault.as_ref();
+        let token_program = ctx.accounts.token_program.as_ref();
+        pay_out_of_deposit(deposit, vault, token_program, &recipient, net_amount)?;
+        pay_out_of_deposit(deposit, vault, token_program, &treasury, fee)?;
+        pay_out_of_deposit(deposit, vault, token_program, &depositor, depositor_amount)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let dispute = &mut ctx.accounts.dispute;
+        dispute.status = DisputeStatus::Resolved;
+        dispute.recipient_amount = recipient_amount;
+        dispute.resolved_at = now;
+
+        emit!(DisputeEvent {
+            dispute: dispute.key(),
+            deposit: dispute.deposit,
+            actor: ctx.accounts.arbiter.key(),
+            status: dispute.status,
+            evidence_hash: None,
+            recipient_amount: Some(recipient_amount),
+            timestamp: now,
+        });
+
+        settle_escrow_deposit(&mut ctx.accounts.deposit, EscrowDepositStatus::Resolved)
+    }
+
+    /// Quote the USDC value of `mint` for fee conversion. Admin-only; the
+    /// quote is usable for `max_age_secs` seconds.
+    pub fn set_fee_conversion_rate(
+        ctx: Context<SetFeeConversionRate>,
+        mint: Pubkey,
+        usdc_per_unit: u64,
+        max_age_secs: i64,
+    ) -> Result<()> {
+        require!(mint != USDC_MINT, EscrowError::InvalidFeeConversionRate);
+        require!(
+            usdc_per_unit > 0 && max_age_secs > 0,
+            EscrowError::InvalidFeeConversionRate
+        );
+
+        let fee_rate = &mut ctx.accounts.fee_rate;
+        fee_rate.mint = mint;
+        fee_rate.usdc_per_unit = usdc_per_unit;
+        fee_rate.quoted_at = Clock::get()?.unix_timestamp;
+        fee_rate.max_age_secs = max_age_secs;
+        fee_rate.bump = ctx.bumps.fee_rate;
+
+        emit!(FeeConversionRateUpdatedEvent {
+            mint,
+            usdc_per_unit,
+            quoted_at: fee_rate.quoted_at,
+            max_age_secs,
+        });
+
+        Ok(())
+    }
+
+    /// SPL payment whose treasury and referral legs are settled in USDC at
+    /// the quoted rate, so the treasury accrues a single asset. The recipient
+    /// still receives the net amount in the payment token.
+    pub fn send_payment_with_usdc_fees(
+        ctx: Context<SendPaymentWithUsdcFees>,
+        amount: u64,
+        valid_after_slot: Option<u64>,
+        valid_before_slot: Option<u64>,
+    ) -> Result<()> {
+        check_slot_window(valid_after_slot, valid_before_slot)?;
+        check_relayer(
+            ctx.accounts.relayer.as_ref(),
+            ctx.accounts.relayer_registry.as_deref(),
+        )?;
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.ensure_active(ctx.accounts.jurisdiction_pause.as_deref())?;
+
+        let fee_rate = &ctx.accounts.fee_rate;
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now - fee_rate.quoted_at <= fee_rate.max_age_secs,
+            EscrowError::StaleFeeConversionRate
+        );
+
+        let (referal_reward, treasury_reward) = tiered_fee_split(
+            amount,
+            ctx.accounts.fee_config.as_deref(),
+            ctx.accounts.escrow.total_fund_regulated,
+        )?;
+        let transferable_amount = amount
+            .checked_sub(referal_reward + treasury_reward)
+            .ok_or(ProgramError::InvalidArgument)?;
+        assert_fee_split(amount, &[transferable_amount, treasury_reward, referal_reward])?;
+
+        let treasury_usdc = convert_fee_to_usdc(treasury_reward, fee_rate.usdc_per_unit)?;
+        let referral_usdc = convert_fee_to_usdc(referal_reward, fee_rate.usdc_per_unit)?;
+
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let authority = ctx.accounts.sender.to_account_info();
+
+        let cpi_recipient = CpiContext::new(
+            token_program.clone(),
+            anchor_token::Transfer {
+                from: ctx.accounts.sender_token_account.to_account_info(),
+                to: ctx.accounts.recipient_token_account.to_account_info(),
+                authority: authority.clone(),
+            },
+        );
+        anchor_token::transfer(cpi_recipient, transferable_amount)?;
+
+        let cpi_treasury = CpiContext::new(
+            token_program.clone(),
+            anchor_token::Transfer {
+                from: ctx.accounts.sender_usdc_account.to_account_info(),
+                to: ctx.accounts.treasury_usdc_account.to_account_info(),
+                authority: authority.clone(),
+            },
+        );
+        anchor_token::transfer(cpi_treasury, treasury_usdc)?;
+
+        let cpi_referral = CpiContext::new(
+            token_program,
+            anchor_token::Transfer {
+                from: ctx.accounts.sender_usdc_account.to_account_info(),
+                to: ctx.accounts.referral_usdc_account.to_account_info(),
+                authority,
+            },
+        );
+        anchor_token::transfer(cpi_referral, referral_usdc)?;
+
+        escrow.total_fund_regulated = escrow
+            .total_fund_regulated
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        emit!(ConfidentialPaymentEvent {
+            timestamp: now,
+            sender: ctx.accounts.sender.key(),
+        });
+        let sequence = sequence_escrow_update(escrow)?;
+        emit!(FeesConvertedEvent {
+            escrow: escrow.key(),
+            sequence,
+            mint: ctx.accounts.mint.key(),
+            token_fees: referal_reward + treasury_reward,
+            treasury_usdc,
+            referral_usdc,
+            usdc_per_unit: fee_rate.usdc_per_unit,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// Replace the vault's M-of-N signer list. Proposals made against the
+    /// previous list can no longer be approved or executed.
+    pub fn set_vault_signers(
+        ctx: Context<SetVaultSigners>,
+        signers: Vec<Pubkey>,
+        required_approvals: u8,
+        withdrawal_threshold: u64,
+    ) -> Result<()> {
+        require!(
+            signers.len() <= MAX_VAULT_SIGNERS,
+            EscrowError::InvalidVaultSigners
+        );
+        for (i, signer) in signers.iter().enumerate() {
+            require!(
+                !signers[..i].contains(signer),
+                EscrowError::InvalidVaultSigners
+            );
+        }
+        if withdrawal_threshold > 0 {
+            require!(
+                required_approvals > 0 && required_approvals as usize <= signers.len(),
+                EscrowError::InvalidVaultSigners
+            );
+        }
+
+        let vault_signers = &mut ctx.accounts.vault_signers;
+        vault_signers.escrow = ctx.accounts.escrow.key();
+        vault_signers.signers = signers;
+        vault_signers.required_approvals = required_approvals;
+        vault_signers.epoch = vault_signers.epoch.wrapping_add(1);
+        vault_signers.bump = ctx.bumps.vault_signers;
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.withdrawal_multisig_threshold = withdrawal_threshold;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+
+        sequence_escrow_update(escrow)?;
+        Ok(())
+    }
+
+    /// Withdraw from an escrow vault to an owner-chosen token account.
+    /// Amounts at or above the multisig threshold must go through
+    /// `propose_withdrawal` instead.
+    pub fn withdraw_escrow_vault(ctx: Context<WithdrawEscrowVault>, amount: u64) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+        require!(
+            escrow.withdrawal_multisig_threshold == 0
+                || amount < escrow.withdrawal_multisig_threshold,
+            EscrowError::WithdrawalProposalRequired
+        );
+
+        transfer_from_escrow_vault(
+            escrow,
+            &ctx.accounts.escrow_vault.to_account_info(),
+            &ctx.accounts.destination_token_account.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            amount,
+        )?;
+
+        emit!(VaultWithdrawalEvent {
+            escrow: escrow.key(),
+            proposal: None,
+            destination: ctx.accounts.destination_token_account.key(),
+            mint: ctx.accounts.escrow_vault.mint,
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Open a vault withdrawal for the signer set to approve. The proposer
+    /// must be a vault signer and counts as the first approval.
+    pub fn propose_withdrawal(
+        ctx: Context<ProposeWithdrawal>,
+        proposal_id: u64,
+        amount: u64,
+    ) -> Result<()> {
+        let vault_signers = &ctx.accounts.vault_signers;
+        let index = vault_signers.index_of(&ctx.accounts.proposer.key())?;
+
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.escrow = ctx.accounts.escrow.key();
+        proposal.proposal_id = proposal_id;
+        proposal.proposer = ctx.accounts.proposer.key();
+        proposal.escrow_vault = ctx.accounts.escrow_vault.key();
+        proposal.destination_token_account = ctx.accounts.destination_token_account.key();
+        proposal.amount = amount;
+        proposal.signers_epoch = vault_signers.epoch;
+        proposal.approvals = 1 << index;
+        proposal.created_at = Clock::get()?.unix_timestamp;
+        proposal.bump = ctx.bumps.proposal;
+
+        emit!(WithdrawalProposalEvent {
+            proposal: proposal.key(),
+            signer: proposal.proposer,
+            amount,
+            approvals: 1,
+            required_approvals: vault_signers.required_approvals,
+            timestamp: proposal.created_at,
+        });
+
+        Ok(())
+    }
+
+    pub fn approve_withdrawal(ctx: Context<ApproveWithdrawal>) -> Result<()> {
+        let vault_signers = &ctx.accounts.vault_signers;
+        let index = vault_signers.index_of(&ctx.accounts.signer.key())?;
+
+        let proposal = &mut ctx.accounts.proposal;
+        require!(
+            proposal.signers_epoch == vault_signers.epoch,
+            EscrowError::StaleWithdrawalProposal
+        );
+        require!(
+            proposal.approvals & (1 << index) == 0,
+            EscrowError::WithdrawalAlreadyApproved
+        );
+        proposal.approvals |= 1 << index;
+
+        emit!(WithdrawalProposalEvent {
+            proposal: proposal.key(),
+            signer: ctx.accounts.signer.key(),
+            amount: proposal.amount,
+            approvals: proposal.approvals.count_ones() as u8,
+            required_approvals: vault_signers.required_approvals,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Release an approved withdrawal. Any vault signer may execute once
+    /// the proposal has enough approvals; the proposal is closed.
+    pub fn execute_withdrawal(ctx: Context<ExecuteWithdrawal>) -> Result<()> {
+        let vault_signers = &ctx.accounts.vault_signers;
+        vault_signers.index_of(&ctx.accounts.signer.key())?;
+
+        let proposal = &ctx.accounts.proposal;
+        require!(
+            proposal.signers_epoch == vault_signers.epoch,
+            EscrowError::StaleWithdrawalProposal
+        );
+        require!(
+            proposal.approvals.count_ones() >= vault_signers.required_approvals as u32,
+            EscrowError::InsufficientWithdrawalApprovals
+        );
+
+        transfer_from_escrow_vault(
+            &ctx.accounts.escrow,
+            &ctx.accounts.escrow_vault.to_account_info(),
+            &ctx.accounts.destination_token_account.to_account_info(),
+            &ctx.accounts.token_program.to_account_info(),
+            proposal.amount,
+        )?;
+
+        emit!(VaultWithdrawalEvent {
+            escrow: ctx.accounts.escrow.key(),
+            proposal: Some(proposal.key()),
+            destination: proposal.destination_token_account,
+            mint: ctx.accounts.escrow_vault.mint,
+            amount: proposal.amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Configure the guardians that can rotate the escrow owner. Replacing
+    /// the set also cancels any recovery in flight.
+    pub fn set_recovery_guardians(
+        ctx: Context<SetRecoveryGuardians>,
+        guardians: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        require!(
+            guardians.len() <= MAX_RECOVERY_GUARDIANS,
+            EscrowError::InvalidRecoveryConfig
+        );
+        for (i, guardian) in guardians.iter().enumerate() {
+            require!(
+                !guardians[..i].contains(guardian),
+                EscrowError::InvalidRecoveryConfig
+            );
+        }
+        require!(
+            guardians.is_empty() || (threshold > 0 && threshold as usize <= guardians.len()),
+            EscrowError::InvalidRecoveryConfig
+        );
+
+        let recovery = &mut ctx.accounts.recovery_config;
+        if let Some(new_owner) = recovery.pending_owner.take() {
+            emit_recovery_event(recovery, new_owner, RecoveryStage::Vetoed, None)?;
+        }
+        recovery.escrow = ctx.accounts.escrow.key();
+        recovery.guardians = guardians;
+        recovery.threshold = threshold;
+        recovery.approvals = 0;
+        recovery.initiated_at = 0;
+        recovery.threshold_reached_at = 0;
+        recovery.bump = ctx.bumps.recovery_config;
+
+        Ok(())
+    }
+
+    /// Start rotating the escrow owner to `new_owner`. The initiating
+    /// guardian counts as the first approval.
+    pub fn initiate_recovery(
+        ctx: Context<GuardianRecoveryAction>,
+        new_owner: Pubkey,
+    ) -> Result<()> {
+        let recovery = &mut ctx.accounts.recovery_config;
+        let index = recovery.guardian_index(&ctx.accounts.guardian.key())?;
+        require!(
+            recovery.pending_owner.is_none(),
+            EscrowError::RecoveryAlreadyPending
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        recovery.pending_owner = Some(new_owner);
+        recovery.approvals = 1 << index;
+        recovery.initiated_at = now;
+        recovery.threshold_reached_at = if recovery.threshold == 1 { now } else { 0 };
+
+        emit_recovery_event(
+            recovery,
+            new_owner,
+            RecoveryStage::Initiated,
+            Some(ctx.accounts.guardian.key()),
+        )
+    }
+
+    pub fn approve_recovery(ctx: Context<GuardianRecoveryAction>) -> Result<()> {
+        let recovery = &mut ctx.accounts.recovery_config;
+        let index = recovery.guardian_index(&ctx.accounts.guardian.key())?;
+        let new_owner = recovery.pending_owner.ok_or(EscrowError::NoPendingRecovery)?;
+        require!(
+            recovery.approvals & (1 << index) == 0,
+            EscrowError::RecoveryAlreadyApproved
+        );
+
+        recovery.approvals |= 1 << index;
+        if recovery.threshold_reached_at == 0
+            && recovery.approvals.count_ones() >= recovery.threshold as u32
+        {
+            recovery.threshold_reached_at = Clock::get()?.unix_timestamp;
+        }
+
+        emit_recovery_event(
+            recovery,
+            new_owner,
+            RecoveryStage::Approved,
+            Some(ctx.accounts.guardian.key()),
+        )
+    }
+
+    /// Cancel a pending recovery. Only the current owner can veto, at any
+    /// point before it is executed.
+    pub fn veto_recovery(ctx: Context<VetoRecovery>) -> Result<()> {
+        let recovery = &mut ctx.accounts.recovery_config;
+        let new_owner = recovery.pending_owner.take().ok_or(EscrowError::NoPendingRecovery)?;
+        recovery.approvals = 0;
+        recovery.initiated_at = 0;
+        recovery.threshold_reached_at = 0;
+
+        emit_recovery_event(recovery, new_owner, RecoveryStage::Vetoed, None)
+    }
+
+    /// Rotate the owner once the guardian threshold is met, the timelock
+    /// has run and the owner's challenge window has closed. Permissionless.
+    pub fn execute_recovery(ctx: Context<ExecuteRecovery>) -> Result<()> {
+        let recovery = &mut ctx.accounts.recovery_config;
+        let new_owner = recovery.pending_owner.ok_or(EscrowError::NoPendingRecovery)?;
+        require!(
+            recovery.threshold_reached_at > 0,
+            EscrowError::RecoveryThresholdNotMet
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now >= recovery.initiated_at + RECOVERY_TIMELOCK_SECS
+                && now >= recovery.threshold_reached_at + RECOVERY_CHALLENGE_SECS,
+            EscrowError::RecoveryTimelocked
+        );
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.recovered_owner = if new_owner == escrow.owner {
+            Pubkey::default()
+        } else {
+            new_owner
+        };
+        escrow.last_updated = now;
+
+        recovery.pending_owner = None;
+        recovery.approvals = 0;
+        recovery.initiated_at = 0;
+        recovery.threshold_reached_at = 0;
+
+        emit_recovery_event(recovery, new_owner, RecoveryStage::Executed, None)
+    }
+
+    /// Open a referral campaign: between `start_ts` and `end_ts`, payments
+    /// in `mint` that pass the campaign pay referrers `referral_bps` instead
+    /// of the default share. The extra comes out of the treasury's share and
+    /// stops once `budget` is spent. `mint` is the default key for SOL.
+    pub fn create_campaign(
+        ctx: Context<CreateCampaign>,
+        campaign_id: u64,
+        mint: Pubkey,
+        start_ts: i64,
+        end_ts: i64,
+        referral_bps: u16,
+        budget: u64,
+    ) -> Result<()> {
+        require!(
+            start_ts < end_ts && referral_bps <= MAX_CAMPAIGN_REFERRAL_BPS,
+            EscrowError::InvalidCampaign
+        );
+
+        let campaign = &mut ctx.accounts.campaign;
+        campaign.escrow = ctx.accounts.escrow.key();
+        campaign.campaign_id = campaign_id;
+        campaign.mint = mint;
+        campaign.start_ts = start_ts;
+        campaign.end_ts = end_ts;
+        campaign.referral_bps = referral_bps;
+        campaign.budget = budget;
+        campaign.spent = 0;
+        campaign.bump = ctx.bumps.campaign;
+
+        Ok(())
+    }
+
+    pub fn close_campaign(_ctx: Context<CloseCampaign>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Replace the protocol-wide treasury fee curve. Tiers must be sorted by
+    /// volume, and fees may only step down as volume grows.
+    pub fn set_fee_config(ctx: Context<SetFeeConfig>, tiers: Vec<FeeTier>) -> Result<()> {
+        require!(tiers.len() <= MAX_FEE_TIERS, EscrowError::InvalidFeeTiers);
+        let mut previous = FeeTier {
+            min_volume: 0,
+            treasury_bps: DEFAULT_TREASURY_FEE_BPS,
+        };
+        for tier in &tiers {
+            require!(
+                tier.min_volume > previous.min_volume && tier.treasury_bps <= previous.treasury_bps,
+                EscrowError::InvalidFeeTiers
+            );
+            previous = *tier;
+        }
+
+        let fee_config = &mut ctx.accounts.fee_config;
+        fee_config.tiers = tiers;
+        fee_config.bump = ctx.bumps.fee_config;
+
+        Ok(())
+    }
+
+    pub fn set_referral_vesting(
+        ctx: Context<ExerciseOwnerCapability>,
+        enabled: bool,
+    ) -> Result<()> {
+        ctx.accounts.escrow.authorize_capability(
+            OwnerCapability::Fees,
+            &ctx.accounts.authority.key(),
+            Clock::get()?.unix_timestamp,
+        )?;
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.vest_referral_rewards = enabled;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        sequence_escrow_update(escrow)?;
+        Ok(())
+    }
+
+    /// Settle referral rewards on SOL payments in USDC: the referral share
+    /// goes to the treasury and the referrer accrues its USDC value at the
+    /// quoted SOL rate, so partners account in a single asset.
+    pub fn set_usdc_referral_rewards(
+        ctx: Context<ExerciseOwnerCapability>,
+        enabled: bool,
+    ) -> Result<()> {
+        ctx.accounts.escrow.authorize_capability(
+            OwnerCapability::Fees,
+            &ctx.accounts.authority.key(),
+            Clock::get()?.unix_timestamp,
+        )?;
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.usdc_referral_rewards = enabled;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        sequence_escrow_update(escrow)?;
+        Ok(())
+    }
+
+    /// Open the ledger a referrer's USDC-denominated rewards accrue on.
+    /// Anyone can pay for it, typically the first payer to use the referral.
+    pub fn init_referral_usdc_ledger(
+        ctx: Context<InitReferralUsdcLedger>,
+        referrer: Pubkey,
+    ) -> Result<()> {
+        let ledger = &mut ctx.accounts.referral_usdc_ledger;
+        ledger.escrow = ctx.accounts.escrow.key();
+        ledger.referrer = referrer;
+        ledger.bump = ctx.bumps.referral_usdc_ledger;
+        Ok(())
+    }
+
+    /// Pay a referrer everything their USDC ledger is owed, out of the
+    /// treasury's USDC account.
+    pub fn settle_usdc_referral_rewards(ctx: Context<SettleUsdcReferralRewards>) -> Result<()> {
+        let ledger = &mut ctx.accounts.referral_usdc_ledger;
+        let amount = ledger.outstanding();
+        require!(amount > 0, EscrowError::NothingToSettle);
+        ledger.settled_usdc = ledger.accrued_usdc;
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            anchor_token::Transfer {
+                from: ctx.accounts.treasury_usdc_account.to_account_info(),
+                to: ctx.accounts.referrer_usdc_account.to_account_info(),
+                authority: ctx.accounts.treasury.to_account_info(),
+            },
+        );
+        anchor_token::transfer(cpi_ctx, amount)?;
+
+        emit!(ReferralUsdcLedgerEvent {
+            escrow: ledger.escrow,
+            referrer: ledger.referrer,
+            movement: ReferralUsdcMovement::Settled,
+            usdc_amount: amount,
+            outstanding_usdc: 0,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Open the account a referrer's vesting rewards accrue in. Anyone can
+    /// pay for it, typically the first payer to use the referral.
+    pub fn init_referral_vesting(
+        ctx: Context<InitReferralVesting>,
+        referrer: Pubkey,
+    ) -> Result<()> {
+        let vesting = &mut ctx.accounts.referral_vesting;
+        vesting.escrow = ctx.accounts.escrow.key();
+        vesting.referrer = referrer;
+        vesting.checkpoint_ts = Clock::get()?.unix_timestamp;
+        vesting.vest_end_ts = vesting.checkpoint_ts;
+        vesting.bump = ctx.bumps.referral_vesting;
+        Ok(())
+    }
+
+    /// Pay out everything vested so far to the referrer.
+    pub fn claim_vested_rewards(ctx: Context<ClaimVestedRewards>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let vesting = &mut ctx.accounts.referral_vesting;
+        vesting.checkpoint(now)?;
+
+        let amount = vesting.claimable;
+        require!(amount > 0, EscrowError::NothingVested);
+        vesting.claimable = 0;
+        vesting.claimed_total = vesting
+            .claimed_total
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        **vesting.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.referrer.to_account_info().try_borrow_mut_lamports()? += amount;
+
+        emit!(VestedRewardsClaimedEvent {
+            escrow: vesting.escrow,
+            referrer: vesting.referrer,
+            amount,
+            still_vesting: vesting.unvested,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// Appoint the compliance role and open the quarantine queue. Once set,
+    /// a tripped circuit breaker quarantines vault payments instead of
+    /// pausing the escrow.
+    pub fn set_compliance_officer(
+        ctx: Context<SetComplianceOfficer>,
+        compliance_officer: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.quarantine.escrow = ctx.accounts.escrow.key();
+        ctx.accounts.quarantine.bump = ctx.bumps.quarantine;
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.compliance_officer = compliance_officer;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        sequence_escrow_update(escrow)?;
+        Ok(())
+    }
+
+    /// Pay out a quarantined vault payment exactly as it was requested.
+    pub fn release_quarantined(ctx: Context<ReviewQuarantined>, payment_id: u64) -> Result<()> {
+        let entry = ctx.accounts.quarantine.take(payment_id)?;
+        require_keys_eq!(
+            ctx.accounts.escrow_vault.key(),
+            entry.escrow_vault,
+            EscrowError::QuarantineAccountMismatch
+        );
+
+        let escrow = &ctx.accounts.escrow;
+        let vault = ctx.accounts.escrow_vault.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+        for (destination, expected, amount) in [
+            (
+                &ctx.accounts.recipient_token_account,
+                entry.recipient_token_account,
+                entry.net_amount,
+            ),
+            (
+                &ctx.accounts.treasury_token_account,
+                entry.treasury_token_account,
+                entry.treasury_reward,
+            ),
+            (
+                &ctx.accounts.referral_token_account,
+                entry.referral_token_account,
+                entry.referal_reward,
+            ),
+        ] {
+            require_keys_eq!(
+                destination.key(),
+                expected,
+                EscrowError::QuarantineAccountMismatch
+            );
+            transfer_from_escrow_vault(
+                escrow,
+                &vault,
+                &destination.to_account_info(),
+                &token_program,
+                amount,
+            )?;
+        }
+
+        let amount = entry.net_amount + entry.treasury_reward + entry.referal_reward;
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.total_fund_regulated = escrow
+            .total_fund_regulated
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        let sequence = sequence_escrow_update(escrow)?;
+        emit!(QuarantineEvent {
+            escrow: escrow.key(),
+            sequence,
+            payment_id,
+            amount,
+            action: QuarantineAction::Released,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Drop a quarantined vault payment; the funds never left the vault.
+    pub fn reject_quarantined(ctx: Context<ComplianceAction>, payment_id: u64) -> Result<()> {
+        let entry = ctx.accounts.quarantine.take(payment_id)?;
+
+        let sequence = sequence_escrow_update(&mut ctx.accounts.escrow)?;
+        emit!(QuarantineEvent {
+            escrow: ctx.accounts.escrow.key(),
+            sequence,
+            payment_id,
+            amount: entry.net_amount + entry.treasury_reward + entry.referal_reward,
+            action: QuarantineAction::Rejected,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Lift the quarantine so vault payments settle immediately again.
+    /// Payments already queued still need an explicit release or reject.
+    pub fn clear_quarantine(ctx: Context<ComplianceAction>) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.quarantine_active = false;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        sequence_escrow_update(escrow)?;
+        Ok(())
+    }
+
+    /// Register (or update) a reusable escrow setup under the caller's key.
+    pub fn register_escrow_template(
+        ctx: Context<RegisterEscrowTemplate>,
+        template_id: u64,
+        config: EscrowTemplateConfig,
+    ) -> Result<()> {
+        require!(
+            config.tip_treasury_fee_bps <= MAX_TIP_TREASURY_FEE_BPS,
+            EscrowError::InvalidTipFee
+        );
+        require!(
+            config.approval_threshold == 0 || config.approval_window_secs > 0,
+            EscrowError::InvalidApprovalWindow
+        );
+
+        let template = &mut ctx.accounts.template;
+        template.creator = ctx.accounts.creator.key();
+        template.template_id = template_id;
+        template.config = config;
+        template.bump = ctx.bumps.template;
+
+        Ok(())
+    }
+
+    /// `initialize_escrow` with the template's treasury, limits and roles
+    /// applied in the same transaction.
+    pub fn create_escrow_from_template(
+        ctx: Context<CreateEscrowFromTemplate>,
+        computation_offset: u64,
+        nonce: u128,
+    ) -> Result<()> {
+        let escrow_key = ctx.accounts.escrow.key();
+        let computation_key = ctx.accounts.computation_account.key();
+        let config = ctx.accounts.template.config;
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.owner = ctx.accounts.owner.key();
+        escrow.total_fund_regulated = 0;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        escrow.active = true;
+        escrow.bump = ctx.bumps.escrow;
+        escrow.nonce = nonce;
+        escrow.encrypted_stats = [[0; 32]; ESCROW_STATS_FIELDS];
+        escrow.stats_dimensions = STATS_DIMENSIONS_ALL;
+        escrow.pending_computation_offset = Some(computation_offset);
+        escrow.pending_since_slot = Clock::get()?.slot;
+        escrow.recent_volume_decayed_at = escrow.last_updated;
+        config.apply(escrow);
+        let sequence = sequence_escrow_update(escrow)?;
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        emit!(EscrowCreatedFromTemplateEvent {
+            escrow: escrow_key,
+            sequence,
+            owner: ctx.accounts.owner.key(),
+            template: ctx.accounts.template.key(),
+            timestamp: ctx.accounts.escrow.last_updated,
+        });
+
+        #[cfg(not(feature = "mock-mpc"))]
+        {
+            let args = vec![Argument::PlaintextU128(nonce)];
+
+            queue_computation(
+                ctx.accounts,
+                computation_offset,
+                args,
+                None,
+                vec![InitEscrowStatsCallback::callback_ix(&[
+                    CallbackAccount {
+                        pubkey: escrow_key,
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: computation_key,
+                        is_writable: false,
+                    },
+                ])],
+                1,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Grant `viewer` read-only access: it may run every analytics
+    /// computation, including `share_stats_with_viewer`, but holds no other
+    /// authority over the escrow.
+    pub fn add_viewer(ctx: Context<AddViewer>, viewer: Pubkey) -> Result<()> {
+        let grant = &mut ctx.accounts.viewer;
+        grant.escrow = ctx.accounts.escrow.key();
+        grant.viewer = viewer;
+        grant.bump = ctx.bumps.viewer;
+
+        emit!(ViewerEvent {
+            escrow: grant.escrow,
+            viewer,
+            revoked: false,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    pub fn remove_viewer(ctx: Context<RemoveViewer>) -> Result<()> {
+        let grant = &ctx.accounts.viewer;
+        emit!(ViewerEvent {
+            escrow: grant.escrow,
+            viewer: grant.viewer,
+            revoked: true,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+        Ok(())
+    }
+
+    /// Re-encrypt the escrow's stats to `viewer_pubkey`, e.g. for an
+    /// accountant or auditor, without revealing anything on-chain.
+    pub fn share_stats_with_viewer(
+        ctx: Context<ShareStatsWithViewer>,
+        computation_offset: u64,
+        viewer_pubkey: [u8; 32],
+        viewer_nonce: u128,
+    ) -> Result<()> {
+        authorize_analytics_request(
+            &ctx.accounts.escrow,
+            &ctx.accounts.authority.key(),
+            ctx.accounts.viewer.as_ref(),
+            ctx.accounts.analytics_operator.as_mut(),
+        )?;
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        #[cfg(not(feature = "mock-mpc"))]
+        {
+            let args = vec![
+                Argument::ArcisPubkey(viewer_pubkey),
+                Argument::PlaintextU128(viewer_nonce),
+                Argument::PlaintextU128(ctx.accounts.escrow.nonce),
+                Argument::Account(
+                    ctx.accounts.escrow.key(),
+                    EscrowAccount::ENCRYPTED_STATS_OFFSET,
+                    32 * ESCROW_STATS_FIELDS as u32,
+                ),
+            ];
+
+            queue_computation(
+                ctx.accounts,
+                computation_offset,
+                args,
+                None,
+                vec![ShareEscrowStatsCallback::callback_ix(&[CallbackAccount {
+                    pubkey: ctx.accounts.escrow.key(),
+                    is_writable: false,
+                }])],
+                1,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "share_escrow_stats")]
+    pub fn share_escrow_stats_callback(
+        ctx: Context<ShareEscrowStatsCallback>,
+        output: ComputationOutputs<ShareEscrowStatsOutput>,
+    ) -> Result<()> {
+        let o = match output {
+            ComputationOutputs::Success(ShareEscrowStatsOutput { field_0 }) => field_0,
+            _ => return Err(EscrowError::AbortedComputation.into()),
+        };
+
+        emit!(SharedStatsEvent {
+            escrow: ctx.accounts.escrow.key(),
+            encryption_key: o.encryption_key,
+            nonce: o.nonce,
+            ciphertexts: o.ciphertexts,
+        });
+
+        Ok(())
+    }
+
+    /// Fold old `PaymentAccount`s, passed as remaining accounts in leaf
+    /// order, into a Merkle root and close them. Their rent goes to the
+    /// rebate pool. Admin-only; use `verify_archive_proof` to check a
+    /// payment against the root later.
+    pub fn archive_payments(ctx: Context<ArchivePayments>, archive_id: u64) -> Result<()> {
+        let count = ctx.remaining_accounts.len();
+        require!(
+            count > 0 && count <= MAX_ARCHIVE_BATCH,
+            EscrowError::InvalidArchiveBatch
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let rebate_pool = ctx.accounts.rebate_pool.to_account_info();
+        let mut leaves = Vec::with_capacity(count);
+        let mut rent_returned: u64 = 0;
+        let mut oldest = i64::MAX;
+        let mut newest = i64::MIN;
+        for info in ctx.remaining_accounts.iter() {
+            require_keys_eq!(*info.owner, crate::ID, EscrowError::InvalidArchiveBatch);
+            let payment = {
+                let data = info.try_borrow_data()?;
+                let payment = PaymentAccount::try_deserialize(&mut &data[..])?;
+                leaves.push(archive_leaf(info.key, &data));
+                payment
+            };
+            require!(
+                now - payment.timestamp >= ARCHIVE_MIN_AGE_SECS,
+                EscrowError::PaymentTooRecent
+            );
+            // Time-locked payments still hold funds until claimed or reclaimed
+            require!(!payment.is_time_locked(), EscrowError::PaymentLocked);
+            oldest = oldest.min(payment.timestamp);
+            newest = newest.max(payment.timestamp);
+
+            rent_returned = rent_returned
+                .checked_add(info.lamports())
+                .ok_or(ProgramError::InvalidArgument)?;
+            anchor_lang::common::close(info.clone(), rebate_pool.clone())?;
+        }
+
+        let archive = &mut ctx.accounts.archive;
+        archive.archive_id = archive_id;
+        archive.root = archive_root(leaves);
+        archive.leaf_count = count as u32;
+        archive.oldest_payment_at = oldest;
+        archive.newest_payment_at = newest;
+        archive.archived_at = now;
+        archive.bump = ctx.bumps.archive;
+
+        let pool = &mut ctx.accounts.rebate_pool;
+        pool.bump = ctx.bumps.rebate_pool;
+        pool.total_collected = pool
+            .total_collected
+            .checked_add(rent_returned)
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        emit!(PaymentsArchivedEvent {
+            archive: archive.key(),
+            root: archive.root,
+            leaf_count: archive.leaf_count,
+            rent_returned,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// Permissionless invariant check for monitoring bots. Returns, as
+    /// return data, a mask of the `HEALTH_*` checks that failed; 0 means
+    /// healthy. `last_seen_nonce` is the stats nonce the caller observed on
+    /// its previous run. Remaining accounts may be this escrow's token
+    /// vaults, checked against the payments quarantined out of them, and
+    /// its sub-accounts, checked against their ledger balances.
+    pub fn health_check(ctx: Context<HealthCheck>, last_seen_nonce: u128) -> Result<u32> {
+        let escrow = &ctx.accounts.escrow;
+        let escrow_key = escrow.key();
+        let mut failed = 0u32;
+
+        if escrow.encrypted_stats.iter().all(|ciphertext| *ciphertext == [0u8; 32]) {
+            failed |= HEALTH_STATS_UNINITIALIZED;
+        }
+        if escrow.nonce < last_seen_nonce {
+            failed |= HEALTH_NONCE_REGRESSED;
+        }
+        let current_slot = Clock::get()?.slot;
+        if escrow.pending_computation_offset.is_some()
+            && current_slot.saturating_sub(escrow.pending_since_slot) >= STATS_LOCK_TIMEOUT_SLOTS
+        {
+            failed |= HEALTH_STUCK_COMPUTATION;
+        }
+
+        let rent = Rent::get()?;
+        for info in ctx.remaining_accounts.iter() {
+            let (held, owed) = if *info.owner == anchor_token::ID {
+                let vault =
+                    anchor_token::TokenAccount::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+                require_keys_eq!(vault.owner, escrow_key, EscrowError::InvalidHealthCheckAccount);
+                let owed = match ctx.accounts.quarantine.as_ref() {
+                    Some(quarantine) => quarantine.owed_from(info.key)?,
+                    None => 0,
+                };
+                (vault.amount, owed)
+            } else if *info.owner == crate::ID {
+                let sub_account = SubAccount::try_deserialize(&mut &info.try_borrow_data()?[..])?;
+                require_keys_eq!(
+                    sub_account.escrow,
+                    escrow_key,
+                    EscrowError::InvalidHealthCheckAccount
+                );
+                let held = info
+                    .lamports()
+                    .saturating_sub(rent.minimum_balance(info.data_len()));
+                (held, sub_account.balance)
+            } else {
+                return err!(EscrowError::InvalidHealthCheckAccount);
+            };
+            if held < owed {
+                failed |= HEALTH_VAULT_SHORTFALL;
+            }
+        }
+
+        Ok(failed)
+    }
+
+    /// Switch SOL payments between paying the treasury fee out on every
+    /// payment and accruing it in the escrow's treasury vault.
+    pub fn set_treasury_accrual(ctx: Context<SetTreasuryAccrual>, enabled: bool) -> Result<()> {
+        ctx.accounts.escrow.authorize_capability(
+            OwnerCapability::Fees,
+            &ctx.accounts.authority.key(),
+            Clock::get()?.unix_timestamp,
+        )?;
+        ctx.accounts.treasury_vault.escrow = ctx.accounts.escrow.key();
+        ctx.accounts.treasury_vault.bump = ctx.bumps.treasury_vault;
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.accrue_treasury_fees = enabled;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        sequence_escrow_update(escrow)?;
+        Ok(())
+    }
+
+    /// Move everything the treasury vault holds above its rent reserve to
+    /// the escrow treasury. Permissionless, so a crank can run it.
+    pub fn sweep_treasury(ctx: Context<SweepTreasury>) -> Result<()> {
+        let vault_info = ctx.accounts.treasury_vault.to_account_info();
+        let reserve = Rent::get()?.minimum_balance(vault_info.data_len());
+        let amount = vault_info.lamports().saturating_sub(reserve);
+        require!(amount > 0, EscrowError::NothingToSweep);
+
+        **vault_info.try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.treasury.try_borrow_mut_lamports()? += amount;
+
+        let vault = &mut ctx.accounts.treasury_vault;
+        vault.total_swept = vault
+            .total_swept
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        emit!(TreasurySweptEvent {
+            escrow: ctx.accounts.escrow.key(),
+            treasury: ctx.accounts.treasury.key(),
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        if let (Some(vault), Some(cranker)) = (
+            ctx.accounts.compute_budget_vault.as_mut(),
+            ctx.accounts.cranker.as_ref(),
+        ) {
+            pay_crank_reward(vault, &cranker.to_account_info(), CrankKind::SweepTreasury)?;
+        }
+
+        Ok(())
+    }
+
+    /// Switch the escrow's referral program on or off. While it is off,
+    /// payments need no referral account and the referral share goes to
+    /// the treasury.
+    pub fn set_referrals_enabled(
+        ctx: Context<ExerciseOwnerCapability>,
+        enabled: bool,
+    ) -> Result<()> {
+        ctx.accounts.escrow.authorize_capability(
+            OwnerCapability::Fees,
+            &ctx.accounts.authority.key(),
+            Clock::get()?.unix_timestamp,
+        )?;
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.referrals_enabled = enabled;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        sequence_escrow_update(escrow)?;
+        Ok(())
+    }
+
+    /// Record who referred the signer. `scope` is the escrow the link
+    /// applies to, or the protocol config to apply it under every escrow.
+    /// The first registration for a scope stands.
+    pub fn register_referral(
+        ctx: Context<RegisterReferral>,
+        scope: Pubkey,
+        referrer: Pubkey,
+    ) -> Result<()> {
+        let referee = ctx.accounts.referee.key();
+        require_keys_neq!(referrer, referee, EscrowError::SelfReferral);
+        let escrow_scope = ctx.accounts.escrow.as_ref().map(|escrow| escrow.key());
+        require!(
+            scope == ctx.accounts.protocol_config.key() || Some(scope) == escrow_scope,
+            EscrowError::InvalidReferralScope
+        );
+
+        let link = &mut ctx.accounts.referral_link;
+        link.scope = scope;
+        link.referee = referee;
+        link.referrer = referrer;
+        link.registered_at = Clock::get()?.unix_timestamp;
+        link.bump = ctx.bumps.referral_link;
+
+        emit!(ReferralRegisteredEvent {
+            scope,
+            referee,
+            referrer,
+            protocol_wide: Some(scope) != escrow_scope,
+            timestamp: link.registered_at,
+        });
+
+        Ok(())
+    }
+
+    /// Set the sender's private monthly budget. `encrypted_cap` is encrypted
+    /// by the sender; `state_nonce` seeds the MXE encryption of the stored
+    /// budget. Resets the spend for a fresh period.
+    pub fn set_spend_cap(
+        ctx: Context<SetSpendCap>,
+        computation_offset: u64,
+        cap_encryption_pubkey: [u8; 32],
+        cap_nonce: u128,
+        encrypted_cap: [u8; 32],
+        state_nonce: u128,
+    ) -> Result<()> {
+        let spend_cap = &mut ctx.accounts.spend_cap;
+        require!(
+            spend_cap.pending_computation_offset.is_none(),
+            EscrowError::StatsComputationPending
+        );
+        spend_cap.sender = ctx.accounts.sender.key();
+        spend_cap.bump = ctx.bumps.spend_cap;
+        spend_cap.period_start = Clock::get()?.unix_timestamp;
+        spend_cap.pending_period_start = spend_cap.period_start;
+        spend_cap.approved_amount = None;
+        spend_cap.pending_computation_offset = Some(computation_offset);
+        let spend_cap_key = spend_cap.key();
+        let computation_key = ctx.accounts.computation_account.key();
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        #[cfg(not(feature = "mock-mpc"))]
+        {
+            let args = vec![
+                Argument::ArcisPubkey(cap_encryption_pubkey),
+                Argument::PlaintextU128(cap_nonce),
+                Argument::EncryptedU64(encrypted_cap),
+                Argument::PlaintextU128(state_nonce),
+            ];
+
+            queue_computation(
+                ctx.accounts,
+                computation_offset,
+                args,
+                None,
+                vec![InitSpendCapCallback::callback_ix(&[
+                    CallbackAccount {
+                        pubkey: spend_cap_key,
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: computation_key,
+                        is_writable: false,
+                    },
+                ])],
+                1,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "init_spend_cap")]
+    pub fn init_spend_cap_callback(
+        ctx: Context<InitSpendCapCallback>,
+        output: ComputationOutputs<InitSpendCapOutput>,
+    ) -> Result<()> {
+        let o = match output {
+            ComputationOutputs::Success(InitSpendCapOutput { field_0 }) => field_0,
+            _ => return Err(EscrowError::AbortedComputation.into()),
+        };
+
+        let spend_cap = &mut ctx.accounts.spend_cap;
+        verify_pending_computation(
+            spend_cap.pending_computation_offset,
+            &ctx.accounts.computation_account.key(),
+        )?;
+        spend_cap.encrypted_state = o.ciphertexts;
+        spend_cap.nonce = o.nonce;
+        spend_cap.pending_computation_offset = None;
+
+        emit!(SpendCapSetEvent {
+            sender: spend_cap.sender,
+            period_start: spend_cap.period_start,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Ask the cluster whether a confidential payment of `encrypted_amount`
+    /// fits the sender's remaining budget. Only the yes/no is revealed; on
+    /// yes the amount is charged and one `send_payment_encrypted` with the
+    /// same ciphertext may go through.
+    pub fn authorize_capped_spend(
+        ctx: Context<AuthorizeCappedSpend>,
+        computation_offset: u64,
+        amount_encryption_pubkey: [u8; 32],
+        amount_nonce: u128,
+        encrypted_amount: [u8; 32],
+    ) -> Result<()> {
+        let spend_cap = &mut ctx.accounts.spend_cap;
+        require!(
+            spend_cap.pending_computation_offset.is_none(),
+            EscrowError::StatsComputationPending
+        );
+        require!(spend_cap.approved_amount.is_none(), EscrowError::SpendApprovalOutstanding);
+
+        // Periods are aligned to the first one, so a late check doesn't
+        // shift every later reset.
+        let now = Clock::get()?.unix_timestamp;
+        let elapsed = now - spend_cap.period_start;
+        let new_period = elapsed >= SPEND_CAP_PERIOD_SECS;
+        spend_cap.pending_period_start = if new_period {
+            now - elapsed % SPEND_CAP_PERIOD_SECS
+        } else {
+            spend_cap.period_start
+        };
+        spend_cap.pending_amount = encrypted_amount;
+        spend_cap.pending_computation_offset = Some(computation_offset);
+        let spend_cap_key = spend_cap.key();
+        let state_nonce = spend_cap.nonce;
+        let computation_key = ctx.accounts.computation_account.key();
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        #[cfg(not(feature = "mock-mpc"))]
+        {
+            let args = vec![
+                Argument::ArcisPubkey(amount_encryption_pubkey),
+                Argument::PlaintextU128(amount_nonce),
+                Argument::EncryptedU64(encrypted_amount),
+                Argument::PlaintextU128(state_nonce),
+                Argument::Account(
+                    spend_cap_key,
+                    SpendCapAccount::ENCRYPTED_STATE_OFFSET,
+                    32 * 2,
+                ),
+                Argument::PlaintextBool(new_period),
+            ];
+
+            queue_computation(
+                ctx.accounts,
+                computation_offset,
+                args,
+                None,
+                vec![CheckSpendCapCallback::callback_ix(&[
+                    CallbackAccount {
+                        pubkey: spend_cap_key,
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: computation_key,
+                        is_writable: false,
+                    },
+                ])],
+                1,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "check_spend_cap")]
+    pub fn check_spend_cap_callback(
+        ctx: Context<CheckSpendCapCallback>,
+        output: ComputationOutputs<CheckSpendCapOutput>,
+    ) -> Result<()> {
+        let (state, approved) = match output {
+            ComputationOutputs::Success(CheckSpendCapOutput { field_0 }) => {
+                (field_0.field_0, field_0.field_1)
+            }
+            _ => return Err(EscrowError::AbortedComputation.into()),
+        };
+
+        let spend_cap = &mut ctx.accounts.spend_cap;
+        verify_pending_computation(
+            spend_cap.pending_computation_offset,
+            &ctx.accounts.computation_account.key(),
+        )?;
+        spend_cap.encrypted_state = state.ciphertexts;
+        spend_cap.nonce = state.nonce;
+        spend_cap.period_start = spend_cap.pending_period_start;
+        spend_cap.pending_computation_offset = None;
+        if approved {
+            spend_cap.approved_amount = Some(spend_cap.pending_amount);
+        }
+
+        emit!(SpendCapCheckedEvent {
+            sender: spend_cap.sender,
+            approved,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Open or reconfigure the escrow's insurance pool. `guardian`
+    /// adjudicates claims; `contribution_bps` of every SOL payment is
+    /// routed to the pool out of the treasury fee.
+    pub fn set_insurance_pool(
+        ctx: Context<SetInsurancePool>,
+        guardian: Pubkey,
+        contribution_bps: u16,
+    ) -> Result<()> {
+        ctx.accounts.escrow.authorize_capability(
+            OwnerCapability::Fees,
+            &ctx.accounts.authority.key(),
+            Clock::get()?.unix_timestamp,
+        )?;
+        require!(contribution_bps <= MAX_INSURANCE_BPS, EscrowError::InvalidFeeBps);
+
+        let pool = &mut ctx.accounts.insurance_pool;
+        pool.escrow = ctx.accounts.escrow.key();
+        pool.guardian = guardian;
+        pool.bump = ctx.bumps.insurance_pool;
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.insurance_bps = contribution_bps;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        sequence_escrow_update(escrow)?;
+        Ok(())
+    }
+
+    /// Ask the pool to compensate the recipient of a failed or erroneous
+    /// settlement. The claimant posts `INSURANCE_CLAIM_BOND_LAMPORTS`,
+    /// returned on approval and slashed into the pool on rejection.
+    pub fn file_claim(
+        ctx: Context<FileClaim>,
+        amount: u64,
+        reason_hash: [u8; 32],
+    ) -> Result<()> {
+        let payment = &ctx.accounts.payment;
+        require!(amount > 0 && amount <= payment.amount, EscrowError::InvalidClaim);
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: ctx.accounts.claimant.to_account_info(),
+                to: ctx.accounts.claim.to_account_info(),
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_ctx, INSURANCE_CLAIM_BOND_LAMPORTS)?;
+
+        let claim = &mut ctx.accounts.claim;
+        claim.pool = ctx.accounts.insurance_pool.key();
+        claim.payment = payment.key();
+        claim.claimant = ctx.accounts.claimant.key();
+        claim.amount = amount;
+        claim.reason_hash = reason_hash;
+        claim.filed_at = Clock::get()?.unix_timestamp;
+        claim.bump = ctx.bumps.claim;
+
+        emit!(InsurancePoolEvent {
+            pool: claim.pool,
+            movement: InsuranceMovement::ClaimFiled,
+            counterparty: claim.claimant,
+            amount,
+            timestamp: claim.filed_at,
+        });
+
+        Ok(())
+    }
+
+    /// Pay a claim out of the pool. The claim account closes to the
+    /// claimant, returning the bond.
+    pub fn approve_claim(ctx: Context<AdjudicateClaim>) -> Result<()> {
+        let amount = ctx.accounts.claim.amount;
+        let pool_info = ctx.accounts.insurance_pool.to_account_info();
+        let reserve = Rent::get()?.minimum_balance(pool_info.data_len());
+        require!(
+            pool_info.lamports().saturating_sub(reserve) >= amount,
+            EscrowError::InsufficientInsuranceFunds
+        );
+
+        **pool_info.try_borrow_mut_lamports()? -= amount;
+        **ctx.accounts.claimant.try_borrow_mut_lamports()? += amount;
+
+        let pool = &mut ctx.accounts.insurance_pool;
+        pool.total_paid_out = pool
+            .total_paid_out
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+        ctx.accounts.claim.close(ctx.accounts.claimant.to_account_info())?;
+
+        emit!(InsurancePoolEvent {
+            pool: pool.key(),
+            movement: InsuranceMovement::ClaimPaid,
+            counterparty: ctx.accounts.claimant.key(),
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Reject a claim. The bond and the claim account's rent are slashed
+    /// into the pool.
+    pub fn reject_claim(ctx: Context<AdjudicateClaim>) -> Result<()> {
+        let slashed = ctx.accounts.claim.to_account_info().lamports();
+        let pool = &mut ctx.accounts.insurance_pool;
+        ctx.accounts.claim.close(pool.to_account_info())?;
+        pool.total_slashed = pool
+            .total_slashed
+            .checked_add(slashed)
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        emit!(InsurancePoolEvent {
+            pool: pool.key(),
+            movement: InsuranceMovement::BondSlashed,
+            counterparty: ctx.accounts.claimant.key(),
+            amount: slashed,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Pay `amount` of an SPL mint out of the pool's token account. SPL
+    /// payments carve their insurance share into that account, so the
+    /// guardian settles claims in those mints here; lamport totals are left
+    /// untouched.
+    pub fn release_insurance_tokens(
+        ctx: Context<ReleaseInsuranceTokens>,
+        amount: u64,
+    ) -> Result<()> {
+        let pool = &ctx.accounts.insurance_pool;
+        let signer_seeds: &[&[&[u8]]] =
+            &[&[seeds::INSURANCE_POOL, pool.escrow.as_ref(), &[pool.bump]]];
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            anchor_token::Transfer {
+                from: ctx.accounts.pool_token_account.to_account_info(),
+                to: ctx.accounts.destination.to_account_info(),
+                authority: pool.to_account_info(),
+            },
+            signer_seeds,
+        );
+        anchor_token::transfer(cpi_ctx, amount)?;
+
+        emit!(InsurancePoolEvent {
+            pool: pool.key(),
+            movement: InsuranceMovement::ClaimPaid,
+            counterparty: ctx.accounts.destination.owner,
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Open or reconfigure the escrow's donation matching pool for
+    /// `recipient`. The terms are one `MatchTerms` struct encrypted by the
+    /// owner; sponsors see the pool balance but not the ratio or ceiling.
+    pub fn set_matching_pool(
+        ctx: Context<SetMatchingPool>,
+        recipient: Pubkey,
+        terms_encryption_pubkey: [u8; 32],
+        terms_nonce: u128,
+        encrypted_terms: [[u8; 32]; 2],
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.matching_pool;
+        if pool.escrow == Pubkey::default() {
+            pool.escrow = ctx.accounts.escrow.key();
+            pool.created_at = Clock::get()?.unix_timestamp;
+            pool.bump = ctx.bumps.matching_pool;
+        }
+        pool.recipient = recipient;
+        pool.terms_encryption_pubkey = terms_encryption_pubkey;
+        pool.terms_nonce = terms_nonce;
+        pool.encrypted_terms = encrypted_terms;
+        Ok(())
+    }
+
+    /// Sponsor deposit. The lamports stay on the pool account until they
+    /// are released as matches.
+    pub fn fund_matching_pool(ctx: Context<FundMatchingPool>, amount: u64) -> Result<()> {
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            anchor_lang::system_program::Transfer {
+                from: ctx.accounts.sponsor.to_account_info(),
+                to: ctx.accounts.matching_pool.to_account_info(),
+            },
+        );
+        anchor_lang::system_program::transfer(cpi_ctx, amount)?;
+
+        let pool = &mut ctx.accounts.matching_pool;
+        pool.total_deposited = pool
+            .total_deposited
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        emit!(MatchingPoolEvent {
+            pool: pool.key(),
+            movement: MatchingMovement::Deposit,
+            counterparty: ctx.accounts.sponsor.key(),
+            amount,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Permissionless: queue `compute_match` for a SOL payment to the pool's
+    /// recipient made after the pool opened. Each payment is matched once.
+    pub fn request_match(ctx: Context<RequestMatch>, computation_offset: u64) -> Result<()> {
+        let pool = &ctx.accounts.matching_pool;
+        let pool_info = pool.to_account_info();
+        let available = pool_info
+            .lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(pool_info.data_len()));
+        let pool_key = pool.key();
+        let terms_encryption_pubkey = pool.terms_encryption_pubkey;
+        let terms_nonce = pool.terms_nonce;
+        let encrypted_terms = pool.encrypted_terms;
+        let amount = ctx.accounts.payment.amount;
+
+        let payment_match = &mut ctx.accounts.payment_match;
+        payment_match.pool = pool_key;
+        payment_match.payment = ctx.accounts.payment.key();
+        payment_match.matched = 0;
+        payment_match.pending_computation_offset = Some(computation_offset);
+        payment_match.bump = ctx.bumps.payment_match;
+        let payment_match_key = payment_match.key();
+        let recipient_key = ctx.accounts.payment.recipient;
+        let computation_key = ctx.accounts.computation_account.key();
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        #[cfg(not(feature = "mock-mpc"))]
+        {
+            let args = vec![
+                Argument::ArcisPubkey(terms_encryption_pubkey),
+                Argument::PlaintextU128(terms_nonce),
+                Argument::EncryptedU64(encrypted_terms[0]),
+                Argument::EncryptedU64(encrypted_terms[1]),
+                Argument::PlaintextU64(amount),
+                Argument::PlaintextU64(available),
+            ];
+
+            queue_computation(
+                ctx.accounts,
+                computation_offset,
+                args,
+                None,
+                vec![ComputeMatchCallback::callback_ix(&[
+                    CallbackAccount {
+                        pubkey: pool_key,
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: payment_match_key,
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: recipient_key,
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: computation_key,
+                        is_writable: false,
+                    },
+                ])],
+                1,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "compute_match")]
+    pub fn compute_match_callback(
+        ctx: Context<ComputeMatchCallback>,
+        output: ComputationOutputs<ComputeMatchOutput>,
+    ) -> Result<()> {
+        let matched = match output {
+            ComputationOutputs::Success(ComputeMatchOutput { field_0 }) => field_0,
+            _ => return Err(EscrowError::AbortedComputation.into()),
+        };
+
+        verify_pending_computation(
+            ctx.accounts.payment_match.pending_computation_offset,
+            &ctx.accounts.computation_account.key(),
+        )?;
+
+        // Matches queued together were each sized against the same balance,
+        // so clamp again to what is left now.
+        let pool_info = ctx.accounts.matching_pool.to_account_info();
+        let available = pool_info
+            .lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(pool_info.data_len()));
+        let matched = matched.min(available);
+        **pool_info.try_borrow_mut_lamports()? -= matched;
+        **ctx.accounts.recipient.try_borrow_mut_lamports()? += matched;
+
+        let pool = &mut ctx.accounts.matching_pool;
+        pool.total_matched = pool
+            .total_matched
+            .checked_add(matched)
+            .ok_or(ProgramError::InvalidArgument)?;
+        let payment_match = &mut ctx.accounts.payment_match;
+        payment_match.matched = matched;
+        payment_match.pending_computation_offset = None;
+
+        emit!(MatchingPoolEvent {
+            pool: pool.key(),
+            movement: MatchingMovement::MatchReleased,
+            counterparty: ctx.accounts.recipient.key(),
+            amount: matched,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Accept fee vouchers minted by `voucher_mint_authority`. The default
+    /// pubkey stops accepting new vouchers; credits already redeemed stay
+    /// spendable.
+    pub fn set_fee_voucher_authority(
+        ctx: Context<ExerciseOwnerCapability>,
+        voucher_mint_authority: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.escrow.authorize_capability(
+            OwnerCapability::Fees,
+            &ctx.accounts.authority.key(),
+            Clock::get()?.unix_timestamp,
+        )?;
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.voucher_mint_authority = voucher_mint_authority;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        sequence_escrow_update(escrow)?;
+        Ok(())
+    }
+
+    /// Burn a fee voucher for a credit that waives the treasury fee on one
+    /// `send_payment` to this escrow. Each voucher mint redeems once, even
+    /// if its authority mints it again.
+    pub fn redeem_fee_voucher(ctx: Context<RedeemFeeVoucher>) -> Result<()> {
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            anchor_token::Burn {
+                mint: ctx.accounts.voucher_mint.to_account_info(),
+                from: ctx.accounts.voucher_token_account.to_account_info(),
+                authority: ctx.accounts.sender.to_account_info(),
+            },
+        );
+        anchor_token::burn(cpi_ctx, 1)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let redemption = &mut ctx.accounts.voucher_redemption;
+        redemption.voucher_mint = ctx.accounts.voucher_mint.key();
+        redemption.escrow = ctx.accounts.escrow.key();
+        redemption.redeemed_by = ctx.accounts.sender.key();
+        redemption.redeemed_at = now;
+        redemption.bump = ctx.bumps.voucher_redemption;
+
+        let waiver = &mut ctx.accounts.fee_waiver;
+        waiver.escrow = ctx.accounts.escrow.key();
+        waiver.sender = ctx.accounts.sender.key();
+        waiver.bump = ctx.bumps.fee_waiver;
+        waiver.credits = waiver
+            .credits
+            .checked_add(1)
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        emit!(FeeVoucherRedeemedEvent {
+            escrow: waiver.escrow,
+            voucher_mint: redemption.voucher_mint,
+            sender: waiver.sender,
+            credits: waiver.credits,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// Tag an escrow with a jurisdiction, or clear it with
+    /// `UNTAGGED_JURISDICTION`. Admin-only so owners can't opt out of a
+    /// jurisdiction pause; creates the tag's pause switch if needed.
+    pub fn set_escrow_jurisdiction(
+        ctx: Context<SetEscrowJurisdiction>,
+        jurisdiction: u16,
+    ) -> Result<()> {
+        let pause = &mut ctx.accounts.jurisdiction_pause;
+        pause.jurisdiction = jurisdiction;
+        pause.bump = ctx.bumps.jurisdiction_pause;
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.jurisdiction = jurisdiction;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        sequence_escrow_update(escrow)?;
+        Ok(())
+    }
+
+    /// Pause or unpause every escrow tagged with `jurisdiction` at once.
+    pub fn set_jurisdiction_paused(
+        ctx: Context<SetJurisdictionPaused>,
+        jurisdiction: u16,
+        paused: bool,
+    ) -> Result<()> {
+        require!(
+            jurisdiction != UNTAGGED_JURISDICTION,
+            EscrowError::InvalidJurisdiction
+        );
+        let pause = &mut ctx.accounts.jurisdiction_pause;
+        pause.jurisdiction = jurisdiction;
+        pause.paused = paused;
+        pause.updated_at = Clock::get()?.unix_timestamp;
+        pause.bump = ctx.bumps.jurisdiction_pause;
+
+        emit!(JurisdictionPauseEvent {
+            jurisdiction,
+            paused,
+            timestamp: pause.updated_at,
+        });
+
+        Ok(())
+    }
+
+    /// Register the program `send_payment` asks for this escrow's fee
+    /// split, or pass the default key to go back to the protocol split.
+    pub fn set_fee_hook(ctx: Context<ExerciseOwnerCapability>, fee_hook: Pubkey) -> Result<()> {
+        ctx.accounts.escrow.authorize_capability(
+            OwnerCapability::Fees,
+            &ctx.accounts.authority.key(),
+            Clock::get()?.unix_timestamp,
+        )?;
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.fee_hook = fee_hook;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        sequence_escrow_update(escrow)?;
+
+        emit!(FeeHookEvent {
+            escrow: escrow.key(),
+            fee_hook,
+            sequence: escrow.sequence,
+            timestamp: escrow.last_updated,
+        });
+
+        Ok(())
+    }
+
+    /// Require recipients of `send_payment` to counter-sign, either in the
+    /// settling transaction or with an ed25519 pre-authorization.
+    pub fn set_counter_signature_required(
+        ctx: Context<UpdateEscrowActive>,
+        required: bool,
+    ) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.require_counter_signature = required;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        sequence_escrow_update(escrow)?;
+        Ok(())
+    }
+
+    /// Restrict vault payments to `open_hour..close_hour` UTC on the days
+    /// set in `days` (bit 0 = Monday). Payments outside it are deferred
+    /// until a crank drains them once the window opens.
+    pub fn set_settlement_window(
+        ctx: Context<SetSettlementWindow>,
+        open_hour: u8,
+        close_hour: u8,
+        days: u8,
+    ) -> Result<()> {
+        require!(
+            open_hour < 24 && close_hour < 24 && open_hour != close_hour,
+            EscrowError::InvalidSettlementWindow
+        );
+        require!(days != 0 && days < 1 << 7, EscrowError::InvalidSettlementWindow);
+
+        let window = &mut ctx.accounts.settlement_window;
+        window.escrow = ctx.accounts.escrow.key();
+        window.open_hour = open_hour;
+        window.close_hour = close_hour;
+        window.days = days;
+        window.bump = ctx.bumps.settlement_window;
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.settlement_window_active = true;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        sequence_escrow_update(escrow)?;
+        Ok(())
+    }
+
+    /// Lift the settlement window. Deferred payments must be drained first.
+    pub fn clear_settlement_window(ctx: Context<ClearSettlementWindow>) -> Result<()> {
+        require!(
+            ctx.accounts.settlement_window.entries.is_empty(),
+            EscrowError::SettlementQueueNotEmpty
+        );
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.settlement_window_active = false;
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        sequence_escrow_update(escrow)?;
+        Ok(())
+    }
+
+    /// Settle a deferred vault payment now that the window is open.
+    /// Permissionless crank, rewarded from the compute budget vault.
+    pub fn drain_settlement_window(
+        ctx: Context<DrainSettlementWindow>,
+        payment_id: u64,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            ctx.accounts.settlement_window.is_open(now),
+            EscrowError::SettlementWindowClosed
+        );
+        let entry = ctx.accounts.settlement_window.take(payment_id)?;
+        require_keys_eq!(
+            ctx.accounts.escrow_vault.key(),
+            entry.escrow_vault,
+            EscrowError::QuarantineAccountMismatch
+        );
+
+        let escrow = &ctx.accounts.escrow;
+        let vault = ctx.accounts.escrow_vault.to_account_info();
+        let token_program = ctx.accounts.token_program.to_account_info();
+        for (destination, expected, amount) in [
+            (
+                &ctx.accounts.recipient_token_account,
+                entry.recipient_token_account,
+                entry.net_amount,
+            ),
+            (
+                &ctx.accounts.treasury_token_account,
+                entry.treasury_token_account,
+                entry.treasury_reward,
+            ),
+            (
+                &ctx.accounts.referral_token_account,
+                entry.referral_token_account,
+                entry.referal_reward,
+            ),
+        ] {
+            require_keys_eq!(
+                destination.key(),
+                expected,
+                EscrowError::QuarantineAccountMismatch
+            );
+            transfer_from_escrow_vault(
+                escrow,
+                &vault,
+                &destination.to_account_info(),
+                &token_program,
+                amount,
+            )?;
+        }
+
+        if let Some(vault) = ctx.accounts.compute_budget_vault.as_mut() {
+            pay_crank_reward(
+                vault,
+                &ctx.accounts.cranker.to_account_info(),
+                CrankKind::SettlementWindow,
+            )?;
+        }
+
+        let amount = entry.net_amount + entry.treasury_reward + entry.referal_reward;
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.total_fund_regulated = escrow
+            .total_fund_regulated
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        let sequence = sequence_escrow_update(escrow)?;
+        emit!(SettlementWindowEvent {
+            escrow: escrow.key(),
+            sequence,
+            payment_id,
+            amount,
+            action: SettlementWindowAction::Settled,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// Schedule when an owner capability sunsets and who inherits it. A
+    /// schedule is a promise to merchants: once set it can only be brought
+    /// forward, never lifted, delayed or handed to someone else.
+    pub fn schedule_capability_sunset(
+        ctx: Context<UpdateEscrowActive>,
+        capability: OwnerCapability,
+        sunset_at: i64,
+        successor: Pubkey,
+    ) -> Result<()> {
+        require!(sunset_at > 0, EscrowError::CapabilitySunsetLocked);
+        let escrow = &mut ctx.accounts.escrow;
+        let sunset = &mut escrow.capability_sunsets[capability as usize];
+        if sunset.sunset_at != 0 {
+            require!(
+                sunset_at <= sunset.sunset_at && successor == sunset.successor,
+                EscrowError::CapabilitySunsetLocked
+            );
+        }
+        sunset.sunset_at = sunset_at;
+        sunset.successor = successor;
+
+        escrow.last_updated = Clock::get()?.unix_timestamp;
+        let sequence = sequence_escrow_update(escrow)?;
+        emit!(CapabilitySunsetEvent {
+            escrow: escrow.key(),
+            sequence,
+            capability,
+            sunset_at,
+            successor,
+            timestamp: escrow.last_updated,
+        });
+
+        Ok(())
+    }
+
+    /// Prove that `payment` paid an amount in `[bucket_min, bucket_max)`.
+    /// The sender or recipient submits the amount encrypted; the MPC checks
+    /// it against the payment and the callback marks the proof verified or
+    /// rejected. Gating programs read the proof, never the amount.
+    pub fn prove_payment(
+        ctx: Context<ProvePayment>,
+        computation_offset: u64,
+        bucket_min: u64,
+        bucket_max: u64,
+        encryption_pubkey: [u8; 32],
+        nonce: u128,
+        encrypted_amount: [u8; 32],
+    ) -> Result<()> {
+        require!(bucket_min < bucket_max, EscrowError::InvalidProofBucket);
+        let prover = ctx.accounts.prover.key();
+        let payment = &ctx.accounts.payment;
+        require!(
+            prover == payment.sender || prover == payment.recipient,
+            EscrowError::InvalidAuthority
+        );
+        let payment_amount = payment.amount;
+        let proof_key = ctx.accounts.payment_proof.key();
+        let computation_key = ctx.accounts.computation_account.key();
+
+        let proof = &mut ctx.accounts.payment_proof;
+        proof.payment = payment.key();
+        proof.sender = payment.sender;
+        proof.recipient = payment.recipient;
+        proof.asset_mint = payment.asset_mint;
+        proof.bucket_min = bucket_min;
+        proof.bucket_max = bucket_max;
+        proof.paid_at = payment.timestamp;
+        proof.status = PaymentProofStatus::Pending;
+        proof.pending_computation_offset = Some(computation_offset);
+        proof.proven_at = 0;
+        proof.bump = ctx.bumps.payment_proof;
+
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        #[cfg(not(feature = "mock-mpc"))]
+        {
+            let args = vec![
+                Argument::ArcisPubkey(encryption_pubkey),
+                Argument::PlaintextU128(nonce),
+                Argument::EncryptedU64(encrypted_amount),
+                Argument::PlaintextU64(payment_amount),
+                Argument::PlaintextU64(bucket_min),
+                Argument::PlaintextU64(bucket_max),
+            ];
+
+            queue_computation(
+                ctx.accounts,
+                computation_offset,
+                args,
+                None,
+                vec![ProvePaymentAmountCallback::callback_ix(&[
+                    CallbackAccount {
+                        pubkey: proof_key,
+                        is_writable: true,
+                    },
+                    CallbackAccount {
+                        pubkey: computation_key,
+                        is_writable: false,
+                    },
+                ])],
+                1,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "prove_payment_amount")]
+    pub fn prove_payment_amount_callback(
+        ctx: Context<ProvePaymentAmountCallback>,
+        output: ComputationOutputs<ProvePaymentAmountOutput>,
+    ) -> Result<()> {
+        let in_bucket = match output {
+            ComputationOutputs::Success(ProvePaymentAmountOutput { field_0 }) => field_0,
+            _ => return Err(EscrowError::AbortedComputation.into()),
+        };
+
+        verify_pending_computation(
+            ctx.accounts.payment_proof.pending_computation_offset,
+            &ctx.accounts.computation_account.key(),
+        )?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let proof = &mut ctx.accounts.payment_proof;
+        proof.pending_computation_offset = None;
+        proof.proven_at = now;
+        proof.status = if in_bucket {
+            PaymentProofStatus::Verified
+        } else {
+            PaymentProofStatus::Rejected
+        };
+
+        emit!(PaymentProofEvent {
+            payment_proof: proof.key(),
+            payment: proof.payment,
+            bucket_min: proof.bucket_min,
+            bucket_max: proof.bucket_max,
+            status: proof.status,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// Have the MPC split an encrypted amount into the default fee legs and
+    /// re-encrypt the breakdown to the caller. Nothing is stored; the
+    /// result arrives as a `FeeQuoteEvent`.
+    pub fn quote_fees(
+        ctx: Context<QuoteFees>,
+        computation_offset: u64,
+        encryption_pubkey: [u8; 32],
+        nonce: u128,
+        encrypted_amount: [u8; 32],
+    ) -> Result<()> {
+        ctx.accounts.sign_pda_account.bump = ctx.bumps.sign_pda_account;
+
+        #[cfg(not(feature = "mock-mpc"))]
+        {
+            let args = vec![
+                Argument::ArcisPubkey(encryption_pubkey),
+                Argument::PlaintextU128(nonce),
+                Argument::EncryptedU64(encrypted_amount),
+            ];
+
+            queue_computation(
+                ctx.accounts,
+                computation_offset,
+                args,
+                None,
+                vec![CalculateFeesCallback::callback_ix(&[])],
+                1,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    #[arcium_callback(encrypted_ix = "calculate_fees")]
+    pub fn calculate_fees_callback(
+        ctx: Context<CalculateFeesCallback>,
+        output: ComputationOutputs<CalculateFeesOutput>,
+    ) -> Result<()> {
+        let o = match output {
+            ComputationOutputs::Success(CalculateFeesOutput { field_0 }) => field_0,
+            _ => return Err(EscrowError::AbortedComputation.into()),
+        };
+
+        emit!(FeeQuoteEvent {
+            encryption_key: o.encryption_key,
+            nonce: o.nonce,
+            ciphertexts: o.ciphertexts,
+        });
+
+        Ok(())
+    }
+
+    /// Let the escrow's merchant pull up to `cap` from `payer_token_account`
+    /// over time, e.g. for utility-style billing. Approves a delegate PDA
+    /// derived from the mandate as the account's SPL delegate for `cap`, so
+    /// only `pull_payment` on this mandate can spend the allowance; an SPL
+    /// account has a single delegate, so it backs at most one mandate.
+    /// `crank` may pull on the merchant's behalf; `Pubkey::default()` means
+    /// only the merchant. A non-zero `expires_at` ends the mandate at that
+    /// unix time, and a non-zero `period` in seconds limits pulls to
+    /// `period_cap` per period.
+    pub fn authorize_pull_payment(
+        ctx: Context<AuthorizePullPayment>,
+        cap: u64,
+        crank: Pubkey,
+        expires_at: i64,
+        period: i64,
+        period_cap: u64,
+    ) -> Result<()> {
+        require!(cap > 0, EscrowError::InvalidPullCap);
+        require!(
+            (period == 0 && period_cap == 0) || (period > 0 && period_cap > 0 && period_cap <= cap),
+            EscrowError::InvalidPullPeriod
+        );
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            expires_at == 0 || expires_at > now,
+            EscrowError::PullMandateExpired
+        );
+        let mint = ctx.accounts.payer_token_account.mint;
+        require!(
+            ctx.accounts.mint_allowlist.mints.contains(&mint),
+            EscrowError::MintNotAllowed
+        );
+
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            anchor_token::Approve {
+                to: ctx.accounts.payer_token_account.to_account_info(),
+                delegate: ctx.accounts.pull_delegate.to_account_info(),
+                authority: ctx.accounts.payer.to_account_info(),
+            },
+        );
+        anchor_token::approve(cpi_ctx, cap)?;
+
+        let mandate = &mut ctx.accounts.mandate;
+        mandate.escrow = ctx.accounts.escrow.key();
+        mandate.payer = ctx.accounts.payer.key();
+        mandate.payer_token_account = ctx.accounts.payer_token_account.key();
+        mandate.merchant = ctx.accounts.escrow.authority();
+        mandate.crank = crank;
+        mandate.mint = mint;
+        mandate.cap = cap;
+        mandate.pulled = 0;
+        mandate.expires_at = expires_at;
+        mandate.period = period;
+        mandate.period_cap = period_cap;
+        mandate.period_start = now;
+        mandate.period_pulled = 0;
+        mandate.created_at = now;
+        mandate.bump = ctx.bumps.mandate;
+        mandate.delegate_bump = ctx.bumps.pull_delegate;
+
+        emit!(PullMandateEvent {
+            mandate: mandate.key(),
+            payer: mandate.payer,
+            merchant: mandate.merchant,
+            cap,
+            revoked: false,
+            timestamp: mandate.created_at,
+        });
+
+        Ok(())
+    }
+
+    /// Charge `amount` against a pull mandate. Signed by the merchant or the
+    /// mandate's crank; the transfer uses the mandate's delegate PDA. Fees go
+    /// to the treasury as on other SPL payments.
+    pub fn pull_payment(ctx: Context<PullPayment>, amount: u64) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        let mandate = &ctx.accounts.mandate;
+        require!(
+            authority == mandate.merchant
+                || (mandate.crank != Pubkey::default() && authority == mandate.crank),
+            EscrowError::InvalidAuthority
+        );
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            mandate.expires_at == 0 || now < mandate.expires_at,
+            EscrowError::PullMandateExpired
+        );
+        let pulled = mandate
+            .pulled
+            .checked_add(amount)
+            .filter(|pulled| *pulled <= mandate.cap)
+            .ok_or(EscrowError::PullCapExceeded)?;
+        let (period_start, period_pulled) = mandate.period_usage(now, amount)?;
+        let mandate_key = mandate.key();
+        let delegate_bump = mandate.delegate_bump;
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.ensure_active(ctx.accounts.jurisdiction_pause.as_deref())?;
+
+        let (referral_fee, treasury_fee) = tiered_fee_split(
+            amount,
+            ctx.accounts.fee_config.as_deref(),
+            escrow.total_fund_regulated,
+        )?;
+        let fee = referral_fee + treasury_fee;
+        let net_amount = amount - fee;
+        assert_fee_split(amount, &[net_amount, fee])?;
+
+        let signer: &[&[&[u8]]] =
+            &[&[seeds::PULL_DELEGATE, mandate_key.as_ref(), &[delegate_bump]]];
+        let token_program = ctx.accounts.token_program.to_account_info();
+        let delegate = ctx.accounts.pull_delegate.to_account_info();
+        for (destination, share) in [
+            (&ctx.accounts.merchant_token_account, net_amount),
+            (&ctx.accounts.treasury_token_account, fee),
+        ] {
+            let cpi_ctx = CpiContext::new_with_signer(
+                token_program.clone(),
+                anchor_token::Transfer {
+                    from: ctx.accounts.payer_token_account.to_account_info(),
+                    to: destination.to_account_info(),
+                    authority: delegate.clone(),
+                },
+                signer,
+            );
+            anchor_token::transfer(cpi_ctx, share)?;
+        }
+
+        escrow.total_fund_regulated = escrow
+            .total_fund_regulated
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+
+        let mandate = &mut ctx.accounts.mandate;
+        mandate.pulled = pulled;
+        mandate.period_start = period_start;
+        mandate.period_pulled = period_pulled;
+
+        let sequence = sequence_escrow_update(escrow)?;
+        emit!(PullPaymentEvent {
+            escrow: escrow.key(),
+            sequence,
+            mandate: mandate.key(),
+            amount,
+            pulled,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// Cancel a pull mandate and revoke its delegate PDA's allowance. Only
+    /// the payer can; the rent goes back to them.
+    pub fn revoke_pull_mandate(ctx: Context<RevokePullMandate>) -> Result<()> {
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            anchor_token::Revoke {
+                source: ctx.accounts.payer_token_account.to_account_info(),
+                authority: ctx.accounts.payer.to_account_info(),
+            },
+        );
+        anchor_token::revoke(cpi_ctx)?;
+
+        let mandate = &ctx.accounts.mandate;
+        emit!(PullMandateEvent {
+            mandate: mandate.key(),
+            payer: mandate.payer,
+            merchant: mandate.merchant,
+            cap: mandate.cap,
+            revoked: true,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+#[queue_computation_accounts("init_escrow_stats", owner)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct InitializeEscrow<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = owner,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_INIT_ESCROW_STATS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.maintenance_mode @ EscrowError::MaintenanceMode,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = cluster_account.key() == protocol_config
+            .active_cluster()
+            .unwrap_or(derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet))
+            @ EscrowError::InvalidCluster
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + EscrowAccount::INIT_SPACE,
+        seeds = [seeds::ESCROW, owner.key().as_ref()],
+        bump
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+}
+
+#[callback_accounts("init_escrow_stats")]
+#[derive(Accounts)]
+pub struct InitEscrowStatsCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_INIT_ESCROW_STATS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// CHECK: checked against escrow.pending_computation_offset in the handler
+    pub computation_account: UncheckedAccount<'info>,
+}
+
+#[init_computation_definition_accounts("init_escrow_stats", payer)]
+#[derive(Accounts)]
+pub struct InitEscrowStatsCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("init_referral_stats", payer)]
+#[derive(Accounts)]
+pub struct InitReferralStatsCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("process_payment", payer)]
+#[derive(Accounts)]
+pub struct InitProcessPaymentCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("update_referral_stats", payer)]
+#[derive(Accounts)]
+pub struct InitUpdateReferralStatsCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("check_volume_threshold", payer)]
+#[derive(Accounts)]
+pub struct InitCheckVolumeThresholdCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("reveal_payment_count", payer)]
+#[derive(Accounts)]
+pub struct InitRevealPaymentCountCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("verify_otc_terms", payer)]
+#[derive(Accounts)]
+pub struct InitVerifyOtcTermsCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("run_payroll", payer)]
+#[derive(Accounts)]
+pub struct InitRunPayrollCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitCircuitRegistry<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        has_one = admin @ EscrowError::InvalidAuthority,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + CircuitRegistry::INIT_SPACE,
+        seeds = [seeds::CIRCUIT_REGISTRY],
+        bump
+    )]
+    pub circuit_registry: Account<'info, CircuitRegistry>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitMintAllowlist<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        has_one = admin @ EscrowError::InvalidAuthority,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + MintAllowlist::INIT_SPACE,
+        seeds = [seeds::MINT_ALLOWLIST],
+        bump
+    )]
+    pub mint_allowlist: Account<'info, MintAllowlist>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetMintAllowed<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        has_one = admin @ EscrowError::InvalidAuthority,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        seeds = [seeds::MINT_ALLOWLIST],
+        bump = mint_allowlist.bump,
+    )]
+    pub mint_allowlist: Account<'info, MintAllowlist>,
+}
+
+#[derive(Accounts)]
+pub struct InitRelayerRegistry<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        has_one = admin @ EscrowError::InvalidAuthority,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + RelayerRegistry::INIT_SPACE,
+        seeds = [seeds::RELAYER_REGISTRY],
+        bump
+    )]
+    pub relayer_registry: Account<'info, RelayerRegistry>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateRelayerRegistry<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        has_one = admin @ EscrowError::InvalidAuthority,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        seeds = [seeds::RELAYER_REGISTRY],
+        bump = relayer_registry.bump,
+    )]
+    pub relayer_registry: Account<'info, RelayerRegistry>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterCircuit<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        has_one = admin @ EscrowError::InvalidAuthority,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        seeds = [seeds::CIRCUIT_REGISTRY],
+        bump = circuit_registry.bump,
+    )]
+    pub circuit_registry: Account<'info, CircuitRegistry>,
+}
+
+#[init_computation_definition_accounts("referral_report", payer)]
+#[derive(Accounts)]
+pub struct InitReferralReportCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("check_recent_volume_threshold", payer)]
+#[derive(Accounts)]
+pub struct InitCheckRecentVolumeThresholdCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("reveal_asset_volume", payer)]
+#[derive(Accounts)]
+pub struct InitRevealAssetVolumeCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("resolve_sealed_bids", payer)]
+#[derive(Accounts)]
+pub struct InitResolveSealedBidsCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("share_escrow_stats", payer)]
+#[derive(Accounts)]
+pub struct InitShareEscrowStatsCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("init_spend_cap", payer)]
+#[derive(Accounts)]
+pub struct InitSpendCapCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("check_spend_cap", payer)]
+#[derive(Accounts)]
+pub struct InitCheckSpendCapCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("compute_match", payer)]
+#[derive(Accounts)]
+pub struct InitComputeMatchCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("process_refund", payer)]
+#[derive(Accounts)]
+pub struct InitProcessRefundCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("check_refund_rate", payer)]
+#[derive(Accounts)]
+pub struct InitCheckRefundRateCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("prove_payment_amount", payer)]
+#[derive(Accounts)]
+pub struct InitProvePaymentAmountCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[init_computation_definition_accounts("calculate_fees", payer)]
+#[derive(Accounts)]
+pub struct InitCalculateFeesCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProtocolConfig<'info> {
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        has_one = admin @ EscrowError::InvalidAuthority,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeProtocolConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + ProtocolConfig::INIT_SPACE,
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("process_payment", sender)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct SendPaymentSolEncrypted<'info> {
+    #[account(mut)]
+    pub sender: Signer<'info>,
+    #[account(mut)]
+    pub recipient: SystemAccount<'info>,
+    /// Not needed while the escrow has referrals switched off
+    #[account(mut)]
+    pub referral: Option<SystemAccount<'info>>,
+    #[account(mut, address = escrow.treasury)]
+    pub treasury: SystemAccount<'info>,
+    /// CHECK: the sender's `SpendCapAccount`, enforced only if it exists
+    #[account(mut, seeds = [seeds::SPEND_CAP, sender.key().as_ref()], bump)]
+    pub spend_cap: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = sender,
+        space = 8 + PaymentAccount::INIT_SPACE,
+        seeds = [seeds::PAYMENTS, sender.key().as_ref(), &computation_offset.to_le_bytes()],
+        bump
+    )]
+    pub payment: Account<'info, PaymentAccount>,
+
+    pub owner: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Required for escrows tagged with a jurisdiction
+    #[account(
+        seeds = [seeds::JURISDICTION_PAUSE, &escrow.jurisdiction.to_le_bytes()],
+        bump = jurisdiction_pause.bump,
+    )]
+    pub jurisdiction_pause: Option<Account<'info, JurisdictionPause>>,
+    /// Fee payer of a sponsored submission; must be in the relayer registry
+    pub relayer: Option<Signer<'info>>,
+    #[account(seeds = [seeds::RELAYER_REGISTRY], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+
+    /// Reimburses the sender's Arcium fee when the owner has funded one.
+    #[account(
+        mut,
+        seeds = [seeds::COMPUTE_BUDGET, escrow.key().as_ref()],
+        bump = compute_budget_vault.bump,
+    )]
+    pub compute_budget_vault: Option<Account<'info, ComputeBudgetVault>>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = sender,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_PAYMENT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.maintenance_mode @ EscrowError::MaintenanceMode,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = cluster_account.key() == protocol_config
+            .active_cluster()
+            .unwrap_or(derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet))
+            @ EscrowError::InvalidCluster
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("process_payment")]
+#[derive(Accounts)]
+pub struct ProcessPaymentCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_PAYMENT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// CHECK: checked against escrow.pending_computation_offset in the handler
+    pub computation_account: UncheckedAccount<'info>,
+
+    /// The payment the computation was queued for
+    #[account(
+        seeds = [
+            seeds::PAYMENTS,
+            payment.sender.as_ref(),
+            &escrow.pending_computation_offset.unwrap_or_default().to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub payment: Account<'info, PaymentAccount>,
+}
+
+#[queue_computation_accounts("process_refund", recipient)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct RefundPayment<'info> {
+    #[account(mut)]
+    pub recipient: Signer<'info>,
+    #[account(mut)]
+    pub sender: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        has_one = sender @ EscrowError::InvalidAuthority,
+        has_one = recipient @ EscrowError::InvalidAuthority,
+    )]
+    pub payment: Account<'info, PaymentAccount>,
+
+    /// The recipient's own escrow, whose stats record the refund
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == recipient.key() @ EscrowError::InvalidAuthority,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = recipient,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_REFUND)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.maintenance_mode @ EscrowError::MaintenanceMode,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = cluster_account.key() == protocol_config
+            .active_cluster()
+            .unwrap_or(derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet))
+            @ EscrowError::InvalidCluster
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("process_refund")]
+#[derive(Accounts)]
+pub struct ProcessRefundCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_REFUND)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// CHECK: checked against escrow.pending_computation_offset in the handler
+    pub computation_account: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitPendingDeltas<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + PendingDeltas::INIT_SPACE,
+        seeds = [seeds::PENDING_DELTAS, escrow.key().as_ref()],
+        bump
+    )]
+    pub pending_deltas: Account<'info, PendingDeltas>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(referal: Pubkey, amount: u64, recipient: Pubkey, payment_encryption_pubkey: [u8; 32], payment_nonce: u128)]
+pub struct SendPaymentBatched<'info> {
+    #[account(mut)]
+    pub sender: Signer<'info>,
+    #[account(mut)]
+    pub recipient: SystemAccount<'info>,
+    /// Not needed while the escrow has referrals switched off
+    #[account(mut)]
+    pub referral: Option<SystemAccount<'info>>,
+    #[account(mut, address = escrow.treasury)]
+    pub treasury: SystemAccount<'info>,
+    #[account(
+        init,
+        payer = sender,
+        space = 8 + PaymentAccount::INIT_SPACE,
+        seeds = [seeds::PAYMENTS, sender.key().as_ref(), &payment_nonce.to_le_bytes()],
+        bump
+    )]
+    pub payment: Account<'info, PaymentAccount>,
+    pub owner: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+    /// Required for escrows tagged with a jurisdiction
+    #[account(
+        seeds = [seeds::JURISDICTION_PAUSE, &escrow.jurisdiction.to_le_bytes()],
+        bump = jurisdiction_pause.bump,
+    )]
+    pub jurisdiction_pause: Option<Account<'info, JurisdictionPause>>,
+    /// Fee payer of a sponsored submission; must be in the relayer registry
+    pub relayer: Option<Signer<'info>>,
+    #[account(seeds = [seeds::RELAYER_REGISTRY], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+    #[account(
+        mut,
+        seeds = [seeds::PENDING_DELTAS, escrow.key().as_ref()],
+        bump = pending_deltas.bump,
+    )]
+    pub pending_deltas: Account<'info, PendingDeltas>,
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("merge_deltas", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct MergeDeltas<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut)]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        seeds = [seeds::PENDING_DELTAS, escrow.key().as_ref()],
+        bump = pending_deltas.bump,
+    )]
+    pub pending_deltas: Account<'info, PendingDeltas>,
+
+    /// Reimburses the cranker's Arcium fee when the owner has funded one.
+    #[account(
+        mut,
+        seeds = [seeds::COMPUTE_BUDGET, escrow.key().as_ref()],
+        bump = compute_budget_vault.bump,
+    )]
+    pub compute_budget_vault: Option<Account<'info, ComputeBudgetVault>>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_MERGE_DELTAS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.maintenance_mode @ EscrowError::MaintenanceMode,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = cluster_account.key() == protocol_config
+            .active_cluster()
+            .unwrap_or(derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet))
+            @ EscrowError::InvalidCluster
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("merge_deltas")]
+#[derive(Accounts)]
+pub struct MergeDeltasCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_MERGE_DELTAS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// CHECK: checked against escrow.pending_computation_offset in the handler
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::PENDING_DELTAS, escrow.key().as_ref()],
+        bump = pending_deltas.bump,
+    )]
+    pub pending_deltas: Account<'info, PendingDeltas>,
+}
+
+#[init_computation_definition_accounts("merge_deltas", payer)]
+#[derive(Accounts)]
+pub struct InitMergeDeltasCompDef<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Box<Account<'info, MXEAccount>>,
+
+    #[account(mut)]
+    /// CHECK: comp_def_account
+    pub comp_def_account: UncheckedAccount<'info>,
+
+    pub arcium_program: Program<'info, Arcium>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitComputeBudgetVault<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + ComputeBudgetVault::INIT_SPACE,
+        seeds = [seeds::COMPUTE_BUDGET, escrow.key().as_ref()],
+        bump
+    )]
+    pub compute_budget_vault: Account<'info, ComputeBudgetVault>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateComputeBudgetVault<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        seeds = [seeds::COMPUTE_BUDGET, escrow.key().as_ref()],
+        bump = compute_budget_vault.bump,
+    )]
+    pub compute_budget_vault: Account<'info, ComputeBudgetVault>,
+}
+
+#[derive(Accounts)]
+pub struct FundComputeBudget<'info> {
+    #[account(mut)]
+    pub funder: Signer<'info>,
+
+    #[account(mut)]
+    pub compute_budget_vault: Account<'info, ComputeBudgetVault>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("check_volume_threshold", authority)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct CheckVolumeThreshold<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Required when `authority` is not the escrow owner
+    #[account(
+        mut,
+        seeds = [seeds::ANALYTICS_OPERATOR, escrow.key().as_ref(), authority.key().as_ref()],
+        bump = analytics_operator.bump,
+    )]
+    pub analytics_operator: Option<Account<'info, AnalyticsOperator>>,
+
+    /// Read-only delegate; an alternative to `analytics_operator`
+    #[account(
+        seeds = [seeds::VIEWER, escrow.key().as_ref(), authority.key().as_ref()],
+        bump = viewer.bump,
+    )]
+    pub viewer: Option<Account<'info, Viewer>>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ACTIVITY_LOG, escrow.key().as_ref()],
+        bump = activity_log.bump,
+    )]
+    pub activity_log: Option<Account<'info, ActivityLog>>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = authority,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_THRESHOLD)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.maintenance_mode @ EscrowError::MaintenanceMode,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = cluster_account.key() == protocol_config
+            .active_cluster()
+            .unwrap_or(derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet))
+            @ EscrowError::InvalidCluster
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("check_volume_threshold")]
+#[derive(Accounts)]
+pub struct CheckVolumeThresholdCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_THRESHOLD)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// CHECK: compared with escrow.circuit_breaker_check_offset in the handler
+    pub computation_account: UncheckedAccount<'info>,
+}
+
+#[queue_computation_accounts("check_recent_volume_threshold", authority)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct CheckRecentVolumeThreshold<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Required when `authority` is not the escrow owner
+    #[account(
+        mut,
+        seeds = [seeds::ANALYTICS_OPERATOR, escrow.key().as_ref(), authority.key().as_ref()],
+        bump = analytics_operator.bump,
+    )]
+    pub analytics_operator: Option<Account<'info, AnalyticsOperator>>,
+
+    /// Read-only delegate; an alternative to `analytics_operator`
+    #[account(
+        seeds = [seeds::VIEWER, escrow.key().as_ref(), authority.key().as_ref()],
+        bump = viewer.bump,
+    )]
+    pub viewer: Option<Account<'info, Viewer>>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ACTIVITY_LOG, escrow.key().as_ref()],
+        bump = activity_log.bump,
+    )]
+    pub activity_log: Option<Account<'info, ActivityLog>>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = authority,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_RECENT_THRESHOLD)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.maintenance_mode @ EscrowError::MaintenanceMode,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = cluster_account.key() == protocol_config
+            .active_cluster()
+            .unwrap_or(derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet))
+            @ EscrowError::InvalidCluster
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("check_recent_volume_threshold")]
+#[derive(Accounts)]
+pub struct CheckRecentVolumeThresholdCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_RECENT_THRESHOLD)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+}
+
+#[queue_computation_accounts("check_refund_rate", authority)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct CheckRefundRate<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Required when `authority` is not the escrow owner
+    #[account(
+        mut,
+        seeds = [seeds::ANALYTICS_OPERATOR, escrow.key().as_ref(), authority.key().as_ref()],
+        bump = analytics_operator.bump,
+    )]
+    pub analytics_operator: Option<Account<'info, AnalyticsOperator>>,
+
+    /// Read-only delegate; an alternative to `analytics_operator`
+    #[account(
+        seeds = [seeds::VIEWER, escrow.key().as_ref(), authority.key().as_ref()],
+        bump = viewer.bump,
+    )]
+    pub viewer: Option<Account<'info, Viewer>>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ACTIVITY_LOG, escrow.key().as_ref()],
+        bump = activity_log.bump,
+    )]
+    pub activity_log: Option<Account<'info, ActivityLog>>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = authority,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_REFUND_RATE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.maintenance_mode @ EscrowError::MaintenanceMode,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = cluster_account.key() == protocol_config
+            .active_cluster()
+            .unwrap_or(derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet))
+            @ EscrowError::InvalidCluster
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("check_refund_rate")]
+#[derive(Accounts)]
+pub struct CheckRefundRateCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_REFUND_RATE)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+}
+
+#[queue_computation_accounts("reveal_payment_count", authority)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct RevealPaymentCount<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Required when `authority` is not the escrow owner
+    #[account(
+        mut,
+        seeds = [seeds::ANALYTICS_OPERATOR, escrow.key().as_ref(), authority.key().as_ref()],
+        bump = analytics_operator.bump,
+    )]
+    pub analytics_operator: Option<Account<'info, AnalyticsOperator>>,
+
+    /// Read-only delegate; an alternative to `analytics_operator`
+    #[account(
+        seeds = [seeds::VIEWER, escrow.key().as_ref(), authority.key().as_ref()],
+        bump = viewer.bump,
+    )]
+    pub viewer: Option<Account<'info, Viewer>>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ACTIVITY_LOG, escrow.key().as_ref()],
+        bump = activity_log.bump,
+    )]
+    pub activity_log: Option<Account<'info, ActivityLog>>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = authority,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REVEAL_COUNT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.maintenance_mode @ EscrowError::MaintenanceMode,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = cluster_account.key() == protocol_config
+            .active_cluster()
+            .unwrap_or(derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet))
+            @ EscrowError::InvalidCluster
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("reveal_payment_count")]
+#[derive(Accounts)]
+pub struct RevealPaymentCountCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REVEAL_COUNT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+}
+
+#[queue_computation_accounts("reveal_asset_volume", authority)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct RevealAssetVolume<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Required when `authority` is not the escrow owner
+    #[account(
+        mut,
+        seeds = [seeds::ANALYTICS_OPERATOR, escrow.key().as_ref(), authority.key().as_ref()],
+        bump = analytics_operator.bump,
+    )]
+    pub analytics_operator: Option<Account<'info, AnalyticsOperator>>,
+
+    /// Read-only delegate; an alternative to `analytics_operator`
+    #[account(
+        seeds = [seeds::VIEWER, escrow.key().as_ref(), authority.key().as_ref()],
+        bump = viewer.bump,
+    )]
+    pub viewer: Option<Account<'info, Viewer>>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = authority,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REVEAL_ASSET_VOLUME)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.maintenance_mode @ EscrowError::MaintenanceMode,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = cluster_account.key() == protocol_config
+            .active_cluster()
+            .unwrap_or(derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet))
+            @ EscrowError::InvalidCluster
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("reveal_asset_volume")]
+#[derive(Accounts)]
+pub struct RevealAssetVolumeCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REVEAL_ASSET_VOLUME)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+}
+
+#[cfg(feature = "mock-mpc")]
+#[derive(Accounts)]
+pub struct MockCallback<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// The computation the mocked output answers; must match
+    /// `escrow.pending_computation_offset` as in the real callbacks
+    /// CHECK: checked against escrow.pending_computation_offset in the handler
+    pub computation_account: UncheckedAccount<'info>,
+
+    /// Required for `ProcessPayment`, as in `ProcessPaymentCallback`
+    #[account(
+        seeds = [
+            seeds::PAYMENTS,
+            payment.sender.as_ref(),
+            &escrow.pending_computation_offset.unwrap_or_default().to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub payment: Option<Account<'info, PaymentAccount>>,
+}
+
+/// Which callback `mock_callback` is standing in for.
+#[cfg(feature = "mock-mpc")]
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MockCallbackKind {
+    InitEscrowStats,
+    /// `is_valid` stands in for the flag `process_payment` reveals
+    ProcessPayment {
+        is_valid: bool,
+    },
+}
+
+/// Per-dimension tracking flags `process_payment` and `merge_deltas` take
+/// after their other arguments.
+fn stats_dimension_args(stats_dimensions: u8) -> [Argument; 4] {
+    [
+        STATS_DIMENSION_PAYMENTS,
+        STATS_DIMENSION_VOLUME,
+        STATS_DIMENSION_FEES,
+        STATS_DIMENSION_ASSETS,
+    ]
+    .map(|dimension| Argument::PlaintextBool(stats_dimensions & dimension != 0))
+}
+
+/// Write an MPC result back to the escrow and release its stats lock.
+/// Shared by the Arcium callbacks and the `mock-mpc` test path so both apply
+/// outputs identically.
+fn store_encrypted_stats(
+    escrow: &mut EscrowAccount,
+    ciphertexts: [[u8; 32]; ESCROW_STATS_FIELDS],
+    nonce: u128,
+) {
+    escrow.encrypted_stats = ciphertexts;
+    escrow.nonce = nonce;
+    escrow.pending_computation_offset = None;
+}
+
+/// Apply a `process_payment` result once its computation is verified: store
+/// the stats, then notify the escrow's webhook receivers if it has any.
+fn record_processed_payment(
+    escrow: &mut Account<EscrowAccount>,
+    payment: Pubkey,
+    ciphertexts: [[u8; 32]; ESCROW_STATS_FIELDS],
+    nonce: u128,
+    is_valid: bool,
+) -> Result<()> {
+    store_encrypted_stats(escrow, ciphertexts, nonce);
+
+    let clock = Clock::get()?;
+    emit!(ConfidentialPaymentEvent {
+        timestamp: clock.unix_timestamp,
+        sender: escrow.owner, // Don't reveal actual sender
+    });
+
+    let sequence = sequence_escrow_update(escrow)?;
+    if escrow.webhook_key != Pubkey::default() {
+        emit!(PaymentNotificationEvent {
+            escrow: escrow.key(),
+            sequence,
+            payment,
+            commitment: stats_commitment(&escrow.key(), &ciphertexts, nonce),
+            status: if is_valid {
+                PaymentNotificationStatus::Recorded
+            } else {
+                PaymentNotificationStatus::Rejected
+            },
+            timestamp: clock.unix_timestamp,
+        });
+    }
+    Ok(())
+}
+
+/// Index of `mint`'s volume counter in the encrypted stats. SOL payments
+/// record `Pubkey::default()` as their asset mint.
+fn stats_asset_index(mint: &Pubkey) -> Result<u8> {
+    if *mint == Pubkey::default() {
+        Ok(STATS_ASSET_SOL)
+    } else if *mint == USDC_MINT {
+        Ok(STATS_ASSET_USDC)
+    } else if *mint == ZENZEC_MINT {
+        Ok(STATS_ASSET_ZENZEC)
+    } else {
+        err!(EscrowError::UnsupportedStatsAsset)
+    }
+}
+
+/// Whole half-lives elapsed since the recent volume was last decayed, capped
+/// at `RECENT_VOLUME_MAX_PERIODS`.
+fn recent_volume_periods(escrow: &EscrowAccount, now: i64) -> u8 {
+    let elapsed = now.saturating_sub(escrow.recent_volume_decayed_at).max(0);
+    (elapsed / RECENT_VOLUME_HALF_LIFE_SECS).min(RECENT_VOLUME_MAX_PERIODS as i64) as u8
+}
+
+/// Periods to decay by in the computation being queued. The clock only
+/// moves by whole half-lives so the remainder carries into the next update.
+fn advance_recent_volume_clock(escrow: &mut EscrowAccount, now: i64) -> u8 {
+    let periods = recent_volume_periods(escrow, now);
+    if periods >= RECENT_VOLUME_MAX_PERIODS {
+        escrow.recent_volume_decayed_at = now;
+    } else {
+        escrow.recent_volume_decayed_at += periods as i64 * RECENT_VOLUME_HALF_LIFE_SECS;
+    }
+    periods
+}
+
+/// Read a fully verified Pyth price for `feed_id` no older than `max_age`.
+/// Returns the price and its exponent.
+fn read_pyth_price(
+    price_update: &AccountInfo,
+    feed_id: &[u8; 32],
+    max_age: i64,
+    now: i64,
+) -> Result<(i64, i32)> {
+    require_keys_eq!(
+        *price_update.owner,
+        PYTH_RECEIVER_PROGRAM_ID,
+        EscrowError::InvalidPriceFeed
+    );
+    let data = price_update.try_borrow_data()?;
+    let update = PythPriceUpdate::deserialize(&mut data.get(8..).unwrap_or_default())
+        .map_err(|_| EscrowError::InvalidPriceFeed)?;
+
+    require!(
+        matches!(update.verification_level, PythVerificationLevel::Full),
+        EscrowError::InvalidPriceFeed
+    );
+    let message = update.price_message;
+    require!(message.feed_id == *feed_id, EscrowError::InvalidPriceFeed);
+    require!(message.price > 0, EscrowError::InvalidPriceFeed);
+    require!(now - message.publish_time <= max_age, EscrowError::StalePrice);
+
+    Ok((message.price, message.exponent))
+}
+
+/// Token base units worth the invoice's USD amount at the current price.
+fn invoice_token_amount(
+    invoice: &Invoice,
+    price_update: &AccountInfo,
+    decimals: u8,
+    now: i64,
+) -> Result<u64> {
+    let (price, exponent) = read_pyth_price(
+        price_update,
+        &invoice.price_feed_id,
+        invoice.max_price_age_secs,
+        now,
+    )?;
+
+    // tokens = cents / 100 / (price * 10^exponent) * 10^decimals
+    let mut numerator = (invoice.usd_cents as u128)
+        .checked_mul(10u128.pow(decimals as u32))
+        .ok_or(ProgramError::InvalidArgument)?;
+    let mut denominator = (price as u128) * 100;
+    if exponent < 0 {
+        numerator = numerator
+            .checked_mul(10u128.pow(exponent.unsigned_abs()))
+            .ok_or(ProgramError::InvalidArgument)?;
+    } else {
+        denominator = denominator
+            .checked_mul(10u128.pow(exponent as u32))
+            .ok_or(ProgramError::InvalidArgument)?;
+    }
+
+    u64::try_from(numerator / denominator).map_err(|_| ProgramError::InvalidArgument.into())
+}
+
+/// Move tokens out of a milestone vault, signing as the `Milestones` PDA.
+fn transfer_from_milestone_vault<'info>(
+    milestones: &Account<'info, Milestones>,
+    vault: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    let milestones_id = milestones.milestones_id.to_le_bytes();
+    let seeds: &[&[u8]] = &[
+        seeds::MILESTONES,
+        milestones.payer.as_ref(),
+        &milestones_id,
+        &[milestones.bump],
+    ];
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.clone(),
+        anchor_token::Transfer {
+            from: vault.clone(),
+            to: to.clone(),
+            authority: milestones.to_account_info(),
+        },
+        &[seeds],
+    );
+    anchor_token::transfer(cpi_ctx, amount)
+}
+
+fn emit_milestone_event(milestones: &Account<Milestones>, index: u8) -> Result<()> {
+    let milestone = &milestones.milestones[index as usize];
+    emit!(MilestoneEvent {
+        milestones: milestones.key(),
+        index,
+        amount: milestone.amount,
+        status: milestone.status,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
+/// A payment submitted through a relayer, which co-signs as fee payer, only
+/// lands while that relayer is registered. Self-submitted payments pass no
+/// relayer and are unaffected.
+fn check_relayer(relayer: Option<&Signer>, registry: Option<&RelayerRegistry>) -> Result<()> {
+    if let Some(relayer) = relayer {
+        let registry = registry.ok_or(EscrowError::RelayerRegistryRequired)?;
+        require!(registry.is_registered(&relayer.key()), EscrowError::RelayerNotRegistered);
+    }
+    Ok(())
+}
+
+/// Owner of a Token-2022 account, after checking it holds `mint`. Both sit
+/// at the start of the base account layout, ahead of any extensions.
+fn token_2022_account_owner(account: &AccountInfo, mint: &Pubkey) -> Result<Pubkey> {
+    let data = account.try_borrow_data()?;
+    require!(
+        data.len() >= 64 && data[..32] == mint.to_bytes(),
+        EscrowError::InvalidConfidentialTokenAccount
+    );
+    Pubkey::try_from(&data[32..64]).map_err(|_| EscrowError::InvalidConfidentialTokenAccount.into())
+}
+
+/// Move `amount` out of a deposit: lamports off the deposit account for SOL,
+/// or a vault transfer signed by the deposit for SPL.
+fn pay_out_of_deposit<'info>(
+    deposit: &Account<'info, EscrowDeposit>,
+    vault: Option<&Account<'info, anchor_token::TokenAccount>>,
+    token_program: Option<&Program<'info, Token>>,
+    to: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    if amount == 0 {
+        return Ok(());
+    }
+    if deposit.mint == Pubkey::default() {
+        **deposit.to_account_info().try_borrow_mut_lamports()? -= amount;
+        **to.try_borrow_mut_lamports()? += amount;
+        return Ok(());
+    }
+
+    let (vault, token_program) = vault
+        .zip(token_program)
+        .ok_or(EscrowError::MissingDepositTokenAccounts)?;
+    let deposit_id = deposit.deposit_id.to_le_bytes();
+    let seeds: &[&[u8]] = &[
+        seeds::ESCROW_DEPOSIT,
+        deposit.depositor.as_ref(),
+        &deposit_id,
+        &[deposit.bump],
+    ];
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        anchor_token::Transfer {
+            from: vault.to_account_info(),
+            to: to.clone(),
+            authority: deposit.to_account_info(),
+        },
+        &[seeds],
+    );
+    anchor_token::transfer(cpi_ctx, amount)
+}
+
+fn settle_escrow_deposit(
+    deposit: &mut Account<EscrowDeposit>,
+    status: EscrowDepositStatus,
+) -> Result<()> {
+    deposit.status = status;
+    emit!(EscrowDepositEvent {
+        deposit: deposit.key(),
+        mint: deposit.mint,
+        amount: deposit.amount,
+        status,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
+/// Reject a payment outside the slot window its sender signed for, so a
+/// relayer holding the pre-signed transaction can't land it early or late.
+/// The window is exclusive at both ends; `None` leaves that side open.
+fn check_slot_window(valid_after_slot: Option<u64>, valid_before_slot: Option<u64>) -> Result<()> {
+    let slot = Clock::get()?.slot;
+    if let Some(after) = valid_after_slot {
+        require!(slot > after, EscrowError::PaymentNotYetValid);
+    }
+    if let Some(before) = valid_before_slot {
+        require!(slot < before, EscrowError::PaymentWindowExpired);
+    }
+    Ok(())
+}
+
+/// Every payment path splits `amount` into net, fee and reward legs; they
+/// must add back up exactly, so a rounding change can't create or drop
+/// lamports or tokens.
+fn assert_fee_split(amount: u64, parts: &[u64]) -> Result<()> {
+    let total = parts
+        .iter()
+        .try_fold(0u64, |acc, part| acc.checked_add(*part))
+        .ok_or(EscrowError::FeeInvariantViolated)?;
+    require_eq!(total, amount, EscrowError::FeeInvariantViolated);
+    Ok(())
+}
+
+/// Let the owner and read-only viewers through unconditionally; anyone else
+/// needs an `AnalyticsOperator` grant and must respect its rate limit.
+fn authorize_analytics_request(
+    escrow: &Account<EscrowAccount>,
+    authority: &Pubkey,
+    viewer: Option<&Account<Viewer>>,
+    analytics_operator: Option<&mut Account<AnalyticsOperator>>,
+) -> Result<()> {
+    if *authority == escrow.authority() {
+        return Ok(());
+    }
+    if let Some(viewer) = viewer {
+        require_keys_eq!(viewer.escrow, escrow.key(), EscrowError::InvalidAuthority);
+        return Ok(());
+    }
+
+    let operator = analytics_operator.ok_or(EscrowError::InvalidAuthority)?;
+    require_keys_eq!(operator.escrow, escrow.key(), EscrowError::InvalidAuthority);
+    let now = Clock::get()?.unix_timestamp;
+    require!(
+        now.saturating_sub(operator.last_request_at) >= operator.min_interval_secs,
+        EscrowError::OperatorRateLimited
+    );
+    operator.last_request_at = now;
+    Ok(())
+}
+
+/// Value a fee denominated in some mint in USDC base units, rounding up so
+/// the treasury is never short-changed by the conversion.
+fn convert_fee_to_usdc(fee: u64, usdc_per_unit: u64) -> Result<u64> {
+    let scale = FEE_RATE_SCALE as u128;
+    let usdc = (fee as u128 * usdc_per_unit as u128 + scale - 1) / scale;
+    u64::try_from(usdc).map_err(|_| ProgramError::InvalidArgument.into())
+}
+
+fn emit_recovery_event(
+    recovery: &RecoveryConfig,
+    new_owner: Pubkey,
+    stage: RecoveryStage,
+    guardian: Option<Pubkey>,
+) -> Result<()> {
+    emit!(RecoveryEvent {
+        escrow: recovery.escrow,
+        new_owner,
+        stage,
+        guardian,
+        approvals: recovery.approvals.count_ones() as u8,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(())
+}
+
+/// The referrer a SOL payment is attributed to: `referal` when one is
+/// passed, otherwise the one `sender` registered under `escrow` or
+/// protocol-wide.
+fn resolve_referrer(
+    referal: Pubkey,
+    link: Option<&ReferralLink>,
+    sender: &Pubkey,
+    escrow: &Pubkey,
+    protocol_scope: Option<Pubkey>,
+) -> Result<Pubkey> {
+    match link {
+        Some(link) if referal == Pubkey::default() => {
+            require_keys_eq!(link.referee, *sender, EscrowError::InvalidReferralScope);
+            require!(
+                link.scope == *escrow || Some(link.scope) == protocol_scope,
+                EscrowError::InvalidReferralScope
+            );
+            Ok(link.referrer)
+        }
+        _ => Ok(referal),
+    }
+}
+
+/// Optional accounts the referral share of a SOL payment can be routed
+/// through, depending on the escrow's referral settings.
+struct SolReferralAccounts<'a, 'info> {
+    referral: Option<&'a SystemAccount<'info>>,
+    incinerator: Option<&'a UncheckedAccount<'info>>,
+    referral_vesting: Option<&'a mut Account<'info, ReferralVesting>>,
+    referral_usdc_ledger: Option<&'a mut Account<'info, ReferralUsdcLedger>>,
+    sol_fee_rate: Option<&'a Account<'info, FeeConversionRate>>,
+}
+
+/// Where the referral share of a SOL payment goes. Without a referrer it
+/// follows the escrow's fallback route instead of requiring a placeholder
+/// account. Escrows with referrals switched off always send it to the
+/// treasury.
+fn sol_referral_destination<'info>(
+    escrow: &EscrowAccount,
+    accounts: SolReferralAccounts<'_, 'info>,
+    referal: Pubkey,
+    referal_reward: u64,
+    now: i64,
+    to_treasury: &AccountInfo<'info>,
+    to_recipient: &AccountInfo<'info>,
+) -> Result<AccountInfo<'info>> {
+    if !escrow.referrals_enabled {
+        Ok(to_treasury.clone())
+    } else if referal == Pubkey::default() {
+        match escrow.referral_fallback {
+            ReferralFallback::Treasury => Ok(to_treasury.clone()),
+            ReferralFallback::RecipientRebate => Ok(to_recipient.clone()),
+            ReferralFallback::Burn => Ok(accounts
+                .incinerator
+                .ok_or(EscrowError::MissingReferralRoute)?
+                .to_account_info()),
+        }
+    } else if escrow.usdc_referral_rewards {
+        // The treasury keeps the lamports and owes the referrer their
+        // USDC value, settled later with `settle_usdc_referral_rewards`.
+        let rate = accounts
+            .sol_fee_rate
+            .ok_or(EscrowError::StaleFeeConversionRate)?;
+        require!(
+            now - rate.quoted_at <= rate.max_age_secs,
+            EscrowError::StaleFeeConversionRate
+        );
+        let ledger = accounts
+            .referral_usdc_ledger
+            .ok_or(EscrowError::MissingReferralUsdcLedger)?;
+        require_keys_eq!(
+            ledger.referrer,
+            referal,
+            EscrowError::MissingReferralUsdcLedger
+        );
+        // Round down: the referrer's credit never exceeds the lamports
+        // the treasury kept.
+        let usdc = u64::try_from(
+            referal_reward as u128 * rate.usdc_per_unit as u128 / FEE_RATE_SCALE as u128,
+        )
+        .map_err(|_| ProgramError::InvalidArgument)?;
+        ledger.accrued_usdc = ledger
+            .accrued_usdc
+            .checked_add(usdc)
+            .ok_or(ProgramError::InvalidArgument)?;
+        emit!(ReferralUsdcLedgerEvent {
+            escrow: ledger.escrow,
+            referrer: ledger.referrer,
+            movement: ReferralUsdcMovement::Accrued,
+            usdc_amount: usdc,
+            outstanding_usdc: ledger.outstanding(),
+            timestamp: now,
+        });
+        Ok(to_treasury.clone())
+    } else if escrow.vest_referral_rewards {
+        let vesting = accounts
+            .referral_vesting
+            .ok_or(EscrowError::MissingReferralVesting)?;
+        require_keys_eq!(
+            vesting.referrer,
+            referal,
+            EscrowError::MissingReferralVesting
+        );
+        vesting.deposit(referal_reward, now)?;
+        Ok(vesting.to_account_info())
+    } else {
+        let referral = accounts.referral.ok_or(EscrowError::MissingReferralRoute)?;
+        require_keys_eq!(referral.key(), referal, EscrowError::MissingReferralRoute);
+        Ok(referral.to_account_info())
+    }
+}
+
+/// Optional accounts the escrow's fee features read on a plaintext payment.
+struct FeeFeatureAccounts<'a, 'info> {
+    fee_config: Option<&'a FeeConfig>,
+    campaign: Option<&'a mut Account<'info, Campaign>>,
+    fee_hook_program: Option<&'a UncheckedAccount<'info>>,
+    fee_waiver: Option<&'a mut Account<'info, FeeWaiver>>,
+}
+
+/// Referral, treasury and insurance legs of a plaintext payment.
+struct PaymentFees {
+    referal_reward: u64,
+    treasury_reward: u64,
+    insurance: u64,
+}
+
+/// Fee pipeline of every plaintext payment, whatever the asset: the
+/// `FeeConfig` tier, a live referral campaign, the escrow's fee hook, a
+/// redeemed fee voucher and last the insurance carve, which comes out of
+/// whatever treasury share is left.
+fn plaintext_payment_fees(
+    escrow: &Account<EscrowAccount>,
+    accounts: FeeFeatureAccounts<'_, '_>,
+    payment: &PaymentAccount,
+) -> Result<PaymentFees> {
+    let amount = payment.amount;
+    let (mut referal_reward, mut treasury_reward) =
+        tiered_fee_split(amount, accounts.fee_config, escrow.total_fund_regulated)?;
+    if escrow.referrals_enabled {
+        (referal_reward, treasury_reward) = apply_campaign_boost(
+            accounts.campaign,
+            &escrow.key(),
+            &payment.asset_mint,
+            amount,
+            referal_reward,
+            treasury_reward,
+            payment.timestamp,
+        )?;
+    }
+
+    if escrow.fee_hook != Pubkey::default() {
+        let hook = accounts
+            .fee_hook_program
+            .ok_or(EscrowError::MissingFeeHook)?;
+        (referal_reward, treasury_reward) = call_fee_hook(
+            hook,
+            &FeeHookInput {
+                escrow: escrow.key(),
+                sender: payment.sender,
+                recipient: payment.recipient,
+                asset_mint: payment.asset_mint,
+                amount,
+                referal_reward,
+                treasury_reward,
+            },
+        )?;
+    }
+
+    // A redeemed fee voucher waives the treasury share of this payment.
+    if let Some(waiver) = accounts.fee_waiver {
+        require!(waiver.credits > 0, EscrowError::NoFeeWaiver);
+        waiver.credits -= 1;
+        treasury_reward = 0;
+    }
+
+    let insurance = (amount
+        .checked_mul(escrow.insurance_bps as u64)
+        .ok_or(ProgramError::InvalidArgument)?
+        / 10_000)
+        .min(treasury_reward);
+    treasury_reward -= insurance;
+
+    Ok(PaymentFees {
+        referal_reward,
+        treasury_reward,
+        insurance,
+    })
+}
+
+/// Accounts a plaintext SPL payment moves tokens through, shared by
+/// `send_payment_spl` and the deprecated per-mint entry points.
+struct SplPaymentAccounts<'a, 'info> {
+    sender: &'a Signer<'info>,
+    sender_token_account: &'a Account<'info, anchor_token::TokenAccount>,
+    recipient_token_account: &'a Account<'info, anchor_token::TokenAccount>,
+    referral_token_account: Option<&'a Account<'info, anchor_token::TokenAccount>>,
+    treasury_token_account: &'a Account<'info, anchor_token::TokenAccount>,
+    insurance_pool: Option<&'a Account<'info, InsurancePool>>,
+    insurance_token_account: Option<&'a Account<'info, anchor_token::TokenAccount>>,
+    mint: &'a Account<'info, anchor_token::Mint>,
+    token_program: &'a Program<'info, Token>,
+    jurisdiction_pause: Option<&'a JurisdictionPause>,
+    fees: FeeFeatureAccounts<'a, 'info>,
+}
+
+/// Where the referral share of an SPL payment goes, routed like
+/// `sol_referral_destination`: the treasury while referrals are off, the
+/// fallback route without a referrer, else the referrer's token account.
+/// `None` burns it, the token counterpart of the incinerator. Vested and
+/// USDC-credited referral rewards only apply to SOL shares.
+fn spl_referral_destination<'info>(
+    escrow: &EscrowAccount,
+    accounts: &SplPaymentAccounts<'_, 'info>,
+    referal: Pubkey,
+) -> Result<Option<AccountInfo<'info>>> {
+    if !escrow.referrals_enabled {
+        Ok(Some(accounts.treasury_token_account.to_account_info()))
+    } else if referal == Pubkey::default() {
+        match escrow.referral_fallback {
+            ReferralFallback::Treasury => {
+                Ok(Some(accounts.treasury_token_account.to_account_info()))
+            }
+            ReferralFallback::RecipientRebate => {
+                Ok(Some(accounts.recipient_token_account.to_account_info()))
+            }
+            ReferralFallback::Burn => {
+                // Burning lowers the supply, so the mint must come writable
+                require!(
+                    accounts.mint.to_account_info().is_writable,
+                    EscrowError::MissingReferralRoute
+                );
+                Ok(None)
+            }
+        }
+    } else {
+        let referral = accounts
+            .referral_token_account
+            .ok_or(EscrowError::MissingReferralRoute)?;
+        require_keys_eq!(referral.owner, referal, EscrowError::MissingReferralRoute);
+        Ok(Some(referral.to_account_info()))
+    }
+}
+
+/// Record and settle a plaintext SPL payment through the same fee pipeline
+/// as `send_payment`. Insurance contributions go to the pool's token
+/// account for the mint, which the guardian pays SPL claims out of.
+fn settle_spl_payment<'info>(
+    escrow: &mut Account<'info, EscrowAccount>,
+    payment: &mut Account<'info, PaymentAccount>,
+    accounts: SplPaymentAccounts<'_, 'info>,
+    referal: Pubkey,
+    amount: u64,
+    recipient: Pubkey,
+    sequence: u64,
+) -> Result<()> {
+    escrow.ensure_active(accounts.jurisdiction_pause)?;
+    let to_referral = spl_referral_destination(escrow, &accounts, referal)?;
+
+    payment.sender = accounts.sender.key();
+    payment.sequence = sequence;
+    payment.recipient = recipient;
+    payment.referal = referal;
+    payment.amount = amount;
+    payment.timestamp = Clock::get()?.unix_timestamp;
+    payment.asset_mint = accounts.mint.key();
+    payment.intent_hash = payment_intent_hash(
+        &payment.sender,
+        &payment.recipient,
+        IntentAmount::Plain(amount),
+        &payment.asset_mint,
+        sequence as u128,
+    );
+    let PaymentFees {
+        referal_reward,
+        treasury_reward,
+        insurance,
+    } = plaintext_payment_fees(escrow, accounts.fees, payment)?;
+    payment.referal_reward = referal_reward;
+    payment.treasury_reward = treasury_reward;
+
+    let transferable_amount = amount
+        .checked_sub(referal_reward + treasury_reward + insurance)
+        .ok_or(ProgramError::InvalidArgument)?;
+    assert_fee_split(
+        amount,
+        &[
+            transferable_amount,
+            treasury_reward,
+            referal_reward,
+            insurance,
+        ],
+    )?;
+
+    let token_program = accounts.token_program.to_account_info();
+    let from = accounts.sender_token_account.to_account_info();
+    let authority = accounts.sender.to_account_info();
+    let transfer = |to: AccountInfo<'info>, share: u64| {
+        let cpi_ctx = CpiContext::new(
+            token_program.clone(),
+            anchor_token::Transfer {
+                from: from.clone(),
+                to,
+                authority: authority.clone(),
+            },
+        );
+        anchor_token::transfer(cpi_ctx, share)
+    };
+    transfer(
+        accounts.recipient_token_account.to_account_info(),
+        transferable_amount,
+    )?;
+    transfer(
+        accounts.treasury_token_account.to_account_info(),
+        treasury_reward,
+    )?;
+    match to_referral {
+        Some(to_referral) => transfer(to_referral, referal_reward)?,
+        None => {
+            let cpi_ctx = CpiContext::new(
+                token_program.clone(),
+                anchor_token::Burn {
+                    mint: accounts.mint.to_account_info(),
+                    from: from.clone(),
+                    authority: authority.clone(),
+                },
+            );
+            anchor_token::burn(cpi_ctx, referal_reward)?;
+        }
+    }
+
+    if insurance > 0 {
+        let pool = accounts
+            .insurance_pool
+            .ok_or(EscrowError::MissingInsurancePool)?;
+        let pool_tokens = accounts
+            .insurance_token_account
+            .ok_or(EscrowError::MissingInsurancePool)?;
+        require_keys_eq!(
+            pool_tokens.owner,
+            pool.key(),
+            EscrowError::MissingInsurancePool
+        );
+        transfer(pool_tokens.to_account_info(), insurance)?;
+    }
+
+    escrow.total_fund_regulated = escrow
+        .total_fund_regulated
+        .checked_add(amount)
+        .ok_or(ProgramError::InvalidArgument)?;
+
+    emit!(ConfidentialPaymentEvent {
+        timestamp: payment.timestamp,
+        sender: payment.sender,
+    });
+
+    sequence_escrow_update(escrow)?;
+    Ok(())
+}
+
+/// Move part of the treasury share to the referrer while `campaign` is
+/// live, up to its remaining budget. Returns the new (referral, treasury)
+/// split; an absent or inactive campaign leaves it untouched.
+fn apply_campaign_boost(
+    campaign: Option<&mut Account<Campaign>>,
+    escrow: &Pubkey,
+    mint: &Pubkey,
+    amount: u64,
+    referal_reward: u64,
+    treasury_reward: u64,
+    now: i64,
+) -> Result<(u64, u64)> {
+    let campaign = match campaign {
+        Some(campaign) => campaign,
+        None => return Ok((referal_reward, treasury_reward)),
+    };
+    require!(
+        campaign.escrow == *escrow && campaign.mint == *mint,
+        EscrowError::CampaignMismatch
+    );
+    if now < campaign.start_ts || now >= campaign.end_ts {
+        return Ok((referal_reward, treasury_reward));
+    }
+
+    let boosted = (amount as u128 * campaign.referral_bps as u128 / 10_000) as u64;
+    let extra = boosted
+        .saturating_sub(referal_reward)
+        .min(treasury_reward)
+        .min(campaign.budget.saturating_sub(campaign.spent));
+    if extra == 0 {
+        return Ok((referal_reward, treasury_reward));
+    }
+    campaign.spent += extra;
+
+    emit!(CampaignBoostEvent {
+        campaign: campaign.key(),
+        extra_referral: extra,
+        spent: campaign.spent,
+        budget: campaign.budget,
+        timestamp: now,
+    });
+
+    Ok((referal_reward + extra, treasury_reward - extra))
+}
+
+/// How, if at all, the recipient approved a payment: by signing the
+/// transaction itself, or through an ed25519 instruction earlier in it.
+/// An ed25519 approval must name the payment's `sequence` and its
+/// `valid_before_slot`, which the caller has already enforced with
+/// `check_slot_window`.
+fn recipient_counter_signature(
+    escrow: &Pubkey,
+    sender: &Pubkey,
+    recipient: &AccountInfo,
+    amount: u64,
+    sequence: u64,
+    valid_before_slot: Option<u64>,
+    instructions_sysvar: Option<&UncheckedAccount>,
+) -> Result<CounterSignature> {
+    if recipient.is_signer {
+        return Ok(CounterSignature::Transaction);
+    }
+    let Some(instructions_sysvar) = instructions_sysvar else {
+        return Ok(CounterSignature::None);
+    };
+    let deadline_slot = valid_before_slot.ok_or(EscrowError::CounterSignatureDeadlineRequired)?;
+
+    let message = counter_signature_message(
+        escrow,
+        sender,
+        recipient.key,
+        amount,
+        sequence,
+        deadline_slot,
+    );
+    let current = load_current_index_checked(instructions_sysvar)?;
+    for index in 0..current {
+        let ix = load_instruction_at_checked(index as usize, instructions_sysvar)?;
+        if ix.program_id == anchor_lang::solana_program::ed25519_program::ID
+            && ed25519_signs(&ix.data, recipient.key, &message)
+        {
+            return Ok(CounterSignature::Ed25519);
+        }
+    }
+    Ok(CounterSignature::None)
+}
+
+/// Whether an ed25519 program instruction verified `signer`'s signature
+/// over `message`. Only signatures whose key and message live in the
+/// instruction's own data count.
+fn ed25519_signs(data: &[u8], signer: &Pubkey, message: &[u8]) -> bool {
+    const HEADER: usize = 2;
+    const OFFSETS_LEN: usize = 14;
+    let read_u16 = |at: usize| -> Option<usize> {
+        Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?) as usize)
+    };
+    let slice = |start: usize, len: usize| data.get(start..start.checked_add(len)?);
+
+    let count = data.first().copied().unwrap_or(0) as usize;
+    (0..count).any(|i| {
+        let at = HEADER + i * OFFSETS_LEN;
+        let signs = || -> Option<bool> {
+            let own_data = [read_u16(at + 2)?, read_u16(at + 6)?, read_u16(at + 12)?]
+                .iter()
+                .all(|index| *index == u16::MAX as usize);
+            let public_key = slice(read_u16(at + 4)?, 32)?;
+            let signed = slice(read_u16(at + 8)?, read_u16(at + 10)?)?;
+            Some(own_data && public_key == signer.as_ref() && signed == message)
+        };
+        signs().unwrap_or(false)
+    })
+}
+
+/// Ask an escrow's fee hook for the (referral, treasury) split of a
+/// payment. The hook may reshape the referral share and add a surcharge,
+/// but it can't cut the treasury share or push total fees past
+/// `MAX_FEE_HOOK_BPS`.
+fn call_fee_hook(hook: &AccountInfo, input: &FeeHookInput) -> Result<(u64, u64)> {
+    let mut data = FEE_HOOK_DISCRIMINATOR.to_vec();
+    input.serialize(&mut data)?;
+    invoke(
+        &Instruction {
+            program_id: hook.key(),
+            accounts: vec![],
+            data,
+        },
+        &[hook.clone()],
+    )?;
+
+    let (program_id, output) = get_return_data().ok_or(EscrowError::InvalidFeeHookOutput)?;
+    require_keys_eq!(program_id, hook.key(), EscrowError::InvalidFeeHookOutput);
+    let output = FeeHookOutput::try_from_slice(&output)
+        .map_err(|_| EscrowError::InvalidFeeHookOutput)?;
+
+    let max_fees = input.amount as u128 * MAX_FEE_HOOK_BPS as u128 / 10_000;
+    let fees = output.referal_reward as u128 + output.treasury_reward as u128;
+    require!(
+        output.treasury_reward >= input.treasury_reward && fees <= max_fees,
+        EscrowError::FeeHookOutOfBounds
+    );
+    Ok((output.referal_reward, output.treasury_reward))
+}
+
+/// Canonical payment intent hash: SHA-256 over the `payment-intent`
+/// crate's preimage of the domain tag, program id, sender, recipient,
+/// tagged amount, mint and nonce, so off-chain systems can recompute it
+/// byte for byte with the same crate. `nonce` is whatever makes the payment
+/// unique on its path: payment id, computation offset, client nonce or the
+/// sender's payment sequence number.
+pub fn payment_intent_hash(
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    amount: IntentAmount,
+    mint: &Pubkey,
+    nonce: u128,
+) -> [u8; 32] {
+    let preimage = payment_intent::Preimage::new(
+        &crate::ID.to_bytes(),
+        &sender.to_bytes(),
+        &recipient.to_bytes(),
+        amount,
+        &mint.to_bytes(),
+        nonce,
+    );
+    anchor_lang::solana_program::hash::hash(preimage.as_bytes()).to_bytes()
+}
+
+// Fails to compile if the shared intent vectors were made for another
+// program id, or no longer hold under this one.
+const _: () = {
+    use payment_intent::vectors::{PAYMENT_INTENT_VECTORS, PROGRAM_ID};
+    let program_id = crate::ID.to_bytes();
+    assert!(
+        bytes_eq(&payment_intent::hex32(PROGRAM_ID), &program_id),
+        "test-vectors/payment_intent.rs targets another program id"
+    );
+    let mut i = 0;
+    while i < PAYMENT_INTENT_VECTORS.len() {
+        assert!(
+            PAYMENT_INTENT_VECTORS[i].holds(&program_id),
+            "payment intent hash diverges from test-vectors/payment_intent.rs"
+        );
+        i += 1;
+    }
+};
+
+/// Message a recipient signs to pre-authorize one counter-signed payment.
+/// `sequence` is the sender's payment sequence number the payment will
+/// take, so the signature can't be replayed on a later payment, and
+/// `deadline_slot` must be the payment's `valid_before_slot`, so it can't
+/// be held back and landed late.
+pub fn counter_signature_message(
+    escrow: &Pubkey,
+    sender: &Pubkey,
+    recipient: &Pubkey,
+    amount: u64,
+    sequence: u64,
+    deadline_slot: u64,
+) -> [u8; 32] {
+    anchor_lang::solana_program::hash::hashv(&[
+        COUNTER_SIGNATURE_DOMAIN,
+        crate::ID.as_ref(),
+        escrow.as_ref(),
+        sender.as_ref(),
+        recipient.as_ref(),
+        &amount.to_le_bytes(),
+        &sequence.to_le_bytes(),
+        &deadline_slot.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// Commitment to an escrow's encrypted stats after a payment, so webhook
+/// receivers can match a notification to the on-chain state without the
+/// ciphertexts themselves.
+pub fn stats_commitment(
+    escrow: &Pubkey,
+    ciphertexts: &[[u8; 32]; ESCROW_STATS_FIELDS],
+    nonce: u128,
+) -> [u8; 32] {
+    let mut parts: Vec<&[u8]> = Vec::with_capacity(ESCROW_STATS_FIELDS + 4);
+    let nonce_le = nonce.to_le_bytes();
+    parts.extend_from_slice(&[STATS_COMMITMENT_DOMAIN, crate::ID.as_ref(), escrow.as_ref()]);
+    parts.extend(ciphertexts.iter().map(|c| c.as_slice()));
+    parts.push(&nonce_le);
+    anchor_lang::solana_program::hash::hashv(&parts).to_bytes()
+}
+
+/// Archive leaf for a payment account: its address and full account data,
+/// discriminator included.
+pub fn archive_leaf(payment: &Pubkey, data: &[u8]) -> [u8; 32] {
+    anchor_lang::solana_program::hash::hashv(&[ARCHIVE_LEAF_DOMAIN, payment.as_ref(), data])
+        .to_bytes()
+}
+
+fn archive_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    anchor_lang::solana_program::hash::hashv(&[ARCHIVE_NODE_DOMAIN, left, right]).to_bytes()
+}
+
+/// Merkle root over `leaves`; an unpaired node is promoted to the next
+/// level unchanged.
+pub fn archive_root(mut level: Vec<[u8; 32]>) -> [u8; 32] {
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => archive_node(left, right),
+                [single] => *single,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    level.first().copied().unwrap_or_default()
+}
+
+/// Check that `leaf` sits at `index` in an archive of `leaf_count` leaves
+/// with the given `root`. `proof` lists siblings from the leaf upwards,
+/// skipping levels where the node was promoted without one.
+pub fn verify_archive_proof(
+    leaf: [u8; 32],
+    mut index: u32,
+    proof: &[[u8; 32]],
+    mut leaf_count: u32,
+    root: &[u8; 32],
+) -> bool {
+    if index >= leaf_count {
+        return false;
+    }
+    let mut hash = leaf;
+    let mut siblings = proof.iter();
+    while leaf_count > 1 {
+        if index % 2 == 1 {
+            match siblings.next() {
+                Some(sibling) => hash = archive_node(sibling, &hash),
+                None => return false,
+            }
+        } else if index + 1 < leaf_count {
+            match siblings.next() {
+                Some(sibling) => hash = archive_node(&hash, sibling),
+                None => return false,
+            }
+        }
+        index /= 2;
+        leaf_count = (leaf_count + 1) / 2;
+    }
+    siblings.next().is_none() && hash == *root
+}
+
+/// Byte-slice equality usable in const assertions.
+const fn bytes_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Reject callbacks that don't belong to the computation the escrow is
+/// waiting on: stale results from an earlier queue, or a replay after the
+/// pending slot was already consumed. Because the offset is recorded on the
+/// target account at queue time, this also rejects a callback aimed at a
+/// different escrow (or swap, schedule, auction) than the one queued.
+fn verify_pending_computation(pending: Option<u64>, computation_account: &Pubkey) -> Result<()> {
+    let offset = pending.ok_or(EscrowError::UnexpectedCallback)?;
+    require_keys_eq!(
+        *computation_account,
+        derive_comp_pda!(offset),
+        EscrowError::UnexpectedCallback
+    );
+    Ok(())
+}
+
+/// Move tokens out of an escrow vault, signing as the escrow PDA.
+fn transfer_from_escrow_vault<'info>(
+    escrow: &Account<'info, EscrowAccount>,
+    vault: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    amount: u64,
+) -> Result<()> {
+    let seeds: &[&[u8]] = &[seeds::ESCROW, escrow.owner.as_ref(), &[escrow.bump]];
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.clone(),
+        anchor_token::Transfer {
+            from: vault.clone(),
+            to: to.clone(),
+            authority: escrow.to_account_info(),
+        },
+        &[seeds],
+    );
+    anchor_token::transfer(cpi_ctx, amount)
+}
+
+/// Lamports in the compute budget vault above its rent-exempt minimum.
+fn available_compute_budget(vault: &Account<ComputeBudgetVault>) -> Result<u64> {
+    let info = vault.to_account_info();
+    let rent_exempt = Rent::get()?.minimum_balance(info.data_len());
+    Ok(info.lamports().saturating_sub(rent_exempt))
+}
+
+/// Reward whoever ran a permissionless crank. Pays nothing, rather than
+/// failing the crank, once the period's cap or the vault is exhausted.
+fn pay_crank_reward(
+    vault: &mut Account<ComputeBudgetVault>,
+    cranker: &AccountInfo,
+    crank: CrankKind,
+) -> Result<()> {
+    if vault.crank_reward == 0 {
+        return Ok(());
+    }
+
+    let now = Clock::get()?.unix_timestamp;
+    if now.saturating_sub(vault.crank_period_start) >= CRANK_REWARD_PERIOD_SECS {
+        vault.crank_period_start = now;
+        vault.crank_paid_in_period = 0;
+    }
+
+    let reward = vault
+        .crank_reward
+        .min(vault.crank_reward_cap.saturating_sub(vault.crank_paid_in_period))
+        .min(available_compute_budget(vault)?);
+    if reward == 0 {
+        return Ok(());
+    }
+
+    **vault.to_account_info().try_borrow_mut_lamports()? -= reward;
+    **cranker.try_borrow_mut_lamports()? += reward;
+    vault.crank_paid_in_period += reward;
+
+    emit!(CrankRewardPaidEvent {
+        escrow: vault.escrow,
+        cranker: cranker.key(),
+        crank,
+        amount: reward,
+        paid_in_period: vault.crank_paid_in_period,
+        timestamp: now,
+    });
+
+    Ok(())
+}
+
+/// Advance the escrow's sequence and announce the mutation. Every
+/// instruction that writes the escrow calls this exactly once, so each
+/// escrow's `EscrowUpdatedEvent`s count up without gaps.
+fn sequence_escrow_update(escrow: &mut Account<EscrowAccount>) -> Result<u64> {
+    escrow.sequence = escrow
+        .sequence
+        .checked_add(1)
+        .ok_or(ProgramError::InvalidArgument)?;
+    emit!(EscrowUpdatedEvent {
+        escrow: escrow.key(),
+        sequence: escrow.sequence,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+    Ok(escrow.sequence)
+}
+
+/// Pay the caller back for the Arcium fee debited by `queue_computation`.
+/// The fee pool still charges the signer; the vault simply makes them whole.
+/// `debited` is the drop in the signer's balance across the call, and the
+/// refund never exceeds it, so a `fee_per_computation` set above the real
+/// fee can't be farmed out of the vault.
+fn reimburse_computation_fee(
+    vault: &mut Account<ComputeBudgetVault>,
+    payer: &AccountInfo,
+    debited: u64,
+) -> Result<()> {
+    let fee = vault.fee_per_computation.min(debited);
+    if fee == 0 {
+        return Ok(());
+    }
+    let available = available_compute_budget(vault)?;
+    require!(available >= fee, EscrowError::InsufficientComputeBudget);
+
+    **vault.to_account_info().try_borrow_mut_lamports()? -= fee;
+    **payer.try_borrow_mut_lamports()? += fee;
+    vault.total_reimbursed = vault
+        .total_reimbursed
+        .checked_add(fee)
+        .ok_or(ProgramError::InvalidArgument)?;
+
+    let remaining = available - fee;
+    if remaining < vault.low_balance_threshold {
+        emit!(ComputeBudgetLowEvent {
+            escrow: vault.escrow,
+            balance: remaining,
+            threshold: vault.low_balance_threshold,
+        });
+    }
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpdateEscrowActive<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ACTIVITY_LOG, escrow.key().as_ref()],
+        bump = activity_log.bump,
+    )]
+    pub activity_log: Option<Account<'info, ActivityLog>>,
+}
+
+/// Owner instructions covered by a capability sunset. The handler checks
+/// `authority` with `EscrowAccount::authorize_capability`, since after the
+/// sunset it may be the successor rather than the owner.
+#[derive(Accounts)]
+pub struct ExerciseOwnerCapability<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ACTIVITY_LOG, escrow.key().as_ref()],
+        bump = activity_log.bump,
+    )]
+    pub activity_log: Option<Account<'info, ActivityLog>>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptTreasury<'info> {
+    pub new_treasury: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.pending_treasury == Some(new_treasury.key())
+            @ EscrowError::NoPendingTreasury,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ACTIVITY_LOG, escrow.key().as_ref()],
+        bump = activity_log.bump,
+    )]
+    pub activity_log: Option<Account<'info, ActivityLog>>,
+}
+
+// Keep existing SendPaymentSol, SendPaymentUsdc, SendPaymentZenZec structures unchanged
+#[derive(Accounts)]
+pub struct SendPaymentSol<'info> {
+    #[account(mut)]
+    pub sender: Signer<'info>,
+    #[account(mut)]
+    pub recipient: SystemAccount<'info>,
+    /// Omitted when paying without a referrer
+    #[account(mut)]
+    pub referral: Option<SystemAccount<'info>>,
+    /// CHECK: only needed when the fallback route burns the referral share
+    #[account(mut, address = anchor_lang::solana_program::incinerator::ID)]
+    pub incinerator: Option<UncheckedAccount<'info>>,
+    /// Required with a non-zero app fee, for the cap, and with a
+    /// protocol-wide referral link
+    #[account(seeds = [seeds::PROTOCOL_CONFIG], bump = protocol_config.bump)]
+    pub protocol_config: Option<Account<'info, ProtocolConfig>>,
+    #[account(mut)]
+    pub app_fee_destination: Option<SystemAccount<'info>>,
+    /// The sender's registered referrer, used when no referrer is passed
+    pub referral_link: Option<Account<'info, ReferralLink>>,
+    /// Referral campaign to boost the split with, if one is running
+    #[account(
+        mut,
+        seeds = [seeds::CAMPAIGN, escrow.key().as_ref(), &campaign.campaign_id.to_le_bytes()],
+        bump = campaign.bump,
+    )]
+    pub campaign: Option<Account<'info, Campaign>>,
+    /// Volume fee curve; without it the default treasury fee applies
+    #[account(seeds = [seeds::FEE_CONFIG], bump = fee_config.bump)]
+    pub fee_config: Option<Account<'info, FeeConfig>>,
+    /// Required while the escrow vests referral rewards
+    #[account(
+        mut,
+        seeds = [
+            seeds::REFERRAL_VESTING,
+            escrow.key().as_ref(),
+            referral_vesting.referrer.as_ref(),
+        ],
+        bump = referral_vesting.bump,
+    )]
+    pub referral_vesting: Option<Account<'info, ReferralVesting>>,
+    /// Required while the escrow settles referral rewards in USDC
+    #[account(
+        mut,
+        seeds = [
+            seeds::REFERRAL_USDC_LEDGER,
+            escrow.key().as_ref(),
+            referral_usdc_ledger.referrer.as_ref(),
+        ],
+        bump = referral_usdc_ledger.bump,
+    )]
+    pub referral_usdc_ledger: Option<Account<'info, ReferralUsdcLedger>>,
+    /// USDC quote for lamports; required with `referral_usdc_ledger`
+    #[account(
+        seeds = [seeds::FEE_RATE, Pubkey::default().as_ref()],
+        bump = sol_fee_rate.bump,
+    )]
+    pub sol_fee_rate: Option<Account<'info, FeeConversionRate>>,
+    /// Required unless the escrow accrues treasury fees
+    #[account(mut)]
+    pub treasury: Option<SystemAccount<'info>>,
+    /// Required while the escrow accrues treasury fees
+    #[account(
+        mut,
+        seeds = [seeds::TREASURY_VAULT, escrow.key().as_ref()],
+        bump = treasury_vault.bump,
+    )]
+    pub treasury_vault: Option<Account<'info, TreasuryVault>>,
+    /// Required while the escrow routes part of its fee to insurance
+    #[account(
+        mut,
+        seeds = [seeds::INSURANCE_POOL, escrow.key().as_ref()],
+        bump = insurance_pool.bump,
+    )]
+    pub insurance_pool: Option<Account<'info, InsurancePool>>,
+    /// Spends one of the sender's fee voucher credits on this payment
+    #[account(
+        mut,
+        seeds = [seeds::FEE_WAIVER, escrow.key().as_ref(), sender.key().as_ref()],
+        bump = fee_waiver.bump,
+    )]
+    pub fee_waiver: Option<Account<'info, FeeWaiver>>,
+    /// Required while the escrow has a fee hook registered
+    /// CHECK: only invoked, and pinned to `escrow.fee_hook`
+    #[account(executable, address = escrow.fee_hook @ EscrowError::MissingFeeHook)]
+    pub fee_hook_program: Option<UncheckedAccount<'info>>,
+    /// Needed when the recipient pre-signed via an ed25519 instruction
+    /// CHECK: instructions_sysvar
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+    /// Numbers this sender's plaintext payments across every asset
+    #[account(
+        init_if_needed,
+        payer = sender,
+        space = 8 + PaymentSequence::INIT_SPACE,
+        seeds = [seeds::PAYMENT_SEQUENCE, sender.key().as_ref()],
+        bump
+    )]
+    pub payment_sequence: Account<'info, PaymentSequence>,
+    #[account(
+        init,
+        payer = sender,
+        space = 8 + PaymentAccount::INIT_SPACE,
+        seeds = [
+            seeds::PAYMENTS,
+            sender.key().as_ref(),
+            seeds::SEQUENCED_PAYMENT,
+            &payment_sequence.next.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub payment: Account<'info, PaymentAccount>,
+    pub owner: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+    /// Required for escrows tagged with a jurisdiction
+    #[account(
+        seeds = [seeds::JURISDICTION_PAUSE, &escrow.jurisdiction.to_le_bytes()],
+        bump = jurisdiction_pause.bump,
+    )]
+    pub jurisdiction_pause: Option<Account<'info, JurisdictionPause>>,
+    /// Fee payer of a sponsored submission; must be in the relayer registry
+    pub relayer: Option<Signer<'info>>,
+    #[account(seeds = [seeds::RELAYER_REGISTRY], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(referal: Pubkey, amount: u64, recipient: Pubkey)]
+pub struct SendPaymentSpl<'info> {
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    #[account(mut, token::mint = mint, token::authority = sender)]
+    pub sender_token_account: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut, token::mint = mint, token::authority = recipient)]
+    pub recipient_token_account: Account<'info, anchor_token::TokenAccount>,
+    /// Omitted when paying without a referrer or while referrals are off
+    #[account(mut, token::mint = mint, token::authority = referal)]
+    pub referral_token_account: Option<Account<'info, anchor_token::TokenAccount>>,
+    #[account(mut, token::mint = mint, token::authority = escrow.treasury)]
+    pub treasury_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    /// Numbers this sender's plaintext payments across every asset
+    #[account(
+        init_if_needed,
+        payer = sender,
+        space = 8 + PaymentSequence::INIT_SPACE,
+        seeds = [seeds::PAYMENT_SEQUENCE, sender.key().as_ref()],
+        bump
+    )]
+    pub payment_sequence: Account<'info, PaymentSequence>,
+
+    #[account(
+        init,
+        payer = sender,
+        space = 8 + PaymentAccount::INIT_SPACE,
+        seeds = [
+            seeds::PAYMENTS,
+            sender.key().as_ref(),
+            mint.key().as_ref(),
+            &payment_sequence.next.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub payment: Account<'info, PaymentAccount>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+    /// Required for escrows tagged with a jurisdiction
+    #[account(
+        seeds = [seeds::JURISDICTION_PAUSE, &escrow.jurisdiction.to_le_bytes()],
+        bump = jurisdiction_pause.bump,
+    )]
+    pub jurisdiction_pause: Option<Account<'info, JurisdictionPause>>,
+    /// Fee payer of a sponsored submission; must be in the relayer registry
+    pub relayer: Option<Signer<'info>>,
+    #[account(seeds = [seeds::RELAYER_REGISTRY], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+
+    #[account(seeds = [seeds::MINT_ALLOWLIST], bump = mint_allowlist.bump)]
+    pub mint_allowlist: Account<'info, MintAllowlist>,
+    /// Pass writable when the referral fallback burns the share
+    pub mint: Account<'info, anchor_token::Mint>,
+
+    pub owner: SystemAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    /// Volume fee curve; without it the default treasury fee applies
+    #[account(seeds = [seeds::FEE_CONFIG], bump = fee_config.bump)]
+    pub fee_config: Option<Account<'info, FeeConfig>>,
+    /// Referral campaign to boost the split with, if one is running
+    #[account(
+        mut,
+        seeds = [seeds::CAMPAIGN, escrow.key().as_ref(), &campaign.campaign_id.to_le_bytes()],
+        bump = campaign.bump,
+    )]
+    pub campaign: Option<Account<'info, Campaign>>,
+    /// Spends one of the sender's fee voucher credits on this payment
+    #[account(
+        mut,
+        seeds = [seeds::FEE_WAIVER, escrow.key().as_ref(), sender.key().as_ref()],
+        bump = fee_waiver.bump,
+    )]
+    pub fee_waiver: Option<Account<'info, FeeWaiver>>,
+    /// Required while the escrow has a fee hook registered
+    /// CHECK: only invoked, and pinned to `escrow.fee_hook`
+    #[account(executable, address = escrow.fee_hook @ EscrowError::MissingFeeHook)]
+    pub fee_hook_program: Option<UncheckedAccount<'info>>,
+    /// Required while the escrow routes part of its fee to insurance
+    #[account(
+        seeds = [seeds::INSURANCE_POOL, escrow.key().as_ref()],
+        bump = insurance_pool.bump,
+    )]
+    pub insurance_pool: Option<Account<'info, InsurancePool>>,
+    /// The insurance pool's token account for `mint`
+    #[account(mut, token::mint = mint)]
+    pub insurance_token_account: Option<Account<'info, anchor_token::TokenAccount>>,
+}
+
+#[derive(Accounts)]
+#[instruction(recipient: Pubkey)]
+pub struct SendPaymentConfidentialToken<'info> {
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    /// CHECK: the sender's Token-2022 account for `mint`; Token-2022
+    /// checks ownership, the mint and the confidential transfer extension
+    #[account(mut, owner = TOKEN_2022_PROGRAM_ID)]
+    pub sender_token_account: UncheckedAccount<'info>,
+    /// CHECK: `recipient`'s Token-2022 account for `mint`; checked by
+    /// Token-2022 against the ciphertexts the proofs were made for
+    #[account(mut, owner = TOKEN_2022_PROGRAM_ID)]
+    pub recipient_token_account: UncheckedAccount<'info>,
+    /// CHECK: a Token-2022 mint with the confidential transfer extension
+    #[account(owner = TOKEN_2022_PROGRAM_ID)]
+    pub mint: UncheckedAccount<'info>,
+    #[account(seeds = [seeds::MINT_ALLOWLIST], bump = mint_allowlist.bump)]
+    pub mint_allowlist: Account<'info, MintAllowlist>,
+
+    /// CHECK: verified equality proof context, read by Token-2022
+    pub equality_proof: UncheckedAccount<'info>,
+    /// CHECK: verified ciphertext-validity proof context, read by Token-2022
+    pub ciphertext_validity_proof: UncheckedAccount<'info>,
+    /// CHECK: verified range proof context, read by Token-2022
+    pub range_proof: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+    /// Required for escrows tagged with a jurisdiction
+    #[account(
+        seeds = [seeds::JURISDICTION_PAUSE, &escrow.jurisdiction.to_le_bytes()],
+        bump = jurisdiction_pause.bump,
+    )]
+    pub jurisdiction_pause: Option<Account<'info, JurisdictionPause>>,
+    /// Fee payer of a sponsored submission; must be in the relayer registry
+    pub relayer: Option<Signer<'info>>,
+    #[account(seeds = [seeds::RELAYER_REGISTRY], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+
+    #[account(mut, address = escrow.treasury)]
+    pub treasury: SystemAccount<'info>,
+
+    /// CHECK: program id checked by address
+    #[account(address = TOKEN_2022_PROGRAM_ID)]
+    pub token_2022_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(referal: Pubkey, amount: u64, recipient: Pubkey)]
+pub struct SendPaymentZenZec<'info> {
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    // Token accounts
+    #[account(mut, token::mint = mint, token::authority = sender)]
+    pub sender_token_account: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut, token::mint = mint, token::authority = recipient)]
+    pub recipient_token_account: Account<'info, anchor_token::TokenAccount>,
+    /// Only paid while referrals are on and a referrer is passed
+    #[account(mut, token::mint = mint)]
+    pub referral_token_account: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut, token::mint = mint, token::authority = escrow.treasury)]
+    pub treasury_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    /// Numbers this sender's plaintext payments across every asset
+    #[account(
+        init_if_needed,
+        payer = sender,
+        space = 8 + PaymentSequence::INIT_SPACE,
+        seeds = [seeds::PAYMENT_SEQUENCE, sender.key().as_ref()],
+        bump
+    )]
+    pub payment_sequence: Account<'info, PaymentSequence>,
+
+    // Payment account
+    #[account(
+        init,
+        payer = sender,
+        space = 8 + PaymentAccount::INIT_SPACE,
+        seeds = [
+            seeds::PAYMENTS,
+            sender.key().as_ref(),
+            seeds::SEQUENCED_PAYMENT,
+            &payment_sequence.next.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub payment: Account<'info, PaymentAccount>,
+
+    // Escrow account
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+    /// Required for escrows tagged with a jurisdiction
+    #[account(
+        seeds = [seeds::JURISDICTION_PAUSE, &escrow.jurisdiction.to_le_bytes()],
+        bump = jurisdiction_pause.bump,
+    )]
+    pub jurisdiction_pause: Option<Account<'info, JurisdictionPause>>,
+    /// Fee payer of a sponsored submission; must be in the relayer registry
+    pub relayer: Option<Signer<'info>>,
+    #[account(seeds = [seeds::RELAYER_REGISTRY], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+
+    // Program accounts
+    pub owner: SystemAccount<'info>,
+    #[account(address = ZENZEC_MINT)]
+    pub mint: Account<'info, anchor_token::Mint>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+
+    // System accounts
+    pub rent: Sysvar<'info, Rent>,
+    pub clock: Sysvar<'info, Clock>,
+
+    // Additional token accounts (kept for backward compatibility)
+    /// CHECK: This is the sender's token account (ATA)
+    #[account(mut)]
+    pub sender_ata: AccountInfo<'info>,
+    /// CHECK: This is the recipient's token account (ATA)
+    #[account(mut)]
+    pub recipient_ata: AccountInfo<'info>,
+    /// CHECK: This is the referral's token account (ATA)
+    #[account(mut)]
+    pub referral_ata: AccountInfo<'info>,
+    /// CHECK: This is the treasury's token account (ATA)
+    #[account(mut)]
+    pub treasury_ata: AccountInfo<'info>,
+
+    // Fee feature accounts, appended so existing clients keep working
+    /// Volume fee curve; without it the default treasury fee applies
+    #[account(seeds = [seeds::FEE_CONFIG], bump = fee_config.bump)]
+    pub fee_config: Option<Account<'info, FeeConfig>>,
+    /// Referral campaign to boost the split with, if one is running
+    #[account(
+        mut,
+        seeds = [seeds::CAMPAIGN, escrow.key().as_ref(), &campaign.campaign_id.to_le_bytes()],
+        bump = campaign.bump,
+    )]
+    pub campaign: Option<Account<'info, Campaign>>,
+    /// Spends one of the sender's fee voucher credits on this payment
+    #[account(
+        mut,
+        seeds = [seeds::FEE_WAIVER, escrow.key().as_ref(), sender.key().as_ref()],
+        bump = fee_waiver.bump,
+    )]
+    pub fee_waiver: Option<Account<'info, FeeWaiver>>,
+    /// Required while the escrow has a fee hook registered
+    /// CHECK: only invoked, and pinned to `escrow.fee_hook`
+    #[account(executable, address = escrow.fee_hook @ EscrowError::MissingFeeHook)]
+    pub fee_hook_program: Option<UncheckedAccount<'info>>,
+    /// Required while the escrow routes part of its fee to insurance
+    #[account(
+        seeds = [seeds::INSURANCE_POOL, escrow.key().as_ref()],
+        bump = insurance_pool.bump,
+    )]
+    pub insurance_pool: Option<Account<'info, InsurancePool>>,
+    /// The insurance pool's token account for `mint`
+    #[account(mut, token::mint = mint)]
+    pub insurance_token_account: Option<Account<'info, anchor_token::TokenAccount>>,
+}
+
+#[derive(Accounts)]
+#[instruction(referal: Pubkey, amount: u64, recipient: Pubkey)]
+pub struct SendPaymentUsdc<'info> {
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    // Token accounts
+    #[account(mut, token::mint = mint, token::authority = sender)]
+    pub sender_token_account: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut, token::mint = mint, token::authority = recipient)]
+    pub recipient_token_account: Account<'info, anchor_token::TokenAccount>,
+    /// Only paid while referrals are on and a referrer is passed
+    #[account(mut, token::mint = mint)]
+    pub referral_token_account: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut, token::mint = mint, token::authority = escrow.treasury)]
+    pub treasury_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    /// Numbers this sender's plaintext payments across every asset
+    #[account(
+        init_if_needed,
+        payer = sender,
+        space = 8 + PaymentSequence::INIT_SPACE,
+        seeds = [seeds::PAYMENT_SEQUENCE, sender.key().as_ref()],
+        bump
+    )]
+    pub payment_sequence: Account<'info, PaymentSequence>,
+
+    // Payment account
+    #[account(
+        init,
+        payer = sender,
+        space = 8 + PaymentAccount::INIT_SPACE,
+        seeds = [
+            seeds::PAYMENTS,
+            sender.key().as_ref(),
+            seeds::SEQUENCED_PAYMENT,
+            &payment_sequence.next.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub payment: Account<'info, PaymentAccount>,
+
+    // Escrow account
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+    /// Required for escrows tagged with a jurisdiction
+    #[account(
+        seeds = [seeds::JURISDICTION_PAUSE, &escrow.jurisdiction.to_le_bytes()],
+        bump = jurisdiction_pause.bump,
+    )]
+    pub jurisdiction_pause: Option<Account<'info, JurisdictionPause>>,
+    /// Fee payer of a sponsored submission; must be in the relayer registry
+    pub relayer: Option<Signer<'info>>,
+    #[account(seeds = [seeds::RELAYER_REGISTRY], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+
+    // Mint account
+    #[account(address = USDC_MINT)]
+    pub mint: Account<'info, anchor_token::Mint>,
+
+    // Program accounts
+    pub owner: SystemAccount<'info>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+
+    // Additional token accounts (kept for backward compatibility)
+    /// CHECK: This is the sender's token account (ATA)
+    #[account(mut)]
+    pub sender_ata: AccountInfo<'info>,
+    /// CHECK: This is the recipient's token account (ATA)
+    #[account(mut)]
+    pub recipient_ata: AccountInfo<'info>,
+    /// CHECK: This is the referral's token account (ATA)
+    #[account(mut)]
+    pub referral_ata: AccountInfo<'info>,
+    /// CHECK: This is the treasury's token account (ATA)
+    #[account(mut)]
+    pub treasury_ata: AccountInfo<'info>,
+
+    // System accounts
+    pub rent: Sysvar<'info, Rent>,
+    pub clock: Sysvar<'info, Clock>,
+
+    // Fee feature accounts, appended so existing clients keep working
+    /// Volume fee curve; without it the default treasury fee applies
+    #[account(seeds = [seeds::FEE_CONFIG], bump = fee_config.bump)]
+    pub fee_config: Option<Account<'info, FeeConfig>>,
+    /// Referral campaign to boost the split with, if one is running
+    #[account(
+        mut,
+        seeds = [seeds::CAMPAIGN, escrow.key().as_ref(), &campaign.campaign_id.to_le_bytes()],
+        bump = campaign.bump,
+    )]
+    pub campaign: Option<Account<'info, Campaign>>,
+    /// Spends one of the sender's fee voucher credits on this payment
+    #[account(
+        mut,
+        seeds = [seeds::FEE_WAIVER, escrow.key().as_ref(), sender.key().as_ref()],
+        bump = fee_waiver.bump,
+    )]
+    pub fee_waiver: Option<Account<'info, FeeWaiver>>,
+    /// Required while the escrow has a fee hook registered
+    /// CHECK: only invoked, and pinned to `escrow.fee_hook`
+    #[account(executable, address = escrow.fee_hook @ EscrowError::MissingFeeHook)]
+    pub fee_hook_program: Option<UncheckedAccount<'info>>,
+    /// Required while the escrow routes part of its fee to insurance
+    #[account(
+        seeds = [seeds::INSURANCE_POOL, escrow.key().as_ref()],
+        bump = insurance_pool.bump,
+    )]
+    pub insurance_pool: Option<Account<'info, InsurancePool>>,
+    /// The insurance pool's token account for `mint`
+    #[account(mut, token::mint = mint)]
+    pub insurance_token_account: Option<Account<'info, anchor_token::TokenAccount>>,
+}
+
+#[derive(Accounts)]
+#[instruction(swap_id: u64)]
+pub struct CreateOtcSwap<'info> {
+    #[account(mut)]
+    pub maker: Signer<'info>,
+
+    #[account(
+        init,
+        payer = maker,
+        space = 8 + OtcSwap::INIT_SPACE,
+        seeds = [seeds::OTC_SWAP, maker.key().as_ref(), &swap_id.to_le_bytes()],
+        bump
+    )]
+    pub otc_swap: Account<'info, OtcSwap>,
+
+    pub mint_a: Account<'info, anchor_token::Mint>,
+    pub mint_b: Account<'info, anchor_token::Mint>,
+
+    #[account(
+        mut,
+        token::mint = mint_a,
+        token::authority = maker,
+    )]
+    pub maker_token_a: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(
+        init,
+        payer = maker,
+        token::mint = mint_a,
+        token::authority = otc_swap,
+        seeds = [seeds::OTC_VAULT_A, otc_swap.key().as_ref()],
+        bump
+    )]
+    pub vault_a: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(
+        init,
+        payer = maker,
+        token::mint = mint_b,
+        token::authority = otc_swap,
+        seeds = [seeds::OTC_VAULT_B, otc_swap.key().as_ref()],
+        bump
+    )]
+    pub vault_b: Account<'info, anchor_token::TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptOtcSwap<'info> {
+    pub taker: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::OTC_SWAP, otc_swap.maker.as_ref(), &otc_swap.swap_id.to_le_bytes()],
+        bump = otc_swap.bump,
+        constraint = otc_swap.taker == taker.key() @ EscrowError::InvalidAuthority,
+    )]
+    pub otc_swap: Account<'info, OtcSwap>,
+
+    #[account(
+        mut,
+        token::mint = otc_swap.mint_b,
+        token::authority = taker,
+    )]
+    pub taker_token_b: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [seeds::OTC_VAULT_B, otc_swap.key().as_ref()],
+        bump
+    )]
+    pub vault_b: Account<'info, anchor_token::TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[queue_computation_accounts("verify_otc_terms", party)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct VerifyOtcSwap<'info> {
+    #[account(mut)]
+    pub party: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::OTC_SWAP, otc_swap.maker.as_ref(), &otc_swap.swap_id.to_le_bytes()],
+        bump = otc_swap.bump,
+    )]
+    pub otc_swap: Account<'info, OtcSwap>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = party,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_VERIFY_OTC_TERMS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.maintenance_mode @ EscrowError::MaintenanceMode,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = cluster_account.key() == protocol_config
+            .active_cluster()
+            .unwrap_or(derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet))
+            @ EscrowError::InvalidCluster
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("verify_otc_terms")]
+#[derive(Accounts)]
+pub struct VerifyOtcTermsCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_VERIFY_OTC_TERMS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::OTC_SWAP, otc_swap.maker.as_ref(), &otc_swap.swap_id.to_le_bytes()],
+        bump = otc_swap.bump,
+    )]
+    pub otc_swap: Account<'info, OtcSwap>,
+
+    /// CHECK: checked against otc_swap.pending_computation_offset in the handler
+    pub computation_account: UncheckedAccount<'info>,
+}
+
+/// Shared by settlement and refunds; both move the two vaults, only the
+/// destinations differ. Anyone may crank it, funds only go to the parties.
+#[derive(Accounts)]
+pub struct SettleOtcSwap<'info> {
+    #[account(
+        mut,
+        seeds = [seeds::OTC_SWAP, otc_swap.maker.as_ref(), &otc_swap.swap_id.to_le_bytes()],
+        bump = otc_swap.bump,
+    )]
+    pub otc_swap: Account<'info, OtcSwap>,
+
+    #[account(
+        mut,
+        seeds = [seeds::OTC_VAULT_A, otc_swap.key().as_ref()],
+        bump
+    )]
+    pub vault_a: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [seeds::OTC_VAULT_B, otc_swap.key().as_ref()],
+        bump
+    )]
+    pub vault_b: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(mut, token::mint = otc_swap.mint_a, token::authority = otc_swap.maker)]
+    pub maker_token_a: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut, token::mint = otc_swap.mint_b, token::authority = otc_swap.maker)]
+    pub maker_token_b: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut, token::mint = otc_swap.mint_a, token::authority = otc_swap.taker)]
+    pub taker_token_a: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut, token::mint = otc_swap.mint_b, token::authority = otc_swap.taker)]
+    pub taker_token_b: Account<'info, anchor_token::TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(schedule_id: u64)]
+pub struct CreatePayrollSchedule<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Required for escrows tagged with a jurisdiction
+    #[account(
+        seeds = [seeds::JURISDICTION_PAUSE, &escrow.jurisdiction.to_le_bytes()],
+        bump = jurisdiction_pause.bump,
+    )]
+    pub jurisdiction_pause: Option<Account<'info, JurisdictionPause>>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + PayrollSchedule::INIT_SPACE,
+        seeds = [seeds::PAYROLL, escrow.key().as_ref(), &schedule_id.to_le_bytes()],
+        bump
+    )]
+    pub payroll_schedule: Account<'info, PayrollSchedule>,
+
+    pub mint: Account<'info, anchor_token::Mint>,
+
+    #[account(
+        init,
+        payer = owner,
+        token::mint = mint,
+        token::authority = payroll_schedule,
+        seeds = [seeds::PAYROLL_VAULT, payroll_schedule.key().as_ref()],
+        bump
+    )]
+    pub payroll_vault: Account<'info, anchor_token::TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePayrollSchedule<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        seeds = [seeds::PAYROLL, escrow.key().as_ref(), &payroll_schedule.schedule_id.to_le_bytes()],
+        bump = payroll_schedule.bump,
+    )]
+    pub payroll_schedule: Account<'info, PayrollSchedule>,
+}
+
+#[derive(Accounts)]
+pub struct FundPayroll<'info> {
+    pub funder: Signer<'info>,
+
+    pub payroll_schedule: Account<'info, PayrollSchedule>,
+
+    #[account(
+        mut,
+        seeds = [seeds::PAYROLL_VAULT, payroll_schedule.key().as_ref()],
+        bump
+    )]
+    pub payroll_vault: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(mut, token::mint = payroll_schedule.mint, token::authority = funder)]
+    pub funder_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[queue_computation_accounts("run_payroll", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct RunPayroll<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(address = payroll_schedule.escrow)]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Required for escrows tagged with a jurisdiction
+    #[account(
+        seeds = [seeds::JURISDICTION_PAUSE, &escrow.jurisdiction.to_le_bytes()],
+        bump = jurisdiction_pause.bump,
+    )]
+    pub jurisdiction_pause: Option<Account<'info, JurisdictionPause>>,
+
+    #[account(
+        mut,
+        seeds = [seeds::PAYROLL, escrow.key().as_ref(), &payroll_schedule.schedule_id.to_le_bytes()],
+        bump = payroll_schedule.bump,
+    )]
+    pub payroll_schedule: Account<'info, PayrollSchedule>,
+
+    #[account(
+        seeds = [seeds::PAYROLL_VAULT, payroll_schedule.key().as_ref()],
+        bump
+    )]
+    pub payroll_vault: Account<'info, anchor_token::TokenAccount>,
+
+    /// Pays the crank reward when the owner has configured one
+    #[account(
+        mut,
+        seeds = [seeds::COMPUTE_BUDGET, escrow.key().as_ref()],
+        bump = compute_budget_vault.bump,
+    )]
+    pub compute_budget_vault: Option<Account<'info, ComputeBudgetVault>>,
+
+    pub token_program: Program<'info, Token>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_RUN_PAYROLL)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.maintenance_mode @ EscrowError::MaintenanceMode,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = cluster_account.key() == protocol_config
+            .active_cluster()
+            .unwrap_or(derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet))
+            @ EscrowError::InvalidCluster
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("run_payroll")]
+#[derive(Accounts)]
+pub struct RunPayrollCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_RUN_PAYROLL)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            seeds::PAYROLL,
+            payroll_schedule.escrow.as_ref(),
+            &payroll_schedule.schedule_id.to_le_bytes()
+        ],
+        bump = payroll_schedule.bump,
+    )]
+    pub payroll_schedule: Account<'info, PayrollSchedule>,
+
+    /// CHECK: checked against payroll_schedule.pending_computation_offset in the handler
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::PAYROLL_VAULT, payroll_schedule.key().as_ref()],
+        bump
+    )]
+    pub payroll_vault: Account<'info, anchor_token::TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct InitEscrowVault<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    pub mint: Account<'info, anchor_token::Mint>,
+
+    #[account(
+        init,
+        payer = owner,
+        token::mint = mint,
+        token::authority = escrow,
+        seeds = [seeds::ESCROW_VAULT, escrow.key().as_ref(), mint.key().as_ref()],
+        bump
+    )]
+    pub escrow_vault: Account<'info, anchor_token::TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct SetSpendingPolicy<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + SpendingPolicy::INIT_SPACE,
+        seeds = [seeds::SPENDING_POLICY, escrow.key().as_ref()],
+        bump
+    )]
+    pub spending_policy: Account<'info, SpendingPolicy>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(payment_id: u64)]
+pub struct SendPaymentFromVault<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Required by the spending policy for payments above its threshold.
+    pub cosigner: Option<Signer<'info>>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Required for escrows tagged with a jurisdiction
+    #[account(
+        seeds = [seeds::JURISDICTION_PAUSE, &escrow.jurisdiction.to_le_bytes()],
+        bump = jurisdiction_pause.bump,
+    )]
+    pub jurisdiction_pause: Option<Account<'info, JurisdictionPause>>,
+    /// Fee payer of a sponsored submission; must be in the relayer registry
+    pub relayer: Option<Signer<'info>>,
+    #[account(seeds = [seeds::RELAYER_REGISTRY], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+
+    #[account(
+        seeds = [seeds::SPENDING_POLICY, escrow.key().as_ref()],
+        bump = spending_policy.bump,
+    )]
+    pub spending_policy: Option<Account<'info, SpendingPolicy>>,
+
+    #[account(
+        mut,
+        seeds = [seeds::CAMPAIGN, escrow.key().as_ref(), &campaign.campaign_id.to_le_bytes()],
+        bump = campaign.bump,
+    )]
+    pub campaign: Option<Account<'info, Campaign>>,
+
+    #[account(seeds = [seeds::FEE_CONFIG], bump = fee_config.bump)]
+    pub fee_config: Option<Account<'info, FeeConfig>>,
+
+    /// Required while the escrow is quarantining vault payments
+    #[account(
+        mut,
+        seeds = [seeds::QUARANTINE, escrow.key().as_ref()],
+        bump = quarantine.bump,
+    )]
+    pub quarantine: Option<Account<'info, Quarantine>>,
+
+    /// Required while the escrow restricts vault payments to a window
+    #[account(
+        mut,
+        seeds = [seeds::SETTLEMENT_WINDOW, escrow.key().as_ref()],
+        bump = settlement_window.bump,
+    )]
+    pub settlement_window: Option<Account<'info, SettlementWindow>>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW_VAULT, escrow.key().as_ref(), escrow_vault.mint.as_ref()],
+        bump
+    )]
+    pub escrow_vault: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(mut, token::mint = escrow_vault.mint)]
+    pub recipient_token_account: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut, token::mint = escrow_vault.mint, token::authority = escrow.treasury)]
+    pub treasury_token_account: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut, token::mint = escrow_vault.mint)]
+    pub referral_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + PaymentAccount::INIT_SPACE,
+        seeds = [seeds::VAULT_PAYMENTS, escrow.key().as_ref(), &payment_id.to_le_bytes()],
+        bump
+    )]
+    pub payment: Account<'info, PaymentAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(approval_id: u64)]
+pub struct RequestVaultPayment<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Required for escrows tagged with a jurisdiction
+    #[account(
+        seeds = [seeds::JURISDICTION_PAUSE, &escrow.jurisdiction.to_le_bytes()],
+        bump = jurisdiction_pause.bump,
+    )]
+    pub jurisdiction_pause: Option<Account<'info, JurisdictionPause>>,
+
+    #[account(
+        seeds = [seeds::SPENDING_POLICY, escrow.key().as_ref()],
+        bump = spending_policy.bump,
+    )]
+    pub spending_policy: Option<Account<'info, SpendingPolicy>>,
+
+    #[account(
+        seeds = [seeds::ESCROW_VAULT, escrow.key().as_ref(), escrow_vault.mint.as_ref()],
+        bump
+    )]
+    pub escrow_vault: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(token::mint = escrow_vault.mint)]
+    pub recipient_token_account: Account<'info, anchor_token::TokenAccount>,
+    #[account(token::mint = escrow_vault.mint)]
+    pub referral_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + PendingApproval::INIT_SPACE,
+        seeds = [seeds::PENDING_APPROVAL, escrow.key().as_ref(), &approval_id.to_le_bytes()],
+        bump
+    )]
+    pub pending_approval: Account<'info, PendingApproval>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApprovePendingPayment<'info> {
+    #[account(address = escrow.approver @ EscrowError::InvalidAuthority)]
+    pub approver: Signer<'info>,
+
+    #[account(mut, address = pending_approval.escrow)]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Required for escrows tagged with a jurisdiction
+    #[account(
+        seeds = [seeds::JURISDICTION_PAUSE, &escrow.jurisdiction.to_le_bytes()],
+        bump = jurisdiction_pause.bump,
+    )]
+    pub jurisdiction_pause: Option<Account<'info, JurisdictionPause>>,
+
+    /// CHECK: rent refund goes back to the escrow owner who opened the request
+    #[account(mut, address = escrow.authority())]
+    pub owner: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [seeds::PENDING_APPROVAL, escrow.key().as_ref(), &pending_approval.approval_id.to_le_bytes()],
+        bump = pending_approval.bump,
+    )]
+    pub pending_approval: Account<'info, PendingApproval>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW_VAULT, escrow.key().as_ref(), pending_approval.mint.as_ref()],
+        bump
+    )]
+    pub escrow_vault: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(mut, address = pending_approval.recipient_token_account)]
+    pub recipient_token_account: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut, token::mint = pending_approval.mint, token::authority = escrow.treasury)]
+    pub treasury_token_account: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut, address = pending_approval.referral_token_account)]
+    pub referral_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ACTIVITY_LOG, escrow.key().as_ref()],
+        bump = activity_log.bump,
+    )]
+    pub activity_log: Option<Account<'info, ActivityLog>>,
+
+    pub token_program: Program<'info, Token>,
+    /// Volume fee curve; without it the default treasury fee applies
+    #[account(seeds = [seeds::FEE_CONFIG], bump = fee_config.bump)]
+    pub fee_config: Option<Account<'info, FeeConfig>>,
+}
+
+#[derive(Accounts)]
+pub struct CancelPendingPayment<'info> {
+    pub closer: Signer<'info>,
+
+    #[account(address = pending_approval.escrow)]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// CHECK: rent refund goes back to the escrow owner who opened the request
+    #[account(mut, address = escrow.authority())]
+    pub owner: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [seeds::PENDING_APPROVAL, escrow.key().as_ref(), &pending_approval.approval_id.to_le_bytes()],
+        bump = pending_approval.bump,
+    )]
+    pub pending_approval: Account<'info, PendingApproval>,
+}
+
+#[derive(Accounts)]
+pub struct InitActivityLog<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + ActivityLog::INIT_SPACE,
+        seeds = [seeds::ACTIVITY_LOG, escrow.key().as_ref()],
+        bump
+    )]
+    pub activity_log: Account<'info, ActivityLog>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("init_referral_stats", referrer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct InitializeReferralStats<'info> {
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+
+    #[account(
+        init,
+        payer = referrer,
+        space = 8 + ReferralStatsAccount::INIT_SPACE,
+        seeds = [seeds::REFERRAL_STATS, referrer.key().as_ref()],
+        bump
+    )]
+    pub referral_stats: Account<'info, ReferralStatsAccount>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = referrer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_INIT_REFERRAL_STATS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.maintenance_mode @ EscrowError::MaintenanceMode,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = cluster_account.key() == protocol_config
+            .active_cluster()
+            .unwrap_or(derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet))
+            @ EscrowError::InvalidCluster
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("init_referral_stats")]
+#[derive(Accounts)]
+pub struct InitReferralStatsCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_INIT_REFERRAL_STATS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::REFERRAL_STATS, referral_stats.referrer.as_ref()],
+        bump = referral_stats.bump,
+    )]
+    pub referral_stats: Account<'info, ReferralStatsAccount>,
+
+    /// CHECK: checked against referral_stats.pending_computation_offset in the handler
+    pub computation_account: UncheckedAccount<'info>,
+}
+
+#[queue_computation_accounts("referral_report", referrer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct RequestReferralReport<'info> {
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::REFERRAL_STATS, referrer.key().as_ref()],
+        bump = referral_stats.bump,
+        has_one = referrer @ EscrowError::InvalidAuthority,
+    )]
+    pub referral_stats: Account<'info, ReferralStatsAccount>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = referrer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REFERRAL_REPORT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.maintenance_mode @ EscrowError::MaintenanceMode,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = cluster_account.key() == protocol_config
+            .active_cluster()
+            .unwrap_or(derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet))
+            @ EscrowError::InvalidCluster
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("referral_report")]
+#[derive(Accounts)]
+pub struct ReferralReportCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REFERRAL_REPORT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    pub referral_stats: Account<'info, ReferralStatsAccount>,
+}
+
+#[derive(Accounts)]
+pub struct SweepDust<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW_VAULT, escrow.key().as_ref(), escrow_vault.mint.as_ref()],
+        bump
+    )]
+    pub escrow_vault: Option<Account<'info, anchor_token::TokenAccount>>,
+
+    #[account(
+        mut,
+        constraint = treasury_token_account.owner == escrow.treasury @ EscrowError::InvalidAuthority,
+    )]
+    pub treasury_token_account: Option<Account<'info, anchor_token::TokenAccount>>,
+
+    #[account(
+        mut,
+        seeds = [seeds::COMPUTE_BUDGET, escrow.key().as_ref()],
+        bump = compute_budget_vault.bump,
+    )]
+    pub compute_budget_vault: Option<Account<'info, ComputeBudgetVault>>,
+
+    #[account(mut, address = escrow.treasury)]
+    pub treasury: SystemAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[queue_computation_accounts("check_volume_threshold", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct CheckCircuitBreaker<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Required for escrows tagged with a jurisdiction
+    #[account(
+        seeds = [seeds::JURISDICTION_PAUSE, &escrow.jurisdiction.to_le_bytes()],
+        bump = jurisdiction_pause.bump,
+    )]
+    pub jurisdiction_pause: Option<Account<'info, JurisdictionPause>>,
+
+    /// Pays the crank reward when the owner has configured one
+    #[account(
+        mut,
+        seeds = [seeds::COMPUTE_BUDGET, escrow.key().as_ref()],
+        bump = compute_budget_vault.bump,
+    )]
+    pub compute_budget_vault: Option<Account<'info, ComputeBudgetVault>>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_THRESHOLD)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.maintenance_mode @ EscrowError::MaintenanceMode,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = cluster_account.key() == protocol_config
+            .active_cluster()
+            .unwrap_or(derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet))
+            @ EscrowError::InvalidCluster
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[derive(Accounts)]
+#[instruction(operator: Pubkey)]
+pub struct SetAnalyticsOperator<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + AnalyticsOperator::INIT_SPACE,
+        seeds = [seeds::ANALYTICS_OPERATOR, escrow.key().as_ref(), operator.as_ref()],
+        bump
+    )]
+    pub analytics_operator: Account<'info, AnalyticsOperator>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveAnalyticsOperator<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [
+            seeds::ANALYTICS_OPERATOR,
+            escrow.key().as_ref(),
+            analytics_operator.operator.as_ref()
+        ],
+        bump = analytics_operator.bump,
+    )]
+    pub analytics_operator: Account<'info, AnalyticsOperator>,
+}
+
+#[derive(Accounts)]
+pub struct SendTip<'info> {
+    #[account(mut)]
+    pub sender: Signer<'info>,
+    #[account(mut)]
+    pub recipient: SystemAccount<'info>,
+    #[account(mut, address = escrow.treasury)]
+    pub treasury: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+    /// Required for escrows tagged with a jurisdiction
+    #[account(
+        seeds = [seeds::JURISDICTION_PAUSE, &escrow.jurisdiction.to_le_bytes()],
+        bump = jurisdiction_pause.bump,
+    )]
+    pub jurisdiction_pause: Option<Account<'info, JurisdictionPause>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(seller: Pubkey)]
+pub struct CreateSubAccount<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + SubAccount::INIT_SPACE,
+        seeds = [seeds::SUB_ACCOUNT, escrow.key().as_ref(), seller.as_ref()],
+        bump
+    )]
+    pub sub_account: Account<'info, SubAccount>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetSubAccountPayout<'info> {
+    pub seller: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::SUB_ACCOUNT, sub_account.escrow.as_ref(), seller.key().as_ref()],
+        bump = sub_account.bump,
+        has_one = seller @ EscrowError::InvalidAuthority,
+    )]
+    pub sub_account: Account<'info, SubAccount>,
+}
+
+#[derive(Accounts)]
+pub struct CreditSubAccount<'info> {
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Required for escrows tagged with a jurisdiction
+    #[account(
+        seeds = [seeds::JURISDICTION_PAUSE, &escrow.jurisdiction.to_le_bytes()],
+        bump = jurisdiction_pause.bump,
+    )]
+    pub jurisdiction_pause: Option<Account<'info, JurisdictionPause>>,
+
+    #[account(
+        mut,
+        seeds = [seeds::SUB_ACCOUNT, escrow.key().as_ref(), sub_account.seller.as_ref()],
+        bump = sub_account.bump,
+        has_one = escrow,
+    )]
+    pub sub_account: Account<'info, SubAccount>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PayoutSubAccount<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        seeds = [seeds::SUB_ACCOUNT, escrow.key().as_ref(), sub_account.seller.as_ref()],
+        bump = sub_account.bump,
+        has_one = escrow,
+        has_one = payout_address,
+    )]
+    pub sub_account: Account<'info, SubAccount>,
+
+    #[account(mut)]
+    pub payout_address: SystemAccount<'info>,
+
+    #[account(mut, address = escrow.treasury)]
+    pub treasury: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(invoice_id: u64)]
+pub struct CreateInvoice<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    pub mint: Account<'info, anchor_token::Mint>,
+
+    #[account(token::mint = mint)]
+    pub recipient_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Invoice::INIT_SPACE,
+        seeds = [seeds::INVOICE, escrow.key().as_ref(), &invoice_id.to_le_bytes()],
+        bump
+    )]
+    pub invoice: Account<'info, Invoice>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct QuoteInvoice<'info> {
+    #[account(
+        mut,
+        seeds = [seeds::INVOICE, invoice.escrow.as_ref(), &invoice.invoice_id.to_le_bytes()],
+        bump = invoice.bump,
+        has_one = mint,
+    )]
+    pub invoice: Account<'info, Invoice>,
+
+    pub mint: Account<'info, anchor_token::Mint>,
+
+    /// CHECK: owner and feed id are checked when the price is read
+    pub price_update: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PayInvoice<'info> {
+    pub payer: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Required for escrows tagged with a jurisdiction
+    #[account(
+        seeds = [seeds::JURISDICTION_PAUSE, &escrow.jurisdiction.to_le_bytes()],
+        bump = jurisdiction_pause.bump,
+    )]
+    pub jurisdiction_pause: Option<Account<'info, JurisdictionPause>>,
+
+    #[account(
+        mut,
+        seeds = [seeds::INVOICE, escrow.key().as_ref(), &invoice.invoice_id.to_le_bytes()],
+        bump = invoice.bump,
+        has_one = escrow,
+        has_one = mint,
+        has_one = recipient_token_account,
+    )]
+    pub invoice: Account<'info, Invoice>,
+
+    pub mint: Account<'info, anchor_token::Mint>,
+
+    #[account(mut, token::mint = mint, token::authority = payer)]
+    pub payer_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(mut)]
+    pub recipient_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(mut, token::mint = mint, token::authority = escrow.treasury)]
+    pub treasury_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    /// Receives the invoice's referral share; required for referred invoices
+    #[account(mut, token::mint = mint, token::authority = invoice.referrer)]
+    pub referral_token_account: Option<Account<'info, anchor_token::TokenAccount>>,
+
+    /// CHECK: owner and feed id are checked when the price is read
+    pub price_update: UncheckedAccount<'info>,
+
+    /// Volume fee curve; without it the default treasury fee applies
+    #[account(seeds = [seeds::FEE_CONFIG], bump = fee_config.bump)]
+    pub fee_config: Option<Account<'info, FeeConfig>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct RefundAppFee<'info> {
+    #[account(mut)]
+    pub app_fee_destination: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = sender,
+        has_one = app_fee_destination @ EscrowError::InvalidAuthority,
+    )]
+    pub payment: Account<'info, PaymentAccount>,
+
+    #[account(mut)]
+    pub sender: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(milestones_id: u64)]
+pub struct CreateMilestones<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Required for escrows tagged with a jurisdiction
+    #[account(
+        seeds = [seeds::JURISDICTION_PAUSE, &escrow.jurisdiction.to_le_bytes()],
+        bump = jurisdiction_pause.bump,
+    )]
+    pub jurisdiction_pause: Option<Account<'info, JurisdictionPause>>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Milestones::INIT_SPACE,
+        seeds = [seeds::MILESTONES, payer.key().as_ref(), &milestones_id.to_le_bytes()],
+        bump
+    )]
+    pub milestones: Account<'info, Milestones>,
+
+    pub mint: Account<'info, anchor_token::Mint>,
+
+    #[account(
+        init,
+        payer = payer,
+        token::mint = mint,
+        token::authority = milestones,
+        seeds = [seeds::MILESTONE_VAULT, milestones.key().as_ref()],
+        bump
+    )]
+    pub milestone_vault: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(mut, token::mint = mint, token::authority = payer)]
+    pub payer_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(token::mint = mint)]
+    pub payee_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveMilestone<'info> {
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::MILESTONES, payer.key().as_ref(), &milestones.milestones_id.to_le_bytes()],
+        bump = milestones.bump,
+        has_one = payer @ EscrowError::InvalidAuthority,
+    )]
+    pub milestones: Account<'info, Milestones>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimMilestone<'info> {
+    pub payee: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        seeds = [
+            seeds::MILESTONES,
+            milestones.payer.as_ref(),
+            &milestones.milestones_id.to_le_bytes()
+        ],
+        bump = milestones.bump,
+        has_one = escrow,
+        has_one = payee @ EscrowError::InvalidAuthority,
+        has_one = payee_token_account,
+    )]
+    pub milestones: Account<'info, Milestones>,
+
+    #[account(
+        mut,
+        seeds = [seeds::MILESTONE_VAULT, milestones.key().as_ref()],
+        bump
+    )]
+    pub milestone_vault: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(mut)]
+    pub payee_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(mut, token::mint = milestones.mint, token::authority = escrow.treasury)]
+    pub treasury_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(mut, token::mint = milestones.mint)]
+    pub referral_token_account: Option<Account<'info, anchor_token::TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+    /// Volume fee curve; without it the default treasury fee applies
+    #[account(seeds = [seeds::FEE_CONFIG], bump = fee_config.bump)]
+    pub fee_config: Option<Account<'info, FeeConfig>>,
+}
+
+#[derive(Accounts)]
+pub struct DisputeMilestone<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            seeds::MILESTONES,
+            milestones.payer.as_ref(),
+            &milestones.milestones_id.to_le_bytes()
+        ],
+        bump = milestones.bump,
+    )]
+    pub milestones: Account<'info, Milestones>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveMilestoneDispute<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        seeds = [
+            seeds::MILESTONES,
+            milestones.payer.as_ref(),
+            &milestones.milestones_id.to_le_bytes()
+        ],
+        bump = milestones.bump,
+        has_one = escrow,
+    )]
+    pub milestones: Account<'info, Milestones>,
+
+    #[account(
+        mut,
+        seeds = [seeds::MILESTONE_VAULT, milestones.key().as_ref()],
+        bump
+    )]
+    pub milestone_vault: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(mut, token::mint = milestones.mint, token::authority = milestones.payer)]
+    pub payer_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(auction_id: u64)]
+pub struct CreateAuction<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + Auction::INIT_SPACE,
+        seeds = [seeds::AUCTION, seller.key().as_ref(), &auction_id.to_le_bytes()],
+        bump
+    )]
+    pub auction: Account<'info, Auction>,
+
+    pub mint: Account<'info, anchor_token::Mint>,
+
+    #[account(
+        init,
+        payer = seller,
+        token::mint = mint,
+        token::authority = auction,
+        seeds = [seeds::AUCTION_VAULT, auction.key().as_ref()],
+        bump
+    )]
+    pub auction_vault: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(token::mint = mint, token::authority = seller)]
+    pub seller_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitSealedBid<'info> {
+    pub bidder: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::AUCTION, auction.seller.as_ref(), &auction.auction_id.to_le_bytes()],
+        bump = auction.bump,
+    )]
+    pub auction: Account<'info, Auction>,
+
+    #[account(
+        mut,
+        seeds = [seeds::AUCTION_VAULT, auction.key().as_ref()],
+        bump
+    )]
+    pub auction_vault: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(mut, token::mint = auction.mint, token::authority = bidder)]
+    pub bidder_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[queue_computation_accounts("resolve_sealed_bids", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ResolveAuction<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::AUCTION, auction.seller.as_ref(), &auction.auction_id.to_le_bytes()],
+        bump = auction.bump,
+    )]
+    pub auction: Account<'info, Auction>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_RESOLVE_SEALED_BIDS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.maintenance_mode @ EscrowError::MaintenanceMode,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = cluster_account.key() == protocol_config
+            .active_cluster()
+            .unwrap_or(derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet))
+            @ EscrowError::InvalidCluster
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("resolve_sealed_bids")]
+#[derive(Accounts)]
+pub struct ResolveSealedBidsCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_RESOLVE_SEALED_BIDS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::AUCTION, auction.seller.as_ref(), &auction.auction_id.to_le_bytes()],
+        bump = auction.bump,
+    )]
+    pub auction: Account<'info, Auction>,
+
+    /// CHECK: checked against auction.pending_computation_offset in the handler
+    pub computation_account: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SettleAuction<'info> {
+    #[account(
+        mut,
+        seeds = [seeds::AUCTION, auction.seller.as_ref(), &auction.auction_id.to_le_bytes()],
+        bump = auction.bump,
+        has_one = seller_token_account,
+    )]
+    pub auction: Account<'info, Auction>,
+
+    #[account(
+        mut,
+        seeds = [seeds::AUCTION_VAULT, auction.key().as_ref()],
+        bump
+    )]
+    pub auction_vault: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(mut)]
+    pub seller_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(htlc_id: u64)]
+pub struct CreateHtlc<'info> {
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    #[account(
+        init,
+        payer = sender,
+        space = 8 + Htlc::INIT_SPACE,
+        seeds = [seeds::HTLC, sender.key().as_ref(), &htlc_id.to_le_bytes()],
+        bump
+    )]
+    pub htlc: Account<'info, Htlc>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimHtlc<'info> {
+    #[account(
+        mut,
+        close = sender,
+        seeds = [seeds::HTLC, htlc.sender.as_ref(), &htlc.htlc_id.to_le_bytes()],
+        bump = htlc.bump,
+        has_one = sender,
+        has_one = recipient,
+    )]
+    pub htlc: Account<'info, Htlc>,
+
+    #[account(mut)]
+    pub recipient: SystemAccount<'info>,
+
+    /// Receives the rent back
+    #[account(mut)]
+    pub sender: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RefundHtlc<'info> {
+    #[account(
+        mut,
+        close = sender,
+        seeds = [seeds::HTLC, htlc.sender.as_ref(), &htlc.htlc_id.to_le_bytes()],
+        bump = htlc.bump,
+        has_one = sender,
+    )]
+    pub htlc: Account<'info, Htlc>,
+
+    #[account(mut)]
+    pub sender: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SendPaymentTimelocked<'info> {
+    #[account(mut)]
+    pub sender: Signer<'info>,
+    /// Omitted when paying without a referrer
+    #[account(mut)]
+    pub referral: Option<SystemAccount<'info>>,
+    /// CHECK: only needed when the fallback route burns the referral share
+    #[account(mut, address = anchor_lang::solana_program::incinerator::ID)]
+    pub incinerator: Option<UncheckedAccount<'info>>,
+    #[account(mut, address = escrow.treasury)]
+    pub treasury: SystemAccount<'info>,
+    /// Required with a protocol-wide referral link
+    #[account(seeds = [seeds::PROTOCOL_CONFIG], bump = protocol_config.bump)]
+    pub protocol_config: Option<Account<'info, ProtocolConfig>>,
+    /// The sender's registered referrer, used when no referrer is passed
+    pub referral_link: Option<Account<'info, ReferralLink>>,
+    /// Referral campaign to boost the split with, if one is running
+    #[account(
+        mut,
+        seeds = [seeds::CAMPAIGN, escrow.key().as_ref(), &campaign.campaign_id.to_le_bytes()],
+        bump = campaign.bump,
+    )]
+    pub campaign: Option<Account<'info, Campaign>>,
+    /// Required while the escrow vests referral rewards
+    #[account(
+        mut,
+        seeds = [
+            seeds::REFERRAL_VESTING,
+            escrow.key().as_ref(),
+            referral_vesting.referrer.as_ref(),
+        ],
+        bump = referral_vesting.bump,
+    )]
+    pub referral_vesting: Option<Account<'info, ReferralVesting>>,
+    /// Required while the escrow settles referral rewards in USDC
+    #[account(
+        mut,
+        seeds = [
+            seeds::REFERRAL_USDC_LEDGER,
+            escrow.key().as_ref(),
+            referral_usdc_ledger.referrer.as_ref(),
+        ],
+        bump = referral_usdc_ledger.bump,
+    )]
+    pub referral_usdc_ledger: Option<Account<'info, ReferralUsdcLedger>>,
+    /// USDC quote for lamports; required with `referral_usdc_ledger`
+    #[account(
+        seeds = [seeds::FEE_RATE, Pubkey::default().as_ref()],
+        bump = sol_fee_rate.bump,
+    )]
+    pub sol_fee_rate: Option<Account<'info, FeeConversionRate>>,
+
+    /// Numbers this sender's plaintext payments across every asset
+    #[account(
+        init_if_needed,
+        payer = sender,
+        space = 8 + PaymentSequence::INIT_SPACE,
+        seeds = [seeds::PAYMENT_SEQUENCE, sender.key().as_ref()],
+        bump
+    )]
+    pub payment_sequence: Account<'info, PaymentSequence>,
+    #[account(
+        init,
+        payer = sender,
+        space = 8 + PaymentAccount::INIT_SPACE,
+        seeds = [
+            seeds::PAYMENTS,
+            sender.key().as_ref(),
+            seeds::SEQUENCED_PAYMENT,
+            &payment_sequence.next.to_le_bytes(),
+        ],
+        bump
+    )]
+    pub payment: Account<'info, PaymentAccount>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+    /// Required for escrows tagged with a jurisdiction
+    #[account(
+        seeds = [seeds::JURISDICTION_PAUSE, &escrow.jurisdiction.to_le_bytes()],
+        bump = jurisdiction_pause.bump,
+    )]
+    pub jurisdiction_pause: Option<Account<'info, JurisdictionPause>>,
+
+    pub system_program: Program<'info, System>,
+    /// Volume fee curve; without it the default treasury fee applies
+    #[account(seeds = [seeds::FEE_CONFIG], bump = fee_config.bump)]
+    pub fee_config: Option<Account<'info, FeeConfig>>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPayment<'info> {
+    pub recipient: Signer<'info>,
+
+    #[account(
+        mut,
+        close = sender,
+        has_one = sender @ EscrowError::InvalidAuthority,
+        has_one = recipient @ EscrowError::InvalidAuthority,
+        constraint = payment.is_time_locked() @ EscrowError::PaymentNotTimeLocked,
+    )]
+    pub payment: Account<'info, PaymentAccount>,
+
+    /// Receives the rent back
+    #[account(mut)]
+    pub sender: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReclaimExpired<'info> {
+    #[account(
+        mut,
+        close = sender,
+        has_one = sender @ EscrowError::InvalidAuthority,
+        constraint = payment.is_time_locked() @ EscrowError::PaymentNotTimeLocked,
+    )]
+    pub payment: Account<'info, PaymentAccount>,
+
+    #[account(mut)]
+    pub sender: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(deposit_id: u64)]
+pub struct DepositToEscrow<'info> {
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Required for escrows tagged with a jurisdiction
+    #[account(
+        seeds = [seeds::JURISDICTION_PAUSE, &escrow.jurisdiction.to_le_bytes()],
+        bump = jurisdiction_pause.bump,
+    )]
+    pub jurisdiction_pause: Option<Account<'info, JurisdictionPause>>,
+
+    #[account(
+        init,
+        payer = depositor,
+        space = 8 + EscrowDeposit::INIT_SPACE,
+        seeds = [seeds::ESCROW_DEPOSIT, depositor.key().as_ref(), &deposit_id.to_le_bytes()],
+        bump
+    )]
+    pub deposit: Account<'info, EscrowDeposit>,
+
+    /// SPL deposits only, with the three accounts below
+    pub mint: Option<Account<'info, anchor_token::Mint>>,
+
+    #[account(
+        init,
+        payer = depositor,
+        token::mint = mint,
+        token::authority = deposit,
+        seeds = [seeds::ESCROW_DEPOSIT_VAULT, deposit.key().as_ref()],
+        bump
+    )]
+    pub deposit_vault: Option<Account<'info, anchor_token::TokenAccount>>,
+
+    #[account(mut, token::mint = mint, token::authority = depositor)]
+    pub depositor_token_account: Option<Account<'info, anchor_token::TokenAccount>>,
+
+    pub token_program: Option<Program<'info, Token>>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseEscrow<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        seeds = [
+            seeds::ESCROW_DEPOSIT,
+            deposit.depositor.as_ref(),
+            &deposit.deposit_id.to_le_bytes()
+        ],
+        bump = deposit.bump,
+        has_one = escrow,
+        has_one = recipient,
+        constraint = deposit.status == EscrowDepositStatus::Held
+            @ EscrowError::InvalidDepositStatus,
+    )]
+    pub deposit: Account<'info, EscrowDeposit>,
+
+    #[account(mut)]
+    pub recipient: SystemAccount<'info>,
+    #[account(mut, address = escrow.treasury)]
+    pub treasury: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW_DEPOSIT_VAULT, deposit.key().as_ref()],
+        bump
+    )]
+    pub deposit_vault: Option<Account<'info, anchor_token::TokenAccount>>,
+    #[account(mut, token::mint = deposit.mint, token::authority = deposit.recipient)]
+    pub recipient_token_account: Option<Account<'info, anchor_token::TokenAccount>>,
+    #[account(mut, token::mint = deposit.mint, token::authority = escrow.treasury)]
+    pub treasury_token_account: Option<Account<'info, anchor_token::TokenAccount>>,
+    pub token_program: Option<Program<'info, Token>>,
+    /// Volume fee curve; without it the default treasury fee applies
+    #[account(seeds = [seeds::FEE_CONFIG], bump = fee_config.bump)]
+    pub fee_config: Option<Account<'info, FeeConfig>>,
+}
+
+#[derive(Accounts)]
+pub struct RefundEscrow<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            seeds::ESCROW_DEPOSIT,
+            deposit.depositor.as_ref(),
+            &deposit.deposit_id.to_le_bytes()
+        ],
+        bump = deposit.bump,
+        has_one = depositor,
+        constraint = deposit.status == EscrowDepositStatus::Held
+            @ EscrowError::InvalidDepositStatus,
+    )]
+    pub deposit: Account<'info, EscrowDeposit>,
+
+    #[account(mut)]
+    pub depositor: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW_DEPOSIT_VAULT, deposit.key().as_ref()],
+        bump
+    )]
+    pub deposit_vault: Option<Account<'info, anchor_token::TokenAccount>>,
+    #[account(mut, token::mint = deposit.mint, token::authority = deposit.depositor)]
+    pub depositor_token_account: Option<Account<'info, anchor_token::TokenAccount>>,
+    pub token_program: Option<Program<'info, Token>>,
+}
+
+#[derive(Accounts)]
+pub struct OpenDispute<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        seeds = [
+            seeds::ESCROW_DEPOSIT,
+            deposit.depositor.as_ref(),
+            &deposit.deposit_id.to_le_bytes()
+        ],
+        bump = deposit.bump,
+        has_one = escrow,
+        constraint = deposit.status == EscrowDepositStatus::Held
+            @ EscrowError::InvalidDepositStatus,
+    )]
+    pub deposit: Account<'info, EscrowDeposit>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + Dispute::INIT_SPACE,
+        seeds = [seeds::DISPUTE, deposit.key().as_ref()],
+        bump
+    )]
+    pub dispute: Account<'info, Dispute>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SubmitEvidenceHash<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [
+            seeds::ESCROW_DEPOSIT,
+            deposit.depositor.as_ref(),
+            &deposit.deposit_id.to_le_bytes()
+        ],
+        bump = deposit.bump,
+    )]
+    pub deposit: Account<'info, EscrowDeposit>,
+
+    #[account(
+        mut,
+        seeds = [seeds::DISPUTE, deposit.key().as_ref()],
+        bump = dispute.bump,
+        constraint = dispute.status == DisputeStatus::Open @ EscrowError::DisputeNotOpen,
+    )]
+    pub dispute: Account<'info, Dispute>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveDispute<'info> {
+    pub arbiter: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        has_one = arbiter @ EscrowError::InvalidAuthority,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        seeds = [
+            seeds::ESCROW_DEPOSIT,
+            deposit.depositor.as_ref(),
+            &deposit.deposit_id.to_le_bytes()
+        ],
+        bump = deposit.bump,
+        has_one = escrow,
+        has_one = depositor,
+        has_one = recipient,
+    )]
+    pub deposit: Account<'info, EscrowDeposit>,
+
+    #[account(
+        mut,
+        seeds = [seeds::DISPUTE, deposit.key().as_ref()],
+        bump = dispute.bump,
+        constraint = dispute.status == DisputeStatus::Open @ EscrowError::DisputeNotOpen,
+    )]
+    pub dispute: Account<'info, Dispute>,
+
+    #[account(mut)]
+    pub depositor: SystemAccount<'info>,
+    #[account(mut)]
+    pub recipient: SystemAccount<'info>,
+    #[account(mut, address = escrow.treasury)]
+    pub treasury: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW_DEPOSIT_VAULT, deposit.key().as_ref()],
+        bump
+    )]
+    pub deposit_vault: Option<Account<'info, anchor_token::TokenAccount>>,
+    #[account(mut, token::mint = deposit.mint, token::authority = deposit.depositor)]
+    pub depositor_token_account: Option<Account<'info, anchor_token::TokenAccount>>,
+    #[account(mut, token::mint = deposit.mint, token::authority = deposit.recipient)]
+    pub recipient_token_account: Option<Account<'info, anchor_token::TokenAccount>>,
+    #[account(mut, token::mint = deposit.mint, token::authority = escrow.treasury)]
+    pub treasury_token_account: Option<Account<'info, anchor_token::TokenAccount>>,
+    pub token_program: Option<Program<'info, Token>>,
+    /// Volume fee curve; without it the default treasury fee applies
+    #[account(seeds = [seeds::FEE_CONFIG], bump = fee_config.bump)]
+    pub fee_config: Option<Account<'info, FeeConfig>>,
+}
+
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct SetFeeConversionRate<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        has_one = admin @ EscrowError::InvalidAuthority,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + FeeConversionRate::INIT_SPACE,
+        seeds = [seeds::FEE_RATE, mint.as_ref()],
+        bump
+    )]
+    pub fee_rate: Account<'info, FeeConversionRate>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SendPaymentWithUsdcFees<'info> {
+    pub sender: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Required for escrows tagged with a jurisdiction
+    #[account(
+        seeds = [seeds::JURISDICTION_PAUSE, &escrow.jurisdiction.to_le_bytes()],
+        bump = jurisdiction_pause.bump,
+    )]
+    pub jurisdiction_pause: Option<Account<'info, JurisdictionPause>>,
+    /// Fee payer of a sponsored submission; must be in the relayer registry
+    pub relayer: Option<Signer<'info>>,
+    #[account(seeds = [seeds::RELAYER_REGISTRY], bump = relayer_registry.bump)]
+    pub relayer_registry: Option<Account<'info, RelayerRegistry>>,
+
+    #[account(
+        seeds = [seeds::FEE_RATE, mint.key().as_ref()],
+        bump = fee_rate.bump,
+    )]
+    pub fee_rate: Account<'info, FeeConversionRate>,
+
+    pub mint: Account<'info, anchor_token::Mint>,
+
+    #[account(mut, token::mint = mint, token::authority = sender)]
+    pub sender_token_account: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut, token::mint = mint)]
+    pub recipient_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(mut, token::mint = USDC_MINT, token::authority = sender)]
+    pub sender_usdc_account: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut, token::mint = USDC_MINT, token::authority = escrow.treasury)]
+    pub treasury_usdc_account: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut, token::mint = USDC_MINT)]
+    pub referral_usdc_account: Account<'info, anchor_token::TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+    /// Volume fee curve; without it the default treasury fee applies
+    #[account(seeds = [seeds::FEE_CONFIG], bump = fee_config.bump)]
+    pub fee_config: Option<Account<'info, FeeConfig>>,
+}
+
+#[derive(Accounts)]
+pub struct SetVaultSigners<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + VaultSigners::INIT_SPACE,
+        seeds = [seeds::VAULT_SIGNERS, escrow.key().as_ref()],
+        bump
+    )]
+    pub vault_signers: Account<'info, VaultSigners>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawEscrowVault<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW_VAULT, escrow.key().as_ref(), escrow_vault.mint.as_ref()],
+        bump
+    )]
+    pub escrow_vault: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(mut, token::mint = escrow_vault.mint)]
+    pub destination_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct ProposeWithdrawal<'info> {
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        seeds = [seeds::VAULT_SIGNERS, escrow.key().as_ref()],
+        bump = vault_signers.bump,
+    )]
+    pub vault_signers: Account<'info, VaultSigners>,
+
+    #[account(
+        seeds = [seeds::ESCROW_VAULT, escrow.key().as_ref(), escrow_vault.mint.as_ref()],
+        bump
+    )]
+    pub escrow_vault: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(token::mint = escrow_vault.mint)]
+    pub destination_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(
+        init,
+        payer = proposer,
+        space = 8 + WithdrawalProposal::INIT_SPACE,
+        seeds = [seeds::WITHDRAWAL_PROPOSAL, escrow.key().as_ref(), &proposal_id.to_le_bytes()],
+        bump
+    )]
+    pub proposal: Account<'info, WithdrawalProposal>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveWithdrawal<'info> {
+    pub signer: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::VAULT_SIGNERS, proposal.escrow.as_ref()],
+        bump = vault_signers.bump,
+    )]
+    pub vault_signers: Account<'info, VaultSigners>,
+
+    #[account(
+        mut,
+        seeds = [
+            seeds::WITHDRAWAL_PROPOSAL,
+            proposal.escrow.as_ref(),
+            &proposal.proposal_id.to_le_bytes(),
+        ],
+        bump = proposal.bump,
+    )]
+    pub proposal: Account<'info, WithdrawalProposal>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteWithdrawal<'info> {
+    pub signer: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        seeds = [seeds::VAULT_SIGNERS, escrow.key().as_ref()],
+        bump = vault_signers.bump,
+    )]
+    pub vault_signers: Account<'info, VaultSigners>,
+
+    #[account(
+        mut,
+        seeds = [seeds::WITHDRAWAL_PROPOSAL, escrow.key().as_ref(), &proposal.proposal_id.to_le_bytes()],
+        bump = proposal.bump,
+        has_one = escrow,
+        has_one = escrow_vault,
+        has_one = destination_token_account,
+        close = proposer,
+    )]
+    pub proposal: Account<'info, WithdrawalProposal>,
+
+    /// CHECK: receives the proposal's rent; checked against `proposal.proposer`
+    #[account(mut, address = proposal.proposer)]
+    pub proposer: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub escrow_vault: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(mut)]
+    pub destination_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SetRecoveryGuardians<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + RecoveryConfig::INIT_SPACE,
+        seeds = [seeds::RECOVERY, escrow.key().as_ref()],
+        bump
+    )]
+    pub recovery_config: Account<'info, RecoveryConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GuardianRecoveryAction<'info> {
+    pub guardian: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::RECOVERY, recovery_config.escrow.as_ref()],
+        bump = recovery_config.bump,
+    )]
+    pub recovery_config: Account<'info, RecoveryConfig>,
+}
+
+#[derive(Accounts)]
+pub struct VetoRecovery<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        seeds = [seeds::RECOVERY, escrow.key().as_ref()],
+        bump = recovery_config.bump,
+    )]
+    pub recovery_config: Account<'info, RecoveryConfig>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteRecovery<'info> {
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        seeds = [seeds::RECOVERY, escrow.key().as_ref()],
+        bump = recovery_config.bump,
+    )]
+    pub recovery_config: Account<'info, RecoveryConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(campaign_id: u64)]
+pub struct CreateCampaign<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Campaign::INIT_SPACE,
+        seeds = [seeds::CAMPAIGN, escrow.key().as_ref(), &campaign_id.to_le_bytes()],
+        bump
+    )]
+    pub campaign: Account<'info, Campaign>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseCampaign<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        seeds = [seeds::CAMPAIGN, escrow.key().as_ref(), &campaign.campaign_id.to_le_bytes()],
+        bump = campaign.bump,
+        close = owner,
+    )]
+    pub campaign: Account<'info, Campaign>,
+}
+
+#[derive(Accounts)]
+pub struct SetFeeConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        has_one = admin @ EscrowError::InvalidAuthority,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + FeeConfig::INIT_SPACE,
+        seeds = [seeds::FEE_CONFIG],
+        bump
+    )]
+    pub fee_config: Account<'info, FeeConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(referrer: Pubkey)]
+pub struct InitReferralVesting<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ReferralVesting::INIT_SPACE,
+        seeds = [seeds::REFERRAL_VESTING, escrow.key().as_ref(), referrer.as_ref()],
+        bump
+    )]
+    pub referral_vesting: Account<'info, ReferralVesting>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(referrer: Pubkey)]
+pub struct InitReferralUsdcLedger<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ReferralUsdcLedger::INIT_SPACE,
+        seeds = [seeds::REFERRAL_USDC_LEDGER, escrow.key().as_ref(), referrer.as_ref()],
+        bump
+    )]
+    pub referral_usdc_ledger: Account<'info, ReferralUsdcLedger>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleUsdcReferralRewards<'info> {
+    #[account(address = escrow.treasury @ EscrowError::InvalidAuthority)]
+    pub treasury: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        seeds = [
+            seeds::REFERRAL_USDC_LEDGER,
+            escrow.key().as_ref(),
+            referral_usdc_ledger.referrer.as_ref(),
+        ],
+        bump = referral_usdc_ledger.bump,
+    )]
+    pub referral_usdc_ledger: Account<'info, ReferralUsdcLedger>,
+
+    #[account(mut, token::mint = USDC_MINT, token::authority = treasury)]
+    pub treasury_usdc_account: Account<'info, anchor_token::TokenAccount>,
+    #[account(
+        mut,
+        token::mint = USDC_MINT,
+        token::authority = referral_usdc_ledger.referrer,
+    )]
+    pub referrer_usdc_account: Account<'info, anchor_token::TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimVestedRewards<'info> {
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            seeds::REFERRAL_VESTING,
+            referral_vesting.escrow.as_ref(),
+            referrer.key().as_ref(),
+        ],
+        bump = referral_vesting.bump,
+    )]
+    pub referral_vesting: Account<'info, ReferralVesting>,
+}
+
+#[derive(Accounts)]
+pub struct SetComplianceOfficer<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + Quarantine::INIT_SPACE,
+        seeds = [seeds::QUARANTINE, escrow.key().as_ref()],
+        bump
+    )]
+    pub quarantine: Account<'info, Quarantine>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReviewQuarantined<'info> {
+    pub compliance_officer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        has_one = compliance_officer @ EscrowError::InvalidAuthority,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        seeds = [seeds::QUARANTINE, escrow.key().as_ref()],
+        bump = quarantine.bump,
+    )]
+    pub quarantine: Account<'info, Quarantine>,
+
+    #[account(mut)]
+    pub escrow_vault: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut)]
+    pub recipient_token_account: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut)]
+    pub treasury_token_account: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut)]
+    pub referral_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct ComplianceAction<'info> {
+    pub compliance_officer: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        has_one = compliance_officer @ EscrowError::InvalidAuthority,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        seeds = [seeds::QUARANTINE, escrow.key().as_ref()],
+        bump = quarantine.bump,
+    )]
+    pub quarantine: Account<'info, Quarantine>,
+}
+
+#[derive(Accounts)]
+#[instruction(template_id: u64)]
+pub struct RegisterEscrowTemplate<'info> {
+    #[account(mut)]
+    pub creator: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = creator,
+        space = 8 + EscrowTemplate::INIT_SPACE,
+        seeds = [seeds::ESCROW_TEMPLATE, creator.key().as_ref(), &template_id.to_le_bytes()],
+        bump
+    )]
+    pub template: Account<'info, EscrowTemplate>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("init_escrow_stats", owner)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct CreateEscrowFromTemplate<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [
+            seeds::ESCROW_TEMPLATE,
+            template.creator.as_ref(),
+            &template.template_id.to_le_bytes(),
+        ],
+        bump = template.bump,
+    )]
+    pub template: Account<'info, EscrowTemplate>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + EscrowAccount::INIT_SPACE,
+        seeds = [seeds::ESCROW, owner.key().as_ref()],
+        bump
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = owner,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_INIT_ESCROW_STATS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.maintenance_mode @ EscrowError::MaintenanceMode,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = cluster_account.key() == protocol_config
+            .active_cluster()
+            .unwrap_or(derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet))
+            @ EscrowError::InvalidCluster
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[derive(Accounts)]
+#[instruction(viewer: Pubkey)]
+pub struct AddViewer<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + Viewer::INIT_SPACE,
+        seeds = [seeds::VIEWER, escrow.key().as_ref(), viewer.as_ref()],
+        bump
+    )]
+    pub viewer: Account<'info, Viewer>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveViewer<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        seeds = [seeds::VIEWER, escrow.key().as_ref(), viewer.viewer.as_ref()],
+        bump = viewer.bump,
+        close = owner,
+    )]
+    pub viewer: Account<'info, Viewer>,
+}
+
+#[queue_computation_accounts("share_escrow_stats", authority)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct ShareStatsWithViewer<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// Required when `authority` is not the escrow owner
+    #[account(
+        mut,
+        seeds = [seeds::ANALYTICS_OPERATOR, escrow.key().as_ref(), authority.key().as_ref()],
+        bump = analytics_operator.bump,
+    )]
+    pub analytics_operator: Option<Account<'info, AnalyticsOperator>>,
+
+    /// Read-only delegate; an alternative to `analytics_operator`
+    #[account(
+        seeds = [seeds::VIEWER, escrow.key().as_ref(), authority.key().as_ref()],
+        bump = viewer.bump,
+    )]
+    pub viewer: Option<Account<'info, Viewer>>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = authority,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SHARE_ESCROW_STATS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.maintenance_mode @ EscrowError::MaintenanceMode,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = cluster_account.key() == protocol_config
+            .active_cluster()
+            .unwrap_or(derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet))
+            @ EscrowError::InvalidCluster
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("share_escrow_stats")]
+#[derive(Accounts)]
+pub struct ShareEscrowStatsCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_SHARE_ESCROW_STATS)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    pub escrow: Account<'info, EscrowAccount>,
+}
+
+#[derive(Accounts)]
+#[instruction(archive_id: u64)]
+pub struct ArchivePayments<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        has_one = admin @ EscrowError::InvalidAuthority,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + Archive::INIT_SPACE,
+        seeds = [seeds::ARCHIVE, &archive_id.to_le_bytes()],
+        bump
+    )]
+    pub archive: Account<'info, Archive>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + RebatePool::INIT_SPACE,
+        seeds = [seeds::REBATE_POOL],
+        bump
+    )]
+    pub rebate_pool: Account<'info, RebatePool>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct HealthCheck<'info> {
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+    #[account(
+        seeds = [seeds::QUARANTINE, escrow.key().as_ref()],
+        bump = quarantine.bump,
+    )]
+    pub quarantine: Option<Account<'info, Quarantine>>,
+}
+
+#[derive(Accounts)]
+pub struct SetTreasuryAccrual<'info> {
+    /// Checked in the handler with `authorize_capability(Fees)`
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + TreasuryVault::INIT_SPACE,
+        seeds = [seeds::TREASURY_VAULT, escrow.key().as_ref()],
+        bump
+    )]
+    pub treasury_vault: Account<'info, TreasuryVault>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SweepTreasury<'info> {
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        seeds = [seeds::TREASURY_VAULT, escrow.key().as_ref()],
+        bump = treasury_vault.bump,
+    )]
+    pub treasury_vault: Account<'info, TreasuryVault>,
+
+    #[account(mut, address = escrow.treasury)]
+    pub treasury: SystemAccount<'info>,
+
+    /// Receives the crank reward, if any
+    pub cranker: Option<Signer<'info>>,
+
+    /// Pays the crank reward when the owner has configured one
+    #[account(
+        mut,
+        seeds = [seeds::COMPUTE_BUDGET, escrow.key().as_ref()],
+        bump = compute_budget_vault.bump,
+    )]
+    pub compute_budget_vault: Option<Account<'info, ComputeBudgetVault>>,
+}
+
+#[derive(Accounts)]
+#[instruction(scope: Pubkey)]
+pub struct RegisterReferral<'info> {
+    #[account(mut)]
+    pub referee: Signer<'info>,
+
+    /// Required for a link scoped to this escrow
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Option<Account<'info, EscrowAccount>>,
+
+    #[account(seeds = [seeds::PROTOCOL_CONFIG], bump = protocol_config.bump)]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        init,
+        payer = referee,
+        space = 8 + ReferralLink::INIT_SPACE,
+        seeds = [seeds::REFERRAL_LINK, scope.as_ref(), referee.key().as_ref()],
+        bump
+    )]
+    pub referral_link: Account<'info, ReferralLink>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("init_spend_cap", sender)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct SetSpendCap<'info> {
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = sender,
+        space = 8 + SpendCapAccount::INIT_SPACE,
+        seeds = [seeds::SPEND_CAP, sender.key().as_ref()],
+        bump
+    )]
+    pub spend_cap: Box<Account<'info, SpendCapAccount>>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = sender,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_INIT_SPEND_CAP)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.maintenance_mode @ EscrowError::MaintenanceMode,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = cluster_account.key() == protocol_config
+            .active_cluster()
+            .unwrap_or(derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet))
+            @ EscrowError::InvalidCluster
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("init_spend_cap")]
+#[derive(Accounts)]
+pub struct InitSpendCapCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_INIT_SPEND_CAP)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::SPEND_CAP, spend_cap.sender.as_ref()],
+        bump = spend_cap.bump,
+    )]
+    pub spend_cap: Account<'info, SpendCapAccount>,
+
+    /// CHECK: checked against spend_cap.pending_computation_offset in the handler
+    pub computation_account: UncheckedAccount<'info>,
+}
+
+#[queue_computation_accounts("check_spend_cap", sender)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct AuthorizeCappedSpend<'info> {
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::SPEND_CAP, sender.key().as_ref()],
+        bump = spend_cap.bump,
+    )]
+    pub spend_cap: Box<Account<'info, SpendCapAccount>>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = sender,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_SPEND_CAP)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.maintenance_mode @ EscrowError::MaintenanceMode,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = cluster_account.key() == protocol_config
+            .active_cluster()
+            .unwrap_or(derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet))
+            @ EscrowError::InvalidCluster
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("check_spend_cap")]
+#[derive(Accounts)]
+pub struct CheckSpendCapCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_SPEND_CAP)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::SPEND_CAP, spend_cap.sender.as_ref()],
+        bump = spend_cap.bump,
+    )]
+    pub spend_cap: Account<'info, SpendCapAccount>,
+
+    /// CHECK: checked against spend_cap.pending_computation_offset in the handler
+    pub computation_account: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetInsurancePool<'info> {
+    /// Checked in the handler with `authorize_capability(Fees)`
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + InsurancePool::INIT_SPACE,
+        seeds = [seeds::INSURANCE_POOL, escrow.key().as_ref()],
+        bump
+    )]
+    pub insurance_pool: Account<'info, InsurancePool>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FileClaim<'info> {
+    #[account(mut)]
+    pub claimant: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::INSURANCE_POOL, insurance_pool.escrow.as_ref()],
+        bump = insurance_pool.bump,
+    )]
+    pub insurance_pool: Account<'info, InsurancePool>,
+
+    /// Only the payment's recipient may claim on it
+    #[account(constraint = payment.recipient == claimant.key() @ EscrowError::InvalidAuthority)]
+    pub payment: Account<'info, PaymentAccount>,
+
+    #[account(
+        init,
+        payer = claimant,
+        space = 8 + InsuranceClaim::INIT_SPACE,
+        seeds = [seeds::INSURANCE_CLAIM, insurance_pool.key().as_ref(), payment.key().as_ref()],
+        bump
+    )]
+    pub claim: Account<'info, InsuranceClaim>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AdjudicateClaim<'info> {
+    pub guardian: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::INSURANCE_POOL, insurance_pool.escrow.as_ref()],
+        bump = insurance_pool.bump,
+        has_one = guardian @ EscrowError::InvalidAuthority,
+    )]
+    pub insurance_pool: Account<'info, InsurancePool>,
+
+    #[account(
+        mut,
+        seeds = [seeds::INSURANCE_CLAIM, insurance_pool.key().as_ref(), claim.payment.as_ref()],
+        bump = claim.bump,
+        has_one = claimant,
+    )]
+    pub claim: Account<'info, InsuranceClaim>,
+
+    #[account(mut)]
+    pub claimant: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseInsuranceTokens<'info> {
+    pub guardian: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::INSURANCE_POOL, insurance_pool.escrow.as_ref()],
+        bump = insurance_pool.bump,
+        has_one = guardian @ EscrowError::InvalidAuthority,
+    )]
+    pub insurance_pool: Account<'info, InsurancePool>,
+
+    #[account(mut, token::authority = insurance_pool)]
+    pub pool_token_account: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut, token::mint = pool_token_account.mint)]
+    pub destination: Account<'info, anchor_token::TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct SetMatchingPool<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + MatchingPool::INIT_SPACE,
+        seeds = [seeds::MATCHING_POOL, escrow.key().as_ref()],
+        bump
+    )]
+    pub matching_pool: Account<'info, MatchingPool>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FundMatchingPool<'info> {
+    #[account(mut)]
+    pub sponsor: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::MATCHING_POOL, matching_pool.escrow.as_ref()],
+        bump = matching_pool.bump,
+    )]
+    pub matching_pool: Account<'info, MatchingPool>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[queue_computation_accounts("compute_match", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct RequestMatch<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::MATCHING_POOL, matching_pool.escrow.as_ref()],
+        bump = matching_pool.bump,
+    )]
+    pub matching_pool: Box<Account<'info, MatchingPool>>,
+
+    #[account(
+        constraint = payment.asset_mint == Pubkey::default()
+            && payment.recipient == matching_pool.recipient
+            && payment.timestamp >= matching_pool.created_at
+            @ EscrowError::PaymentNotMatchable,
+    )]
+    pub payment: Box<Account<'info, PaymentAccount>>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PaymentMatch::INIT_SPACE,
+        seeds = [seeds::PAYMENT_MATCH, matching_pool.key().as_ref(), payment.key().as_ref()],
+        bump
+    )]
+    pub payment_match: Box<Account<'info, PaymentMatch>>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_COMPUTE_MATCH)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.maintenance_mode @ EscrowError::MaintenanceMode,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = cluster_account.key() == protocol_config
+            .active_cluster()
+            .unwrap_or(derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet))
+            @ EscrowError::InvalidCluster
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("compute_match")]
+#[derive(Accounts)]
+pub struct ComputeMatchCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_COMPUTE_MATCH)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::MATCHING_POOL, matching_pool.escrow.as_ref()],
+        bump = matching_pool.bump,
+    )]
+    pub matching_pool: Account<'info, MatchingPool>,
+
+    #[account(
+        mut,
+        seeds = [seeds::PAYMENT_MATCH, matching_pool.key().as_ref(), payment_match.payment.as_ref()],
+        bump = payment_match.bump,
+    )]
+    pub payment_match: Account<'info, PaymentMatch>,
+
+    #[account(mut, address = matching_pool.recipient)]
+    pub recipient: SystemAccount<'info>,
+
+    /// CHECK: checked against payment_match.pending_computation_offset in the handler
+    pub computation_account: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RedeemFeeVoucher<'info> {
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.voucher_mint_authority != Pubkey::default()
+            @ EscrowError::InvalidFeeVoucher,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    /// One-of-one voucher minted by the escrow's voucher authority
+    #[account(
+        mut,
+        constraint = voucher_mint.mint_authority
+            == COption::Some(escrow.voucher_mint_authority)
+            && voucher_mint.decimals == 0
+            @ EscrowError::InvalidFeeVoucher,
+    )]
+    pub voucher_mint: Account<'info, anchor_token::Mint>,
+
+    #[account(
+        mut,
+        token::mint = voucher_mint,
+        token::authority = sender,
+    )]
+    pub voucher_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    #[account(
+        init,
+        payer = sender,
+        space = 8 + VoucherRedemption::INIT_SPACE,
+        seeds = [seeds::FEE_VOUCHER, voucher_mint.key().as_ref()],
+        bump
+    )]
+    pub voucher_redemption: Account<'info, VoucherRedemption>,
+
+    #[account(
+        init_if_needed,
+        payer = sender,
+        space = 8 + FeeWaiver::INIT_SPACE,
+        seeds = [seeds::FEE_WAIVER, escrow.key().as_ref(), sender.key().as_ref()],
+        bump
+    )]
+    pub fee_waiver: Account<'info, FeeWaiver>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(jurisdiction: u16)]
+pub struct SetEscrowJurisdiction<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        has_one = admin @ EscrowError::InvalidAuthority,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + JurisdictionPause::INIT_SPACE,
+        seeds = [seeds::JURISDICTION_PAUSE, &jurisdiction.to_le_bytes()],
+        bump
+    )]
+    pub jurisdiction_pause: Account<'info, JurisdictionPause>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(jurisdiction: u16)]
+pub struct SetJurisdictionPaused<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        has_one = admin @ EscrowError::InvalidAuthority,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + JurisdictionPause::INIT_SPACE,
+        seeds = [seeds::JURISDICTION_PAUSE, &jurisdiction.to_le_bytes()],
+        bump
+    )]
+    pub jurisdiction_pause: Account<'info, JurisdictionPause>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetSettlementWindow<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + SettlementWindow::INIT_SPACE,
+        seeds = [seeds::SETTLEMENT_WINDOW, escrow.key().as_ref()],
+        bump
+    )]
+    pub settlement_window: Account<'info, SettlementWindow>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClearSettlementWindow<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+        constraint = escrow.authority() == owner.key(),
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        close = owner,
+        seeds = [seeds::SETTLEMENT_WINDOW, escrow.key().as_ref()],
+        bump = settlement_window.bump,
+    )]
+    pub settlement_window: Account<'info, SettlementWindow>,
+}
+
+#[derive(Accounts)]
+pub struct DrainSettlementWindow<'info> {
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        mut,
+        seeds = [seeds::SETTLEMENT_WINDOW, escrow.key().as_ref()],
+        bump = settlement_window.bump,
+    )]
+    pub settlement_window: Account<'info, SettlementWindow>,
+
+    #[account(mut)]
+    pub escrow_vault: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut)]
+    pub recipient_token_account: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut)]
+    pub treasury_token_account: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut)]
+    pub referral_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    /// Pays the crank reward when the owner has configured one
+    #[account(
+        mut,
+        seeds = [seeds::COMPUTE_BUDGET, escrow.key().as_ref()],
+        bump = compute_budget_vault.bump,
+    )]
+    pub compute_budget_vault: Option<Account<'info, ComputeBudgetVault>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+#[queue_computation_accounts("prove_payment_amount", prover)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64, bucket_min: u64, bucket_max: u64)]
+pub struct ProvePayment<'info> {
+    #[account(mut)]
+    pub prover: Signer<'info>,
+
+    pub payment: Box<Account<'info, PaymentAccount>>,
+
+    #[account(
+        init,
+        payer = prover,
+        space = 8 + PaymentProof::INIT_SPACE,
+        seeds = [
+            seeds::PAYMENT_PROOF,
+            payment.key().as_ref(),
+            &bucket_min.to_le_bytes(),
+            &bucket_max.to_le_bytes()
+        ],
+        bump
+    )]
+    pub payment_proof: Box<Account<'info, PaymentProof>>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = prover,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROVE_PAYMENT_AMOUNT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.maintenance_mode @ EscrowError::MaintenanceMode,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = cluster_account.key() == protocol_config
+            .active_cluster()
+            .unwrap_or(derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet))
+            @ EscrowError::InvalidCluster
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
+    )]
+    pub pool_account: Account<'info, FeePool>,
+
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("prove_payment_amount")]
+#[derive(Accounts)]
+pub struct ProvePaymentAmountCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROVE_PAYMENT_AMOUNT)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        seeds = [
+            seeds::PAYMENT_PROOF,
+            payment_proof.payment.as_ref(),
+            &payment_proof.bucket_min.to_le_bytes(),
+            &payment_proof.bucket_max.to_le_bytes()
+        ],
+        bump = payment_proof.bump,
+    )]
+    pub payment_proof: Account<'info, PaymentProof>,
+
+    /// CHECK: checked against payment_proof.pending_computation_offset in the handler
+    pub computation_account: UncheckedAccount<'info>,
+}
+
+#[queue_computation_accounts("calculate_fees", payer)]
+#[derive(Accounts)]
+#[instruction(computation_offset: u64)]
+pub struct QuoteFees<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        init_if_needed,
+        space = 9,
+        payer = payer,
+        seeds = [&SIGN_PDA_SEED],
+        bump,
+        address = derive_sign_pda!(),
+    )]
+    pub sign_pda_account: Account<'info, SignerAccount>,
+
+    #[account(
+        address = derive_mxe_pda!()
+    )]
+    pub mxe_account: Account<'info, MXEAccount>,
+
+    #[account(
+        mut,
+        address = derive_mempool_pda!()
+    )]
+    /// CHECK: mempool_account
+    pub mempool_account: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_execpool_pda!()
+    )]
+    /// CHECK: executing_pool
+    pub executing_pool: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address = derive_comp_pda!(computation_offset)
+    )]
+    /// CHECK: computation_account
+    pub computation_account: UncheckedAccount<'info>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CALCULATE_FEES)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(
+        seeds = [seeds::PROTOCOL_CONFIG],
+        bump = protocol_config.bump,
+        constraint = !protocol_config.maintenance_mode @ EscrowError::MaintenanceMode,
+    )]
+    pub protocol_config: Account<'info, ProtocolConfig>,
+
+    #[account(
+        mut,
+        constraint = cluster_account.key() == protocol_config
+            .active_cluster()
+            .unwrap_or(derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet))
+            @ EscrowError::InvalidCluster
+    )]
+    pub cluster_account: Account<'info, Cluster>,
+
+    #[account(
+        mut,
+        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
     )]
     pub pool_account: Account<'info, FeePool>,
 
-    #[account(
-        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
-    )]
-    pub clock_account: Account<'info, ClockAccount>,
+    #[account(
+        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
+    )]
+    pub clock_account: Account<'info, ClockAccount>,
+
+    pub system_program: Program<'info, System>,
+    pub arcium_program: Program<'info, Arcium>,
+}
+
+#[callback_accounts("calculate_fees")]
+#[derive(Accounts)]
+pub struct CalculateFeesCallback<'info> {
+    pub arcium_program: Program<'info, Arcium>,
+
+    #[account(
+        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CALCULATE_FEES)
+    )]
+    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+
+    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: instructions_sysvar
+    pub instructions_sysvar: AccountInfo<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AuthorizePullPayment<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut, token::authority = payer)]
+    pub payer_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    /// The merchant's escrow; its authority is the merchant
+    #[account(
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PullMandate::INIT_SPACE,
+        seeds = [seeds::PULL_MANDATE, payer_token_account.key().as_ref()],
+        bump
+    )]
+    pub mandate: Account<'info, PullMandate>,
+
+    /// CHECK: data-less PDA approved as the SPL delegate; only signs
+    #[account(seeds = [seeds::PULL_DELEGATE, mandate.key().as_ref()], bump)]
+    pub pull_delegate: UncheckedAccount<'info>,
+
+    #[account(seeds = [seeds::MINT_ALLOWLIST], bump = mint_allowlist.bump)]
+    pub mint_allowlist: Account<'info, MintAllowlist>,
+
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PullPayment<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [seeds::ESCROW, escrow.owner.as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, EscrowAccount>,
+    /// Required for escrows tagged with a jurisdiction
+    #[account(
+        seeds = [seeds::JURISDICTION_PAUSE, &escrow.jurisdiction.to_le_bytes()],
+        bump = jurisdiction_pause.bump,
+    )]
+    pub jurisdiction_pause: Option<Account<'info, JurisdictionPause>>,
+
+    #[account(
+        mut,
+        seeds = [seeds::PULL_MANDATE, payer_token_account.key().as_ref()],
+        bump = mandate.bump,
+        has_one = escrow,
+        has_one = payer_token_account,
+    )]
+    pub mandate: Account<'info, PullMandate>,
+
+    #[account(mut)]
+    pub payer_token_account: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut, token::mint = mandate.mint, token::authority = mandate.merchant)]
+    pub merchant_token_account: Account<'info, anchor_token::TokenAccount>,
+    #[account(mut, token::mint = mandate.mint, token::authority = escrow.treasury)]
+    pub treasury_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    /// CHECK: data-less PDA holding the mandate's SPL delegation
+    #[account(
+        seeds = [seeds::PULL_DELEGATE, mandate.key().as_ref()],
+        bump = mandate.delegate_bump,
+    )]
+    pub pull_delegate: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    /// Volume fee curve; without it the default treasury fee applies
+    #[account(seeds = [seeds::FEE_CONFIG], bump = fee_config.bump)]
+    pub fee_config: Option<Account<'info, FeeConfig>>,
+}
+
+#[derive(Accounts)]
+pub struct RevokePullMandate<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(
+        mut,
+        close = payer,
+        seeds = [seeds::PULL_MANDATE, payer_token_account.key().as_ref()],
+        bump = mandate.bump,
+        has_one = payer,
+        has_one = payer_token_account,
+    )]
+    pub mandate: Account<'info, PullMandate>,
+
+    #[account(mut)]
+    pub payer_token_account: Account<'info, anchor_token::TokenAccount>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+// Updated EscrowAccount with encrypted statistics
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct EscrowAccount {
+    pub owner: Pubkey,
+    pub total_fund_regulated: u64, // Keep for backwards compatibility
+    pub last_updated: i64,
+    pub active: bool,
+    pub treasury: Pubkey,
+    pub bump: u8,
+    // New fields for Arcium encryption
+    pub nonce: u128,
+    /// Encrypted statistics: [total_payments, total_volume,
+    /// total_fees_collected, recent_volume, total_refunds, refund_volume,
+    /// asset_volumes[STATS_ASSET_COUNT]]
+    pub encrypted_stats: [[u8; 32]; ESCROW_STATS_FIELDS],
+    /// Offset of the queued computation allowed to overwrite `encrypted_stats`
+    pub pending_computation_offset: Option<u64>,
+    /// Slot at which the pending computation was queued
+    pub pending_since_slot: u64,
+    /// Vault payments at or above this need `approver`'s sign-off; 0 disables
+    pub approval_threshold: u64,
+    pub approver: Pubkey,
+    pub approval_window_secs: i64,
+    /// Time up to which the encrypted recent volume has been decayed
+    pub recent_volume_decayed_at: i64,
+    /// Lifetime volume at which `check_circuit_breaker` pauses the escrow; 0 disables
+    pub circuit_breaker_threshold: u64,
+    /// Offset of the queued circuit breaker check, if any
+    pub circuit_breaker_check_offset: Option<u64>,
+    /// Where the referral share goes when a payment has no referrer
+    pub referral_fallback: ReferralFallback,
+    /// Treasury fee charged on `send_tip`, in basis points; 0 waives it
+    pub tip_treasury_fee_bps: u16,
+    /// Ed25519 key merchant backends use to authenticate off-chain
+    /// notifications for this escrow; default means none registered
+    pub webhook_key: Pubkey,
+    /// Vault withdrawals at or above this amount need M-of-N approval
+    /// through a `WithdrawalProposal`; 0 disables the multisig path
+    pub withdrawal_multisig_threshold: u64,
+    /// Owner installed by social recovery; default means `owner` is still
+    /// in control. `owner` stays the PDA seed either way.
+    pub recovered_owner: Pubkey,
+    /// Route SOL referral rewards into per-referrer vesting accounts
+    /// instead of paying them out instantly
+    pub vest_referral_rewards: bool,
+    /// Reviews quarantined vault payments; default means no compliance
+    /// role, and a tripped circuit breaker pauses the escrow instead
+    pub compliance_officer: Pubkey,
+    /// Set by the circuit breaker when a compliance officer is configured;
+    /// vault payments are parked in the `Quarantine` queue while it holds
+    pub quarantine_active: bool,
+    /// Accrue SOL treasury fees in the `TreasuryVault` instead of paying
+    /// them out on every payment
+    pub accrue_treasury_fees: bool,
+    /// Pay referral shares to referrers; when off, the share goes to the
+    /// treasury and payments need no referral account
+    pub referrals_enabled: bool,
+    /// Share of each SOL payment routed to the `InsurancePool`, in basis
+    /// points of the payment
+    pub insurance_bps: u16,
+    /// Treasury proposed by the owner, awaiting its own `accept_treasury`
+    pub pending_treasury: Option<Pubkey>,
+    /// Bumped once by every instruction that mutates the escrow and stamped
+    /// on the events it emits, so indexers can spot gaps and backfill
+    pub sequence: u64,
+    /// Mint authority of the fee vouchers this escrow accepts; default
+    /// when vouchers are off
+    pub voucher_mint_authority: Pubkey,
+    /// Regulatory tag set by the protocol admin; `UNTAGGED_JURISDICTION`
+    /// when none applies
+    pub jurisdiction: u16,
+    /// Program consulted for this escrow's fee split; default when the
+    /// protocol split applies
+    pub fee_hook: Pubkey,
+    /// `send_payment` settles only once the recipient has counter-signed
+    pub require_counter_signature: bool,
+    /// Referral rewards on SOL payments accrue in USDC on a
+    /// `ReferralUsdcLedger` instead of being paid in lamports
+    pub usdc_referral_rewards: bool,
+    /// Vault payments settle only inside the escrow's `SettlementWindow`
+    pub settlement_window_active: bool,
+    /// Sunset schedule per `OwnerCapability`, indexed by its discriminant
+    pub capability_sunsets: [CapabilitySunset; 3],
+    /// `STATS_DIMENSION_*` bits chosen at creation
+    pub stats_dimensions: u8,
+    /// Resolves disputes on deposits held for this escrow;
+    /// `Pubkey::default()` means disputes can't be opened
+    pub arbiter: Pubkey,
+}
+
+impl EscrowAccount {
+    /// Byte offset of `encrypted_stats`, discriminator included, as read by
+    /// the MPC nodes.
+    pub const ENCRYPTED_STATS_OFFSET: u32 = (8
+        + Pubkey::INIT_SPACE // owner
+        + u64::INIT_SPACE // total_fund_regulated
+        + i64::INIT_SPACE // last_updated
+        + bool::INIT_SPACE // active
+        + Pubkey::INIT_SPACE // treasury
+        + u8::INIT_SPACE // bump
+        + u128::INIT_SPACE) as u32; // nonce
+
+    /// The escrow must be active and, when tagged, its jurisdiction must not
+    /// be paused by the protocol admin.
+    pub fn ensure_active(&self, jurisdiction_pause: Option<&JurisdictionPause>) -> Result<()> {
+        require!(self.active, EscrowError::EscrowPaused);
+        if self.jurisdiction != UNTAGGED_JURISDICTION {
+            let pause = jurisdiction_pause.ok_or(EscrowError::JurisdictionPauseRequired)?;
+            require!(!pause.paused, EscrowError::JurisdictionPaused);
+        }
+        Ok(())
+    }
+
+    /// Check that `signer` may exercise `capability`: the owner until its
+    /// sunset, then the successor if one was named, otherwise nobody.
+    pub fn authorize_capability(
+        &self,
+        capability: OwnerCapability,
+        signer: &Pubkey,
+        now: i64,
+    ) -> Result<()> {
+        let sunset = &self.capability_sunsets[capability as usize];
+        let holder = if sunset.sunset_at == 0 || now < sunset.sunset_at {
+            self.authority()
+        } else if sunset.successor == Pubkey::default() {
+            return err!(EscrowError::CapabilitySunset);
+        } else {
+            sunset.successor
+        };
+        require_keys_eq!(*signer, holder, EscrowError::InvalidAuthority);
+        Ok(())
+    }
+
+    /// Fail if the escrow doesn't track `dimension`, whose encrypted value
+    /// would then be a meaningless zero.
+    pub fn require_stats_dimension(&self, dimension: u8) -> Result<()> {
+        require!(
+            self.stats_dimensions & dimension != 0,
+            EscrowError::StatsDimensionNotTracked
+        );
+        Ok(())
+    }
+
+    /// Key currently allowed to act as the escrow owner.
+    pub fn authority(&self) -> Pubkey {
+        if self.recovered_owner == Pubkey::default() {
+            self.owner
+        } else {
+            self.recovered_owner
+        }
+    }
+}
+
+/// Owner powers that can be scheduled to sunset.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OwnerCapability {
+    /// `set_tip_fee`, `set_fee_hook`, `set_fee_voucher_authority`, the
+    /// referral settings, `set_treasury_accrual` and `set_insurance_pool`
+    Fees,
+    /// `propose_treasury`
+    Treasury,
+    /// `pause_escrow`, `resume_escrow` and `set_circuit_breaker`
+    Pausing,
+}
+
+/// When an owner capability ends and who, if anyone, inherits it.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace, Debug, PartialEq, Eq)]
+pub struct CapabilitySunset {
+    /// 0 while no sunset is scheduled
+    pub sunset_at: i64,
+    /// Governance key holding the capability after `sunset_at`; default
+    /// disables it for good
+    pub successor: Pubkey,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace, Debug, PartialEq, Eq)]
+pub enum ReferralFallback {
+    #[default]
+    Treasury,
+    Burn,
+    RecipientRebate,
+}
+
+/// A client-encrypted payment amount waiting to be folded into the stats.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace, Debug)]
+pub struct EncryptedDelta {
+    pub encryption_pubkey: [u8; 32],
+    pub nonce: u128,
+    pub ciphertext: [u8; 32],
+}
+
+/// Buffer of encrypted payment deltas merged into `EscrowAccount`'s stats in
+/// batches by `merge_deltas`.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct PendingDeltas {
+    pub escrow: Pubkey,
+    /// Number of occupied slots in `deltas`
+    pub count: u8,
+    /// Slots covered by the merge computation currently queued
+    pub in_flight: u8,
+    pub bump: u8,
+    pub deltas: [EncryptedDelta; MAX_PENDING_DELTAS],
+}
+
+impl PendingDeltas {
+    /// Drop the deltas the last merge folded in, shifting later appends down.
+    pub fn drain_merged(&mut self) {
+        let merged = self.in_flight as usize;
+        let count = self.count as usize;
+        self.deltas.copy_within(merged..count, 0);
+        for delta in self.deltas[count - merged..].iter_mut() {
+            *delta = EncryptedDelta::default();
+        }
+        self.count -= self.in_flight;
+        self.in_flight = 0;
+    }
+}
+
+/// Owner-funded lamport reserve that covers Arcium fees for an escrow's
+/// payment flows. The balance lives on the account itself.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct ComputeBudgetVault {
+    pub escrow: Pubkey,
+    /// Lamports paid back to the caller for each queued computation
+    pub fee_per_computation: u64,
+    /// Emit `ComputeBudgetLowEvent` once the balance drops below this
+    pub low_balance_threshold: u64,
+    pub total_reimbursed: u64,
+    pub bump: u8,
+    /// Lamports paid to whoever runs one of the escrow's permissionless
+    /// cranks; 0 disables crank rewards
+    pub crank_reward: u64,
+    /// Most the vault pays out in crank rewards per `CRANK_REWARD_PERIOD_SECS`
+    pub crank_reward_cap: u64,
+    pub crank_period_start: i64,
+    pub crank_paid_in_period: u64,
+}
+
+/// Program-wide settings shared by every escrow.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct ProtocolConfig {
+    pub admin: Pubkey,
+    /// Default treasury used by bootstrap flows for new escrows.
+    pub treasury: Pubkey,
+    pub primary_cluster: Option<Pubkey>,
+    /// Failover target when the primary cluster is down
+    pub secondary_cluster: Option<Pubkey>,
+    pub use_secondary_cluster: bool,
+    pub bump: u8,
+    /// Cap on integrator app fees, in basis points; 0 disables app fees
+    pub max_app_fee_bps: u16,
+    /// Set ahead of program upgrades: no new Arcium computations may be
+    /// queued, so no callback lands on a changed account layout. Plain
+    /// payments and refunds are unaffected.
+    pub maintenance_mode: bool,
+}
+
+impl ProtocolConfig {
+    /// Cluster queue instructions must use, or `None` to defer to the MXE.
+    pub fn active_cluster(&self) -> Option<Pubkey> {
+        if self.use_secondary_cluster {
+            self.secondary_cluster
+        } else {
+            self.primary_cluster
+        }
+    }
+}
+
+// Keep existing PaymentAccount structure
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct PaymentAccount {
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub referal: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+    pub referal_reward: u64,
+    pub treasury_reward: u64,
+    pub asset_mint: Pubkey,
+    /// Integrator fee charged on top of the protocol fees
+    pub app_fee: u64,
+    pub app_fee_destination: Pubkey,
+    pub app_fee_refunded: bool,
+    /// `payment_intent_hash` of this payment, for off-chain reconciliation
+    pub intent_hash: [u8; 32],
+    /// Position in the sender's `PaymentSequence`, shared by the plaintext
+    /// SOL, USDC and ZenZEC paths; 0 on the other paths
+    pub sequence: u64,
+    /// How the recipient approved this payment. The sender always signs, so
+    /// anything but `None` means both parties did.
+    pub counter_signature: CounterSignature,
+    /// Lamports the recipient has handed back with `refund_payment`
+    pub refunded: u64,
+    /// Set on `send_payment_timelocked`: the recipient can't
+    /// `claim_payment` before this
+    pub unlock_at: Option<Deadline>,
+    /// Set on `send_payment_timelocked`: unclaimed funds go back to the
+    /// sender via `reclaim_expired` once this passes
+    pub expires_at: Option<Deadline>,
+}
+
+impl PaymentAccount {
+    /// Whether the net amount is still held on this account rather than
+    /// paid out. Time-locked payments are closed when claimed or reclaimed.
+    pub fn is_time_locked(&self) -> bool {
+        self.unlock_at.is_some() || self.expires_at.is_some()
+    }
+
+    /// Amount held for the recipient; the fee legs were paid at send time.
+    pub fn net_amount(&self) -> u64 {
+        self.amount - self.referal_reward - self.treasury_reward
+    }
+}
+
+/// Recipient approval recorded on a counter-signed payment.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace, Debug, PartialEq, Eq)]
+pub enum CounterSignature {
+    #[default]
+    None,
+    /// The recipient signed the settling transaction.
+    Transaction,
+    /// An ed25519 instruction earlier in the transaction verified the
+    /// recipient's signature over `counter_signature_message`.
+    Ed25519,
+}
+
+/// Per-sender counter that keys plaintext payments, so one history covers
+/// every asset in the order the payments were made.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct PaymentSequence {
+    pub sender: Pubkey,
+    /// Sequence number the sender's next plaintext payment will take
+    pub next: u64,
+    pub bump: u8,
+}
+
+impl PaymentSequence {
+    /// Take the next sequence number, initializing the counter on first use.
+    pub fn advance(&mut self, sender: Pubkey, bump: u8) -> Result<u64> {
+        self.sender = sender;
+        self.bump = bump;
+        let sequence = self.next;
+        self.next = sequence.checked_add(1).ok_or(ProgramError::InvalidArgument)?;
+        Ok(sequence)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub enum OtcSwapStatus {
+    /// Maker deposited, waiting for the taker
+    Open,
+    /// Both sides deposited
+    Funded,
+    /// Terms check queued on the MPC cluster
+    Verifying,
+    Verified,
+    Rejected,
+    Settled,
+    Refunded,
+}
+
+/// Two-sided swap: the maker's `mint_a` deposit for the taker's `mint_b`
+/// deposit, each gated on the other's encrypted minimum.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct OtcSwap {
+    pub swap_id: u64,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub amount_a: u64,
+    pub amount_b: u64,
+    /// Maker's encrypted minimum of `mint_b`
+    pub maker_terms: EncryptedDelta,
+    /// Taker's encrypted minimum of `mint_a`
+    pub taker_terms: EncryptedDelta,
+    pub expires_at: Deadline,
+    pub status: OtcSwapStatus,
+    pub pending_computation_offset: Option<u64>,
+    pub bump: u8,
+}
+
+/// Recurring payroll for up to `MAX_PAYROLL_RECIPIENTS` token accounts.
+/// Salaries are only ever stored encrypted; the circuit reveals each payout
+/// to the callback at run time.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct PayrollSchedule {
+    pub escrow: Pubkey,
+    pub schedule_id: u64,
+    pub mint: Pubkey,
+    pub period_secs: i64,
+    pub next_run_at: i64,
+    pub recipient_count: u8,
+    /// Recipient token accounts, in the same order as the salary ciphertexts
+    pub recipients: [Pubkey; MAX_PAYROLL_RECIPIENTS],
+    pub salaries_encryption_pubkey: [u8; 32],
+    pub salaries_nonce: u128,
+    pub encrypted_salaries: [[u8; 32]; MAX_PAYROLL_RECIPIENTS],
+    pub pending_computation_offset: Option<u64>,
+    pub bump: u8,
+}
+
+/// Composable controls for vault payments. Empty allowlists and zero
+/// limits disable the corresponding rule.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, InitSpace, Debug)]
+pub struct SpendingPolicyRules {
+    /// Largest single payment allowed; 0 means no cap
+    pub max_per_tx: u64,
+    #[max_len(MAX_POLICY_MINTS)]
+    pub allowed_mints: Vec<Pubkey>,
+    /// Wallets (token account owners) the vault may pay
+    #[max_len(MAX_POLICY_RECIPIENTS)]
+    pub allowed_recipients: Vec<Pubkey>,
+    /// UTC hour range payments are allowed in; equal bounds means all day.
+    /// A range like 22..6 wraps past midnight.
+    pub window_start_hour: u8,
+    pub window_end_hour: u8,
+    /// Second signer required for payments at or above `cosign_threshold`
+    pub cosigner: Option<Pubkey>,
+    pub cosign_threshold: u64,
+}
+
+impl SpendingPolicyRules {
+    pub fn check(
+        &self,
+        mint: &Pubkey,
+        recipient: &Pubkey,
+        amount: u64,
+        now: i64,
+        cosigner: Option<Pubkey>,
+    ) -> Result<()> {
+        if self.max_per_tx > 0 {
+            require!(amount <= self.max_per_tx, EscrowError::PolicyAmountExceeded);
+        }
+        if !self.allowed_mints.is_empty() {
+            require!(self.allowed_mints.contains(mint), EscrowError::PolicyMintNotAllowed);
+        }
+        if !self.allowed_recipients.is_empty() {
+            require!(
+                self.allowed_recipients.contains(recipient),
+                EscrowError::PolicyRecipientNotAllowed
+            );
+        }
+        if self.window_start_hour != self.window_end_hour {
+            let hour = (now.rem_euclid(86_400) / 3_600) as u8;
+            let in_window = if self.window_start_hour < self.window_end_hour {
+                hour >= self.window_start_hour && hour < self.window_end_hour
+            } else {
+                hour >= self.window_start_hour || hour < self.window_end_hour
+            };
+            require!(in_window, EscrowError::PolicyOutsideWindow);
+        }
+        if let Some(required) = self.cosigner {
+            if amount >= self.cosign_threshold {
+                require!(cosigner == Some(required), EscrowError::PolicyCosignerRequired);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct SpendingPolicy {
+    pub escrow: Pubkey,
+    pub rules: SpendingPolicyRules,
+    pub bump: u8,
+}
+
+/// A vault payment above the escrow's approval threshold, waiting for the
+/// approver. Closed on approval or cancellation.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct PendingApproval {
+    pub escrow: Pubkey,
+    pub approval_id: u64,
+    pub mint: Pubkey,
+    pub recipient_token_account: Pubkey,
+    pub referral_token_account: Pubkey,
+    pub referal: Pubkey,
+    pub amount: u64,
+    pub requested_at: i64,
+    pub expires_at: i64,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, InitSpace, Debug)]
+pub enum ActivityKind {
+    #[default]
+    None,
+    Paused,
+    Resumed,
+    TreasuryChanged,
+    ThresholdChecked,
+    CountRevealed,
+    LargePayment,
+    ApprovalConfigChanged,
+    PendingComputationCleared,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace, Debug)]
+pub struct ActivityEntry {
+    pub kind: ActivityKind,
+    pub actor: Pubkey,
+    pub timestamp: i64,
+    /// Action-specific value: an amount, threshold or computation offset
+    pub value: u64,
+    /// Action-specific key, e.g. the new treasury
+    pub key: Pubkey,
+}
+
+/// Ring buffer of the most recent admin and high-value actions on an escrow,
+/// so the history can be read without an indexer.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct ActivityLog {
+    pub escrow: Pubkey,
+    /// Slot the next entry is written to
+    pub head: u16,
+    pub total_recorded: u64,
+    pub bump: u8,
+    pub entries: [ActivityEntry; ACTIVITY_LOG_CAPACITY],
+}
+
+impl ActivityLog {
+    pub fn record(&mut self, kind: ActivityKind, actor: Pubkey, value: u64) -> Result<()> {
+        self.push(kind, actor, value, Pubkey::default())
+    }
+
+    pub fn record_with_key(
+        &mut self,
+        kind: ActivityKind,
+        actor: Pubkey,
+        key: Pubkey,
+    ) -> Result<()> {
+        self.push(kind, actor, 0, key)
+    }
+
+    fn push(&mut self, kind: ActivityKind, actor: Pubkey, value: u64, key: Pubkey) -> Result<()> {
+        self.entries[self.head as usize] = ActivityEntry {
+            kind,
+            actor,
+            timestamp: Clock::get()?.unix_timestamp,
+            value,
+            key,
+        };
+        self.head = ((self.head as usize + 1) % ACTIVITY_LOG_CAPACITY) as u16;
+        self.total_recorded = self.total_recorded.saturating_add(1);
+        Ok(())
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace, Debug)]
+pub struct CircuitEntry {
+    #[max_len(MAX_CIRCUIT_NAME_LEN)]
+    pub name: String,
+    pub comp_def_offset: u32,
+    pub version: u16,
+    pub enabled: bool,
+}
+
+/// Which encrypted instructions this deployment supports, so clients can
+/// discover circuits and versions at runtime instead of hardcoding them.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct CircuitRegistry {
+    #[max_len(MAX_REGISTERED_CIRCUITS)]
+    pub circuits: Vec<CircuitEntry>,
+    pub bump: u8,
+}
+
+impl CircuitRegistry {
+    pub fn find(&self, name: &str) -> Option<&CircuitEntry> {
+        self.circuits.iter().find(|entry| entry.name == name)
+    }
+}
+
+/// Protocol-wide list of mints `send_payment_spl` accepts, managed by the
+/// protocol admin.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct MintAllowlist {
+    #[max_len(MAX_ALLOWED_MINTS)]
+    pub mints: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+/// Relayers allowed to sponsor payments, managed by the protocol admin.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct RelayerRegistry {
+    #[max_len(MAX_RELAYERS)]
+    pub relayers: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl RelayerRegistry {
+    pub fn is_registered(&self, relayer: &Pubkey) -> bool {
+        self.relayers.contains(relayer)
+    }
+}
+
+/// A referrer's encrypted lifetime totals, readable only through
+/// `request_referral_report`.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct ReferralStatsAccount {
+    pub referrer: Pubkey,
+    pub bump: u8,
+    pub nonce: u128,
+    /// Encrypted statistics: [total_referrals, total_rewards]
+    pub encrypted_stats: [[u8; 32]; 2],
+    pub pending_computation_offset: Option<u64>,
+}
+
+impl ReferralStatsAccount {
+    /// Byte offset of `encrypted_stats`, discriminator included.
+    pub const ENCRYPTED_STATS_OFFSET: u32 = 8 + 32 + 1 + 16;
+}
+
+/// Grants a non-owner key, e.g. a dashboard service, the right to trigger
+/// read-style computations (threshold checks, reveals) on one escrow.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct AnalyticsOperator {
+    pub escrow: Pubkey,
+    pub operator: Pubkey,
+    /// Minimum seconds between two requests from this operator
+    pub min_interval_secs: i64,
+    pub last_request_at: i64,
+    pub bump: u8,
+}
+
+/// A seller's ledger inside a marketplace escrow. The credited lamports
+/// are held on this account on top of its rent-exempt minimum.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct SubAccount {
+    pub escrow: Pubkey,
+    pub seller: Pubkey,
+    pub payout_address: Pubkey,
+    /// Marketplace cut taken at payout, in basis points
+    pub marketplace_fee_bps: u16,
+    /// Lamports credited and not yet paid out
+    pub balance: u64,
+    pub total_paid_out: u64,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub enum InvoiceStatus {
+    Open,
+    Paid,
+}
+
+/// A USD-denominated invoice settled in tokens at the oracle price.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct Invoice {
+    pub escrow: Pubkey,
+    pub invoice_id: u64,
+    pub mint: Pubkey,
+    pub recipient_token_account: Pubkey,
+    pub usd_cents: u64,
+    /// Pyth price feed id for `mint` in USD
+    pub price_feed_id: [u8; 32],
+    /// Allowed drift between the quote and the settlement price
+    pub max_slippage_bps: u16,
+    /// Maximum age of both the oracle price and the quote
+    pub max_price_age_secs: i64,
+    /// Who the referral share goes to; the default key for unreferred invoices
+    pub referrer: Pubkey,
+    /// Negotiated referral share of the settled amount, in basis points
+    pub referral_bps: u16,
+    pub quoted_token_amount: u64,
+    pub quoted_at: i64,
+    pub settled_token_amount: u64,
+    pub status: InvoiceStatus,
+    pub bump: u8,
+}
+
+// Mirror of the Pyth receiver's `PriceUpdateV2` layout, so prices can be read
+// without pulling the receiver SDK into the program.
+#[derive(AnchorDeserialize)]
+enum PythVerificationLevel {
+    Partial {
+        #[allow(dead_code)]
+        num_signatures: u8,
+    },
+    Full,
+}
+
+#[derive(AnchorDeserialize)]
+#[allow(dead_code)]
+struct PythPriceMessage {
+    feed_id: [u8; 32],
+    price: i64,
+    conf: u64,
+    exponent: i32,
+    publish_time: i64,
+    prev_publish_time: i64,
+    ema_price: i64,
+    ema_conf: u64,
+}
+
+#[derive(AnchorDeserialize)]
+#[allow(dead_code)]
+struct PythPriceUpdate {
+    write_authority: Pubkey,
+    verification_level: PythVerificationLevel,
+    price_message: PythPriceMessage,
+    posted_slot: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, InitSpace, Debug)]
+pub enum MilestoneStatus {
+    #[default]
+    Pending,
+    /// Payer signed off; the payee can claim
+    Approved,
+    Claimed,
+    /// Frozen until the escrow owner resolves it
+    Disputed,
+    Refunded,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace, Debug)]
+pub struct Milestone {
+    pub amount: u64,
+    /// Hash of the off-chain deliverable description
+    pub description_hash: [u8; 32],
+    pub status: MilestoneStatus,
+}
+
+/// Tranches of a freelance contract, funded up front into a vault owned by
+/// this account and released per milestone.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct Milestones {
+    pub escrow: Pubkey,
+    pub milestones_id: u64,
+    pub payer: Pubkey,
+    pub payee: Pubkey,
+    pub mint: Pubkey,
+    pub payee_token_account: Pubkey,
+    pub count: u8,
+    pub milestones: [Milestone; MAX_MILESTONES],
+    pub bump: u8,
+}
+
+impl Milestones {
+    pub fn milestone_mut(&mut self, index: u8) -> Result<&mut Milestone> {
+        require!(index < self.count, EscrowError::InvalidMilestones);
+        Ok(&mut self.milestones[index as usize])
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub enum AuctionStatus {
+    Open,
+    /// Winner selection queued on the MPC cluster
+    Resolving,
+    Resolved,
+    Settled,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace, Debug)]
+pub struct SealedBid {
+    pub bidder: Pubkey,
+    pub refund_token_account: Pubkey,
+    /// Public deposit, also the most this bid can pay
+    pub deposit: u64,
+    pub encryption_pubkey: [u8; 32],
+    pub nonce: u128,
+    pub ciphertext: [u8; 32],
+}
+
+/// Second-price sealed-bid auction with deposits held in an auction vault.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct Auction {
+    pub seller: Pubkey,
+    pub auction_id: u64,
+    pub mint: Pubkey,
+    pub seller_token_account: Pubkey,
+    pub reserve_price: u64,
+    pub bid_deadline: Deadline,
+    pub bidder_count: u8,
+    pub bids: [SealedBid; MAX_AUCTION_BIDDERS],
+    pub status: AuctionStatus,
+    /// `NO_AUCTION_WINNER` until resolved, or if no bid met the reserve
+    pub winner_index: u8,
+    pub clearing_price: u64,
+    pub pending_computation_offset: Option<u64>,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HtlcAction {
+    Created,
+    Claimed,
+    Refunded,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TimeLockAction {
+    Created,
+    Claimed,
+    Reclaimed,
+}
+
+/// Hash-time-locked SOL payment. The locked amount sits on this account
+/// on top of its rent.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct Htlc {
+    pub sender: Pubkey,
+    pub htlc_id: u64,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    /// SHA-256 of the secret preimage
+    pub hash_lock: [u8; 32],
+    pub expires_at: Deadline,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub enum EscrowDepositStatus {
+    Held,
+    Released,
+    Refunded,
+    /// Frozen by `open_dispute` until the arbiter rules
+    Disputed,
+    /// Split by the arbiter; the ruling is on the `Dispute`
+    Resolved,
+}
+
+/// Funds held in custody for a recipient. SOL sits on this account on top
+/// of its rent; SPL tokens in the vault at `ESCROW_DEPOSIT_VAULT`.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct EscrowDeposit {
+    pub escrow: Pubkey,
+    pub deposit_id: u64,
+    pub depositor: Pubkey,
+    pub recipient: Pubkey,
+    /// May release or refund on either party's behalf; `Pubkey::default()`
+    /// leaves the deposit to the parties alone
+    pub arbiter: Pubkey,
+    /// `Pubkey::default()` for SOL
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub status: EscrowDepositStatus,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl EscrowDeposit {
+    pub fn is_arbiter(&self, key: &Pubkey) -> bool {
+        self.arbiter != Pubkey::default() && self.arbiter == *key
+    }
+
+    pub fn is_party(&self, key: &Pubkey) -> bool {
+        *key == self.depositor || *key == self.recipient
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub enum DisputeStatus {
+    Open,
+    Resolved,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace, Debug)]
+pub struct DisputeEvidence {
+    pub submitter: Pubkey,
+    pub hash: [u8; 32],
+}
+
+/// Arbitration of one `EscrowDeposit` by its escrow's arbiter.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct Dispute {
+    pub deposit: Pubkey,
+    pub opened_by: Pubkey,
+    #[max_len(MAX_DISPUTE_EVIDENCE)]
+    pub evidence: Vec<DisputeEvidence>,
+    pub status: DisputeStatus,
+    /// Share of the deposit the ruling gave the recipient, before fees
+    pub recipient_amount: u64,
+    pub opened_at: i64,
+    pub resolved_at: i64,
+    pub bump: u8,
+}
+
+/// Admin-quoted price of an SPL mint in USDC, used to settle protocol fees
+/// in USDC instead of the payment token.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct FeeConversionRate {
+    pub mint: Pubkey,
+    /// USDC base units per `FEE_RATE_SCALE` base units of `mint`
+    pub usdc_per_unit: u64,
+    pub quoted_at: i64,
+    pub max_age_secs: i64,
+    pub bump: u8,
+}
+
+/// M-of-N signer set guarding large vault withdrawals.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct VaultSigners {
+    pub escrow: Pubkey,
+    #[max_len(MAX_VAULT_SIGNERS)]
+    pub signers: Vec<Pubkey>,
+    pub required_approvals: u8,
+    /// Bumped on every signer change so old proposals go stale
+    pub epoch: u32,
+    pub bump: u8,
+}
+
+impl VaultSigners {
+    /// Position of `signer` in the list, which is also its approval bit.
+    pub fn index_of(&self, signer: &Pubkey) -> Result<usize> {
+        self.signers
+            .iter()
+            .position(|s| s == signer)
+            .ok_or_else(|| error!(EscrowError::NotVaultSigner))
+    }
+}
+
+/// A vault withdrawal above the multisig threshold, collecting approvals.
+/// Closed to the proposer when executed.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct WithdrawalProposal {
+    pub escrow: Pubkey,
+    pub proposal_id: u64,
+    pub proposer: Pubkey,
+    pub escrow_vault: Pubkey,
+    pub destination_token_account: Pubkey,
+    pub amount: u64,
+    pub signers_epoch: u32,
+    /// Bit i set once `VaultSigners::signers[i]` has approved
+    pub approvals: u16,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+/// Guardians allowed to rotate a lost escrow owner key, and the recovery
+/// currently in flight, if any.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct RecoveryConfig {
+    pub escrow: Pubkey,
+    #[max_len(MAX_RECOVERY_GUARDIANS)]
+    pub guardians: Vec<Pubkey>,
+    pub threshold: u8,
+    pub pending_owner: Option<Pubkey>,
+    /// Bit i set once `guardians[i]` has approved the pending recovery
+    pub approvals: u8,
+    pub initiated_at: i64,
+    /// When approvals first met the threshold; 0 until then
+    pub threshold_reached_at: i64,
+    pub bump: u8,
+}
+
+impl RecoveryConfig {
+    pub fn guardian_index(&self, guardian: &Pubkey) -> Result<usize> {
+        self.guardians
+            .iter()
+            .position(|g| g == guardian)
+            .ok_or_else(|| error!(EscrowError::NotRecoveryGuardian))
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecoveryStage {
+    Initiated,
+    Approved,
+    Vetoed,
+    Executed,
+}
+
+/// Time-boxed referral boost with a hard cap on the extra paid out.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct Campaign {
+    pub escrow: Pubkey,
+    pub campaign_id: u64,
+    /// Payment asset the budget is denominated in; default for SOL
+    pub mint: Pubkey,
+    pub start_ts: i64,
+    pub end_ts: i64,
+    /// Referral share while the campaign runs, in basis points
+    pub referral_bps: u16,
+    /// Most the campaign may pay on top of the default referral share
+    pub budget: u64,
+    pub spent: u64,
+    pub bump: u8,
+}
+
+/// Treasury fee curve: the fee steps down as an escrow's lifetime volume
+/// crosses each milestone.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct FeeConfig {
+    #[max_len(MAX_FEE_TIERS)]
+    pub tiers: Vec<FeeTier>,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace, Debug)]
+pub struct FeeTier {
+    /// Lifetime volume at which this tier starts to apply
+    pub min_volume: u64,
+    pub treasury_bps: u16,
+}
+
+impl FeeConfig {
+    /// Treasury fee for an escrow that has moved `volume` so far.
+    pub fn treasury_bps(&self, volume: u64) -> u16 {
+        self.tiers
+            .iter()
+            .rev()
+            .find(|tier| volume >= tier.min_volume)
+            .map_or(DEFAULT_TREASURY_FEE_BPS, |tier| tier.treasury_bps)
+    }
+}
+
+/// Referral rewards held back and released linearly. Each deposit is
+/// blended into the running schedule, weighting the end time by amount, so
+/// earlier rewards are not reset to a fresh 30 days.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct ReferralVesting {
+    pub escrow: Pubkey,
+    pub referrer: Pubkey,
+    /// Lamports still vesting as of `checkpoint_ts`
+    pub unvested: u64,
+    /// Vested but not yet claimed
+    pub claimable: u64,
+    pub claimed_total: u64,
+    pub checkpoint_ts: i64,
+    pub vest_end_ts: i64,
+    pub bump: u8,
+}
+
+impl ReferralVesting {
+    /// Release whatever vested between the last checkpoint and `now`.
+    pub fn checkpoint(&mut self, now: i64) -> Result<()> {
+        let released = if now >= self.vest_end_ts {
+            self.unvested
+        } else if now <= self.checkpoint_ts {
+            0
+        } else {
+            (self.unvested as u128 * (now - self.checkpoint_ts) as u128
+                / (self.vest_end_ts - self.checkpoint_ts) as u128) as u64
+        };
+        self.unvested -= released;
+        self.claimable = self
+            .claimable
+            .checked_add(released)
+            .ok_or(ProgramError::InvalidArgument)?;
+        self.checkpoint_ts = self.checkpoint_ts.max(now);
+        Ok(())
+    }
+
+    pub fn deposit(&mut self, amount: u64, now: i64) -> Result<()> {
+        self.checkpoint(now)?;
+        let remaining = (self.vest_end_ts - now).max(0) as u128;
+        let total = self.unvested as u128 + amount as u128;
+        if total > 0 {
+            let weighted = self.unvested as u128 * remaining
+                + amount as u128 * REFERRAL_VESTING_SECS as u128;
+            self.vest_end_ts = now + (weighted / total) as i64;
+        }
+        self.unvested = self
+            .unvested
+            .checked_add(amount)
+            .ok_or(ProgramError::InvalidArgument)?;
+        Ok(())
+    }
+}
+
+/// Vault payments parked while the escrow is quarantined, awaiting a
+/// compliance decision.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct Quarantine {
+    pub escrow: Pubkey,
+    #[max_len(MAX_QUARANTINED_PAYMENTS)]
+    pub entries: Vec<QuarantinedPayment>,
+    pub bump: u8,
+}
+
+impl Quarantine {
+    /// Remove and return the entry for `payment_id`.
+    pub fn take(&mut self, payment_id: u64) -> Result<QuarantinedPayment> {
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.payment_id == payment_id)
+            .ok_or(EscrowError::QuarantinedPaymentNotFound)?;
+        Ok(self.entries.swap_remove(index))
+    }
+
+    /// Total still owed to recipient, treasury and referrer out of `vault`.
+    pub fn owed_from(&self, vault: &Pubkey) -> Result<u64> {
+        let owed = self
+            .entries
+            .iter()
+            .filter(|entry| entry.escrow_vault == *vault)
+            .try_fold(0u64, |total, entry| {
+                total
+                    .checked_add(entry.net_amount)?
+                    .checked_add(entry.treasury_reward)?
+                    .checked_add(entry.referal_reward)
+            })
+            .ok_or(ProgramError::InvalidArgument)?;
+        Ok(owed)
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace, Debug)]
+pub struct QuarantinedPayment {
+    pub payment_id: u64,
+    pub escrow_vault: Pubkey,
+    pub recipient_token_account: Pubkey,
+    pub treasury_token_account: Pubkey,
+    pub referral_token_account: Pubkey,
+    pub net_amount: u64,
+    pub treasury_reward: u64,
+    pub referal_reward: u64,
+    pub quarantined_at: i64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuarantineAction {
+    Quarantined,
+    Released,
+    Rejected,
+}
+
+/// Escrow setup a platform registers once and stamps onto every merchant
+/// escrow it onboards.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct EscrowTemplate {
+    pub creator: Pubkey,
+    pub template_id: u64,
+    pub config: EscrowTemplateConfig,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace, Debug)]
+pub struct EscrowTemplateConfig {
+    pub treasury: Pubkey,
+    pub referral_fallback: ReferralFallback,
+    pub tip_treasury_fee_bps: u16,
+    pub circuit_breaker_threshold: u64,
+    pub approval_threshold: u64,
+    pub approver: Pubkey,
+    pub approval_window_secs: i64,
+    pub vest_referral_rewards: bool,
+    pub referrals_enabled: bool,
+}
+
+impl EscrowTemplateConfig {
+    pub fn apply(&self, escrow: &mut EscrowAccount) {
+        escrow.treasury = self.treasury;
+        escrow.referral_fallback = self.referral_fallback;
+        escrow.tip_treasury_fee_bps = self.tip_treasury_fee_bps;
+        escrow.circuit_breaker_threshold = self.circuit_breaker_threshold;
+        escrow.approval_threshold = self.approval_threshold;
+        escrow.approver = self.approver;
+        escrow.approval_window_secs = self.approval_window_secs;
+        escrow.vest_referral_rewards = self.vest_referral_rewards;
+        escrow.referrals_enabled = self.referrals_enabled;
+    }
+}
+
+/// Read-only delegate on one escrow, e.g. an accountant. Viewers can run
+/// analytics computations and read the escrow's accounts, nothing more.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct Viewer {
+    pub escrow: Pubkey,
+    pub viewer: Pubkey,
+    pub bump: u8,
+}
+
+/// Merkle root over a batch of closed `PaymentAccount`s. Leaves are
+/// `archive_leaf` of each account in the order they were archived.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct Archive {
+    pub archive_id: u64,
+    pub root: [u8; 32],
+    pub leaf_count: u32,
+    pub oldest_payment_at: i64,
+    pub newest_payment_at: i64,
+    pub archived_at: i64,
+    pub bump: u8,
+}
+
+/// Collects the rent of archived payment accounts.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct RebatePool {
+    pub total_collected: u64,
+    pub bump: u8,
+}
+
+/// A deadline in either clock. Slot deadlines suit integrators whose
+/// runtime, such as an ephemeral rollup, keeps its own wall clock.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace, Debug, PartialEq, Eq)]
+pub enum Deadline {
+    UnixTimestamp(i64),
+    Slot(u64),
+}
+
+impl Deadline {
+    pub fn has_passed(&self, clock: &Clock) -> bool {
+        match *self {
+            Deadline::UnixTimestamp(timestamp) => clock.unix_timestamp >= timestamp,
+            Deadline::Slot(slot) => clock.slot >= slot,
+        }
+    }
+}
+
+// Layout snapshots. MPC nodes read ciphertexts straight out of account data
+// at fixed byte offsets (`Argument::Account`), and clients dispatch on the
+// 8-byte discriminators, so neither may move silently. If one of these
+// fails to compile, re-derive every offset into that account before
+// updating the snapshot.
+const _: () = {
+    const DISCRIMINATORS: &[(&[u8], [u8; 8])] = &[
+        (EscrowAccount::DISCRIMINATOR, [36, 69, 48, 18, 128, 225, 125, 135]),
+        (PendingDeltas::DISCRIMINATOR, [200, 143, 144, 246, 63, 95, 132, 255]),
+        (ComputeBudgetVault::DISCRIMINATOR, [212, 225, 101, 184, 9, 169, 252, 26]),
+        (ProtocolConfig::DISCRIMINATOR, [207, 91, 250, 28, 152, 179, 215, 209]),
+        (PaymentAccount::DISCRIMINATOR, [47, 239, 218, 78, 43, 193, 1, 61]),
+        (PaymentSequence::DISCRIMINATOR, [221, 170, 123, 197, 3, 66, 129, 119]),
+        (OtcSwap::DISCRIMINATOR, [215, 59, 233, 43, 181, 178, 17, 85]),
+        (PayrollSchedule::DISCRIMINATOR, [4, 119, 84, 50, 215, 112, 106, 144]),
+        (SpendingPolicy::DISCRIMINATOR, [13, 105, 173, 25, 180, 167, 137, 203]),
+        (PendingApproval::DISCRIMINATOR, [67, 115, 34, 204, 157, 43, 83, 27]),
+        (ActivityLog::DISCRIMINATOR, [25, 244, 19, 242, 176, 113, 202, 61]),
+        (CircuitRegistry::DISCRIMINATOR, [248, 61, 106, 55, 138, 152, 88, 37]),
+        (ReferralStatsAccount::DISCRIMINATOR, [223, 154, 183, 200, 79, 70, 193, 189]),
+        (AnalyticsOperator::DISCRIMINATOR, [194, 201, 181, 132, 122, 102, 133, 32]),
+        (SubAccount::DISCRIMINATOR, [227, 47, 166, 42, 242, 171, 32, 114]),
+        (Invoice::DISCRIMINATOR, [51, 194, 250, 114, 6, 104, 18, 164]),
+        (Milestones::DISCRIMINATOR, [151, 111, 187, 203, 80, 227, 12, 222]),
+        (Auction::DISCRIMINATOR, [218, 94, 247, 242, 126, 233, 131, 81]),
+        (Htlc::DISCRIMINATOR, [66, 26, 231, 135, 121, 129, 82, 249]),
+        (FeeConversionRate::DISCRIMINATOR, [4, 96, 130, 219, 183, 9, 199, 195]),
+        (VaultSigners::DISCRIMINATOR, [98, 129, 232, 92, 157, 177, 199, 245]),
+        (WithdrawalProposal::DISCRIMINATOR, [133, 217, 76, 47, 128, 98, 53, 3]),
+        (RecoveryConfig::DISCRIMINATOR, [53, 220, 64, 189, 255, 179, 242, 74]),
+        (Campaign::DISCRIMINATOR, [50, 40, 49, 11, 157, 220, 229, 192]),
+        (FeeConfig::DISCRIMINATOR, [143, 52, 146, 187, 219, 123, 76, 155]),
+        (ReferralVesting::DISCRIMINATOR, [207, 127, 79, 44, 103, 73, 140, 223]),
+        (Quarantine::DISCRIMINATOR, [160, 117, 138, 237, 177, 73, 230, 250]),
+        (EscrowTemplate::DISCRIMINATOR, [93, 188, 253, 216, 146, 219, 1, 7]),
+        (Viewer::DISCRIMINATOR, [155, 54, 245, 187, 133, 206, 37, 209]),
+        (Archive::DISCRIMINATOR, [231, 36, 31, 240, 210, 212, 36, 107]),
+        (RebatePool::DISCRIMINATOR, [111, 211, 11, 147, 116, 127, 107, 35]),
+        (TreasuryVault::DISCRIMINATOR, [86, 102, 19, 109, 56, 58, 144, 81]),
+        (ReferralLink::DISCRIMINATOR, [30, 231, 159, 98, 189, 47, 48, 5]),
+        (SpendCapAccount::DISCRIMINATOR, [157, 48, 218, 22, 189, 48, 122, 130]),
+        (InsurancePool::DISCRIMINATOR, [239, 152, 145, 201, 228, 155, 139, 140]),
+        (InsuranceClaim::DISCRIMINATOR, [52, 189, 95, 73, 117, 235, 160, 230]),
+        (MatchingPool::DISCRIMINATOR, [193, 46, 246, 112, 41, 79, 78, 195]),
+        (PaymentMatch::DISCRIMINATOR, [144, 38, 82, 191, 111, 253, 161, 11]),
+        (VoucherRedemption::DISCRIMINATOR, [158, 68, 58, 94, 170, 144, 102, 66]),
+        (FeeWaiver::DISCRIMINATOR, [66, 18, 17, 254, 87, 223, 159, 8]),
+        (JurisdictionPause::DISCRIMINATOR, [99, 222, 78, 124, 18, 177, 6, 178]),
+        (ReferralUsdcLedger::DISCRIMINATOR, [85, 4, 246, 200, 118, 31, 66, 136]),
+        (SettlementWindow::DISCRIMINATOR, [199, 82, 184, 134, 174, 127, 127, 181]),
+        (MintAllowlist::DISCRIMINATOR, [155, 85, 58, 143, 107, 199, 161, 198]),
+        (RelayerRegistry::DISCRIMINATOR, [84, 117, 180, 9, 105, 26, 39, 149]),
+        (EscrowDeposit::DISCRIMINATOR, [56, 152, 208, 160, 159, 83, 6, 17]),
+        (Dispute::DISCRIMINATOR, [36, 49, 241, 67, 40, 36, 241, 74]),
+        (PaymentProof::DISCRIMINATOR, [24, 203, 155, 24, 187, 121, 233, 43]),
+        (PullMandate::DISCRIMINATOR, [209, 155, 228, 78, 65, 88, 81, 88]),
+    ];
+    let mut i = 0;
+    while i < DISCRIMINATORS.len() {
+        let (actual, expected) = DISCRIMINATORS[i];
+        assert!(bytes_eq(actual, &expected), "account discriminator changed");
+        let mut j = i + 1;
+        while j < DISCRIMINATORS.len() {
+            assert!(!bytes_eq(actual, DISCRIMINATORS[j].0), "account discriminator collision");
+            j += 1;
+        }
+        i += 1;
+    }
+
+    assert!(EscrowAccount::INIT_SPACE == 852, "EscrowAccount layout changed");
+    assert!(EscrowAccount::ENCRYPTED_STATS_OFFSET == 106, "encrypted_stats moved");
+    assert!(
+        EscrowAccount::ENCRYPTED_STATS_OFFSET as usize + 32 * ESCROW_STATS_FIELDS
+            <= 8 + EscrowAccount::INIT_SPACE
+    );
+    assert!(ReferralStatsAccount::INIT_SPACE == 122, "ReferralStatsAccount layout changed");
+    assert!(SpendCapAccount::INIT_SPACE == 203, "SpendCapAccount layout changed");
+    assert!(SpendCapAccount::ENCRYPTED_STATE_OFFSET == 56, "encrypted_state moved");
+    assert!(
+        ReferralStatsAccount::ENCRYPTED_STATS_OFFSET as usize
+            == 8 + Pubkey::INIT_SPACE + u8::INIT_SPACE + u128::INIT_SPACE
+    );
+};
+
+/// Fee fixtures shared with the circuits; see `test-vectors/fees.rs`.
+pub mod fee_vectors {
+    include!("../../../test-vectors/fees.rs");
+}
+
+/// Referral and treasury fee on a plaintext payment. The treasury leg follows
+/// the protocol fee curve for an escrow that has moved `volume` so far, or
+/// the default rate without a `FeeConfig`.
+fn tiered_fee_split(
+    amount: u64,
+    fee_config: Option<&FeeConfig>,
+    volume: u64,
+) -> Result<(u64, u64)> {
+    let treasury_bps = fee_config.map_or(DEFAULT_TREASURY_FEE_BPS, |config| {
+        config.treasury_bps(volume)
+    });
+    Ok(fee_split(amount, treasury_bps))
+}
+
+/// Referral and treasury fee on `amount`: 0.6% to the referral leg and
+/// `treasury_bps` to the treasury, each rounded down on its own. Every
+/// payment path charges through this.
+pub const fn fee_split(amount: u64, treasury_bps: u16) -> (u64, u64) {
+    let referral_fee = amount as u128 * REFERRAL_FEE_BPS as u128 / 10_000;
+    let treasury_fee = amount as u128 * treasury_bps as u128 / 10_000;
+    (referral_fee as u64, treasury_fee as u64)
+}
+
+// Fails to compile if `fee_split` drifts from the shared fixtures. The
+// circuits' `calculate_fees` is run over the same rows in the tests.
+const _: () = {
+    let mut i = 0;
+    while i < fee_vectors::FEE_VECTORS.len() {
+        let (amount, referral_fee, treasury_fee) = fee_vectors::FEE_VECTORS[i];
+        let (referral, treasury) = fee_split(amount, DEFAULT_TREASURY_FEE_BPS);
+        assert!(
+            referral == referral_fee && treasury == treasury_fee,
+            "program fee split diverges from test-vectors/fees.rs"
+        );
+        i += 1;
+    }
+};
+
+/// Holds an escrow's accrued SOL treasury fees as lamports until they are
+/// swept to the treasury.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct TreasuryVault {
+    pub escrow: Pubkey,
+    pub total_swept: u64,
+    pub bump: u8,
+}
+
+/// Who referred `referee`, under one escrow or, when `scope` is the
+/// protocol config, under every escrow of the protocol.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct ReferralLink {
+    pub scope: Pubkey,
+    pub referee: Pubkey,
+    pub referrer: Pubkey,
+    pub registered_at: i64,
+    pub bump: u8,
+}
+
+/// A sender's self-imposed budget. The cap and the running spend stay
+/// encrypted; the cluster only reveals whether a given payment fits.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct SpendCapAccount {
+    pub sender: Pubkey,
+    pub nonce: u128,
+    /// Encrypted [cap, spent this period]
+    pub encrypted_state: [[u8; 32]; 2],
+    /// Start of the period the encrypted spend covers
+    pub period_start: i64,
+    /// Period start adopted when the queued check lands
+    pub pending_period_start: i64,
+    pub pending_computation_offset: Option<u64>,
+    /// Amount ciphertext under the queued check
+    pub pending_amount: [u8; 32],
+    /// Amount ciphertext the cap approved, consumed by one confidential
+    /// payment
+    pub approved_amount: Option<[u8; 32]>,
+    pub bump: u8,
+}
+
+impl SpendCapAccount {
+    /// Byte offset of `encrypted_state`, discriminator included.
+    pub const ENCRYPTED_STATE_OFFSET: u32 =
+        (8 + Pubkey::INIT_SPACE + u128::INIT_SPACE) as u32;
+}
+
+/// Per-escrow pool compensating recipients of failed or erroneous
+/// settlements. Holds its funds as lamports above its rent reserve.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct InsurancePool {
+    pub escrow: Pubkey,
+    /// Approves or rejects claims
+    pub guardian: Pubkey,
+    pub total_contributed: u64,
+    pub total_paid_out: u64,
+    /// Bonds of rejected claims
+    pub total_slashed: u64,
+    pub bump: u8,
+}
+
+/// One claim per payment against an escrow's insurance pool.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct InsuranceClaim {
+    pub pool: Pubkey,
+    pub payment: Pubkey,
+    pub claimant: Pubkey,
+    pub amount: u64,
+    /// Hash of the off-chain evidence the guardian reviews
+    pub reason_hash: [u8; 32],
+    pub filed_at: i64,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InsuranceMovement {
+    Contribution,
+    ClaimFiled,
+    ClaimPaid,
+    BondSlashed,
+}
+
+/// Permissionless cranks a `ComputeBudgetVault` can reward.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CrankKind {
+    MergeDeltas,
+    RunPayroll,
+    CircuitBreaker,
+    SweepTreasury,
+    SettlementWindow,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TreasuryRotationStage {
+    Proposed,
+    Accepted,
+}
+
+/// Sponsor-funded pool matching SOL donations to one recipient. The match
+/// ratio and per-payment ceiling stay encrypted; the balance is the pool
+/// account's lamports above rent.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct MatchingPool {
+    pub escrow: Pubkey,
+    /// Donations to this wallet qualify for a match
+    pub recipient: Pubkey,
+    pub terms_encryption_pubkey: [u8; 32],
+    pub terms_nonce: u128,
+    /// Encrypted `MatchTerms` [ratio_bps, max_match]
+    pub encrypted_terms: [[u8; 32]; 2],
+    /// Payments before this are not matched
+    pub created_at: i64,
+    pub total_deposited: u64,
+    pub total_matched: u64,
+    pub bump: u8,
+}
+
+/// Marks a payment as matched by a pool, so it is matched only once.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct PaymentMatch {
+    pub pool: Pubkey,
+    pub payment: Pubkey,
+    pub matched: u64,
+    pub pending_computation_offset: Option<u64>,
+    pub bump: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchingMovement {
+    Deposit,
+    MatchReleased,
+}
+
+/// Marks a fee voucher mint as redeemed.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct VoucherRedemption {
+    pub voucher_mint: Pubkey,
+    pub escrow: Pubkey,
+    pub redeemed_by: Pubkey,
+    pub redeemed_at: i64,
+    pub bump: u8,
+}
+
+/// Treasury fee waivers a sender has redeemed vouchers for on one escrow.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct FeeWaiver {
+    pub escrow: Pubkey,
+    pub sender: Pubkey,
+    pub credits: u32,
+    pub bump: u8,
+}
+
+/// Protocol-wide pause switch for every escrow tagged with one
+/// jurisdiction.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct JurisdictionPause {
+    pub jurisdiction: u16,
+    pub paused: bool,
+    pub updated_at: i64,
+    pub bump: u8,
+}
 
-    pub system_program: Program<'info, System>,
-    pub arcium_program: Program<'info, Arcium>,
+/// Payment summary a fee hook receives, after the protocol split and any
+/// campaign boost have been applied.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct FeeHookInput {
+    pub escrow: Pubkey,
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub asset_mint: Pubkey,
+    pub amount: u64,
+    pub referal_reward: u64,
+    pub treasury_reward: u64,
+}
 
-    #[account(
-        init,
-        payer = owner,
-        space = 8 + EscrowAccount::INIT_SPACE,
-        seeds = [b"escrow", owner.key().as_ref()],
-        bump
-    )]
-    pub escrow: Account<'info, EscrowAccount>,
+/// Fee split a hook hands back through `set_return_data`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct FeeHookOutput {
+    pub referal_reward: u64,
+    pub treasury_reward: u64,
 }
 
-#[callback_accounts("init_escrow_stats")]
-#[derive(Accounts)]
-pub struct InitEscrowStatsCallback<'info> {
-    pub arcium_program: Program<'info, Arcium>,
+/// USDC value of the referral rewards a referrer has earned under one
+/// escrow while it settles them in USDC. The lamports went to the treasury,
+/// which pays the difference between accrued and settled.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct ReferralUsdcLedger {
+    pub escrow: Pubkey,
+    pub referrer: Pubkey,
+    pub accrued_usdc: u64,
+    pub settled_usdc: u64,
+    pub bump: u8,
+}
 
-    #[account(
-        address = derive_comp_def_pda!(COMP_DEF_OFFSET_INIT_ESCROW_STATS)
-    )]
-    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+impl ReferralUsdcLedger {
+    /// USDC the treasury still owes the referrer.
+    pub fn outstanding(&self) -> u64 {
+        self.accrued_usdc - self.settled_usdc
+    }
+}
 
-    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
-    /// CHECK: instructions_sysvar
-    pub instructions_sysvar: AccountInfo<'info>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReferralUsdcMovement {
+    Accrued,
+    Settled,
+}
 
-    #[account(mut)]
-    pub escrow: Account<'info, EscrowAccount>,
+/// Business hours for an escrow's vault payments, in UTC, plus the
+/// payments deferred until they next open. Entries have the same shape as
+/// quarantined payments; `quarantined_at` records when each was deferred.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct SettlementWindow {
+    pub escrow: Pubkey,
+    /// First hour (0-23) of the window
+    pub open_hour: u8,
+    /// Hour the window closes at; below `open_hour` for windows that run
+    /// past midnight
+    pub close_hour: u8,
+    /// Days the window opens on, bit 0 = Monday through bit 6 = Sunday
+    pub days: u8,
+    #[max_len(MAX_DEFERRED_PAYMENTS)]
+    pub entries: Vec<QuarantinedPayment>,
+    pub bump: u8,
 }
 
-#[init_computation_definition_accounts("init_escrow_stats", payer)]
-#[derive(Accounts)]
-pub struct InitEscrowStatsCompDef<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
+impl SettlementWindow {
+    /// Whether `now` falls inside the window. An overnight window belongs
+    /// to the day it opens on only until midnight; after midnight the next
+    /// day's bit applies.
+    pub fn is_open(&self, now: i64) -> bool {
+        let day = now.div_euclid(86_400);
+        // 1970-01-01 was a Thursday.
+        let weekday = (day + 3).rem_euclid(7) as u8;
+        let hour = (now.rem_euclid(86_400) / 3_600) as u8;
+        let in_hours = if self.open_hour < self.close_hour {
+            hour >= self.open_hour && hour < self.close_hour
+        } else {
+            hour >= self.open_hour || hour < self.close_hour
+        };
+        in_hours && self.days & (1 << weekday) != 0
+    }
 
-    #[account(
-        mut,
-        address = derive_mxe_pda!()
-    )]
-    pub mxe_account: Box<Account<'info, MXEAccount>>,
+    /// Remove and return the entry for `payment_id`.
+    pub fn take(&mut self, payment_id: u64) -> Result<QuarantinedPayment> {
+        let index = self
+            .entries
+            .iter()
+            .position(|entry| entry.payment_id == payment_id)
+            .ok_or(EscrowError::DeferredPaymentNotFound)?;
+        Ok(self.entries.swap_remove(index))
+    }
+}
 
-    #[account(mut)]
-    /// CHECK: comp_def_account
-    pub comp_def_account: UncheckedAccount<'info>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SettlementWindowAction {
+    Deferred,
+    Settled,
+}
 
-    pub arcium_program: Program<'info, Arcium>,
-    pub system_program: Program<'info, System>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace, Debug)]
+pub enum PaymentProofStatus {
+    Pending,
+    Verified,
+    Rejected,
 }
 
-#[init_computation_definition_accounts("init_referral_stats", payer)]
-#[derive(Accounts)]
-pub struct InitReferralStatsCompDef<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
+/// Attestation that `sender` paid `recipient` an amount of `asset_mint` in
+/// `[bucket_min, bucket_max)` at `paid_at`. Other programs gate access by
+/// deriving the PDA for the bucket they require and checking `is_verified`.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct PaymentProof {
+    pub payment: Pubkey,
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub asset_mint: Pubkey,
+    pub bucket_min: u64,
+    pub bucket_max: u64,
+    /// `timestamp` of the proven payment
+    pub paid_at: i64,
+    pub status: PaymentProofStatus,
+    pub pending_computation_offset: Option<u64>,
+    pub proven_at: i64,
+    pub bump: u8,
+}
 
-    #[account(
-        mut,
-        address = derive_mxe_pda!()
-    )]
-    pub mxe_account: Box<Account<'info, MXEAccount>>,
+impl PaymentProof {
+    pub fn is_verified(&self) -> bool {
+        self.status == PaymentProofStatus::Verified
+    }
+}
 
-    #[account(mut)]
-    /// CHECK: comp_def_account
-    pub comp_def_account: UncheckedAccount<'info>,
+/// Outcome of a confidential payment as reported to webhook receivers.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PaymentNotificationStatus {
+    /// Folded into the encrypted stats
+    Recorded,
+    /// Failed the circuit's validity check and left the stats untouched
+    Rejected,
+}
 
-    pub arcium_program: Program<'info, Arcium>,
-    pub system_program: Program<'info, System>,
+/// Standing authorization for an escrow's merchant to pull from one payer
+/// token account, backed by an SPL delegation to the mandate's own delegate
+/// PDA under `seeds::PULL_DELEGATE`.
+#[account]
+#[derive(InitSpace, Debug)]
+pub struct PullMandate {
+    pub escrow: Pubkey,
+    pub payer: Pubkey,
+    pub payer_token_account: Pubkey,
+    pub merchant: Pubkey,
+    /// May pull on the merchant's behalf; `Pubkey::default()` if none
+    pub crank: Pubkey,
+    pub mint: Pubkey,
+    /// Lifetime total the merchant may pull, matching the SPL delegation
+    pub cap: u64,
+    pub pulled: u64,
+    /// Unix time from which pulls fail; 0 if the mandate never expires
+    pub expires_at: i64,
+    /// Length in seconds of the periods `period_cap` applies to; 0 for none
+    pub period: i64,
+    /// Most the merchant may pull within one period
+    pub period_cap: u64,
+    /// Start of the current period
+    pub period_start: i64,
+    /// Pulled so far in the current period
+    pub period_pulled: u64,
+    pub created_at: i64,
+    pub bump: u8,
+    pub delegate_bump: u8,
 }
 
-#[init_computation_definition_accounts("process_payment", payer)]
-#[derive(Accounts)]
-pub struct InitProcessPaymentCompDef<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
+impl PullMandate {
+    /// Start of the period `now` falls in and its running total once
+    /// `amount` is pulled. Periods roll over in whole multiples of `period`
+    /// from the mandate's creation.
+    pub fn period_usage(&self, now: i64, amount: u64) -> Result<(i64, u64)> {
+        if self.period == 0 {
+            return Ok((self.period_start, 0));
+        }
+        let elapsed = now.saturating_sub(self.period_start);
+        let (start, pulled) = if elapsed >= self.period {
+            (self.period_start + elapsed / self.period * self.period, 0)
+        } else {
+            (self.period_start, self.period_pulled)
+        };
+        let pulled = pulled
+            .checked_add(amount)
+            .filter(|pulled| *pulled <= self.period_cap)
+            .ok_or(EscrowError::PullPeriodCapExceeded)?;
+        Ok((start, pulled))
+    }
+}
 
-    #[account(
-        mut,
-        address = derive_mxe_pda!()
-    )]
-    pub mxe_account: Box<Account<'info, MXEAccount>>,
+// Enhanced error codes
+#[error_code]
+pub enum EscrowError {
+    #[msg("Escrow is paused")]
+    EscrowPaused,
+    #[msg("Escrow already paused")]
+    AlreadyPaused,
+    #[msg("Escrow already active")]
+    AlreadyActive,
+    #[msg("Invalid authority")]
+    InvalidAuthority,
+    #[msg("The computation was aborted")]
+    AbortedComputation,
+    #[msg("Cluster not set")]
+    ClusterNotSet,
+    #[msg("Cluster account is not the active cluster")]
+    InvalidCluster,
+    #[msg("Callback does not match the pending computation")]
+    UnexpectedCallback,
+    #[msg("An encrypted stats computation is already in flight")]
+    StatsComputationPending,
+    #[msg("No encrypted stats computation is pending")]
+    NoPendingComputation,
+    #[msg("Pending delta buffer is full, run merge_deltas first")]
+    DeltaBufferFull,
+    #[msg("No pending deltas to merge")]
+    NoPendingDeltas,
+    #[msg("Compute budget vault cannot cover the computation fee")]
+    InsufficientComputeBudget,
+    #[msg("OTC swap amount must be greater than zero")]
+    InvalidOtcAmount,
+    #[msg("OTC swap is not in the required state")]
+    InvalidOtcStatus,
+    #[msg("OTC swap has expired")]
+    OtcSwapExpired,
+    #[msg("Payroll period must be positive")]
+    InvalidPayrollPeriod,
+    #[msg("Payroll recipient list is empty, too long or mismatched")]
+    TooManyPayrollRecipients,
+    #[msg("Payroll period has not elapsed yet")]
+    PayrollNotDue,
+    #[msg("Spending policy list is too long")]
+    PolicyListTooLong,
+    #[msg("Spending policy hours must be between 0 and 23")]
+    InvalidPolicyWindow,
+    #[msg("Payment exceeds the spending policy per-transaction limit")]
+    PolicyAmountExceeded,
+    #[msg("Mint is not allowed by the spending policy")]
+    PolicyMintNotAllowed,
+    #[msg("Recipient is not allowed by the spending policy")]
+    PolicyRecipientNotAllowed,
+    #[msg("Payment is outside the spending policy time window")]
+    PolicyOutsideWindow,
+    #[msg("Spending policy requires the co-signer for this amount")]
+    PolicyCosignerRequired,
+    #[msg("Payment requires second-signer approval")]
+    ApprovalRequired,
+    #[msg("Payment is below the approval threshold")]
+    ApprovalNotRequired,
+    #[msg("Approval window must be positive")]
+    InvalidApprovalWindow,
+    #[msg("Approval window has expired")]
+    ApprovalExpired,
+    #[msg("Circuit name is empty or too long")]
+    InvalidCircuitName,
+    #[msg("Circuit registry is full")]
+    CircuitRegistryFull,
+    #[msg("Circuit version cannot go backwards")]
+    CircuitVersionRegression,
+    #[msg("Asset has no encrypted volume counter")]
+    UnsupportedStatsAsset,
+    #[msg("Treasury token account required to sweep the token vault")]
+    MissingTreasuryTokenAccount,
+    #[msg("No balance at or below the dust threshold")]
+    NothingToSweep,
+    #[msg("Circuit breaker is not configured for this escrow")]
+    CircuitBreakerDisabled,
+    #[msg("Operator request interval cannot be negative")]
+    InvalidOperatorInterval,
+    #[msg("Analytics operator is rate limited, try again later")]
+    OperatorRateLimited,
+    #[msg("Referral or incinerator account missing for this payment's referral route")]
+    MissingReferralRoute,
+    #[msg("Tip treasury fee exceeds the standard treasury fee")]
+    InvalidTipFee,
+    #[msg("Fee must be between 0 and 10000 basis points")]
+    InvalidFeeBps,
+    #[msg("Sub-account has nothing to pay out")]
+    EmptySubAccount,
+    #[msg("Invalid invoice parameters")]
+    InvalidInvoice,
+    #[msg("Invoice is not open")]
+    InvalidInvoiceStatus,
+    #[msg("Invoice quote is missing or stale, re-quote first")]
+    StaleInvoiceQuote,
+    #[msg("Settlement price moved beyond the invoice slippage bound")]
+    InvoiceSlippageExceeded,
+    #[msg("Price account is not a verified update for the invoice feed")]
+    InvalidPriceFeed,
+    #[msg("Oracle price is too old")]
+    StalePrice,
+    #[msg("App fee exceeds the protocol cap or its accounts are missing")]
+    AppFeeNotAllowed,
+    #[msg("Payment has no unrefunded app fee")]
+    NoAppFeeToRefund,
+    #[msg("Milestone list is empty, too long or malformed")]
+    InvalidMilestones,
+    #[msg("Milestone is not in the required state")]
+    InvalidMilestoneStatus,
+    #[msg("Invalid auction parameters")]
+    InvalidAuction,
+    #[msg("Auction is not in the required state")]
+    InvalidAuctionStatus,
+    #[msg("Bidding has closed")]
+    AuctionClosed,
+    #[msg("Bidding is still open")]
+    AuctionStillOpen,
+    #[msg("Auction has no free bid slots")]
+    AuctionFull,
+    #[msg("Invalid HTLC parameters")]
+    InvalidHtlc,
+    #[msg("HTLC has expired")]
+    HtlcExpired,
+    #[msg("HTLC has not expired yet")]
+    HtlcNotExpired,
+    #[msg("Preimage does not match the hash lock")]
+    InvalidPreimage,
+    #[msg("Protocol is in maintenance mode; computations cannot be queued")]
+    MaintenanceMode,
+    #[msg("Payment legs do not add up to the payment amount")]
+    FeeInvariantViolated,
+    #[msg("Invalid fee conversion rate")]
+    InvalidFeeConversionRate,
+    #[msg("Fee conversion rate is stale")]
+    StaleFeeConversionRate,
+    #[msg("Invalid vault signer configuration")]
+    InvalidVaultSigners,
+    #[msg("Signer is not in the vault signer set")]
+    NotVaultSigner,
+    #[msg("Withdrawal is above the multisig threshold; open a proposal")]
+    WithdrawalProposalRequired,
+    #[msg("Signer has already approved this withdrawal")]
+    WithdrawalAlreadyApproved,
+    #[msg("Withdrawal does not have enough approvals")]
+    InsufficientWithdrawalApprovals,
+    #[msg("Vault signers changed since this proposal was opened")]
+    StaleWithdrawalProposal,
+    #[msg("Invalid recovery guardian configuration")]
+    InvalidRecoveryConfig,
+    #[msg("Signer is not a recovery guardian")]
+    NotRecoveryGuardian,
+    #[msg("A recovery is already pending")]
+    RecoveryAlreadyPending,
+    #[msg("No recovery is pending")]
+    NoPendingRecovery,
+    #[msg("Guardian has already approved this recovery")]
+    RecoveryAlreadyApproved,
+    #[msg("Recovery does not have enough guardian approvals")]
+    RecoveryThresholdNotMet,
+    #[msg("Recovery timelock or challenge period has not elapsed")]
+    RecoveryTimelocked,
+    #[msg("Invalid campaign parameters")]
+    InvalidCampaign,
+    #[msg("Campaign does not apply to this escrow or asset")]
+    CampaignMismatch,
+    #[msg("Fee tiers must ascend in volume and not raise the fee")]
+    InvalidFeeTiers,
+    #[msg("Escrow vests referral rewards; pass the referrer's vesting account")]
+    MissingReferralVesting,
+    #[msg("Nothing has vested yet")]
+    NothingVested,
+    #[msg("Escrow is quarantining payments; pass its quarantine account")]
+    QuarantineRequired,
+    #[msg("Quarantine queue is full")]
+    QuarantineFull,
+    #[msg("No quarantined payment with this id")]
+    QuarantinedPaymentNotFound,
+    #[msg("Account does not match the quarantined payment")]
+    QuarantineAccountMismatch,
+    #[msg("Archive batch is empty, too large or contains a non-payment account")]
+    InvalidArchiveBatch,
+    #[msg("Payment is too recent to archive")]
+    PaymentTooRecent,
+    #[msg("Health check accounts must be this escrow's vaults or sub-accounts")]
+    InvalidHealthCheckAccount,
+    #[msg("Treasury or treasury vault account missing for this payment")]
+    MissingTreasuryRoute,
+    #[msg("Treasury vault holds nothing above its rent reserve")]
+    NothingToSweep,
+    #[msg("Referral link scope must be this escrow or the protocol config")]
+    InvalidReferralScope,
+    #[msg("A referee cannot refer themselves")]
+    SelfReferral,
+    #[msg("An approved capped spend is still waiting to be used")]
+    SpendApprovalOutstanding,
+    #[msg("This confidential payment was not approved against the sender's spend cap")]
+    SpendCapNotApproved,
+    #[msg("Escrow routes fees to insurance; pass its insurance pool")]
+    MissingInsurancePool,
+    #[msg("Claim amount must be positive and at most the payment amount")]
+    InvalidClaim,
+    #[msg("Insurance pool cannot cover this claim")]
+    InsufficientInsuranceFunds,
+    #[msg("Crank reward must not exceed the per-period cap")]
+    InvalidCrankReward,
+    #[msg("Signer is not the proposed treasury")]
+    NoPendingTreasury,
+    #[msg("Remaining accounts must be recipient, token account, referral triples")]
+    InvalidRecipientAccounts,
+    #[msg("Only SOL payments to the pool's recipient made after it opened are matched")]
+    PaymentNotMatchable,
+    #[msg("Voucher was not minted by this escrow's voucher authority")]
+    InvalidFeeVoucher,
+    #[msg("No redeemed fee voucher left to spend")]
+    NoFeeWaiver,
+    #[msg("Escrows tagged with a jurisdiction must pass its pause account")]
+    JurisdictionPauseRequired,
+    #[msg("Escrow's jurisdiction is paused by the protocol")]
+    JurisdictionPaused,
+    #[msg("Jurisdiction 0 means untagged and cannot be paused")]
+    InvalidJurisdiction,
+    #[msg("Escrow has a fee hook; pass its program")]
+    MissingFeeHook,
+    #[msg("Fee hook returned no or malformed fee data")]
+    InvalidFeeHookOutput,
+    #[msg("Fee hook split is outside protocol bounds")]
+    FeeHookOutOfBounds,
+    #[msg("Escrow requires the recipient to counter-sign this payment")]
+    CounterSignatureRequired,
+    #[msg("Payment's validity window has not opened yet")]
+    PaymentNotYetValid,
+    #[msg("Payment's validity window has closed")]
+    PaymentWindowExpired,
+    #[msg("Escrow settles referral rewards in USDC; pass the referrer's ledger")]
+    MissingReferralUsdcLedger,
+    #[msg("Referrer has no USDC rewards outstanding")]
+    NothingToSettle,
+    #[msg("Escrow restricts vault payments to a settlement window; pass it")]
+    SettlementWindowRequired,
+    #[msg("Settlement window hours or days are invalid")]
+    InvalidSettlementWindow,
+    #[msg("Settlement window is closed")]
+    SettlementWindowClosed,
+    #[msg("Settlement window queue is full")]
+    SettlementQueueFull,
+    #[msg("Drain deferred payments before clearing the settlement window")]
+    SettlementQueueNotEmpty,
+    #[msg("No deferred payment with this id")]
+    DeferredPaymentNotFound,
+    #[msg("This owner capability has been sunset")]
+    CapabilitySunset,
+    #[msg("A capability sunset can only be brought forward")]
+    CapabilitySunsetLocked,
+    #[msg("Stats dimensions must be a non-empty set of STATS_DIMENSION_* bits")]
+    InvalidStatsDimensions,
+    #[msg("Escrow does not track this confidential metric")]
+    StatsDimensionNotTracked,
+    #[msg("Mint is not on the SPL payment allowlist")]
+    MintNotAllowed,
+    #[msg("Mint allowlist is full")]
+    MintAllowlistFull,
+    #[msg("Sponsored payments must pass the relayer registry")]
+    RelayerRegistryRequired,
+    #[msg("Relayer is not registered")]
+    RelayerNotRegistered,
+    #[msg("Relayer is already registered")]
+    RelayerAlreadyRegistered,
+    #[msg("Relayer registry is full")]
+    RelayerRegistryFull,
+    #[msg("Token account is not the owner's Token-2022 account for this mint")]
+    InvalidConfidentialTokenAccount,
+    #[msg("Only SOL payments can be refunded")]
+    UnsupportedRefundAsset,
+    #[msg("Refund is zero or exceeds what the recipient received")]
+    RefundExceedsPayment,
+    #[msg("Refund rate threshold must be at most 10000 bps")]
+    InvalidRefundRateThreshold,
+    #[msg("Escrow deposit amount must be positive")]
+    InvalidEscrowDeposit,
+    #[msg("Escrow deposit is not held")]
+    InvalidDepositStatus,
+    #[msg("SPL escrow deposits need the mint, vault, token accounts and token program")]
+    MissingDepositTokenAccounts,
+    #[msg("Escrow has no arbiter to resolve disputes")]
+    ArbiterNotSet,
+    #[msg("Dispute is not open")]
+    DisputeNotOpen,
+    #[msg("Dispute already holds the maximum evidence")]
+    DisputeEvidenceFull,
+    #[msg("Dispute split exceeds the deposit")]
+    InvalidDisputeSplit,
+    #[msg("Proof bucket must be a non-empty range")]
+    InvalidProofBucket,
+    #[msg("Time-locked payment needs a valid unlock or expiry")]
+    InvalidTimeLock,
+    #[msg("Payment is still time-locked")]
+    PaymentLocked,
+    #[msg("Payment has expired")]
+    PaymentExpired,
+    #[msg("Payment has not expired")]
+    PaymentNotExpired,
+    #[msg("Payment is not time-locked")]
+    PaymentNotTimeLocked,
+    #[msg("Pull mandate cap must be non-zero")]
+    InvalidPullCap,
+    #[msg("Pull would exceed the mandate's cap")]
+    PullCapExceeded,
+    #[msg("Pull period and period cap must both be set, within the cap")]
+    InvalidPullPeriod,
+    #[msg("Pull mandate has expired")]
+    PullMandateExpired,
+    #[msg("Pull would exceed the mandate's cap for this period")]
+    PullPeriodCapExceeded,
+    #[msg("An ed25519 counter-signature needs the payment's valid_before_slot")]
+    CounterSignatureDeadlineRequired,
+}
 
-    #[account(mut)]
-    /// CHECK: comp_def_account
-    pub comp_def_account: UncheckedAccount<'info>,
+// Events for encrypted operations
+#[event]
+pub struct ConfidentialPaymentEvent {
+    pub timestamp: i64,
+    pub sender: Pubkey,
+}
 
-    pub arcium_program: Program<'info, Arcium>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct ThresholdCheckEvent {
+    pub meets_threshold: bool,
+    pub timestamp: i64,
 }
 
-// Split the large struct into smaller components
-#[account]
-pub struct PaymentAccounts<'info> {
-    #[account(mut)]
-    pub sender: Signer<'info>,
-    #[account(
-        mut,
-        seeds = [b"payments", sender.key().as_ref(), &computation_offset.to_le_bytes()],
-        bump
-    )]
-    pub payment: Account<'info, PaymentAccount>,
-    pub owner: SystemAccount<'info>,
-    #[account(
-        mut,
-        seeds = [b"escrow", owner.key().as_ref()],
-        bump = escrow.bump,
-        constraint = escrow.owner == owner.key(),
-    )]
-    pub escrow: Account<'info, EscrowAccount>,
+#[event]
+pub struct PaymentCountEvent {
+    pub total_payments: u64,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct PaymentTransferAccounts<'info> {
-    #[account(mut)]
-    pub recipient: SystemAccount<'info>,
-    /// CHECK: Referral account
-    #[account(mut)]
-    pub referrer: AccountInfo<'info>,
-    /// CHECK: Treasury account
-    #[account(mut)]
-    pub treasury: AccountInfo<'info>,
+#[event]
+pub struct ProtocolConfigInitializedEvent {
+    pub admin: Pubkey,
+    pub treasury: Pubkey,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct ComputationAccounts<'info> {
-    /// CHECK: Computation account
-    #[account(mut)]
-    pub computation: AccountInfo<'info>,
-    /// CHECK: Callback account
-    #[account(mut)]
-    pub callback: AccountInfo<'info>,
-    /// CHECK: Callback accounts
-    pub remaining_accounts: Vec<AccountInfo<'info>>,
+#[event]
+pub struct PendingComputationClearedEvent {
+    pub escrow: Pubkey,
+    /// Escrow `sequence` as of this event
+    pub sequence: u64,
+    pub computation_offset: u64,
+    pub pending_since_slot: u64,
+    pub cleared_at_slot: u64,
 }
 
-// Grouped computation accounts for better organization
-#[derive(Accounts)]
-pub struct ComputationPdaAccounts<'info> {
-    #[account(
-        mut,
-        address = derive_mempool_pda!()
-    )]
-    /// CHECK: mempool_account
-    pub mempool_account: UncheckedAccount<'info>,
+#[event]
+pub struct DeltasMergedEvent {
+    pub escrow: Pubkey,
+    /// Escrow `sequence` as of this event
+    pub sequence: u64,
+    pub merged: u8,
+    pub remaining: u8,
+    pub timestamp: i64,
+}
 
-    #[account(
-        mut,
-        address = derive_execpool_pda!()
-    )]
-    /// CHECK: executing_pool
-    pub executing_pool: UncheckedAccount<'info>,
+#[event]
+pub struct ComputeBudgetFundedEvent {
+    pub escrow: Pubkey,
+    pub funder: Pubkey,
+    pub amount: u64,
+    pub balance: u64,
+}
 
-    #[account(
-        mut,
-        address = derive_comp_pda!(computation_offset)
-    )]
-    /// CHECK: computation_account
-    pub computation_account: UncheckedAccount<'info>,
+#[event]
+pub struct ComputeBudgetLowEvent {
+    pub escrow: Pubkey,
+    pub balance: u64,
+    pub threshold: u64,
+}
+
+#[event]
+pub struct ActiveClusterChangedEvent {
+    pub cluster: Option<Pubkey>,
+    pub use_secondary: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct OtcSwapEvent {
+    pub otc_swap: Pubkey,
+    pub status: OtcSwapStatus,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PayrollRunEvent {
+    pub payroll_schedule: Pubkey,
+    pub recipients: u8,
+    pub total: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct EscrowVaultCreatedEvent {
+    pub escrow: Pubkey,
+    pub vault: Pubkey,
+    pub mint: Pubkey,
+}
+
+#[event]
+pub struct VaultPaymentEvent {
+    pub escrow: Pubkey,
+    /// Escrow `sequence` as of this event
+    pub sequence: u64,
+    pub payment_id: u64,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub asset_mint: Pubkey,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PendingApprovalEvent {
+    pub pending_approval: Pubkey,
+    pub escrow: Pubkey,
+    /// Escrow `sequence` as of this event
+    pub sequence: u64,
+    pub amount: u64,
+    pub approved: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CircuitRegisteredEvent {
+    pub name: String,
+    pub comp_def_offset: u32,
+    pub version: u16,
+    pub enabled: bool,
+}
+
+#[event]
+pub struct ReferralReportEvent {
+    pub referrer: Pubkey,
+    /// Totals encrypted to the referrer's report key:
+    /// [total_referrals, total_rewards]
+    pub encryption_key: [u8; 32],
+    pub nonce: u128,
+    pub ciphertexts: [[u8; 32]; 2],
+}
+
+#[event]
+pub struct RecentVolumeThresholdCheckEvent {
+    pub meets_threshold: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AssetVolumeEvent {
+    pub asset_index: u8,
+    pub volume: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DustSweptEvent {
+    pub escrow: Pubkey,
+    pub treasury: Pubkey,
+    /// Mint of the swept token vault, if one was swept
+    pub mint: Option<Pubkey>,
+    pub tokens_swept: u64,
+    pub lamports_swept: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CircuitBreakerTrippedEvent {
+    pub escrow: Pubkey,
+    /// Escrow `sequence` as of this event
+    pub sequence: u64,
+    pub threshold: u64,
+    pub timestamp: i64,
+}
 
-    #[account(
-        address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_PAYMENT)
-    )]
-    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+#[event]
+pub struct TipEvent {
+    pub escrow: Pubkey,
+    /// Escrow `sequence` as of this event
+    pub sequence: u64,
+    pub recipient: Pubkey,
+    /// Only set when the sender opted into public attribution
+    pub sender: Option<Pubkey>,
+    pub amount: u64,
+    pub treasury_fee: u64,
+    pub timestamp: i64,
 }
 
-#[queue_computation_accounts("process_payment", sender)]
-#[derive(Accounts)]
-#[instruction(computation_offset: u64)]
-pub struct SendPaymentSolEncrypted<'info> {
-    // Payment related accounts
-    #[account(mut)]
-    pub payment_accounts: PaymentAccounts<'info>,
-    
-    // Transfer related accounts
-    pub transfer_accounts: PaymentTransferAccounts<'info>,
-    
-    // Computation related accounts
-    pub computation_accounts: ComputationAccounts<'info>,
-    
-    // Computation PDA accounts
-    pub pda_accounts: ComputationPdaAccounts<'info>,
-    
-    // System program
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct SubAccountCreditedEvent {
+    pub escrow: Pubkey,
+    /// Escrow `sequence` as of this event
+    pub sequence: u64,
+    pub seller: Pubkey,
+    pub amount: u64,
+    pub balance: u64,
+    pub timestamp: i64,
+}
 
-    #[account(
-        mut,
-        address = derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet)
-    )]
-    pub cluster_account: Account<'info, Cluster>,
+#[event]
+pub struct SubAccountPayoutEvent {
+    pub escrow: Pubkey,
+    pub seller: Pubkey,
+    pub payout_address: Pubkey,
+    pub amount: u64,
+    pub marketplace_fee: u64,
+    pub timestamp: i64,
+}
 
-    #[account(
-        mut,
-        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
-    )]
-    pub pool_account: Account<'info, FeePool>,
+#[event]
+pub struct InvoiceQuotedEvent {
+    pub invoice: Pubkey,
+    pub usd_cents: u64,
+    pub token_amount: u64,
+    pub timestamp: i64,
+}
 
-    #[account(
-        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
-    )]
-    pub clock_account: Account<'info, ClockAccount>,
+#[event]
+pub struct InvoiceSettledEvent {
+    pub invoice: Pubkey,
+    pub payer: Pubkey,
+    pub mint: Pubkey,
+    pub usd_cents: u64,
+    pub token_amount: u64,
+    /// Part of `token_amount` paid to the escrow treasury
+    pub treasury_fee: u64,
+    /// Part of `token_amount` paid to the referrer
+    pub referral_reward: u64,
+    pub timestamp: i64,
+}
 
-    pub system_program: Program<'info, System>,
-    pub arcium_program: Program<'info, Arcium>,
+#[event]
+pub struct AppFeeChargedEvent {
+    pub payment: Pubkey,
+    pub app_fee_destination: Pubkey,
+    pub app_fee: u64,
+    pub timestamp: i64,
 }
 
-#[callback_accounts("process_payment")]
-#[derive(Accounts)]
-pub struct ProcessPaymentCallback<'info> {
-    pub arcium_program: Program<'info, Arcium>,
+#[event]
+pub struct AppFeeRefundedEvent {
+    pub payment: Pubkey,
+    pub app_fee_destination: Pubkey,
+    pub app_fee: u64,
+    pub timestamp: i64,
+}
 
-    #[account(
-        address = derive_comp_def_pda!(COMP_DEF_OFFSET_PROCESS_PAYMENT)
-    )]
-    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+#[event]
+pub struct WebhookKeyRegisteredEvent {
+    pub escrow: Pubkey,
+    /// Escrow `sequence` as of this event
+    pub sequence: u64,
+    pub webhook_key: Pubkey,
+    pub timestamp: i64,
+}
 
-    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
-    /// CHECK: instructions_sysvar
-    pub instructions_sysvar: AccountInfo<'info>,
+#[event]
+pub struct MilestoneEvent {
+    pub milestones: Pubkey,
+    pub index: u8,
+    pub amount: u64,
+    pub status: MilestoneStatus,
+    pub timestamp: i64,
+}
 
-    #[account(mut)]
-    pub escrow: Account<'info, EscrowAccount>,
+#[event]
+pub struct AuctionResolvedEvent {
+    pub auction: Pubkey,
+    /// None when no bid met the reserve
+    pub winner: Option<Pubkey>,
+    pub clearing_price: u64,
+    pub timestamp: i64,
 }
 
-#[queue_computation_accounts("check_volume_threshold", authority)]
-#[derive(Accounts)]
-#[instruction(computation_offset: u64)]
-pub struct CheckVolumeThreshold<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
+#[event]
+pub struct HtlcEvent {
+    pub htlc: Pubkey,
+    pub hash_lock: [u8; 32],
+    /// Revealed preimage, set on claim
+    pub preimage: Option<Vec<u8>>,
+    pub action: HtlcAction,
+    pub timestamp: i64,
+}
 
-    #[account(
-        seeds = [b"escrow", authority.key().as_ref()],
-        bump = escrow.bump,
-    )]
-    pub escrow: Account<'info, EscrowAccount>,
+#[event]
+pub struct MaintenanceModeChangedEvent {
+    pub enabled: bool,
+    pub timestamp: i64,
+}
 
-    #[account(
-        init_if_needed,
-        space = 9,
-        payer = authority,
-        seeds = [&SIGN_PDA_SEED],
-        bump,
-        address = derive_sign_pda!(),
-    )]
-    pub sign_pda_account: Account<'info, SignerAccount>,
+#[event]
+pub struct FeeConversionRateUpdatedEvent {
+    pub mint: Pubkey,
+    pub usdc_per_unit: u64,
+    pub quoted_at: i64,
+    pub max_age_secs: i64,
+}
 
-    #[account(
-        address = derive_mxe_pda!()
-    )]
-    pub mxe_account: Account<'info, MXEAccount>,
+#[event]
+pub struct FeesConvertedEvent {
+    pub escrow: Pubkey,
+    /// Escrow `sequence` as of this event
+    pub sequence: u64,
+    pub mint: Pubkey,
+    /// Fees owed in the payment token, before conversion
+    pub token_fees: u64,
+    pub treasury_usdc: u64,
+    pub referral_usdc: u64,
+    pub usdc_per_unit: u64,
+    pub timestamp: i64,
+}
 
-    #[account(
-        mut,
-        address = derive_mempool_pda!()
-    )]
-    /// CHECK: mempool_account
-    pub mempool_account: UncheckedAccount<'info>,
+#[event]
+pub struct WithdrawalProposalEvent {
+    pub proposal: Pubkey,
+    /// Proposer on creation, otherwise the approving signer
+    pub signer: Pubkey,
+    pub amount: u64,
+    pub approvals: u8,
+    pub required_approvals: u8,
+    pub timestamp: i64,
+}
 
-    #[account(
-        mut,
-        address = derive_execpool_pda!()
-    )]
-    /// CHECK: executing_pool
-    pub executing_pool: UncheckedAccount<'info>,
+#[event]
+pub struct VaultWithdrawalEvent {
+    pub escrow: Pubkey,
+    /// None for owner withdrawals below the multisig threshold
+    pub proposal: Option<Pubkey>,
+    pub destination: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
 
-    #[account(
-        mut,
-        address = derive_comp_pda!(computation_offset)
-    )]
-    /// CHECK: computation_account
-    pub computation_account: UncheckedAccount<'info>,
+#[event]
+pub struct RecoveryEvent {
+    pub escrow: Pubkey,
+    pub new_owner: Pubkey,
+    pub stage: RecoveryStage,
+    /// Guardian that initiated or approved; None for owner and crank actions
+    pub guardian: Option<Pubkey>,
+    pub approvals: u8,
+    pub timestamp: i64,
+}
 
-    #[account(
-        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_THRESHOLD)
-    )]
-    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+#[event]
+pub struct CampaignBoostEvent {
+    pub campaign: Pubkey,
+    /// Referral paid above the default share on this payment
+    pub extra_referral: u64,
+    pub spent: u64,
+    pub budget: u64,
+    pub timestamp: i64,
+}
 
-    #[account(
-        mut,
-        address = derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet)
-    )]
-    pub cluster_account: Account<'info, Cluster>,
+#[event]
+pub struct VestedRewardsClaimedEvent {
+    pub escrow: Pubkey,
+    pub referrer: Pubkey,
+    pub amount: u64,
+    pub still_vesting: u64,
+    pub timestamp: i64,
+}
 
-    #[account(
-        mut,
-        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
-    )]
-    pub pool_account: Account<'info, FeePool>,
+#[event]
+pub struct QuarantineEvent {
+    pub escrow: Pubkey,
+    /// Escrow `sequence` as of this event
+    pub sequence: u64,
+    pub payment_id: u64,
+    pub amount: u64,
+    pub action: QuarantineAction,
+    pub timestamp: i64,
+}
 
-    #[account(
-        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
-    )]
-    pub clock_account: Account<'info, ClockAccount>,
+#[event]
+pub struct EscrowCreatedFromTemplateEvent {
+    pub escrow: Pubkey,
+    /// Escrow `sequence` as of this event
+    pub sequence: u64,
+    pub owner: Pubkey,
+    pub template: Pubkey,
+    pub timestamp: i64,
+}
 
-    pub system_program: Program<'info, System>,
-    pub arcium_program: Program<'info, Arcium>,
+/// `calculate_fees` output, encrypted to the `quote_fees` caller: treasury
+/// fee, referral fee and net amount, in that order.
+#[event]
+pub struct FeeQuoteEvent {
+    pub encryption_key: [u8; 32],
+    pub nonce: u128,
+    pub ciphertexts: [[u8; 32]; 3],
 }
 
-#[callback_accounts("check_volume_threshold")]
-#[derive(Accounts)]
-pub struct CheckVolumeThresholdCallback<'info> {
-    pub arcium_program: Program<'info, Arcium>,
+#[event]
+pub struct SharedStatsEvent {
+    pub escrow: Pubkey,
+    pub encryption_key: [u8; 32],
+    pub nonce: u128,
+    pub ciphertexts: [[u8; 32]; ESCROW_STATS_FIELDS],
+}
 
-    #[account(
-        address = derive_comp_def_pda!(COMP_DEF_OFFSET_CHECK_THRESHOLD)
-    )]
-    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+#[event]
+pub struct PaymentsArchivedEvent {
+    pub archive: Pubkey,
+    pub root: [u8; 32],
+    pub leaf_count: u32,
+    pub rent_returned: u64,
+    pub timestamp: i64,
+}
 
-    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
-    /// CHECK: instructions_sysvar
-    pub instructions_sysvar: AccountInfo<'info>,
+#[event]
+pub struct TreasurySweptEvent {
+    pub escrow: Pubkey,
+    pub treasury: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
 }
 
-#[queue_computation_accounts("reveal_payment_count", authority)]
-#[derive(Accounts)]
-#[instruction(computation_offset: u64)]
-pub struct RevealPaymentCount<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
+#[event]
+pub struct ReferralRegisteredEvent {
+    pub scope: Pubkey,
+    pub referee: Pubkey,
+    pub referrer: Pubkey,
+    pub protocol_wide: bool,
+    pub timestamp: i64,
+}
 
-    #[account(
-        seeds = [b"escrow", authority.key().as_ref()],
-        bump = escrow.bump,
-    )]
-    pub escrow: Account<'info, EscrowAccount>,
+#[event]
+pub struct SpendCapSetEvent {
+    pub sender: Pubkey,
+    pub period_start: i64,
+    pub timestamp: i64,
+}
 
-    #[account(
-        init_if_needed,
-        space = 9,
-        payer = authority,
-        seeds = [&SIGN_PDA_SEED],
-        bump,
-        address = derive_sign_pda!(),
-    )]
-    pub sign_pda_account: Account<'info, SignerAccount>,
+#[event]
+pub struct SpendCapCheckedEvent {
+    pub sender: Pubkey,
+    pub approved: bool,
+    pub timestamp: i64,
+}
 
-    #[account(
-        address = derive_mxe_pda!()
-    )]
-    pub mxe_account: Account<'info, MXEAccount>,
+#[event]
+pub struct InsurancePoolEvent {
+    pub pool: Pubkey,
+    pub movement: InsuranceMovement,
+    pub counterparty: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
 
-    #[account(
-        mut,
-        address = derive_mempool_pda!()
-    )]
-    /// CHECK: mempool_account
-    pub mempool_account: UncheckedAccount<'info>,
+#[event]
+pub struct CrankRewardPaidEvent {
+    pub escrow: Pubkey,
+    pub cranker: Pubkey,
+    pub crank: CrankKind,
+    pub amount: u64,
+    pub paid_in_period: u64,
+    pub timestamp: i64,
+}
 
-    #[account(
-        mut,
-        address = derive_execpool_pda!()
-    )]
-    /// CHECK: executing_pool
-    pub executing_pool: UncheckedAccount<'info>,
+#[event]
+pub struct TreasuryRotationEvent {
+    pub escrow: Pubkey,
+    /// Escrow `sequence` as of this event
+    pub sequence: u64,
+    pub treasury: Pubkey,
+    pub stage: TreasuryRotationStage,
+    pub timestamp: i64,
+}
 
-    #[account(
-        mut,
-        address = derive_comp_pda!(computation_offset)
-    )]
-    /// CHECK: computation_account
-    pub computation_account: UncheckedAccount<'info>,
+#[event]
+pub struct MatchingPoolEvent {
+    pub pool: Pubkey,
+    pub movement: MatchingMovement,
+    pub counterparty: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
 
-    #[account(
-        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REVEAL_COUNT)
-    )]
-    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+#[event]
+pub struct EscrowUpdatedEvent {
+    pub escrow: Pubkey,
+    pub sequence: u64,
+    pub timestamp: i64,
+}
 
-    #[account(
-        mut,
-        address = derive_cluster_pda!(mxe_account, EscrowError::ClusterNotSet)
-    )]
-    pub cluster_account: Account<'info, Cluster>,
+#[event]
+pub struct FeeVoucherRedeemedEvent {
+    pub escrow: Pubkey,
+    pub voucher_mint: Pubkey,
+    pub sender: Pubkey,
+    /// Waivers the sender has left after this redemption
+    pub credits: u32,
+    pub timestamp: i64,
+}
 
-    #[account(
-        mut,
-        address = ARCIUM_FEE_POOL_ACCOUNT_ADDRESS,
-    )]
-    pub pool_account: Account<'info, FeePool>,
+#[event]
+pub struct JurisdictionPauseEvent {
+    pub jurisdiction: u16,
+    pub paused: bool,
+    pub timestamp: i64,
+}
 
-    #[account(
-        address = ARCIUM_CLOCK_ACCOUNT_ADDRESS,
-    )]
-    pub clock_account: Account<'info, ClockAccount>,
+#[event]
+pub struct FeeHookEvent {
+    pub escrow: Pubkey,
+    pub fee_hook: Pubkey,
+    /// Escrow `sequence` as of this event
+    pub sequence: u64,
+    pub timestamp: i64,
+}
 
-    pub system_program: Program<'info, System>,
-    pub arcium_program: Program<'info, Arcium>,
+#[event]
+pub struct ReferralUsdcLedgerEvent {
+    pub escrow: Pubkey,
+    pub referrer: Pubkey,
+    pub movement: ReferralUsdcMovement,
+    pub usdc_amount: u64,
+    pub outstanding_usdc: u64,
+    pub timestamp: i64,
 }
 
-#[callback_accounts("reveal_payment_count")]
-#[derive(Accounts)]
-pub struct RevealPaymentCountCallback<'info> {
-    pub arcium_program: Program<'info, Arcium>,
+#[event]
+pub struct SettlementWindowEvent {
+    pub escrow: Pubkey,
+    /// Escrow `sequence` as of this event
+    pub sequence: u64,
+    pub payment_id: u64,
+    pub amount: u64,
+    pub action: SettlementWindowAction,
+    pub timestamp: i64,
+}
 
-    #[account(
-        address = derive_comp_def_pda!(COMP_DEF_OFFSET_REVEAL_COUNT)
-    )]
-    pub comp_def_account: Account<'info, ComputationDefinitionAccount>,
+#[event]
+pub struct CapabilitySunsetEvent {
+    pub escrow: Pubkey,
+    /// Escrow `sequence` as of this event
+    pub sequence: u64,
+    pub capability: OwnerCapability,
+    pub sunset_at: i64,
+    pub successor: Pubkey,
+    pub timestamp: i64,
+}
 
-    #[account(address = ::anchor_lang::solana_program::sysvar::instructions::ID)]
-    /// CHECK: instructions_sysvar
-    pub instructions_sysvar: AccountInfo<'info>,
+#[event]
+pub struct MintAllowlistEvent {
+    pub mint: Pubkey,
+    pub allowed: bool,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct UpdateEscrowActive<'info> {
-    #[account(mut)]
-    pub owner: Signer<'info>,
+#[event]
+pub struct RelayerRegistryEvent {
+    pub relayer: Pubkey,
+    pub registered: bool,
+    pub timestamp: i64,
+}
 
-    #[account(
-        mut,
-        seeds = [b"escrow", owner.key().as_ref()],
-        bump = escrow.bump,
-        constraint = escrow.owner == owner.key(),
-    )]
-    pub escrow: Account<'info, EscrowAccount>,
+#[event]
+pub struct ConfidentialTokenPaymentEvent {
+    pub escrow: Pubkey,
+    /// Escrow `sequence` as of this event
+    pub sequence: u64,
+    pub sender: Pubkey,
+    pub recipient: Pubkey,
+    pub mint: Pubkey,
+    pub fee_lamports: u64,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct UpdateTreasury<'info> {
-    #[account(mut)]
-    pub owner: Signer<'info>,
+#[event]
+pub struct PaymentRefundedEvent {
+    pub escrow: Pubkey,
+    /// Escrow `sequence` as of this event
+    pub sequence: u64,
+    pub payment: Pubkey,
+    pub sender: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
 
-    #[account(
-        mut,
-        seeds = [b"escrow", owner.key().as_ref()],
-        bump = escrow.bump,
-        constraint = escrow.owner == owner.key(),
-    )]
-    pub escrow: Account<'info, EscrowAccount>,
+#[event]
+pub struct RefundRateCheckEvent {
+    pub threshold_bps: u16,
+    pub exceeds_threshold: bool,
+    pub timestamp: i64,
 }
 
-// Keep existing SendPaymentSol, SendPaymentUsdc, SendPaymentZenZec structures unchanged
-#[derive(Accounts)]
-pub struct SendPaymentSol<'info> {
-    #[account(mut)]
-    pub sender: Signer<'info>,
-    #[account(mut)]
-    pub recipient: SystemAccount<'info>,
-    #[account(mut)]
-    pub referral: SystemAccount<'info>,
-    #[account(mut)]
-    pub treasury: SystemAccount<'info>,
-    #[account(
-        init,
-        payer = sender,
-        space = 8 + PaymentAccount::INIT_SPACE,
-        seeds = [b"payments", sender.key().as_ref(), b"sol"],
-        bump
-    )]
-    pub payment: Account<'info, PaymentAccount>,
-    pub owner: SystemAccount<'info>,
-    #[account(
-        mut,
-        seeds = [b"escrow", owner.key().as_ref()],
-        bump = escrow.bump,
-        constraint = escrow.owner == owner.key(),
-    )]
-    pub escrow: Account<'info, EscrowAccount>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct EscrowDepositEvent {
+    pub deposit: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub status: EscrowDepositStatus,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct SendPaymentZenZec<'info> {
-    #[account(mut)]
-    pub sender: Signer<'info>,
-    
-    // Token accounts
-    #[account(mut)]
-    pub sender_token_account: Account<'info, token_state::Account>,
-    #[account(mut)]
-    pub recipient_token_account: Account<'info, token_state::Account>,
-    #[account(mut)]
-    pub referral_token_account: Account<'info, token_state::Account>,
-    #[account(mut)]
-    pub treasury_token_account: Account<'info, token_state::Account>,
-    
-    // Payment account
-    #[account(
-        init,
-        payer = sender,
-        space = 8 + PaymentAccount::INIT_SPACE,
-        seeds = [b"payments", sender.key().as_ref(), b"zenzec"],
-        bump
-    )]
-    pub payment: Account<'info, PaymentAccount>,
-    
-    // Escrow account
-    #[account(
-        mut,
-        seeds = [b"escrow", owner.key().as_ref()],
-        bump = escrow.bump,
-        constraint = escrow.owner == owner.key(),
-    )]
-    pub escrow: Account<'info, EscrowAccount>,
-    
-    // Program accounts
-    pub owner: SystemAccount<'info>,
-    #[account(address = ZENZEC_MINT)]
-    pub mint: Account<'info, token_state::Mint>,
-    pub token_program: Program<'info, token_2022::spl_token::ID>,
-    pub system_program: Program<'info, System>,
-    
-    // System accounts
-    pub rent: Sysvar<'info, Rent>,
-    pub clock: Sysvar<'info, Clock>,
-    
-    // Additional token accounts (kept for backward compatibility)
-    /// CHECK: This is the sender's token account (ATA)
-    #[account(mut)]
-    pub sender_ata: AccountInfo<'info>,
-    /// CHECK: This is the recipient's token account (ATA)
-    #[account(mut)]
-    pub recipient_ata: AccountInfo<'info>,
-    /// CHECK: This is the referral's token account (ATA)
-    #[account(mut)]
-    pub referral_ata: AccountInfo<'info>,
-    /// CHECK: This is the treasury's token account (ATA)
-    #[account(mut)]
-    pub treasury_ata: AccountInfo<'info>,
+#[event]
+pub struct DisputeEvent {
+    pub dispute: Pubkey,
+    pub deposit: Pubkey,
+    pub actor: Pubkey,
+    pub status: DisputeStatus,
+    /// Set when evidence is submitted
+    pub evidence_hash: Option<[u8; 32]>,
+    /// Set on resolution
+    pub recipient_amount: Option<u64>,
+    pub timestamp: i64,
 }
 
-#[derive(Accounts)]
-pub struct SendPaymentUsdc<'info> {
-    #[account(mut)]
-    pub sender: Signer<'info>,
-    
-    // Token accounts
-    #[account(mut)]
-    pub sender_token_account: Account<'info, token_state::Account>,
-    #[account(mut)]
-    pub recipient_token_account: Account<'info, token_state::Account>,
-    #[account(mut)]
-    pub referral_token_account: Account<'info, token_state::Account>,
-    #[account(mut)]
-    pub treasury_token_account: Account<'info, token_state::Account>,
-    
-    // Payment account
-    #[account(
-        init,
-        payer = sender,
-        space = 8 + PaymentAccount::INIT_SPACE,
-        seeds = [b"payments", sender.key().as_ref(), b"usdc"],
-        bump
-    )]
-    pub payment: Account<'info, PaymentAccount>,
-    
-    // Escrow account
-    #[account(
-        mut,
-        seeds = [b"escrow", owner.key().as_ref()],
-        bump = escrow.bump,
-        constraint = escrow.owner == owner.key(),
-    )]
-    pub escrow: Account<'info, EscrowAccount>,
-    
-    // Mint account
-    #[account(address = USDC_MINT)]
-    pub mint: Account<'info, token_state::Mint>,
-    
-    // Program accounts
-    pub owner: SystemAccount<'info>,
-    pub token_program: Program<'info, token_2022::spl_token::ID>,
-    pub system_program: Program<'info, System>,
-    
-    // Additional token accounts (kept for backward compatibility)
-    /// CHECK: This is the sender's token account (ATA)
-    #[account(mut)]
-    pub sender_ata: AccountInfo<'info>,
-    /// CHECK: This is the recipient's token account (ATA)
-    #[account(mut)]
-    pub recipient_ata: AccountInfo<'info>,
-    /// CHECK: This is the referral's token account (ATA)
-    #[account(mut)]
-    pub referral_ata: AccountInfo<'info>,
-    /// CHECK: This is the treasury's token account (ATA)
-    #[account(mut)]
-    pub treasury_ata: AccountInfo<'info>,
-    
-    // System accounts
-    pub rent: Sysvar<'info, Rent>,
-    pub clock: Sysvar<'info, Clock>,
-    pub system_program: Program<'info, System>,
+#[event]
+pub struct PaymentProofEvent {
+    pub payment_proof: Pubkey,
+    pub payment: Pubkey,
+    pub bucket_min: u64,
+    pub bucket_max: u64,
+    pub status: PaymentProofStatus,
+    pub timestamp: i64,
 }
 
-// Updated EscrowAccount with encrypted statistics
-#[account]
-#[derive(InitSpace, Debug)]
-pub struct EscrowAccount {
-    pub owner: Pubkey,
-    pub total_fund_regulated: u64, // Keep for backwards compatibility
-    pub last_updated: i64,
-    pub active: bool,
-    pub treasury: Pubkey,
-    pub bump: u8,
-    // New fields for Arcium encryption
-    pub nonce: u128,
-    /// Encrypted statistics: [total_payments, total_volume, total_fees_collected]
-    pub encrypted_stats: [[u8; 32]; 3],
+/// Webhook payload for a confidential payment. Notifications are opt-in per
+/// escrow: this is emitted only once the owner has registered a
+/// `webhook_key` with `register_webhook_key`. Notifiers sign the Borsh
+/// encoding with that key and forward it to the merchant's endpoint.
+#[event]
+pub struct PaymentNotificationEvent {
+    pub escrow: Pubkey,
+    /// Escrow `sequence` as of this event
+    pub sequence: u64,
+    /// The payment's `PaymentAccount`, which receivers can fetch and key
+    /// their records on
+    pub payment: Pubkey,
+    /// `stats_commitment` of the stats this payment produced
+    pub commitment: [u8; 32],
+    pub status: PaymentNotificationStatus,
+    pub timestamp: i64,
 }
 
-// Keep existing PaymentAccount structure
-#[account]
-#[derive(InitSpace, Debug)]
-pub struct PaymentAccount {
-    pub sender: Pubkey,
-    pub recipient: Pubkey,
-    pub referal: Pubkey,
+#[event]
+pub struct TimeLockedPaymentEvent {
+    pub payment: Pubkey,
+    pub action: TimeLockAction,
+    /// Net amount held, claimed or reclaimed
     pub amount: u64,
     pub timestamp: i64,
-    pub referal_reward: u64,
-    pub treasury_reward: u64,
-    pub asset_mint: Pubkey,
 }
 
-// Enhanced error codes
-#[error_code]
-pub enum EscrowError {
-    #[msg("Escrow is paused")]
-    EscrowPaused,
-    #[msg("Escrow already paused")]
-    AlreadyPaused,
-    #[msg("Escrow already active")]
-    AlreadyActive,
-    #[msg("Invalid authority")]
-    InvalidAuthority,
-    #[msg("The computation was aborted")]
-    AbortedComputation,
-    #[msg("Cluster not set")]
-    ClusterNotSet,
+#[event]
+pub struct PullMandateEvent {
+    pub mandate: Pubkey,
+    pub payer: Pubkey,
+    pub merchant: Pubkey,
+    pub cap: u64,
+    pub revoked: bool,
+    pub timestamp: i64,
 }
 
-// Events for encrypted operations
 #[event]
-pub struct ConfidentialPaymentEvent {
+pub struct PullPaymentEvent {
+    pub escrow: Pubkey,
+    /// Escrow `sequence` as of this event
+    pub sequence: u64,
+    pub mandate: Pubkey,
+    pub amount: u64,
+    /// Running total pulled under the mandate
+    pub pulled: u64,
     pub timestamp: i64,
-    pub sender: Pubkey,
 }
 
 #[event]
-pub struct ThresholdCheckEvent {
-    pub meets_threshold: bool,
+pub struct AnalyticsOperatorEvent {
+    pub escrow: Pubkey,
+    pub operator: Pubkey,
+    pub min_interval_secs: i64,
+    /// Set when the grant was removed rather than added or updated
+    pub revoked: bool,
     pub timestamp: i64,
 }
 
 #[event]
-pub struct PaymentCountEvent {
-    pub total_payments: u64,
+pub struct ViewerEvent {
+    pub escrow: Pubkey,
+    pub viewer: Pubkey,
+    /// Set when the viewer was removed rather than added
+    pub revoked: bool,
     pub timestamp: i64,
 }
@@ -0,0 +1,158 @@
+// Arcium plumbing shared by the deploy script, the tests and any other
+// client: comp def offsets for every circuit, the Arcium accounts a queued
+// computation needs, and the init + finalize sequence for comp defs.
+
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import {
+  buildFinalizeCompDefTx,
+  getArciumEnv,
+  getClusterAccAddress,
+  getCompDefAccAddress,
+  getCompDefAccOffset,
+  getComputationAccAddress,
+  getExecutingPoolAccAddress,
+  getMempoolAccAddress,
+  getMXEAccAddress,
+} from "@arcium-hq/client";
+import { Escrow } from "../target/types/escrow";
+
+// Every circuit the escrow program queues, with the instruction that
+// initializes its comp def, in the order a fresh deployment sets them up.
+export const CIRCUITS = [
+  { name: "init_escrow_stats", method: "initEscrowStatsCompDef" },
+  { name: "init_referral_stats", method: "initReferralStatsCompDef" },
+  { name: "process_payment", method: "initProcessPaymentCompDef" },
+  { name: "update_referral_stats", method: "initUpdateReferralStatsCompDef" },
+  { name: "check_volume_threshold", method: "initCheckVolumeThresholdCompDef" },
+  { name: "reveal_payment_count", method: "initRevealPaymentCountCompDef" },
+  { name: "merge_deltas", method: "initMergeDeltasCompDef" },
+  { name: "verify_otc_terms", method: "initVerifyOtcTermsCompDef" },
+  { name: "run_payroll", method: "initRunPayrollCompDef" },
+  { name: "referral_report", method: "initReferralReportCompDef" },
+  { name: "check_recent_volume_threshold", method: "initCheckRecentVolumeThresholdCompDef" },
+  { name: "reveal_asset_volume", method: "initRevealAssetVolumeCompDef" },
+  { name: "resolve_sealed_bids", method: "initResolveSealedBidsCompDef" },
+  { name: "share_escrow_stats", method: "initShareEscrowStatsCompDef" },
+  { name: "init_spend_cap", method: "initSpendCapCompDef" },
+  { name: "check_spend_cap", method: "initCheckSpendCapCompDef" },
+  { name: "compute_match", method: "initComputeMatchCompDef" },
+  { name: "process_refund", method: "initProcessRefundCompDef" },
+  { name: "check_refund_rate", method: "initCheckRefundRateCompDef" },
+  { name: "prove_payment_amount", method: "initProvePaymentAmountCompDef" },
+  { name: "calculate_fees", method: "initCalculateFeesCompDef" },
+] as const;
+
+export type CircuitName = (typeof CIRCUITS)[number]["name"];
+
+// Mirrors the program's STATS_DIMENSION_* bits for `initializeEscrow`. They
+// pick the tracked metrics; the escrow account's size and rent are the same
+// for every mask.
+export const STATS_DIMENSION_PAYMENTS = 1 << 0;
+export const STATS_DIMENSION_VOLUME = 1 << 1;
+export const STATS_DIMENSION_FEES = 1 << 2;
+export const STATS_DIMENSION_ASSETS = 1 << 3;
+export const STATS_DIMENSION_REFUNDS = 1 << 4;
+export const STATS_DIMENSIONS_ALL =
+  STATS_DIMENSION_PAYMENTS |
+  STATS_DIMENSION_VOLUME |
+  STATS_DIMENSION_FEES |
+  STATS_DIMENSION_ASSETS |
+  STATS_DIMENSION_REFUNDS;
+
+/** Comp def offset of `circuit`, as the program's `comp_def_offset` computes it. */
+export function compDefOffset(circuit: string): number {
+  return Buffer.from(getCompDefAccOffset(circuit)).readUInt32LE();
+}
+
+/** Comp def offsets for every circuit in `CIRCUITS`. */
+export const COMP_DEF_OFFSETS = Object.fromEntries(
+  CIRCUITS.map((circuit) => [circuit.name, compDefOffset(circuit.name)])
+) as Record<CircuitName, number>;
+
+/**
+ * Arcium accounts of one deployment. `clusterOffset` selects the cluster
+ * on devnet/testnet; null uses ARCIUM_CLUSTER_PUBKEY from the env, as on
+ * localnet.
+ */
+export class ArciumAccounts {
+  readonly mxeAccount: PublicKey;
+  readonly mempoolAccount: PublicKey;
+  readonly executingPool: PublicKey;
+  readonly clusterAccount: PublicKey;
+
+  constructor(readonly programId: PublicKey, clusterOffset: number | null) {
+    this.mxeAccount = getMXEAccAddress(programId);
+    this.mempoolAccount = getMempoolAccAddress(programId);
+    this.executingPool = getExecutingPoolAccAddress(programId);
+    this.clusterAccount =
+      clusterOffset !== null
+        ? getClusterAccAddress(clusterOffset)
+        : getArciumEnv().arciumClusterPubkey;
+  }
+
+  compDefAccount(circuit: string): PublicKey {
+    return getCompDefAccAddress(this.programId, compDefOffset(circuit));
+  }
+
+  computationAccount(computationOffset: anchor.BN): PublicKey {
+    return getComputationAccAddress(this.programId, computationOffset);
+  }
+
+  /** Arcium accounts for queueing `circuit` at `computationOffset`. */
+  queue(computationOffset: anchor.BN, circuit: string) {
+    return {
+      computationAccount: this.computationAccount(computationOffset),
+      clusterAccount: this.clusterAccount,
+      mxeAccount: this.mxeAccount,
+      mempoolAccount: this.mempoolAccount,
+      executingPool: this.executingPool,
+      compDefAccount: this.compDefAccount(circuit),
+    };
+  }
+}
+
+/**
+ * Initialize and finalize the comp def for `circuit` unless it already
+ * exists. Returns the init signature, or null if it was skipped.
+ */
+export async function initCompDefIfMissing(
+  provider: anchor.AnchorProvider,
+  program: Program<Escrow>,
+  circuit: string,
+  method: string,
+  payer?: Keypair
+): Promise<string | null> {
+  const compDefAccount = getCompDefAccAddress(
+    program.programId,
+    compDefOffset(circuit)
+  );
+  if (await provider.connection.getAccountInfo(compDefAccount)) {
+    return null;
+  }
+
+  const sig = await (program.methods as any)
+    [method]()
+    .accounts({
+      compDefAccount,
+      payer: payer?.publicKey ?? provider.wallet.publicKey,
+      mxeAccount: getMXEAccAddress(program.programId),
+    })
+    .signers(payer ? [payer] : [])
+    .rpc({ commitment: "confirmed" });
+
+  const finalizeTx = await buildFinalizeCompDefTx(
+    provider,
+    compDefOffset(circuit),
+    program.programId
+  );
+  const latestBlockhash = await provider.connection.getLatestBlockhash();
+  finalizeTx.recentBlockhash = latestBlockhash.blockhash;
+  finalizeTx.lastValidBlockHeight = latestBlockhash.lastValidBlockHeight;
+  if (payer) {
+    finalizeTx.sign(payer);
+  }
+  await provider.sendAndConfirm(finalizeTx);
+  return sig;
+}
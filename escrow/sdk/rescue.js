@@ -0,0 +1,44 @@
+// Rescue encryption and decryption for the Rust client crate. Arcium ships
+// its cipher only in @arcium-hq/client, so `cipher::NodeCipher` runs this
+// script instead of reimplementing it:
+//
+//   node sdk/rescue.js encrypt <shared secret> <nonce> <value>...
+//   node sdk/rescue.js decrypt <shared secret> <nonce> <ciphertext>...
+//
+// The secret, nonce and ciphertexts are hex, the nonce as its 16
+// little-endian bytes; values are decimal. Prints one ciphertext or
+// plaintext per line as 32 bytes of hex, plaintexts little-endian.
+
+const { RescueCipher } = require("@arcium-hq/client");
+
+const [command, secret, nonce, ...inputs] = process.argv.slice(2);
+if (!["encrypt", "decrypt"].includes(command) || !secret || !nonce) {
+  console.error("usage: rescue.js encrypt|decrypt <secret> <nonce> <input>...");
+  process.exit(2);
+}
+
+const cipher = new RescueCipher(Buffer.from(secret, "hex"));
+const nonceBytes = new Uint8Array(Buffer.from(nonce, "hex"));
+
+function littleEndian(value) {
+  const bytes = Buffer.alloc(32);
+  for (let i = 0; i < 32; i++) {
+    bytes[i] = Number((value >> BigInt(8 * i)) & BigInt(0xff));
+  }
+  return bytes;
+}
+
+const outputs =
+  command === "encrypt"
+    ? cipher
+        .encrypt(inputs.map(BigInt), nonceBytes)
+        .map((ciphertext) => Buffer.from(ciphertext))
+    : cipher
+        .decrypt(
+          inputs.map((input) => Array.from(Buffer.from(input, "hex"))),
+          nonceBytes
+        )
+        .map(littleEndian);
+for (const output of outputs) {
+  console.log(output.toString("hex"));
+}
@@ -0,0 +1,14 @@
+// Compute unit ceilings for the escrow instructions clients submit most, by
+// instruction name. The tests simulate each row's instruction on localnet
+// and fail if it consumes more than its ceiling, so an instruction that
+// grows more expensive has to raise its row here too. The client crate's
+// `tx` module sizes compute-budget instructions from these rows.
+//
+// `send_payment_encrypted` is held to the runtime's default per-instruction
+// limit: it always ran without a compute-budget instruction, so the test
+// only has to keep it there.
+
+pub const COMPUTE_UNIT_BUDGETS: [(&str, u32); 2] = [
+    ("send_payment", 100_000),
+    ("send_payment_encrypted", 200_000),
+];
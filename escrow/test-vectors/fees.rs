@@ -0,0 +1,23 @@
+// Canonical fee breakdowns at the default protocol rates. The escrow
+// program `include!`s them and checks its `fee_split`, which every payment
+// path charges through, at compile time. The tests run the circuits'
+// `calculate_fees`, which shares its split with `record_payment`, over the
+// same rows on localnet, so the on-chain and MPC splits can't drift apart.
+//
+// Each row is (amount, referral_fee, treasury_fee): 0.6% and 1.4%, each
+// rounded down on its own. Rows near rounding boundaries are the ones that
+// catch a fee computed as one rounded 2% instead of two rounded legs.
+
+pub const FEE_VECTORS: [(u64, u64, u64); 11] = [
+    (0, 0, 0),
+    (1, 0, 0),
+    (99, 0, 1),
+    (166, 0, 2),
+    (167, 1, 2),
+    (250, 1, 3),
+    (1_000, 6, 14),
+    (71_428, 428, 999),
+    (1_000_000, 6_000, 14_000),
+    (1_000_000_000, 6_000_000, 14_000_000),
+    (1_000_000_000_000_000, 6_000_000_000_000, 14_000_000_000_000),
+];
@@ -0,0 +1,32 @@
+// Canonical payment intent hashes. The `payment-intent` crate `include!`s
+// them, and the escrow program checks its preimage encoding against them at
+// compile time. The tests rebuild the same preimages with Node's SHA-256 and
+// compare against these rows and against `intent_hash` on live payments, so
+// off-chain systems can't drift from the program.
+//
+// Keys, amounts and hashes are hex; `nonce` is the u128 written little-endian
+// into the preimage. The first row is a plaintext SOL payment, the second a
+// confidential one committing to its amount ciphertext.
+
+pub const PROGRAM_ID: &str = "cea9e1e87d6bcae79689a64ae3f727f1fb6da37656cf3fc9a096b3c5acbc848a";
+
+pub const PAYMENT_INTENT_VECTORS: [IntentVector; 2] = [
+    IntentVector {
+        sender: "0101010101010101010101010101010101010101010101010101010101010101",
+        recipient: "0202020202020202020202020202020202020202020202020202020202020202",
+        amount: VectorAmount::Plain(1_000_000),
+        mint: "0000000000000000000000000000000000000000000000000000000000000000",
+        nonce: 7,
+        hash: "d683e1570cc87efc3813e78b587906ef72ca556c1882f8143c8ea71c34c3475b",
+    },
+    IntentVector {
+        sender: "0303030303030303030303030303030303030303030303030303030303030303",
+        recipient: "0404040404040404040404040404040404040404040404040404040404040404",
+        amount: VectorAmount::Commitment(
+            "abababababababababababababababababababababababababababababababab",
+        ),
+        mint: "0505050505050505050505050505050505050505050505050505050505050505",
+        nonce: 340_282_366_920_938_463_463_374_607_431_768_211_455,
+        hash: "62b16dca65806253dffcbc62eda9d34233c899d79880fbe2e8114bb247af2044",
+    },
+];